@@ -0,0 +1,171 @@
+//! Embedded IATA airport and metropolitan-area dataset.
+//!
+//! The airport list lives in `data/airports.csv` and is compiled into the binary,
+//! so lookups never leave the process. It covers major airports only, for names,
+//! cities and distances; codes missing from it are not invalid.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+const AIRPORTS_CSV: &str = include_str!("../data/airports.csv");
+
+/// Most airports [`nearby`] returns for one code.
+pub const MAX_NEARBY_AIRPORTS: usize = 4;
+
+/// How far an airport outside any metropolitan area looks for neighbours.
+const NEARBY_RADIUS_KM: f64 = 100.0;
+
+/// Multi-airport city codes Duffel accepts in place of a single airport.
+const METRO_AREAS: &[(&str, &str, &[&str])] = &[
+    ("BJS", "Beijing", &["PEK", "PKX"]),
+    ("BUE", "Buenos Aires", &["EZE", "AEP"]),
+    ("CHI", "Chicago", &["ORD", "MDW"]),
+    ("LON", "London", &["LHR", "LGW", "STN", "LCY", "LTN", "SEN"]),
+    ("MIL", "Milan", &["MXP", "LIN", "BGY"]),
+    ("MOW", "Moscow", &["SVO", "DME"]),
+    ("NYC", "New York", &["JFK", "LGA", "EWR"]),
+    ("OSA", "Osaka", &["KIX", "ITM"]),
+    ("PAR", "Paris", &["CDG", "ORY", "BVA"]),
+    ("RIO", "Rio de Janeiro", &["GIG", "SDU"]),
+    ("ROM", "Rome", &["FCO", "CIA"]),
+    ("SAO", "São Paulo", &["GRU", "CGH", "VCP"]),
+    ("SEL", "Seoul", &["ICN", "GMP"]),
+    ("STO", "Stockholm", &["ARN", "BMA"]),
+    ("TYO", "Tokyo", &["NRT", "HND"]),
+    ("WAS", "Washington", &["IAD", "DCA", "BWI"]),
+    ("YMQ", "Montreal", &["YUL"]),
+    ("YTO", "Toronto", &["YYZ", "YTZ"]),
+];
+
+/// A single airport from the embedded dataset.
+#[derive(Debug, Clone)]
+pub struct Airport {
+    pub iata_code: &'static str,
+    pub name: &'static str,
+    pub city: &'static str,
+    /// ISO 3166-1 alpha-2 country code.
+    pub country: &'static str,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+fn airports() -> &'static HashMap<&'static str, Airport> {
+    static AIRPORTS: OnceLock<HashMap<&'static str, Airport>> = OnceLock::new();
+    AIRPORTS.get_or_init(|| {
+        AIRPORTS_CSV
+            .lines()
+            .skip(1)
+            .filter_map(|line| {
+                let fields: Vec<&'static str> = line.split(',').collect();
+                let [iata_code, name, city, country, latitude, longitude] = fields[..] else {
+                    return None;
+                };
+                let airport = Airport {
+                    iata_code,
+                    name,
+                    city,
+                    country,
+                    latitude: latitude.parse().ok()?,
+                    longitude: longitude.parse().ok()?,
+                };
+                Some((iata_code, airport))
+            })
+            .collect()
+    })
+}
+
+/// Look up an airport by its (uppercase) IATA code.
+pub fn find(iata_code: &str) -> Option<&'static Airport> {
+    airports().get(iata_code)
+}
+
+/// Airports served by a metropolitan-area code such as `LON` or `NYC`.
+pub fn city_airports(city_code: &str) -> Option<&'static [&'static str]> {
+    METRO_AREAS
+        .iter()
+        .find(|(code, _, _)| *code == city_code)
+        .map(|(_, _, airports)| *airports)
+}
+
+/// City served by an airport or metropolitan-area code, e.g. `London` for `LHR` or `LON`.
+pub fn city_name(code: &str) -> Option<&'static str> {
+    METRO_AREAS
+        .iter()
+        .find(|(metro, _, _)| *metro == code)
+        .map(|(_, name, _)| *name)
+        .or_else(|| find(code).map(|airport| airport.city))
+}
+
+/// Where an airport is, or the midpoint of a metropolitan area's airports.
+pub fn coordinates(code: &str) -> Option<(f64, f64)> {
+    if let Some(airport) = find(code) {
+        return Some((airport.latitude, airport.longitude));
+    }
+    let metro: Vec<&Airport> = city_airports(code)?.iter().filter_map(|airport| find(airport)).collect();
+    if metro.is_empty() {
+        return None;
+    }
+    let count = metro.len() as f64;
+    let latitude = metro.iter().map(|airport| airport.latitude).sum::<f64>() / count;
+    let longitude = metro.iter().map(|airport| airport.longitude).sum::<f64>() / count;
+    Some((latitude, longitude))
+}
+
+/// Code for a city name such as `London` or `new york`: its metropolitan-area code,
+/// or the airport code when the dataset has exactly one airport in that city.
+pub fn city_code(name: &str) -> Option<&'static str> {
+    let name = name.trim();
+    if let Some((code, _, _)) = METRO_AREAS.iter().find(|(_, city, _)| city.eq_ignore_ascii_case(name)) {
+        return Some(code);
+    }
+    let mut matches = airports().values().filter(|airport| airport.city.eq_ignore_ascii_case(name));
+    let first = matches.next()?;
+    matches.next().is_none().then_some(first.iata_code)
+}
+
+/// Airports worth searching instead of `code`, at most [`MAX_NEARBY_AIRPORTS`].
+///
+/// A metropolitan-area code gives its airports; an airport in one gives itself
+/// first, then the others. Any other airport gives itself and the closest airports
+/// within 100 km. Unknown codes give nothing.
+pub fn nearby(code: &str) -> Vec<&'static str> {
+    let metro = METRO_AREAS
+        .iter()
+        .find(|(metro, _, airports)| *metro == code || airports.contains(&code));
+    let mut nearby: Vec<&'static str> = match (metro, find(code)) {
+        (Some((_, _, airports)), _) => {
+            let mut nearby = airports.to_vec();
+            if let Some(index) = nearby.iter().position(|airport| *airport == code) {
+                let airport = nearby.remove(index);
+                nearby.insert(0, airport);
+            }
+            nearby
+        }
+        (None, Some(origin)) => {
+            let mut by_distance: Vec<(f64, &'static str)> = airports()
+                .values()
+                .map(|airport| {
+                    let distance =
+                        distance_km(origin.latitude, origin.longitude, airport.latitude, airport.longitude);
+                    (distance, airport.iata_code)
+                })
+                .filter(|(distance, _)| *distance <= NEARBY_RADIUS_KM)
+                .collect();
+            by_distance.sort_by(|a, b| a.0.total_cmp(&b.0));
+            by_distance.into_iter().map(|(_, airport)| airport).collect()
+        }
+        (None, None) => Vec::new(),
+    };
+    nearby.truncate(MAX_NEARBY_AIRPORTS);
+    nearby
+}
+
+/// Great-circle distance between two coordinates, in kilometres.
+pub fn distance_km(latitude_a: f64, longitude_a: f64, latitude_b: f64, longitude_b: f64) -> f64 {
+    const EARTH_RADIUS_KM: f64 = 6371.0;
+    let (lat_a, lat_b) = (latitude_a.to_radians(), latitude_b.to_radians());
+    let d_lat = lat_b - lat_a;
+    let d_lon = (longitude_b - longitude_a).to_radians();
+    let h = (d_lat / 2.0).sin().powi(2) + lat_a.cos() * lat_b.cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * h.sqrt().asin()
+}
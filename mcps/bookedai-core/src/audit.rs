@@ -0,0 +1,474 @@
+//! Append-only audit log of tool calls.
+//!
+//! [`McpServer`](crate::McpServer) records every `tools/call` it answers: when, which
+//! server and tool, the arguments with traveller details and secrets masked
+//! ([`redact`]), the Duffel request IDs behind it, whether it succeeded, and the price
+//! involved. Servers sharing a database write to one table, so the log spans them
+//! all. Entries are never updated; the `[audit]` retention settings delete the oldest
+//! ([`AuditLog::prune`]), and `query_audit_log` reads them back.
+
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use rusqlite::{params, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{error, info};
+
+use crate::config;
+use crate::jsonrpc::RpcError;
+use crate::mcp::{self, Tool};
+use crate::pagination;
+use crate::store::Store;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS audit_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    recorded_at TEXT NOT NULL,
+    server TEXT NOT NULL,
+    tool TEXT NOT NULL,
+    request_id TEXT,
+    arguments TEXT NOT NULL,
+    duffel_request_ids TEXT NOT NULL,
+    outcome TEXT NOT NULL,
+    error TEXT,
+    price TEXT,
+    currency TEXT,
+    duration_ms INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS audit_log_recorded ON audit_log(recorded_at);
+CREATE INDEX IF NOT EXISTS audit_log_tool ON audit_log(tool, recorded_at);
+CREATE TRIGGER IF NOT EXISTS audit_log_append_only BEFORE UPDATE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log is append-only');
+END;
+";
+
+/// How often entries past the `[audit]` retention limits are deleted.
+pub const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+
+const ENTRY_COLUMNS: &str = "id, recorded_at, server, tool, request_id, arguments, duffel_request_ids, \
+     outcome, error, price, currency, duration_ms";
+
+/// Argument fields masked before they are logged: traveller details, documents,
+/// account numbers and credentials, wherever they appear.
+const SENSITIVE_FIELDS: &[&str] = &[
+    "given_name",
+    "family_name",
+    "name",
+    "born_on",
+    "gender",
+    "email",
+    "phone_number",
+    "passport",
+    "number",
+    "account_number",
+    "corporate_code",
+    "tracking_reference",
+    "address",
+    "line_one",
+    "postal_code",
+    "password",
+    "secret",
+    "token",
+];
+
+/// Outcomes `query_audit_log` filters on.
+const OUTCOMES: &[&str] = &["success", "error"];
+
+tokio::task_local! {
+    static DUFFEL_REQUEST_IDS: Mutex<Vec<String>>;
+}
+
+/// Run a tool call, collecting the IDs of the Duffel requests it makes.
+pub async fn collect_duffel_requests<F: Future>(future: F) -> (F::Output, Vec<String>) {
+    DUFFEL_REQUEST_IDS
+        .scope(Mutex::new(Vec::new()), async {
+            let output = future.await;
+            let ids = DUFFEL_REQUEST_IDS.with(|ids| std::mem::take(&mut *ids.lock().unwrap()));
+            (output, ids)
+        })
+        .await
+}
+
+/// Note a Duffel request ID against the current tool call; a no-op outside one.
+pub fn note_duffel_request(id: &str) {
+    let _ = DUFFEL_REQUEST_IDS.try_with(|ids| {
+        let mut ids = ids.lock().unwrap();
+        if !ids.iter().any(|existing| existing == id) {
+            ids.push(id.to_string());
+        }
+    });
+}
+
+/// A copy of tool arguments with every [`SENSITIVE_FIELDS`] value replaced by `***`.
+pub fn redact(arguments: &Value) -> Value {
+    match arguments {
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| {
+                    let masked = if SENSITIVE_FIELDS.contains(&key.as_str()) && !value.is_null() {
+                        json!("***")
+                    } else {
+                        redact(value)
+                    };
+                    (key.clone(), masked)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(redact).collect()),
+        other => other.clone(),
+    }
+}
+
+/// The price a tool result concerns: what an order or booking costs, or the cheapest
+/// of the offers a search found.
+fn price_of(result: &Value) -> Option<(String, String)> {
+    fn amount(item: &Value) -> Option<(String, String)> {
+        let amount = item["total_amount"].as_str().or_else(|| item["price"].as_str())?;
+        Some((amount.to_string(), item["currency"].as_str()?.to_string()))
+    }
+    let content = &result["structuredContent"];
+    amount(content).or_else(|| {
+        content["offers"]
+            .as_array()?
+            .iter()
+            .filter_map(amount)
+            .min_by(|a, b| {
+                let price = |amount: &str| amount.parse::<f64>().unwrap_or(f64::MAX);
+                price(&a.0).total_cmp(&price(&b.0))
+            })
+    })
+}
+
+/// One recorded tool call.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Assigned when the entry is recorded.
+    pub id: i64,
+    pub recorded_at: String,
+    /// The MCP server that answered, e.g. `duffel-flights-mcp`.
+    pub server: String,
+    pub tool: String,
+    /// The request's correlation ID, as in the logs and `X-Request-Id`.
+    pub request_id: Option<String>,
+    /// The call's arguments, redacted.
+    pub arguments: Value,
+    pub duffel_request_ids: Vec<String>,
+    /// `success` or `error`.
+    pub outcome: String,
+    pub error: Option<String>,
+    pub price: Option<String>,
+    pub currency: Option<String>,
+    pub duration_ms: u64,
+}
+
+impl AuditEntry {
+    /// The entry for a finished call; `arguments` are redacted here.
+    pub fn new(
+        server: &str,
+        tool: &str,
+        request_id: Option<&str>,
+        arguments: &Value,
+        result: &Result<Value, RpcError>,
+        duffel_request_ids: Vec<String>,
+        duration: Duration,
+    ) -> Self {
+        let (outcome, error, price) = match result {
+            Ok(result) => ("success", None, price_of(result)),
+            Err(e) => ("error", Some(format!("{} ({})", e.message, e.code)), None),
+        };
+        let (price, currency) = price.unzip();
+        Self {
+            id: 0,
+            recorded_at: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            server: server.to_string(),
+            tool: tool.to_string(),
+            request_id: request_id.map(str::to_string),
+            arguments: redact(arguments),
+            duffel_request_ids,
+            outcome: outcome.to_string(),
+            error,
+            price,
+            currency,
+            duration_ms: duration.as_millis() as u64,
+        }
+    }
+}
+
+fn entry_from_row(row: &Row) -> rusqlite::Result<AuditEntry> {
+    let arguments: String = row.get(5)?;
+    let duffel_request_ids: String = row.get(6)?;
+    Ok(AuditEntry {
+        id: row.get(0)?,
+        recorded_at: row.get(1)?,
+        server: row.get(2)?,
+        tool: row.get(3)?,
+        request_id: row.get(4)?,
+        arguments: serde_json::from_str(&arguments).unwrap_or(Value::Null),
+        duffel_request_ids: serde_json::from_str(&duffel_request_ids).unwrap_or_default(),
+        outcome: row.get(7)?,
+        error: row.get(8)?,
+        price: row.get(9)?,
+        currency: row.get(10)?,
+        duration_ms: row.get(11)?,
+    })
+}
+
+/// Which entries `query_audit_log` returns.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuditQuery {
+    pub tool: Option<String>,
+    pub server: Option<String>,
+    pub outcome: Option<String>,
+    /// Entries whose calls made this Duffel request.
+    pub duffel_request_id: Option<String>,
+    /// `YYYY-MM-DD` or an RFC 3339 time; entries recorded at or after it.
+    pub since: Option<String>,
+    /// `YYYY-MM-DD` (the whole day) or an RFC 3339 time; entries recorded before it.
+    pub until: Option<String>,
+    pub limit: Option<usize>,
+}
+
+/// A `since` / `until` bound as stored timestamps compare; a date ends at the next
+/// midnight when `end_of_day` is set.
+fn parse_bound(field: &str, value: &str, end_of_day: bool) -> Result<String, RpcError> {
+    let time = match NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        Ok(date) => {
+            let date = if end_of_day { date.succ_opt().unwrap_or(date) } else { date };
+            date.and_hms_opt(0, 0, 0).map(|time| time.and_utc())
+        }
+        Err(_) => DateTime::parse_from_rfc3339(value).ok().map(|time| time.with_timezone(&Utc)),
+    };
+    time.map(|time| time.to_rfc3339_opts(SecondsFormat::Millis, true))
+        .ok_or_else(|| {
+            RpcError::invalid_params(format!(
+                "Invalid parameters: {} must be YYYY-MM-DD or an RFC 3339 time, got '{}'",
+                field, value
+            ))
+        })
+}
+
+/// The `audit_log` table in the shared [`Store`].
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    store: Store,
+}
+
+impl AuditLog {
+    pub async fn new(store: Store) -> Result<Self> {
+        store.migrate(SCHEMA).await?;
+        Ok(Self { store })
+    }
+
+    /// The audit log, or `None` when `audit.enabled` (`AUDIT_LOG_ENABLED`) is off.
+    pub async fn from_config(store: Store) -> Result<Option<Self>> {
+        if !config::get().audit.enabled {
+            info!("AUDIT_LOG_ENABLED is false, tool calls are not audited");
+            return Ok(None);
+        }
+        Self::new(store).await.map(Some)
+    }
+
+    /// Append an entry, returning its ID.
+    pub async fn record(&self, entry: AuditEntry) -> Result<i64> {
+        self.store
+            .call(move |connection| {
+                connection.execute(
+                    "INSERT INTO audit_log (recorded_at, server, tool, request_id, arguments, duffel_request_ids,
+                                            outcome, error, price, currency, duration_ms)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                    params![
+                        entry.recorded_at,
+                        entry.server,
+                        entry.tool,
+                        entry.request_id,
+                        entry.arguments.to_string(),
+                        serde_json::to_string(&entry.duffel_request_ids)?,
+                        entry.outcome,
+                        entry.error,
+                        entry.price,
+                        entry.currency,
+                        entry.duration_ms
+                    ],
+                )?;
+                Ok(connection.last_insert_rowid())
+            })
+            .await
+    }
+
+    /// Entries matching `query`, newest first; `since` and `until` must already be in
+    /// the stored timestamp format.
+    async fn list(&self, query: AuditQuery, limit: usize) -> Result<Vec<AuditEntry>> {
+        self.store
+            .call(move |connection| {
+                let sql = format!(
+                    "SELECT {} FROM audit_log
+                     WHERE (?1 IS NULL OR tool = ?1) AND (?2 IS NULL OR server = ?2) AND (?3 IS NULL OR outcome = ?3)
+                       AND (?4 IS NULL OR EXISTS (SELECT 1 FROM json_each(duffel_request_ids) WHERE value = ?4))
+                       AND (?5 IS NULL OR recorded_at >= ?5) AND (?6 IS NULL OR recorded_at < ?6)
+                     ORDER BY id DESC LIMIT ?7",
+                    ENTRY_COLUMNS
+                );
+                let mut statement = connection.prepare(&sql)?;
+                let entries = statement
+                    .query_map(
+                        params![
+                            query.tool,
+                            query.server,
+                            query.outcome,
+                            query.duffel_request_id,
+                            query.since,
+                            query.until,
+                            limit as i64
+                        ],
+                        entry_from_row,
+                    )?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                Ok(entries)
+            })
+            .await
+    }
+
+    /// Delete entries older than `retention_days` and beyond the newest `max_entries`;
+    /// `0` turns either limit off. Returns how many were deleted.
+    pub async fn prune(&self, retention_days: u64, max_entries: u64) -> Result<usize> {
+        let cutoff = (retention_days > 0).then(|| {
+            (Utc::now() - chrono::Duration::days(retention_days as i64)).to_rfc3339_opts(SecondsFormat::Millis, true)
+        });
+        self.store
+            .call(move |connection| {
+                let mut deleted = 0;
+                if let Some(cutoff) = cutoff {
+                    deleted += connection.execute("DELETE FROM audit_log WHERE recorded_at < ?1", [cutoff])?;
+                }
+                if max_entries > 0 {
+                    deleted += connection.execute(
+                        "DELETE FROM audit_log WHERE id <= (SELECT id FROM audit_log ORDER BY id DESC LIMIT 1 OFFSET ?1)",
+                        [max_entries as i64],
+                    )?;
+                }
+                Ok(deleted)
+            })
+            .await
+    }
+
+    /// [`prune`](Self::prune) with the `[audit]` settings.
+    pub async fn prune_from_config(&self) -> Result<usize> {
+        let audit = &config::get().audit;
+        self.prune(audit.retention_days, audit.max_entries).await
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct QueryAuditLogResponse {
+    entries: Vec<AuditEntry>,
+}
+
+pub fn query_audit_log_tool() -> Tool {
+    Tool::new(
+        "query_audit_log",
+        "Admin: list recorded tool calls (searches, bookings and every other call, across the servers sharing this database), newest first, with redacted arguments, Duffel request IDs, outcome and price",
+        json!({
+            "type": "object",
+            "properties": {
+                "tool": { "type": "string", "description": "Only calls to this tool, e.g. create_order" },
+                "server": { "type": "string", "description": "Only calls answered by this server, e.g. duffel-stays-mcp" },
+                "outcome": { "type": "string", "enum": OUTCOMES, "description": "Only successful or only failed calls" },
+                "duffel_request_id": { "type": "string", "description": "Only the call that made this Duffel request" },
+                "since": { "type": "string", "description": "Recorded at or after this date (YYYY-MM-DD) or RFC 3339 time" },
+                "until": { "type": "string", "description": "Recorded before this RFC 3339 time, or up to the end of this date (YYYY-MM-DD)" },
+                "limit": {
+                    "type": "integer",
+                    "description": format!(
+                        "Entries to return (1-{}, default: {})",
+                        pagination::MAX_LIMIT,
+                        pagination::DEFAULT_LIMIT
+                    )
+                }
+            }
+        }),
+    )
+    .with_output_schema(json!({
+        "type": "object",
+        "properties": {
+            "entries": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "id": { "type": "integer" },
+                        "recorded_at": { "type": "string" },
+                        "server": { "type": "string" },
+                        "tool": { "type": "string" },
+                        "request_id": { "type": ["string", "null"] },
+                        "arguments": {},
+                        "duffel_request_ids": { "type": "array", "items": { "type": "string" } },
+                        "outcome": { "type": "string", "enum": OUTCOMES },
+                        "error": { "type": ["string", "null"] },
+                        "price": { "type": ["string", "null"] },
+                        "currency": { "type": ["string", "null"] },
+                        "duration_ms": { "type": "integer" }
+                    },
+                    "required": ["id", "recorded_at", "server", "tool", "arguments", "duffel_request_ids", "outcome", "duration_ms"]
+                }
+            }
+        },
+        "required": ["entries"]
+    }))
+}
+
+fn format_entries(entries: &[AuditEntry]) -> String {
+    if entries.is_empty() {
+        return "No audit log entries match.".to_string();
+    }
+    let mut result = format!("{} audit log entries, newest first:\n", entries.len());
+    for entry in entries {
+        result.push_str(&format!(
+            "\n#{} {} {} {}: {} ({} ms)\n",
+            entry.id, entry.recorded_at, entry.server, entry.tool, entry.outcome, entry.duration_ms
+        ));
+        if let (Some(price), Some(currency)) = (&entry.price, &entry.currency) {
+            result.push_str(&format!("   Price: {} {}\n", price, currency));
+        }
+        if let Some(error) = &entry.error {
+            result.push_str(&format!("   Error: {}\n", error));
+        }
+        if !entry.duffel_request_ids.is_empty() {
+            result.push_str(&format!("   Duffel requests: {}\n", entry.duffel_request_ids.join(", ")));
+        }
+        result.push_str(&format!("   Arguments: {}\n", entry.arguments));
+    }
+    result
+}
+
+pub async fn query_audit_log(audit: Option<AuditLog>, arguments: Value) -> Result<Value, RpcError> {
+    let mut query: AuditQuery = mcp::parse_arguments("query_audit_log", arguments)?;
+    let limit = pagination::resolve_limit(query.limit)?;
+    if let Some(outcome) = &query.outcome {
+        if !OUTCOMES.contains(&outcome.as_str()) {
+            return Err(RpcError::invalid_params(format!(
+                "Invalid parameters: outcome must be one of {}",
+                OUTCOMES.join(", ")
+            )));
+        }
+    }
+    query.since = query.since.as_deref().map(|since| parse_bound("since", since, false)).transpose()?;
+    query.until = query.until.as_deref().map(|until| parse_bound("until", until, true)).transpose()?;
+    let Some(audit) = audit else {
+        return Err(RpcError::server_error(
+            "The audit log is unavailable: it is turned off (AUDIT_LOG_ENABLED) or the database at BOOKEDAI_DB_PATH could not be opened",
+        ));
+    };
+
+    let entries = audit.list(query, limit).await.map_err(|e| {
+        error!("Audit log error: {}", e);
+        RpcError::server_error(format!("Audit log query failed: {}", e))
+    })?;
+    let response = QueryAuditLogResponse { entries };
+    mcp::structured_content(format_entries(&response.entries), &response)
+}
@@ -0,0 +1,64 @@
+//! Bearer-token authentication for the HTTP transport.
+//!
+//! With `MCP_AUTH_TOKEN` set, every `/mcp` request must carry
+//! `Authorization: Bearer <token>`; anything else is answered with `401` before
+//! the request reaches a tool.
+
+use std::fmt;
+use std::sync::Arc;
+
+use tracing::info;
+
+use crate::config;
+
+/// Header carrying the client's credentials.
+pub const AUTHORIZATION_HEADER: &str = "authorization";
+
+/// The shared secret clients must present.
+#[derive(Clone)]
+pub struct BearerAuth {
+    token: Arc<str>,
+}
+
+impl fmt::Debug for BearerAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BearerAuth").finish_non_exhaustive()
+    }
+}
+
+impl BearerAuth {
+    pub fn new(token: impl Into<String>) -> Self {
+        Self {
+            token: token.into().into(),
+        }
+    }
+
+    /// Build from `server.auth_token` (`MCP_AUTH_TOKEN`); `None` leaves `/mcp` open.
+    pub fn from_config() -> Option<Self> {
+        let token = config::get()
+            .server
+            .auth_token
+            .as_deref()
+            .map(str::trim)
+            .filter(|token| !token.is_empty());
+        match &token {
+            Some(_) => info!("MCP_AUTH_TOKEN set, /mcp requires a bearer token"),
+            None => info!("MCP_AUTH_TOKEN not set, /mcp is unauthenticated"),
+        }
+        token.map(Self::new)
+    }
+
+    /// Whether an `Authorization` header value carries the expected token.
+    pub fn check(&self, header: Option<&str>) -> bool {
+        let Some((scheme, token)) = header.and_then(|header| header.trim().split_once(' ')) else {
+            return false;
+        };
+        scheme.eq_ignore_ascii_case("bearer") && constant_time_eq(token.trim().as_bytes(), self.token.as_bytes())
+    }
+}
+
+/// Compare without stopping at the first differing byte, so response timing
+/// does not reveal how much of a guess was right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
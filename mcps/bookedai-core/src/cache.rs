@@ -0,0 +1,89 @@
+use std::future::Future;
+use std::time::Duration;
+
+use moka::future::Cache;
+use serde_json::Value;
+use tracing::{debug, info};
+
+use crate::config;
+use crate::jsonrpc::RpcError;
+
+/// Argument that lets a client bypass the cache for one call (`"cache": false`).
+pub const CACHE_ARGUMENT: &str = "cache";
+
+/// In-memory TTL cache of tool results, keyed on tool name and normalized arguments.
+///
+/// Concurrent identical calls are coalesced into a single upstream request.
+#[derive(Clone)]
+pub struct ResponseCache {
+    results: Cache<String, Value>,
+}
+
+impl ResponseCache {
+    pub fn new(ttl: Duration, max_entries: u64) -> Self {
+        Self {
+            results: Cache::builder()
+                .time_to_live(ttl)
+                .max_capacity(max_entries)
+                .build(),
+        }
+    }
+
+    /// Build a cache from `[cache]` in the config (`SEARCH_CACHE_TTL_SECS` and
+    /// `SEARCH_CACHE_MAX_ENTRIES`).
+    ///
+    /// Returns `None` when the TTL is set to `0`, disabling caching.
+    pub fn from_config() -> Option<Self> {
+        let config::CacheConfig { ttl_secs, max_entries } = config::get().cache;
+        if ttl_secs == 0 || max_entries == 0 {
+            info!("Search result cache disabled");
+            return None;
+        }
+
+        info!("Search result cache: ttl={}s, max_entries={}", ttl_secs, max_entries);
+        Some(Self::new(Duration::from_secs(ttl_secs), max_entries))
+    }
+
+    /// Cache key for a tool call; the `cache` flag itself does not affect the key.
+    ///
+    /// `serde_json` objects keep their keys sorted, so argument order does not matter.
+    pub fn key(tool_name: &str, arguments: &Value) -> String {
+        let mut arguments = arguments.clone();
+        if let Some(object) = arguments.as_object_mut() {
+            object.remove(CACHE_ARGUMENT);
+            object.retain(|_, value| !value.is_null());
+        }
+        format!("{}:{}", tool_name, arguments)
+    }
+
+    /// Return the cached result for `key`, or run `call` and cache it on success.
+    pub async fn get_or_try_insert<F>(&self, key: String, call: F) -> Result<Value, RpcError>
+    where
+        F: Future<Output = Result<Value, RpcError>>,
+    {
+        if let Some(result) = self.results.get(&key).await {
+            debug!("Cache hit for {}", key);
+            return Ok(result);
+        }
+
+        self.results
+            .try_get_with(key, call)
+            .await
+            .map_err(|e| (*e).clone())
+    }
+
+    /// Results currently cached; approximate while entries are being added or evicted.
+    pub fn entry_count(&self) -> u64 {
+        self.results.entry_count()
+    }
+
+    /// Run `call` without reading the cache, replacing any cached result on success.
+    pub async fn refresh<F>(&self, key: String, call: F) -> Result<Value, RpcError>
+    where
+        F: Future<Output = Result<Value, RpcError>>,
+    {
+        let result = call.await?;
+        self.results.insert(key, result.clone()).await;
+        Ok(result)
+    }
+}
@@ -0,0 +1,105 @@
+//! Circuit breaker for outbound Duffel calls.
+//!
+//! After `DUFFEL_CIRCUIT_FAILURES` consecutive upstream failures (connection errors,
+//! timeouts, 5xx responses), calls fail immediately for `DUFFEL_CIRCUIT_COOLDOWN_SECS`
+//! instead of each waiting out its own timeouts and retries. Once the cool-down has
+//! passed a single trial call is let through: success closes the circuit, failure
+//! opens it for another cool-down.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tracing::{info, warn};
+
+use crate::config;
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    /// Calls go through; counts failures in a row.
+    Closed { failures: u32 },
+    /// Calls fail fast until the cool-down ends.
+    Open { until: Instant },
+    /// One trial call is running; others fail fast.
+    HalfOpen { since: Instant },
+}
+
+/// Shared by every clone of a client, so one outage trips it for all callers.
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cool_down: Duration,
+    state: Arc<Mutex<State>>,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, cool_down: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            cool_down,
+            state: Arc::new(Mutex::new(State::Closed { failures: 0 })),
+        }
+    }
+
+    /// Build from `duffel.circuit_failures` and `duffel.circuit_cooldown_secs`
+    /// (`DUFFEL_CIRCUIT_FAILURES`, `DUFFEL_CIRCUIT_COOLDOWN_SECS`); a threshold of `0`
+    /// disables the breaker.
+    pub fn from_config() -> Option<Self> {
+        let duffel = &config::get().duffel;
+        let failure_threshold = duffel.circuit_failures;
+        let cool_down = Duration::from_secs(duffel.circuit_cooldown_secs);
+
+        if failure_threshold == 0 {
+            info!("Duffel circuit breaker disabled");
+            return None;
+        }
+        Some(Self::new(failure_threshold, cool_down))
+    }
+
+    /// `Err` with the time left when calls should fail fast, `Ok` when one may proceed.
+    pub fn check(&self) -> Result<(), Duration> {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        match *state {
+            State::Closed { .. } => Ok(()),
+            State::Open { until } if now < until => Err(until - now),
+            State::HalfOpen { since } if now < since + self.cool_down => Err(since + self.cool_down - now),
+            // Cool-down over, or the last trial never reported back (e.g. it was cancelled)
+            State::Open { .. } | State::HalfOpen { .. } => {
+                info!("Duffel circuit half-open, sending a trial request");
+                *state = State::HalfOpen { since: now };
+                Ok(())
+            }
+        }
+    }
+
+    /// Duffel answered, even if with a client error.
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        if !matches!(*state, State::Closed { .. }) {
+            info!("Duffel circuit closed");
+        }
+        *state = State::Closed { failures: 0 };
+    }
+
+    /// Duffel could not be reached, timed out, or returned a server error.
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        let failures = match *state {
+            State::Closed { failures } => failures + 1,
+            // A failed trial reopens the circuit straight away
+            State::HalfOpen { .. } => self.failure_threshold,
+            State::Open { .. } => return,
+        };
+        if failures >= self.failure_threshold {
+            warn!(
+                "Duffel circuit open after {} consecutive failures; failing fast for {:?}",
+                failures, self.cool_down
+            );
+            *state = State::Open {
+                until: Instant::now() + self.cool_down,
+            };
+        } else {
+            *state = State::Closed { failures };
+        }
+    }
+}
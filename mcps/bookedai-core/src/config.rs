@@ -0,0 +1,690 @@
+//! Settings shared by every server, from `config.toml` with environment overrides.
+//!
+//! The file is the one named by `--config <path>`, else `BOOKEDAI_CONFIG`, else
+//! `config.toml` in the working directory when there is one; settings it leaves out
+//! keep their defaults. Each setting's environment variable (e.g. `PORT` for
+//! `server.port`, see `config.example.toml`) overrides the file, so deployments
+//! configured through the environment alone keep working.
+//!
+//! [`init`] loads and validates the result once at startup, before any server is
+//! built; everything else reads it with [`get`]. `--print-config` prints the
+//! effective configuration, secrets masked, and exits.
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{duffel_version, profiles};
+
+const DEFAULT_CONFIG_PATH: &str = "config.toml";
+
+/// Shown instead of secrets by `--print-config`.
+const MASKED: &str = "***";
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub rate_limit: RateLimitConfig,
+    pub cache: CacheConfig,
+    pub duffel: DuffelConfig,
+    pub storage: StorageConfig,
+    pub fx: FxConfig,
+    pub price_alerts: PriceAlertsConfig,
+    pub transfers: TransfersConfig,
+    pub reviews: ReviewsConfig,
+    pub notifications: NotificationsConfig,
+    pub profiles: ProfilesConfig,
+    pub audit: AuditConfig,
+    pub upstream: UpstreamConfig,
+}
+
+/// The HTTP transport and tool calls.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind_addr: IpAddr,
+    /// Each server has its own default port.
+    pub port: Option<u16>,
+    /// Bearer token required on `/mcp`; `None` leaves it open.
+    pub auth_token: Option<String>,
+    pub tls_cert_path: Option<PathBuf>,
+    pub tls_key_path: Option<PathBuf>,
+    /// Deadline for each tool call; `0` disables it.
+    pub tool_call_timeout_secs: u64,
+    pub log_format: LogFormat,
+    /// How much search tools return when a call does not say.
+    pub verbosity: Verbosity,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind_addr: IpAddr::from([127, 0, 0, 1]),
+            port: None,
+            auth_token: None,
+            tls_cert_path: None,
+            tls_key_path: None,
+            tool_call_timeout_secs: 120,
+            log_format: LogFormat::Text,
+            verbosity: Verbosity::Full,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            other => Err(anyhow!("expected \"json\" or \"text\", got {:?}", other)),
+        }
+    }
+}
+
+/// How much of each result search tools return.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verbosity {
+    /// Price, airline or hotel, and times or rating only, to save context.
+    Summary,
+    /// Everything, e.g. flight segments and every room rate.
+    Full,
+}
+
+impl Verbosity {
+    /// `requested`, or the configured default when the call did not say.
+    pub fn resolve(requested: Option<Verbosity>) -> Verbosity {
+        requested.unwrap_or(get().server.verbosity)
+    }
+}
+
+impl FromStr for Verbosity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "summary" => Ok(Verbosity::Summary),
+            "full" => Ok(Verbosity::Full),
+            other => Err(anyhow!("expected \"summary\" or \"full\", got {:?}", other)),
+        }
+    }
+}
+
+/// Per-client limits on `POST /mcp`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    /// `0` disables rate limiting.
+    pub per_minute: u32,
+    pub burst: u32,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            per_minute: 60,
+            burst: 20,
+        }
+    }
+}
+
+/// The in-memory cache of cacheable tool results.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    /// `0` disables the cache.
+    pub ttl_secs: u64,
+    pub max_entries: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl_secs: 300,
+            max_entries: 1000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DuffelConfig {
+    pub mode: DuffelMode,
+    /// Required in `live` mode.
+    pub api_token: Option<String>,
+    /// Refuse to start unless `api_token` is a Duffel test token.
+    pub test_mode: bool,
+    /// `Duffel-Version` header sent with every call.
+    pub api_version: String,
+    /// Where the Duffel API is reached, e.g. a fake Duffel in tests.
+    pub base_url: String,
+    pub max_retries: u32,
+    /// Requests in flight at once, across every caller.
+    pub max_concurrency: usize,
+    pub connect_timeout_secs: u64,
+    /// Time allowed for one request attempt, from sending it to reading the whole body.
+    pub timeout_secs: u64,
+    /// Failures in a row that open the circuit breaker; `0` disables it.
+    pub circuit_failures: u32,
+    pub circuit_cooldown_secs: u64,
+    /// Largest Duffel response read; longer lists are cut to the items that fit.
+    pub max_response_bytes: usize,
+    /// Longest wait for airlines to finish answering an offer request; `0` fetches offers right away.
+    pub offer_poll_timeout_secs: u64,
+    /// Signing secret for Duffel webhooks; `None` leaves the endpoint disabled.
+    pub webhook_secret: Option<String>,
+}
+
+impl Default for DuffelConfig {
+    fn default() -> Self {
+        Self {
+            mode: DuffelMode::Live,
+            api_token: None,
+            test_mode: false,
+            api_version: duffel_version::BASELINE.to_string(),
+            base_url: "https://api.duffel.com".to_string(),
+            max_retries: 3,
+            max_concurrency: 8,
+            connect_timeout_secs: 10,
+            // Offer searches are the slowest calls and usually finish well within this
+            timeout_secs: 60,
+            circuit_failures: 5,
+            circuit_cooldown_secs: 30,
+            max_response_bytes: 32 * 1024 * 1024,
+            offer_poll_timeout_secs: 20,
+            webhook_secret: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DuffelMode {
+    /// Call the Duffel API.
+    Live,
+    /// Answer from embedded fixtures (see [`crate::mock`]).
+    Mock,
+}
+
+impl FromStr for DuffelMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "live" => Ok(DuffelMode::Live),
+            "mock" => Ok(DuffelMode::Mock),
+            other => Err(anyhow!("expected \"live\" or \"mock\", got {:?}", other)),
+        }
+    }
+}
+
+impl fmt::Display for DuffelMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DuffelMode::Live => write!(f, "live"),
+            DuffelMode::Mock => write!(f, "mock"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    pub db_path: PathBuf,
+    /// How long saved search results stay available.
+    pub search_results_ttl_secs: u64,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            db_path: PathBuf::from("bookedai.db"),
+            // Duffel offers are typically bookable for about half an hour
+            search_results_ttl_secs: 1800,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FxConfig {
+    /// How long ECB exchange rates are reused before refetching.
+    pub rates_ttl_secs: u64,
+}
+
+impl Default for FxConfig {
+    fn default() -> Self {
+        Self {
+            // ECB publishes once per working day, so twelve hours never misses more than one update
+            rates_ttl_secs: 12 * 60 * 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PriceAlertsConfig {
+    pub interval_secs: u64,
+}
+
+impl Default for PriceAlertsConfig {
+    fn default() -> Self {
+        Self { interval_secs: 3600 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TransfersConfig {
+    pub provider: String,
+}
+
+impl Default for TransfersConfig {
+    fn default() -> Self {
+        Self {
+            provider: "sample".to_string(),
+        }
+    }
+}
+
+/// Guest reviews added to hotel search results (stays).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReviewsConfig {
+    pub provider: String,
+    /// Reviews are off until this is set.
+    pub google_places_api_key: Option<String>,
+}
+
+impl Default for ReviewsConfig {
+    fn default() -> Self {
+        Self {
+            provider: "google_places".to_string(),
+            google_places_api_key: None,
+        }
+    }
+}
+
+/// Booking confirmations by email and webhook (flights, stays).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationsConfig {
+    /// Send a confirmation after every new flight order, not only on `send_confirmation`.
+    pub send_on_booking: bool,
+    /// Email is off until this is set.
+    pub smtp_host: Option<String>,
+    pub smtp_port: u16,
+    /// `starttls`, `tls`, or `none`.
+    pub smtp_security: String,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<String>,
+    /// Sender address, e.g. `BookedAI <bookings@example.com>`; required with `smtp_host`.
+    pub email_from: Option<String>,
+    /// Blind copy of every confirmation, e.g. an agency inbox.
+    pub email_bcc: Option<String>,
+    /// Webhook delivery is off until this is set.
+    pub webhook_url: Option<String>,
+    /// Signs webhook bodies with HMAC-SHA256 when set.
+    pub webhook_secret: Option<String>,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            send_on_booking: false,
+            smtp_host: None,
+            smtp_port: 587,
+            smtp_security: "starttls".to_string(),
+            smtp_username: None,
+            smtp_password: None,
+            email_from: None,
+            email_bcc: None,
+            webhook_url: None,
+            webhook_secret: None,
+        }
+    }
+}
+
+/// Saved traveller profiles (flights).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProfilesConfig {
+    /// Base64 of the 32-byte AES-256 key profiles are encrypted with; profiles are off without it.
+    pub encryption_key: Option<String>,
+}
+
+/// The audit log of tool calls (flights and stays).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuditConfig {
+    pub enabled: bool,
+    /// Entries older than this are deleted; `0` keeps them forever.
+    pub retention_days: u64,
+    /// Most entries kept, the oldest deleted first; `0` means no limit.
+    pub max_entries: u64,
+    /// Offer the `query_audit_log` admin tool. Any client of the server could read every
+    /// recorded call, bookings included, so it is off unless turned on for an admin deployment.
+    pub query_tool: bool,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            retention_days: 90,
+            max_entries: 100_000,
+            query_tool: false,
+        }
+    }
+}
+
+/// The servers the trip planner calls.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UpstreamConfig {
+    pub flights_mcp_url: String,
+    pub stays_mcp_url: String,
+    /// Bearer token sent to both, for when they require one.
+    pub auth_token: Option<String>,
+}
+
+impl Default for UpstreamConfig {
+    fn default() -> Self {
+        Self {
+            flights_mcp_url: "http://localhost:3001/mcp".to_string(),
+            stays_mcp_url: "http://localhost:3002/mcp".to_string(),
+            auth_token: None,
+        }
+    }
+}
+
+impl Config {
+    /// Defaults, then the file at `path` (if any), then the environment, validated.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let mut config = match path {
+            Some(path) => {
+                let text = fs::read_to_string(path)
+                    .with_context(|| format!("failed to read config file {}", path.display()))?;
+                toml::from_str(&text).with_context(|| format!("invalid config file {}", path.display()))?
+            }
+            None => Self::default(),
+        };
+        config.apply_env()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn apply_env(&mut self) -> Result<()> {
+        let server = &mut self.server;
+        env_value("BIND_ADDR", &mut server.bind_addr)?;
+        env_option("PORT", &mut server.port)?;
+        env_option("MCP_AUTH_TOKEN", &mut server.auth_token)?;
+        env_option("TLS_CERT_PATH", &mut server.tls_cert_path)?;
+        env_option("TLS_KEY_PATH", &mut server.tls_key_path)?;
+        env_value("TOOL_CALL_TIMEOUT_SECS", &mut server.tool_call_timeout_secs)?;
+        env_value("LOG_FORMAT", &mut server.log_format)?;
+        env_value("RESPONSE_VERBOSITY", &mut server.verbosity)?;
+
+        env_value("RATE_LIMIT_PER_MINUTE", &mut self.rate_limit.per_minute)?;
+        env_value("RATE_LIMIT_BURST", &mut self.rate_limit.burst)?;
+
+        env_value("SEARCH_CACHE_TTL_SECS", &mut self.cache.ttl_secs)?;
+        env_value("SEARCH_CACHE_MAX_ENTRIES", &mut self.cache.max_entries)?;
+
+        let duffel = &mut self.duffel;
+        env_value("DUFFEL_MODE", &mut duffel.mode)?;
+        env_option("DUFFEL_API_TOKEN", &mut duffel.api_token)?;
+        env_flag("DUFFEL_TEST_MODE", &mut duffel.test_mode)?;
+        env_value("DUFFEL_API_VERSION", &mut duffel.api_version)?;
+        env_value("DUFFEL_API_BASE_URL", &mut duffel.base_url)?;
+        env_value("DUFFEL_MAX_RETRIES", &mut duffel.max_retries)?;
+        env_value("DUFFEL_MAX_CONCURRENCY", &mut duffel.max_concurrency)?;
+        env_value("DUFFEL_CONNECT_TIMEOUT_SECS", &mut duffel.connect_timeout_secs)?;
+        env_value("DUFFEL_TIMEOUT_SECS", &mut duffel.timeout_secs)?;
+        env_value("DUFFEL_CIRCUIT_FAILURES", &mut duffel.circuit_failures)?;
+        env_value("DUFFEL_CIRCUIT_COOLDOWN_SECS", &mut duffel.circuit_cooldown_secs)?;
+        env_value("DUFFEL_MAX_RESPONSE_BYTES", &mut duffel.max_response_bytes)?;
+        env_value("DUFFEL_OFFER_POLL_TIMEOUT_SECS", &mut duffel.offer_poll_timeout_secs)?;
+        env_option("DUFFEL_WEBHOOK_SECRET", &mut duffel.webhook_secret)?;
+
+        env_value("BOOKEDAI_DB_PATH", &mut self.storage.db_path)?;
+        env_value("SEARCH_RESULTS_TTL_SECS", &mut self.storage.search_results_ttl_secs)?;
+        env_value("FX_RATES_TTL_SECS", &mut self.fx.rates_ttl_secs)?;
+        env_value("PRICE_ALERT_INTERVAL_SECS", &mut self.price_alerts.interval_secs)?;
+        env_value("TRANSFERS_PROVIDER", &mut self.transfers.provider)?;
+        env_value("REVIEWS_PROVIDER", &mut self.reviews.provider)?;
+        env_option("GOOGLE_PLACES_API_KEY", &mut self.reviews.google_places_api_key)?;
+
+        let notifications = &mut self.notifications;
+        env_flag("NOTIFY_ON_BOOKING", &mut notifications.send_on_booking)?;
+        env_option("SMTP_HOST", &mut notifications.smtp_host)?;
+        env_value("SMTP_PORT", &mut notifications.smtp_port)?;
+        env_value("SMTP_SECURITY", &mut notifications.smtp_security)?;
+        env_option("SMTP_USERNAME", &mut notifications.smtp_username)?;
+        env_option("SMTP_PASSWORD", &mut notifications.smtp_password)?;
+        env_option("NOTIFY_EMAIL_FROM", &mut notifications.email_from)?;
+        env_option("NOTIFY_EMAIL_BCC", &mut notifications.email_bcc)?;
+        env_option("NOTIFY_WEBHOOK_URL", &mut notifications.webhook_url)?;
+        env_option("NOTIFY_WEBHOOK_SECRET", &mut notifications.webhook_secret)?;
+        env_option("TRAVELER_PROFILE_KEY", &mut self.profiles.encryption_key)?;
+        env_flag("AUDIT_LOG_ENABLED", &mut self.audit.enabled)?;
+        env_value("AUDIT_LOG_RETENTION_DAYS", &mut self.audit.retention_days)?;
+        env_value("AUDIT_LOG_MAX_ENTRIES", &mut self.audit.max_entries)?;
+        env_flag("AUDIT_QUERY_TOOL_ENABLED", &mut self.audit.query_tool)?;
+
+        env_value("FLIGHTS_MCP_URL", &mut self.upstream.flights_mcp_url)?;
+        env_value("STAYS_MCP_URL", &mut self.upstream.stays_mcp_url)?;
+        env_option("UPSTREAM_MCP_AUTH_TOKEN", &mut self.upstream.auth_token)?;
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<()> {
+        let positive = [
+            ("duffel.max_concurrency", "DUFFEL_MAX_CONCURRENCY", self.duffel.max_concurrency as u64),
+            ("duffel.connect_timeout_secs", "DUFFEL_CONNECT_TIMEOUT_SECS", self.duffel.connect_timeout_secs),
+            ("duffel.timeout_secs", "DUFFEL_TIMEOUT_SECS", self.duffel.timeout_secs),
+            ("duffel.max_response_bytes", "DUFFEL_MAX_RESPONSE_BYTES", self.duffel.max_response_bytes as u64),
+            ("storage.search_results_ttl_secs", "SEARCH_RESULTS_TTL_SECS", self.storage.search_results_ttl_secs),
+            ("price_alerts.interval_secs", "PRICE_ALERT_INTERVAL_SECS", self.price_alerts.interval_secs),
+        ];
+        for (key, var, value) in positive {
+            if value == 0 {
+                bail!("{} ({}) must be greater than 0", key, var);
+            }
+        }
+        let api_version = &self.duffel.api_version;
+        if api_version.is_empty() || !api_version.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')) {
+            bail!(
+                "duffel.api_version (DUFFEL_API_VERSION) must be a version name like {}, got '{}'",
+                duffel_version::BASELINE,
+                api_version
+            );
+        }
+        if self.rate_limit.per_minute > 0 && self.rate_limit.burst == 0 {
+            bail!("rate_limit.burst (RATE_LIMIT_BURST) must be greater than 0 while rate limiting is on");
+        }
+
+        match (&self.server.tls_cert_path, &self.server.tls_key_path) {
+            (Some(cert_path), Some(key_path)) => {
+                for path in [cert_path, key_path] {
+                    if !path.is_file() {
+                        bail!("TLS file not found: {}", path.display());
+                    }
+                }
+            }
+            (None, None) => {}
+            _ => bail!("server.tls_cert_path and server.tls_key_path (TLS_CERT_PATH / TLS_KEY_PATH) must be set together"),
+        }
+
+        let notifications = &self.notifications;
+        if !matches!(notifications.smtp_security.as_str(), "starttls" | "tls" | "none") {
+            bail!(
+                "notifications.smtp_security (SMTP_SECURITY) must be starttls, tls or none, got '{}'",
+                notifications.smtp_security
+            );
+        }
+        if notifications.smtp_host.is_some() && notifications.email_from.is_none() {
+            bail!("notifications.email_from (NOTIFY_EMAIL_FROM) is required with notifications.smtp_host (SMTP_HOST)");
+        }
+        if notifications.smtp_username.is_some() != notifications.smtp_password.is_some() {
+            bail!("notifications.smtp_username and notifications.smtp_password (SMTP_USERNAME / SMTP_PASSWORD) must be set together");
+        }
+
+        if let Some(key) = &self.profiles.encryption_key {
+            if let Err(e) = profiles::decode_key(key) {
+                bail!("profiles.encryption_key (TRAVELER_PROFILE_KEY) is invalid: {}", e);
+            }
+        }
+
+        let mut urls = vec![
+            ("duffel.base_url", "DUFFEL_API_BASE_URL", &self.duffel.base_url),
+            ("upstream.flights_mcp_url", "FLIGHTS_MCP_URL", &self.upstream.flights_mcp_url),
+            ("upstream.stays_mcp_url", "STAYS_MCP_URL", &self.upstream.stays_mcp_url),
+        ];
+        if let Some(webhook_url) = &notifications.webhook_url {
+            urls.push(("notifications.webhook_url", "NOTIFY_WEBHOOK_URL", webhook_url));
+        }
+        for (key, var, url) in urls {
+            let valid = reqwest::Url::parse(url).is_ok_and(|url| matches!(url.scheme(), "http" | "https"));
+            if !valid {
+                bail!("{} ({}) must be an http:// or https:// URL, got '{}'", key, var, url);
+            }
+        }
+        Ok(())
+    }
+
+    /// This configuration as TOML, with secrets masked.
+    pub fn to_toml(&self) -> Result<String> {
+        let mut masked = self.clone();
+        for secret in [
+            &mut masked.server.auth_token,
+            &mut masked.duffel.api_token,
+            &mut masked.duffel.webhook_secret,
+            &mut masked.reviews.google_places_api_key,
+            &mut masked.notifications.smtp_password,
+            &mut masked.notifications.webhook_secret,
+            &mut masked.profiles.encryption_key,
+            &mut masked.upstream.auth_token,
+        ] {
+            if secret.is_some() {
+                *secret = Some(MASKED.to_string());
+            }
+        }
+        Ok(toml::to_string(&masked)?)
+    }
+}
+
+/// `--config <path>` (or `--config=<path>`), then `BOOKEDAI_CONFIG`, then
+/// `config.toml` if it exists.
+fn config_path() -> Result<Option<PathBuf>> {
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            let path = args
+                .next()
+                .ok_or_else(|| anyhow!("--config requires a path"))?;
+            return Ok(Some(PathBuf::from(path)));
+        }
+        if let Some(path) = arg.strip_prefix("--config=") {
+            return Ok(Some(PathBuf::from(path)));
+        }
+    }
+    if let Some(path) = env::var_os("BOOKEDAI_CONFIG").filter(|path| !path.is_empty()) {
+        return Ok(Some(PathBuf::from(path)));
+    }
+    let default = Path::new(DEFAULT_CONFIG_PATH);
+    Ok(default.is_file().then(|| default.to_path_buf()))
+}
+
+/// Load, validate and install this process's configuration.
+///
+/// Call once at startup, before anything reads [`get`]. With `--print-config` the
+/// effective configuration is printed to stdout and the process exits.
+pub fn init() -> Result<&'static Config> {
+    let path = config_path()?;
+    let config = Config::load(path.as_deref())?;
+    if env::args().skip(1).any(|arg| arg == "--print-config") {
+        match &path {
+            Some(path) => println!("# Effective configuration: {} with environment overrides", path.display()),
+            None => println!("# Effective configuration: defaults with environment overrides"),
+        }
+        print!("{}", config.to_toml()?);
+        std::process::exit(0);
+    }
+    Ok(CONFIG.get_or_init(|| config))
+}
+
+/// The configuration installed by [`init`].
+///
+/// Without `init` (e.g. in tools and tests), falls back to the defaults and the
+/// environment, ignoring invalid values.
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(|| Config::load(None).unwrap_or_default())
+}
+
+/// An environment variable's trimmed value; `None` when unset.
+fn env_var(name: &str) -> Option<String> {
+    env::var(name).ok().map(|value| value.trim().to_string())
+}
+
+fn parse<T: FromStr>(name: &str, raw: &str) -> Result<T>
+where
+    T::Err: fmt::Display,
+{
+    raw.parse()
+        .map_err(|e| anyhow!("{} has an invalid value '{}': {}", name, raw, e))
+}
+
+/// Override `value` with `name`, unless it is unset or empty.
+fn env_value<T: FromStr>(name: &str, value: &mut T) -> Result<()>
+where
+    T::Err: fmt::Display,
+{
+    if let Some(raw) = env_var(name).filter(|raw| !raw.is_empty()) {
+        *value = parse(name, &raw)?;
+    }
+    Ok(())
+}
+
+/// Override an optional setting with `name`; an empty variable unsets it.
+fn env_option<T: FromStr>(name: &str, value: &mut Option<T>) -> Result<()>
+where
+    T::Err: fmt::Display,
+{
+    match env_var(name) {
+        Some(raw) if raw.is_empty() => *value = None,
+        Some(raw) => *value = Some(parse(name, &raw)?),
+        None => {}
+    }
+    Ok(())
+}
+
+/// Override a switch with `name`: `true`/`1` or `false`/`0`.
+fn env_flag(name: &str, value: &mut bool) -> Result<()> {
+    match env_var(name).as_deref() {
+        None | Some("") => {}
+        Some("true" | "1") => *value = true,
+        Some("false" | "0") => *value = false,
+        Some(other) => bail!("{} must be true or false, got '{}'", name, other),
+    }
+    Ok(())
+}
@@ -0,0 +1,502 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::Semaphore;
+use tracing::{error, info, info_span, warn, Instrument};
+
+use crate::audit;
+use crate::circuit_breaker::CircuitBreaker;
+use crate::config::{self, DuffelMode};
+use crate::duffel_version::{self, ApiVersion};
+use crate::errors::{DuffelError, ErrorKind};
+use crate::metrics;
+use crate::mock::MockDuffel;
+use crate::retry::{self, RetryPolicy};
+use crate::streaming::{self, ListPage};
+
+/// Default Duffel API location; `duffel.base_url` (`DUFFEL_API_BASE_URL`) overrides it.
+pub const DUFFEL_API_BASE: &str = "https://api.duffel.com";
+
+/// Prefix of Duffel test-mode access tokens; live tokens start `duffel_live_`.
+const TEST_TOKEN_PREFIX: &str = "duffel_test_";
+
+/// Duffel requests in flight at once, across every clone of a client.
+const DEFAULT_MAX_CONCURRENCY: usize = 8;
+
+/// Time allowed to establish a connection to Duffel.
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Time allowed for one request attempt, from sending it to reading the whole body.
+/// Offer searches are the slowest calls and usually finish well within this.
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Largest response body read from Duffel; a few hundred offers with full segment
+/// data come to a few megabytes.
+const DEFAULT_MAX_RESPONSE_BYTES: usize = 32 * 1024 * 1024;
+
+/// A response body, read up to the client's size limit.
+struct Body {
+    bytes: Vec<u8>,
+    /// Whether the rest of the body was dropped at the limit.
+    truncated: bool,
+}
+
+/// Authenticated client for the Duffel REST API.
+///
+/// Cheap to clone: the underlying `reqwest::Client` shares its connection pool, and
+/// clones share rate-limit state so one exhausted window pauses every caller.
+/// Clones also share a concurrency cap; requests beyond it wait for a free slot, and
+/// a [`CircuitBreaker`] that fails calls fast while Duffel is down.
+///
+/// Every call carries the `Duffel-Version` of its [`ApiVersion`], which also adapts
+/// payloads and responses for that version (see [`crate::duffel_version`]).
+///
+/// Bodies are read up to a size limit. Past it, [`get_list`](Self::get_list) and
+/// [`post_list`](Self::post_list) keep the items that arrived whole, while other
+/// calls fail rather than buffer an unbounded response.
+///
+/// A [`mock`](Self::mock) client answers from embedded fixtures instead of calling Duffel.
+#[derive(Debug, Clone)]
+pub struct DuffelClient {
+    api_token: String,
+    base_url: String,
+    version: Arc<dyn ApiVersion>,
+    mock: Option<MockDuffel>,
+    client: reqwest::Client,
+    retry_policy: RetryPolicy,
+    paused_until: Arc<Mutex<Option<Instant>>>,
+    permits: Arc<Semaphore>,
+    breaker: Option<CircuitBreaker>,
+    max_response_bytes: usize,
+}
+
+fn http_client(connect_timeout: Duration, request_timeout: Duration) -> reqwest::Client {
+    reqwest::Client::builder()
+        .connect_timeout(connect_timeout)
+        .timeout(request_timeout)
+        .build()
+        .expect("HTTP client configuration is valid")
+}
+
+impl DuffelClient {
+    pub fn new(api_token: impl Into<String>) -> Self {
+        Self {
+            api_token: api_token.into(),
+            base_url: DUFFEL_API_BASE.to_string(),
+            version: Arc::new(duffel_version::V2),
+            mock: None,
+            client: http_client(DEFAULT_CONNECT_TIMEOUT, DEFAULT_REQUEST_TIMEOUT),
+            retry_policy: RetryPolicy::default(),
+            paused_until: Arc::new(Mutex::new(None)),
+            permits: Arc::new(Semaphore::new(DEFAULT_MAX_CONCURRENCY)),
+            breaker: None,
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+        }
+    }
+
+    /// A client that serves canned responses (see [`crate::mock`]) and never calls Duffel.
+    pub fn mock() -> Self {
+        Self {
+            mock: Some(MockDuffel::new()),
+            ..Self::new("")
+        }
+    }
+
+    /// Build a client from `[duffel]` in the config.
+    ///
+    /// `mode = "mock"` (`DUFFEL_MODE=mock`) builds a [`mock`](Self::mock) client instead,
+    /// with no token needed, though its answers are still held to `max_response_bytes`
+    /// (`DUFFEL_MAX_RESPONSE_BYTES`). Otherwise `api_token` (`DUFFEL_API_TOKEN`) is required, and
+    /// with `test_mode` (`DUFFEL_TEST_MODE=true`) only a Duffel test token is accepted,
+    /// so nothing is ever booked for real. The API location and version, retries, the concurrency
+    /// cap, the timeouts and the circuit breaker ([`CircuitBreaker::from_config`]) come
+    /// from the same section.
+    pub fn from_config() -> Result<Self> {
+        let duffel = &config::get().duffel;
+        if duffel.mode == DuffelMode::Mock {
+            info!("DUFFEL_MODE=mock: answering Duffel calls from built-in fixtures");
+            return Ok(Self::mock().with_max_response_bytes(duffel.max_response_bytes));
+        }
+
+        let api_token = duffel
+            .api_token
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("duffel.api_token (DUFFEL_API_TOKEN) must be set"))?;
+        let test_token = api_token.starts_with(TEST_TOKEN_PREFIX);
+        if duffel.test_mode && !test_token {
+            bail!("DUFFEL_TEST_MODE is set, but DUFFEL_API_TOKEN is not a Duffel test token ({}...)", TEST_TOKEN_PREFIX);
+        }
+        if test_token {
+            info!("Using a Duffel test token: searches and bookings run against Duffel's sandbox");
+        }
+
+        let retry_policy = RetryPolicy {
+            max_retries: duffel.max_retries,
+            ..RetryPolicy::default()
+        };
+        Ok(Self::new(api_token)
+            .with_base_url(&duffel.base_url)
+            .with_api_version(duffel_version::for_name(&duffel.api_version))
+            .with_retry_policy(retry_policy)
+            .with_timeouts(
+                Duration::from_secs(duffel.connect_timeout_secs),
+                Duration::from_secs(duffel.timeout_secs),
+            )
+            .with_circuit_breaker(CircuitBreaker::from_config())
+            .with_max_concurrency(duffel.max_concurrency)
+            .with_max_response_bytes(duffel.max_response_bytes))
+    }
+
+    /// Send requests to `base_url` (e.g. `http://127.0.0.1:8080`) instead of [`DUFFEL_API_BASE`].
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Target another Duffel API version than [`duffel_version::BASELINE`].
+    pub fn with_api_version(mut self, version: Arc<dyn ApiVersion>) -> Self {
+        self.version = version;
+        self
+    }
+
+    pub fn api_version(&self) -> &str {
+        self.version.name()
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.permits = Arc::new(Semaphore::new(max_concurrency.max(1)));
+        self
+    }
+
+    /// Replace the connect timeout and the per-attempt request timeout.
+    pub fn with_timeouts(mut self, connect_timeout: Duration, request_timeout: Duration) -> Self {
+        self.client = http_client(connect_timeout, request_timeout);
+        self
+    }
+
+    pub fn with_max_response_bytes(mut self, max_response_bytes: usize) -> Self {
+        self.max_response_bytes = max_response_bytes.max(1);
+        self
+    }
+
+    pub fn with_circuit_breaker(mut self, breaker: Option<CircuitBreaker>) -> Self {
+        self.breaker = breaker;
+        self
+    }
+
+    /// `GET` a Duffel path (e.g. `/air/offers?offer_request_id=...`) and return the JSON body.
+    pub async fn get(&self, path: &str) -> Result<Value> {
+        let request = self.client.get(self.url(path));
+        let body = self.send("GET", path, None, request, true).await?;
+        self.json(path, body)
+    }
+
+    /// `GET` a Duffel path with URL-encoded query parameters.
+    pub async fn get_with_query<Q: Serialize + ?Sized>(&self, path: &str, query: &Q) -> Result<Value> {
+        let request = self.client.get(self.url(path)).query(query);
+        let body = self.send("GET", path, None, request, true).await?;
+        self.json(path, body)
+    }
+
+    /// `GET` a Duffel list and parse the items at `items_at` (e.g. `["data"]`) one by one
+    /// into `T`; see [`streaming::parse_list`].
+    ///
+    /// A response over the size limit keeps the items that arrived whole and is
+    /// marked [`truncated`](ListPage::truncated) rather than failing.
+    pub async fn get_list<T: DeserializeOwned, Q: Serialize + ?Sized>(
+        &self,
+        path: &str,
+        query: &Q,
+        items_at: &[&str],
+        kind: &str,
+    ) -> Result<ListPage<T>> {
+        let request = self.client.get(self.url(path)).query(query);
+        let body = self.send("GET", path, None, request, true).await?;
+        self.list(path, body, items_at, kind)
+    }
+
+    /// `POST` a JSON payload to a Duffel path and return the JSON body.
+    ///
+    /// POSTs are only retried when Duffel did not process them (429s and connection failures).
+    pub async fn post(&self, path: &str, payload: &Value) -> Result<Value> {
+        let adapted = self.version.adapt_request(path, payload.clone());
+        let request = self
+            .client
+            .post(self.url(path))
+            .header("Content-Type", "application/json")
+            .json(&adapted);
+        let body = self.send("POST", path, Some(payload), request, false).await?;
+        self.json(path, body)
+    }
+
+    /// `POST` a JSON payload and parse the list in the response like [`get_list`](Self::get_list).
+    pub async fn post_list<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        payload: &Value,
+        items_at: &[&str],
+        kind: &str,
+    ) -> Result<ListPage<T>> {
+        let adapted = self.version.adapt_request(path, payload.clone());
+        let request = self
+            .client
+            .post(self.url(path))
+            .header("Content-Type", "application/json")
+            .json(&adapted);
+        let body = self.send("POST", path, Some(payload), request, false).await?;
+        self.list(path, body, items_at, kind)
+    }
+
+    /// `POST` a JSON payload with Duffel's `Idempotency-Key` header.
+    ///
+    /// Duffel answers a repeated key with the original result instead of acting
+    /// twice, so these POSTs are retried like GETs.
+    pub async fn post_idempotent(&self, path: &str, payload: &Value, idempotency_key: &str) -> Result<Value> {
+        let adapted = self.version.adapt_request(path, payload.clone());
+        let request = self
+            .client
+            .post(self.url(path))
+            .header("Content-Type", "application/json")
+            .header("Idempotency-Key", idempotency_key)
+            .json(&adapted);
+        let body = self.send("POST", path, Some(payload), request, true).await?;
+        self.json(path, body)
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    /// Parse a whole body, which must have fit within the size limit.
+    ///
+    /// The mock speaks [`duffel_version::BASELINE`], so its answers are not adapted.
+    fn json(&self, path: &str, body: Body) -> Result<Value> {
+        if body.truncated {
+            return Err(DuffelError::new(
+                ErrorKind::Other,
+                None,
+                format!(
+                    "Duffel's response to {} is larger than duffel.max_response_bytes (DUFFEL_MAX_RESPONSE_BYTES, {} bytes)",
+                    path, self.max_response_bytes
+                ),
+            )
+            .into());
+        }
+        let value = serde_json::from_slice(&body.bytes)?;
+        Ok(match self.mock {
+            Some(_) => value,
+            None => self.version.adapt_response(path, value),
+        })
+    }
+
+    fn list<T: DeserializeOwned>(&self, path: &str, body: Body, items_at: &[&str], kind: &str) -> Result<ListPage<T>> {
+        let adapt = |item: &_| match self.mock {
+            Some(_) => None,
+            None => self.version.adapt_list_item(path, item),
+        };
+        let page = streaming::parse_list(&body.bytes, items_at, kind, &adapt, body.truncated)?;
+        if page.truncated {
+            warn!(
+                "Duffel's response to {} passed {} bytes; kept the first {} {}s",
+                path, self.max_response_bytes, page.received, kind
+            );
+        }
+        Ok(page)
+    }
+
+    /// Cut a body at the size limit.
+    fn limited(&self, mut bytes: Vec<u8>) -> Body {
+        let truncated = bytes.len() > self.max_response_bytes;
+        bytes.truncate(self.max_response_bytes);
+        Body { bytes, truncated }
+    }
+
+    /// Read a response body in chunks, stopping at the size limit.
+    async fn read_body(&self, mut response: reqwest::Response) -> Result<Body> {
+        let limit = self.max_response_bytes;
+        let expected = response.content_length().unwrap_or(0).min(limit as u64) as usize;
+        let mut bytes = Vec::with_capacity(expected);
+        while let Some(chunk) = response.chunk().await? {
+            if bytes.len() + chunk.len() > limit {
+                bytes.extend_from_slice(&chunk[..limit - bytes.len()]);
+                return Ok(Body { bytes, truncated: true });
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        Ok(Body { bytes, truncated: false })
+    }
+
+    /// Wait out a rate-limit window reported by an earlier response.
+    async fn wait_for_rate_limit(&self) {
+        let paused_until = *self.paused_until.lock().unwrap();
+        if let Some(until) = paused_until {
+            let now = Instant::now();
+            if until > now {
+                warn!("Duffel rate limit exhausted, waiting {:?}", until - now);
+                tokio::time::sleep(until - now).await;
+            }
+        }
+    }
+
+    fn pause_for(&self, delay: std::time::Duration) {
+        let until = Instant::now() + delay;
+        let mut paused_until = self.paused_until.lock().unwrap();
+        if paused_until.is_none_or(|current| current < until) {
+            *paused_until = Some(until);
+        }
+    }
+
+    /// Tell the circuit breaker whether Duffel answered a call.
+    fn record_outcome(&self, reachable: bool) {
+        if let Some(breaker) = &self.breaker {
+            if reachable {
+                breaker.record_success();
+            } else {
+                breaker.record_failure();
+            }
+        }
+    }
+
+    /// Send a request with retries, in a `duffel` span nested under the caller's
+    /// request span so its logs carry the MCP request ID.
+    async fn send(
+        &self,
+        method: &'static str,
+        path: &str,
+        payload: Option<&Value>,
+        request: reqwest::RequestBuilder,
+        idempotent: bool,
+    ) -> Result<Body> {
+        if let Some(mock) = &self.mock {
+            let response = mock.respond(method, path, payload)?;
+            if let Some(request_id) = response["meta"]["request_id"].as_str() {
+                audit::note_duffel_request(request_id);
+            }
+            return Ok(self.limited(serde_json::to_vec(&response)?));
+        }
+        if let Some(breaker) = &self.breaker {
+            if let Err(remaining) = breaker.check() {
+                return Err(DuffelError::new(
+                    ErrorKind::Unavailable,
+                    None,
+                    format!(
+                        "Duffel is unavailable after repeated failures; not calling it again for {}s",
+                        remaining.as_secs().max(1)
+                    ),
+                )
+                .into());
+            }
+        }
+        let endpoint = metrics::endpoint(path);
+        let span = info_span!("duffel", method, endpoint = %endpoint);
+        self.send_with_retries(method, path, request, idempotent)
+            .instrument(span)
+            .await
+    }
+
+    async fn send_with_retries(
+        &self,
+        method: &'static str,
+        path: &str,
+        request: reqwest::RequestBuilder,
+        idempotent: bool,
+    ) -> Result<Body> {
+        let request = request
+            .header("Authorization", format!("Bearer {}", self.api_token))
+            .header("Accept", "application/json")
+            .header("Duffel-Version", self.version.name());
+
+        let max_retries = self.retry_policy.max_retries;
+        let mut attempt = 0;
+        loop {
+            self.wait_for_rate_limit().await;
+            // Held until the body is read; released before any backoff sleep
+            let permit = self.permits.acquire().await?;
+
+            let attempt_request = request
+                .try_clone()
+                .ok_or_else(|| anyhow::anyhow!("Duffel request body cannot be retried"))?;
+
+            let started = Instant::now();
+            let response = match attempt_request.send().await {
+                Ok(response) => response,
+                Err(e) => {
+                    metrics::record_duffel_request(method, path, None, started.elapsed());
+                    // Connection failures never reached Duffel, so any method may be retried
+                    let retryable = e.is_connect() || (idempotent && e.is_timeout());
+                    if retryable && attempt < max_retries {
+                        let delay = self.retry_policy.delay(attempt, None);
+                        warn!("Duffel request failed ({}), retrying in {:?}", e, delay);
+                        drop(permit);
+                        tokio::time::sleep(delay).await;
+                        attempt += 1;
+                        continue;
+                    }
+                    self.record_outcome(false);
+                    return Err(DuffelError::unreachable(&e).with_attempts(attempt + 1).into());
+                }
+            };
+
+            let status = response.status();
+            if let Some(request_id) = response.headers().get("x-request-id").and_then(|id| id.to_str().ok()) {
+                audit::note_duffel_request(request_id);
+            }
+            metrics::record_duffel_request(method, path, Some(status.as_u16()), started.elapsed());
+            let server_delay = retry::server_delay(response.headers());
+            if retry::rate_limit_exhausted(response.headers()) {
+                if let Some(delay) = server_delay {
+                    self.pause_for(delay);
+                }
+            }
+
+            if status.is_success() {
+                self.record_outcome(true);
+                return self.read_body(response).await;
+            }
+
+            let rate_limited = status == reqwest::StatusCode::TOO_MANY_REQUESTS;
+            let retryable = rate_limited || (idempotent && status.is_server_error());
+            if retryable && attempt < max_retries {
+                let delay = self.retry_policy.delay(attempt, server_delay);
+                if rate_limited {
+                    self.pause_for(delay);
+                }
+                warn!(
+                    "Duffel API returned {}, retrying in {:?} (attempt {}/{})",
+                    status,
+                    delay,
+                    attempt + 1,
+                    max_retries
+                );
+                drop(permit);
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+                continue;
+            }
+
+            // Client errors and rate limiting still show Duffel is up
+            self.record_outcome(!status.is_server_error());
+            // Error bodies are held to the same limit; a truncated one still names the status
+            let body = self.read_body(response).await?;
+            let error_text = String::from_utf8_lossy(&body.bytes);
+            let duffel_error = DuffelError::from_response(status.as_u16(), &error_text).with_attempts(attempt + 1);
+            if duffel_version::is_version_mismatch(&duffel_error) {
+                error!(
+                    "Duffel rejected API version '{}' ({}); set duffel.api_version (DUFFEL_API_VERSION) to a version Duffel supports",
+                    self.version.name(),
+                    duffel_error
+                );
+            }
+            return Err(duffel_error.into());
+        }
+    }
+}
@@ -0,0 +1,258 @@
+//! Typed views of the Duffel API payloads the servers read.
+//!
+//! Only the fields we use are modelled; everything else is ignored. Fields Duffel
+//! may omit or null out are `Option`s or default to empty, so a single unusual
+//! offer fails on its own rather than taking the whole response down with it.
+
+use std::fmt;
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::warn;
+
+/// Deserialize every item of a Duffel `data` array, skipping (and logging) the ones
+/// that do not match `T`. Returns the parsed items and one warning per skipped item.
+pub fn parse_each<T: DeserializeOwned>(items: &[Value], kind: &str) -> (Vec<T>, Vec<String>) {
+    let mut parsed = Vec::with_capacity(items.len());
+    let mut skipped = Vec::new();
+    for item in items {
+        match T::deserialize(item) {
+            Ok(value) => parsed.push(value),
+            Err(e) => skipped.push(skipped_item(kind, item["id"].as_str(), &e)),
+        }
+    }
+    (parsed, skipped)
+}
+
+/// Log and return the warning for a `kind` item Duffel sent in an unexpected shape.
+pub fn skipped_item(kind: &str, id: Option<&str>, error: &dyn fmt::Display) -> String {
+    let warning = format!("Skipped {} {}: {}", kind, id.unwrap_or("without id"), error);
+    warn!("{}", warning);
+    warning
+}
+
+/// A priced itinerary from `GET /air/offers`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Offer {
+    pub id: String,
+    pub total_amount: String,
+    pub total_currency: String,
+    #[serde(default)]
+    pub slices: Vec<Slice>,
+    pub owner: Option<Carrier>,
+    pub expires_at: Option<String>,
+    #[serde(default)]
+    pub passengers: Vec<OfferPassenger>,
+    pub conditions: Option<Conditions>,
+}
+
+/// A traveller on an offer; segment fares and baggage refer to them by `id`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OfferPassenger {
+    pub id: String,
+    /// `adult`, `child`, or `infant_without_seat`; unset when only an age was given.
+    #[serde(rename = "type")]
+    pub passenger_type: Option<String>,
+    pub age: Option<u32>,
+}
+
+/// Fare rules for an offer or one of its slices.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Conditions {
+    pub change_before_departure: Option<Condition>,
+    pub refund_before_departure: Option<Condition>,
+}
+
+/// Whether a change or refund is allowed, and at what cost.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Condition {
+    pub allowed: bool,
+    pub penalty_amount: Option<String>,
+    pub penalty_currency: Option<String>,
+}
+
+/// One leg of an offer, made of one or more segments.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Slice {
+    pub id: Option<String>,
+    pub origin: Place,
+    pub destination: Place,
+    /// ISO 8601 duration, e.g. `PT7H20M`.
+    pub duration: Option<String>,
+    /// Airline's name for the fare, e.g. `Economy Light`.
+    pub fare_brand_name: Option<String>,
+    pub conditions: Option<Conditions>,
+    #[serde(default)]
+    pub segments: Vec<Segment>,
+}
+
+/// A single flight.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Segment {
+    pub id: Option<String>,
+    pub origin: Place,
+    pub destination: Place,
+    pub departing_at: String,
+    pub arriving_at: String,
+    pub duration: Option<String>,
+    pub marketing_carrier: Carrier,
+    pub marketing_carrier_flight_number: String,
+    pub operating_carrier: Option<Carrier>,
+    pub aircraft: Option<Aircraft>,
+    #[serde(default)]
+    pub passengers: Vec<SegmentPassenger>,
+}
+
+/// One passenger's cabin and included baggage on a segment.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SegmentPassenger {
+    pub passenger_id: String,
+    pub cabin_class: Option<String>,
+    pub cabin_class_marketing_name: Option<String>,
+    pub fare_basis_code: Option<String>,
+    #[serde(default)]
+    pub baggages: Vec<Baggage>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Baggage {
+    /// `checked` or `carry_on`.
+    #[serde(rename = "type")]
+    pub baggage_type: String,
+    pub quantity: u32,
+}
+
+/// An airport or city.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Place {
+    pub iata_code: String,
+    pub name: Option<String>,
+    pub city_name: Option<String>,
+    /// IANA time zone of the airport, e.g. `Europe/London`; Duffel's times are local to it.
+    pub time_zone: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Carrier {
+    pub name: String,
+    pub iata_code: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Aircraft {
+    pub name: String,
+}
+
+/// One accommodation from `POST /stays/search`, or with every room and rate from
+/// `fetch_all_rates`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct StaysSearchResult {
+    pub id: String,
+    pub accommodation: Accommodation,
+    pub cheapest_rate_total_amount: Option<String>,
+    pub cheapest_rate_currency: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Accommodation {
+    pub id: Option<String>,
+    pub name: String,
+    pub description: Option<String>,
+    /// Star rating, 1-5.
+    pub rating: Option<f64>,
+    pub review_score: Option<f64>,
+    pub location: Option<Location>,
+    #[serde(default)]
+    pub amenities: Vec<Amenity>,
+    #[serde(default)]
+    pub photos: Vec<Photo>,
+    pub check_in_information: Option<CheckInInformation>,
+    pub phone_number: Option<String>,
+    pub email: Option<String>,
+    /// The hotel group the property belongs to, if any.
+    pub chain: Option<Chain>,
+    #[serde(default)]
+    pub rooms: Vec<Room>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Chain {
+    pub name: String,
+}
+
+/// A property matching a name, from `POST /stays/accommodation/suggestions`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AccommodationSuggestion {
+    pub accommodation_id: String,
+    pub accommodation_name: String,
+    pub accommodation_location: Option<Location>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Photo {
+    pub url: String,
+}
+
+/// Local times, e.g. `15:00`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CheckInInformation {
+    pub check_in_after_time: Option<String>,
+    pub check_in_before_time: Option<String>,
+    pub check_out_before_time: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Location {
+    pub address: Option<Address>,
+    pub geographic_coordinates: Option<Coordinates>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Address {
+    pub line_one: Option<String>,
+    pub city_name: Option<String>,
+    pub region: Option<String>,
+    pub postal_code: Option<String>,
+    pub country_code: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Coordinates {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Amenity {
+    #[serde(rename = "type")]
+    pub amenity_type: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Room {
+    pub name: Option<String>,
+    #[serde(default)]
+    pub rates: Vec<Rate>,
+}
+
+/// A bookable price for a room.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Rate {
+    pub id: String,
+    pub total_amount: String,
+    pub total_currency: String,
+    /// Meal plan, e.g. `room_only` or `breakfast`.
+    pub board_type: Option<String>,
+    /// Refunds by deadline; empty for non-refundable rates.
+    #[serde(default)]
+    pub cancellation_timeline: Vec<CancellationWindow>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CancellationWindow {
+    pub refund_amount: String,
+    pub currency: String,
+    pub before: String,
+}
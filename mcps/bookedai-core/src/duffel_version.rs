@@ -0,0 +1,91 @@
+//! Duffel API versions: the `Duffel-Version` header sent with every call, and the
+//! payload and response differences between versions.
+//!
+//! Call sites build payloads and parse responses in the shape of [`BASELINE`], the
+//! version this crate is written against. An [`ApiVersion`] rewrites payloads on the
+//! way out and responses on the way in, so targeting another version is a matter of
+//! `duffel.api_version` (`DUFFEL_API_VERSION`) plus, where that version differs, an
+//! implementation here, rather than changes to every caller.
+
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use serde_json::value::RawValue;
+use serde_json::Value;
+use tracing::warn;
+
+use crate::errors::DuffelError;
+
+/// The version payloads and parsers throughout the crate are written for.
+pub const BASELINE: &str = "v2";
+
+/// One Duffel API version and how it differs from [`BASELINE`].
+///
+/// Every hook defaults to passing the JSON through unchanged.
+pub trait ApiVersion: Debug + Send + Sync {
+    /// The `Duffel-Version` header value.
+    fn name(&self) -> &str;
+
+    /// Rewrite a request payload written for [`BASELINE`] into this version's shape.
+    fn adapt_request(&self, _path: &str, payload: Value) -> Value {
+        payload
+    }
+
+    /// Rewrite a successful response body into the shape [`BASELINE`] parsers expect.
+    fn adapt_response(&self, _path: &str, body: Value) -> Value {
+        body
+    }
+
+    /// Rewrite one item of a list response read item by item (see [`crate::streaming`]),
+    /// or `None` when it needs no change.
+    fn adapt_list_item(&self, _path: &str, _item: &RawValue) -> Option<Value> {
+        None
+    }
+}
+
+/// Duffel API v2, which call sites already speak.
+#[derive(Debug, Clone, Copy)]
+pub struct V2;
+
+impl ApiVersion for V2 {
+    fn name(&self) -> &str {
+        BASELINE
+    }
+}
+
+/// A version with no known differences from [`BASELINE`]: sent as configured, with
+/// payloads and responses untouched.
+#[derive(Debug, Clone)]
+pub struct Unrecognised(String);
+
+impl ApiVersion for Unrecognised {
+    fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+/// The [`ApiVersion`] for a `duffel.api_version` value.
+///
+/// Versions without an implementation here are used as they are, with a warning,
+/// so a compatible new version needs only the config change.
+pub fn for_name(name: &str) -> Arc<dyn ApiVersion> {
+    match name {
+        BASELINE => Arc::new(V2),
+        other => {
+            warn!(
+                "Duffel API version '{}' is not one this server knows ({} is); sending requests in the {} shape unchanged",
+                other, BASELINE, BASELINE
+            );
+            Arc::new(Unrecognised(other.to_string()))
+        }
+    }
+}
+
+/// Whether Duffel rejected a call because of its `Duffel-Version` header, e.g. a
+/// version it no longer supports.
+pub fn is_version_mismatch(error: &DuffelError) -> bool {
+    error.errors.iter().any(|detail| {
+        detail.code.as_deref().is_some_and(|code| code.contains("version"))
+            || detail.message.contains("Duffel-Version")
+    })
+}
@@ -0,0 +1,323 @@
+//! Duffel failures, classified so clients can tell them apart without reading messages.
+//!
+//! [`DuffelClient`](crate::DuffelClient) fails with a [`DuffelError`] (inside the
+//! `anyhow::Error`) built from Duffel's `errors` array rather than the raw response
+//! body. [`rpc_error`] turns it into a JSON-RPC error with a code per kind of failure
+//! and the details in `error.data`:
+//!
+//! | Kind | Code | Duffel error types / statuses |
+//! |------|------|-------------------------------|
+//! | `validation_error` | -32010 | `validation_error`, `invalid_request_error`, 400, 422 |
+//! | `authentication_error` | -32011 | `authentication_error`, 401, 403 |
+//! | `rate_limit_error` | -32012 | `rate_limit_error`, 429 |
+//! | `airline_error` | -32013 | `airline_error` |
+//! | `not_found` | -32014 | 404 |
+//! | `invalid_state_error` | -32015 | `invalid_state_error`, 409 (e.g. an offer already booked) |
+//! | `unavailable` | -32016 | `api_error`, 5xx, timeouts, connection failures, open circuit |
+//!
+//! Anything else, including errors that did not come from Duffel, stays a plain
+//! `-32000` server error.
+//!
+//! Searches that only partly fail (some results unreadable, some follow-up calls
+//! failed) still succeed, listing what went wrong as `warnings`.
+
+use std::fmt;
+
+use serde_json::{json, Value};
+
+use crate::jsonrpc::{self, RpcError};
+
+/// Longest non-JSON error body quoted in a message.
+const MAX_BODY_CHARS: usize = 200;
+
+/// Most warnings listed by [`append_warnings`]; all of them are in `structuredContent`.
+const WARNINGS_SHOWN: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// Duffel rejected the request's content.
+    Validation,
+    /// The API token is missing, invalid, or lacks access.
+    Authentication,
+    /// Duffel's rate limit was still exhausted after retrying.
+    RateLimit,
+    /// The airline refused or failed the request.
+    Airline,
+    NotFound,
+    /// The request conflicts with the resource's state, e.g. an expired offer.
+    InvalidState,
+    /// Duffel could not be reached, timed out, or failed on its side.
+    Unavailable,
+    Other,
+}
+
+impl ErrorKind {
+    fn from_duffel_type(error_type: &str) -> Option<Self> {
+        match error_type {
+            "validation_error" | "invalid_request_error" => Some(Self::Validation),
+            "authentication_error" => Some(Self::Authentication),
+            "rate_limit_error" => Some(Self::RateLimit),
+            "airline_error" => Some(Self::Airline),
+            "invalid_state_error" => Some(Self::InvalidState),
+            "api_error" => Some(Self::Unavailable),
+            _ => None,
+        }
+    }
+
+    fn from_status(status: u16) -> Self {
+        match status {
+            400 | 422 => Self::Validation,
+            401 | 403 => Self::Authentication,
+            404 => Self::NotFound,
+            409 => Self::InvalidState,
+            429 => Self::RateLimit,
+            500..=599 => Self::Unavailable,
+            _ => Self::Other,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Validation => "validation_error",
+            Self::Authentication => "authentication_error",
+            Self::RateLimit => "rate_limit_error",
+            Self::Airline => "airline_error",
+            Self::NotFound => "not_found",
+            Self::InvalidState => "invalid_state_error",
+            Self::Unavailable => "unavailable",
+            Self::Other => "api_error",
+        }
+    }
+
+    pub fn rpc_code(self) -> i64 {
+        match self {
+            Self::Validation => jsonrpc::DUFFEL_VALIDATION_ERROR,
+            Self::Authentication => jsonrpc::DUFFEL_AUTHENTICATION_ERROR,
+            Self::RateLimit => jsonrpc::DUFFEL_RATE_LIMITED,
+            Self::Airline => jsonrpc::DUFFEL_AIRLINE_ERROR,
+            Self::NotFound => jsonrpc::DUFFEL_NOT_FOUND,
+            Self::InvalidState => jsonrpc::DUFFEL_INVALID_STATE,
+            Self::Unavailable => jsonrpc::DUFFEL_UNAVAILABLE,
+            Self::Other => jsonrpc::SERVER_ERROR,
+        }
+    }
+
+    /// Whether the same call may succeed if tried again later.
+    pub fn retryable(self) -> bool {
+        matches!(self, Self::RateLimit | Self::Unavailable)
+    }
+}
+
+/// One entry of Duffel's `errors` array.
+#[derive(Debug, Clone)]
+pub struct ErrorDetail {
+    pub title: Option<String>,
+    pub message: String,
+    pub code: Option<String>,
+    /// The request field Duffel objected to, when it says.
+    pub field: Option<String>,
+}
+
+impl ErrorDetail {
+    fn from_duffel(error: &Value) -> Option<Self> {
+        let title = error["title"].as_str().map(str::to_string);
+        let message = error["message"].as_str().map(str::to_string).or_else(|| title.clone())?;
+        Some(Self {
+            title,
+            message,
+            code: error["code"].as_str().map(str::to_string),
+            field: error["source"]["field"]
+                .as_str()
+                .or_else(|| error["source"]["pointer"].as_str())
+                .map(str::to_string),
+        })
+    }
+
+    fn to_value(&self) -> Value {
+        let mut detail = json!({ "message": self.message });
+        if let Some(title) = &self.title {
+            detail["title"] = json!(title);
+        }
+        if let Some(code) = &self.code {
+            detail["code"] = json!(code);
+        }
+        if let Some(field) = &self.field {
+            detail["field"] = json!(field);
+        }
+        detail
+    }
+}
+
+/// A failed Duffel call.
+#[derive(Debug, Clone)]
+pub struct DuffelError {
+    pub kind: ErrorKind,
+    /// HTTP status; `None` when Duffel never answered.
+    pub status: Option<u16>,
+    /// At least one entry, the first being the main cause.
+    pub errors: Vec<ErrorDetail>,
+    /// Duffel's `meta.request_id`, for support requests.
+    pub request_id: Option<String>,
+    pub documentation_url: Option<String>,
+    /// Attempts made, counting retries.
+    pub attempts: u32,
+}
+
+impl DuffelError {
+    pub fn new(kind: ErrorKind, status: Option<u16>, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            status,
+            errors: vec![ErrorDetail {
+                title: None,
+                message: message.into(),
+                code: None,
+                field: None,
+            }],
+            request_id: None,
+            documentation_url: None,
+            attempts: 1,
+        }
+    }
+
+    /// Parse an error response; bodies that are not Duffel's error JSON are quoted, shortened.
+    pub fn from_response(status: u16, body: &str) -> Self {
+        let parsed: Value = serde_json::from_str(body).unwrap_or(Value::Null);
+        let errors: Vec<ErrorDetail> = parsed["errors"]
+            .as_array()
+            .map(|errors| errors.iter().filter_map(ErrorDetail::from_duffel).collect())
+            .unwrap_or_default();
+        if errors.is_empty() {
+            let body = body.trim();
+            let message = match body.char_indices().nth(MAX_BODY_CHARS) {
+                Some((end, _)) => format!("{}...", &body[..end]),
+                None if body.is_empty() => canonical_reason(status).to_string(),
+                None => body.to_string(),
+            };
+            return Self::new(ErrorKind::from_status(status), Some(status), message);
+        }
+
+        let first = &parsed["errors"][0];
+        let kind = first["type"]
+            .as_str()
+            .and_then(ErrorKind::from_duffel_type)
+            .unwrap_or_else(|| ErrorKind::from_status(status));
+        Self {
+            kind,
+            status: Some(status),
+            errors,
+            request_id: parsed["meta"]["request_id"].as_str().map(str::to_string),
+            documentation_url: first["documentation_url"].as_str().map(str::to_string),
+            attempts: 1,
+        }
+    }
+
+    /// Duffel could not be reached or did not answer in time.
+    pub fn unreachable(error: &reqwest::Error) -> Self {
+        let message = if error.is_timeout() {
+            "Duffel did not respond in time".to_string()
+        } else {
+            format!("Could not reach Duffel: {}", error)
+        };
+        Self::new(ErrorKind::Unavailable, None, message)
+    }
+
+    pub fn with_attempts(mut self, attempts: u32) -> Self {
+        self.attempts = attempts;
+        self
+    }
+
+    /// The JSON-RPC error for a tool call that failed with this error.
+    ///
+    /// `context` says what failed, e.g. `Flight search failed`.
+    pub fn to_rpc_error(&self, context: &str) -> RpcError {
+        let mut data = json!({
+            "source": "duffel",
+            "type": self.kind.as_str(),
+            "retryable": self.kind.retryable(),
+            "attempts": self.attempts
+        });
+        if let Some(status) = self.status {
+            data["status"] = json!(status);
+        }
+        if let Some(first) = self.errors.first() {
+            if let Some(code) = &first.code {
+                data["code"] = json!(code);
+            }
+            if let Some(field) = &first.field {
+                data["field"] = json!(field);
+            }
+        }
+        if self.errors.len() > 1 {
+            data["errors"] = self.errors.iter().map(ErrorDetail::to_value).collect();
+        }
+        if let Some(request_id) = &self.request_id {
+            data["request_id"] = json!(request_id);
+        }
+        if let Some(url) = &self.documentation_url {
+            data["documentation_url"] = json!(url);
+        }
+        RpcError::new(self.kind.rpc_code(), format!("{}: {}", context, self)).with_data(data)
+    }
+}
+
+fn canonical_reason(status: u16) -> &'static str {
+    reqwest::StatusCode::from_u16(status)
+        .ok()
+        .and_then(|status| status.canonical_reason())
+        .unwrap_or("Unknown status")
+}
+
+impl fmt::Display for DuffelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let summary = self
+            .errors
+            .first()
+            .map(|error| match &error.title {
+                Some(title) if *title != error.message => format!("{}: {}", title, error.message),
+                _ => error.message.clone(),
+            })
+            .unwrap_or_default();
+        match self.status {
+            Some(status) => write!(f, "Duffel API error ({} {}", status, canonical_reason(status))?,
+            None => write!(f, "Duffel API error (no response")?,
+        }
+        if self.attempts > 1 {
+            write!(f, ", after {} attempts", self.attempts)?;
+        }
+        write!(f, "): {}", summary)?;
+        if self.errors.len() > 1 {
+            write!(f, " (and {} more)", self.errors.len() - 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for DuffelError {}
+
+/// JSON-RPC error for a failed tool call: a Duffel failure gets its own code and
+/// `error.data`, an [`RpcError`] raised along the way is kept as it is, and anything
+/// else becomes `-32000` with `context` and the message.
+pub fn rpc_error(context: &str, error: &anyhow::Error) -> RpcError {
+    if let Some(rpc) = error.downcast_ref::<RpcError>() {
+        return rpc.clone();
+    }
+    match error.downcast_ref::<DuffelError>() {
+        Some(duffel) => duffel.to_rpc_error(context),
+        None => RpcError::server_error(format!("{}: {}", context, error)),
+    }
+}
+
+/// List what went wrong with a partly failed search under its text results.
+pub fn append_warnings(result: &mut String, warnings: &[String]) {
+    if warnings.is_empty() {
+        return;
+    }
+    result.push_str("\n\nPartial results:");
+    for warning in warnings.iter().take(WARNINGS_SHOWN) {
+        result.push_str(&format!("\n- {}", warning));
+    }
+    if warnings.len() > WARNINGS_SHOWN {
+        result.push_str(&format!("\n- and {} more", warnings.len() - WARNINGS_SHOWN));
+    }
+}
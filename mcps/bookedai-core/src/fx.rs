@@ -0,0 +1,247 @@
+//! Currency conversion for the `display_currency` search parameter.
+//!
+//! Rates come from an [`FxRatesProvider`]; the default is the European Central
+//! Bank's daily reference rates. A [`CurrencyConverter`] keeps the last fetched
+//! rates in memory for `FX_RATES_TTL_SECS` and falls back to them if a refresh fails.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+
+use crate::config;
+use crate::jsonrpc::RpcError;
+
+const ECB_DAILY_URL: &str = "https://www.ecb.europa.eu/stats/eurofxref/eurofxref-daily.xml";
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Exchange rates against a single base currency.
+#[derive(Debug, Clone)]
+pub struct FxRates {
+    pub base: String,
+    /// Publication date of the rates, `YYYY-MM-DD`.
+    pub date: String,
+    /// Units of each currency per one unit of `base`; includes `base` itself.
+    pub rates: HashMap<String, f64>,
+}
+
+impl FxRates {
+    /// Units of `to` per one unit of `from`.
+    pub fn rate(&self, from: &str, to: &str) -> Option<f64> {
+        Some(self.rates.get(to)? / self.rates.get(from)?)
+    }
+
+    pub fn supports(&self, currency: &str) -> bool {
+        self.rates.contains_key(currency)
+    }
+
+    /// Supported currency codes, sorted.
+    pub fn currencies(&self) -> Vec<&str> {
+        let mut currencies: Vec<&str> = self.rates.keys().map(String::as_str).collect();
+        currencies.sort_unstable();
+        currencies
+    }
+}
+
+/// Source of exchange rates.
+pub trait FxRatesProvider: fmt::Debug + Send + Sync {
+    /// Short name for logs, e.g. `ECB`.
+    fn name(&self) -> &str;
+
+    fn fetch(&self) -> BoxFuture<'_, Result<FxRates>>;
+}
+
+/// The European Central Bank's daily euro reference rates (about 30 currencies).
+#[derive(Debug, Clone, Default)]
+pub struct EcbRatesProvider {
+    client: reqwest::Client,
+}
+
+impl EcbRatesProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    async fn fetch_rates(&self) -> Result<FxRates> {
+        let body = self
+            .client
+            .get(ECB_DAILY_URL)
+            .timeout(FETCH_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        parse_ecb(&body)
+    }
+}
+
+impl FxRatesProvider for EcbRatesProvider {
+    fn name(&self) -> &str {
+        "ECB"
+    }
+
+    fn fetch(&self) -> BoxFuture<'_, Result<FxRates>> {
+        Box::pin(self.fetch_rates())
+    }
+}
+
+/// Value of `name='...'` (or `name="..."`) inside one XML tag.
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let start = tag.find(&format!("{}=", name))? + name.len() + 1;
+    let quote = tag[start..].chars().next()?;
+    let value = &tag[start + 1..];
+    Some(&value[..value.find(quote)?])
+}
+
+/// Parse the `<Cube time=... ><Cube currency=... rate=.../>...` layout of the ECB feed.
+fn parse_ecb(xml: &str) -> Result<FxRates> {
+    let mut date = None;
+    let mut rates = HashMap::from([("EUR".to_string(), 1.0)]);
+    for tag in xml.split("<Cube").skip(1) {
+        let tag = &tag[..tag.find('>').unwrap_or(tag.len())];
+        if let Some(time) = attribute(tag, "time") {
+            date = Some(time.to_string());
+        }
+        if let (Some(currency), Some(rate)) = (attribute(tag, "currency"), attribute(tag, "rate")) {
+            let rate: f64 = rate
+                .parse()
+                .with_context(|| format!("ECB rate for {} is not a number: {}", currency, rate))?;
+            rates.insert(currency.to_string(), rate);
+        }
+    }
+    if rates.len() == 1 {
+        anyhow::bail!("ECB response contained no exchange rates");
+    }
+    Ok(FxRates {
+        base: "EUR".to_string(),
+        date: date.context("ECB response has no rate date")?,
+        rates,
+    })
+}
+
+/// A price converted into the caller's display currency.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConvertedAmount {
+    /// Converted total, rounded to two decimal places.
+    pub amount: String,
+    pub currency: String,
+    /// Units of `currency` per unit of the original currency.
+    pub exchange_rate: f64,
+    /// Publication date of the rate used.
+    pub rates_date: String,
+}
+
+/// A validated display currency and the rates to convert into it.
+#[derive(Debug, Clone)]
+pub struct DisplayCurrency {
+    pub currency: String,
+    rates: FxRates,
+}
+
+impl DisplayCurrency {
+    /// Convert a decimal `amount` in `from`; `None` when either side cannot be read.
+    pub fn convert(&self, amount: &str, from: &str) -> Option<ConvertedAmount> {
+        let amount: f64 = amount.parse().ok()?;
+        let exchange_rate = self.rates.rate(from, &self.currency)?;
+        Some(ConvertedAmount {
+            amount: format!("{:.2}", amount * exchange_rate),
+            currency: self.currency.clone(),
+            exchange_rate,
+            rates_date: self.rates.date.clone(),
+        })
+    }
+}
+
+#[derive(Debug)]
+struct CachedRates {
+    rates: FxRates,
+    fetched_at: Instant,
+}
+
+/// Shared, cached access to an [`FxRatesProvider`].
+#[derive(Debug, Clone)]
+pub struct CurrencyConverter {
+    provider: Arc<dyn FxRatesProvider>,
+    ttl: Duration,
+    cached: Arc<Mutex<Option<CachedRates>>>,
+}
+
+impl CurrencyConverter {
+    pub fn new(provider: Arc<dyn FxRatesProvider>, ttl: Duration) -> Self {
+        Self {
+            provider,
+            ttl,
+            cached: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// ECB rates, cached for `fx.rates_ttl_secs` (default: 12 hours).
+    pub fn from_config() -> Self {
+        let ttl = Duration::from_secs(config::get().fx.rates_ttl_secs);
+        Self::new(Arc::new(EcbRatesProvider::new()), ttl)
+    }
+
+    /// Current rates, fetched on first use and again once they are older than the TTL.
+    pub async fn rates(&self) -> Result<FxRates> {
+        // Held across the fetch so concurrent searches share one request
+        let mut cached = self.cached.lock().await;
+        if let Some(current) = cached.as_ref() {
+            if current.fetched_at.elapsed() < self.ttl {
+                return Ok(current.rates.clone());
+            }
+        }
+
+        match self.provider.fetch().await {
+            Ok(rates) => {
+                info!("Loaded {} exchange rates from {} ({})", rates.rates.len(), self.provider.name(), rates.date);
+                *cached = Some(CachedRates {
+                    rates: rates.clone(),
+                    fetched_at: Instant::now(),
+                });
+                Ok(rates)
+            }
+            Err(e) => match cached.as_ref() {
+                Some(stale) => {
+                    warn!("Could not refresh exchange rates ({}), using rates from {}", e, stale.rates.date);
+                    Ok(stale.rates.clone())
+                }
+                None => Err(e),
+            },
+        }
+    }
+
+    /// Validate an optional `display_currency` argument and load the rates for it.
+    pub async fn display_currency(&self, currency: Option<&str>) -> Result<Option<DisplayCurrency>, RpcError> {
+        let Some(currency) = currency else {
+            return Ok(None);
+        };
+        let currency = currency.trim().to_uppercase();
+        if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(RpcError::invalid_params(format!(
+                "Invalid parameters: display_currency must be a three-letter ISO 4217 code, got '{}'",
+                currency
+            )));
+        }
+
+        let rates = self.rates().await.map_err(|e| {
+            error!("Exchange rate error: {}", e);
+            RpcError::server_error(format!("Currency conversion failed: {}", e))
+        })?;
+        if !rates.supports(&currency) {
+            return Err(RpcError::invalid_params(format!(
+                "Invalid parameters: display_currency {} is not supported; use one of {}",
+                currency,
+                rates.currencies().join(", ")
+            )));
+        }
+        Ok(Some(DisplayCurrency { currency, rates }))
+    }
+}
@@ -0,0 +1,565 @@
+//! HTTP transport implementing MCP Streamable HTTP.
+//!
+//! `POST /mcp` accepts a JSON-RPC message and answers with either a JSON body or an
+//! SSE stream, depending on the client's `Accept` header; the stream carries the
+//! request's progress notifications ahead of its response. A session ID is
+//! issued on `initialize` via `Mcp-Session-Id`; `GET /mcp` opens an SSE stream for
+//! server-initiated messages, such as the progress notifications of the session's
+//! requests answered as plain JSON, and `DELETE /mcp` ends the session. Sessions are
+//! optional, so plain JSON-over-POST clients keep working.
+//! A JSON-RPC batch (an array of messages) is answered with an array of responses
+//! in one piece, without per-entry progress notifications.
+//!
+//! When a [`WebhookReceiver`] is configured, `POST /webhooks/duffel` accepts signed
+//! Duffel webhook deliveries as well.
+//!
+//! With `MCP_AUTH_TOKEN` set, every `/mcp` method requires a matching bearer token.
+//! `POST /mcp` is rate limited per client IP (see [`RateLimiter`]), each entry of a
+//! batch counting as one request.
+//!
+//! Every `POST /mcp` gets a request ID, taken from the client's `X-Request-Id` header
+//! or generated, which is echoed back on the response and attached to its logs.
+//! `GET /metrics` serves Prometheus metrics (see [`metrics`]).
+//! `GET /health` answers as long as the process runs; `GET /health/ready` also runs
+//! the server's [`Readiness`] checks and reports the cache, answering 503 when a
+//! dependency is degraded.
+//!
+//! The listener binds `BIND_ADDR:PORT`, serves HTTPS when `TLS_CERT_PATH` and
+//! `TLS_KEY_PATH` are set, and shuts down gracefully on Ctrl-C or SIGTERM.
+
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use futures_util::{stream, StreamExt};
+use serde_json::{json, Value};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio_stream::wrappers::{BroadcastStream, UnboundedReceiverStream};
+use tracing::{info, warn};
+use warp::http::StatusCode;
+use warp::sse::Event;
+use warp::{Filter, Reply};
+
+use crate::auth::{BearerAuth, AUTHORIZATION_HEADER};
+use crate::config;
+use crate::jsonrpc::{self, RpcError};
+use crate::mcp::{McpServer, RequestContext};
+use crate::metrics;
+use crate::rate_limit::RateLimiter;
+use crate::readiness::{CheckResult, Readiness};
+use crate::session::{SessionStore, SESSION_HEADER};
+use crate::webhooks::{self, WebhookReceiver};
+
+const EVENT_STREAM: &str = "text/event-stream";
+
+/// Correlation ID header, accepted from clients and set on every `POST /mcp` response.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest client-supplied request ID that is kept; longer ones are replaced.
+const MAX_REQUEST_ID_LEN: usize = 128;
+const SSE_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// How long in-flight requests get to finish after a shutdown signal.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(30);
+
+/// Largest webhook body accepted; Duffel events are a few kilobytes.
+const MAX_WEBHOOK_BODY: u64 = 1024 * 1024;
+
+/// Per-binary settings for the HTTP transport.
+#[derive(Debug, Clone)]
+pub struct HttpOptions {
+    /// Port used when `server.port` (`PORT`) is unset.
+    pub default_port: u16,
+    /// Target name for warp's request log.
+    pub log_target: &'static str,
+    /// Mounts `POST /webhooks/duffel` when set.
+    pub webhooks: Option<WebhookReceiver>,
+    /// Dependency checks for `GET /health/ready`.
+    pub readiness: Readiness,
+}
+
+/// Rejection for `/mcp` requests without a valid bearer token.
+#[derive(Debug)]
+struct Unauthorized;
+
+impl warp::reject::Reject for Unauthorized {}
+
+/// Pass requests through when `auth` is unset or the header matches it.
+fn authorize(auth: Option<BearerAuth>) -> impl Filter<Extract = (), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>(AUTHORIZATION_HEADER)
+        .and_then(move |header: Option<String>| {
+            let auth = auth.clone();
+            async move {
+                match auth {
+                    Some(auth) if !auth.check(header.as_deref()) => Err(warp::reject::custom(Unauthorized)),
+                    _ => Ok(()),
+                }
+            }
+        })
+        .untuple_one()
+}
+
+async fn handle_unauthorized(rejection: warp::Rejection) -> Result<warp::reply::Response, warp::Rejection> {
+    if rejection.find::<Unauthorized>().is_none() {
+        return Err(rejection);
+    }
+    warn!("Rejected /mcp request with a missing or invalid bearer token");
+    let mut reply = transport_error(StatusCode::UNAUTHORIZED, "Missing or invalid bearer token");
+    reply
+        .headers_mut()
+        .insert(warp::http::header::WWW_AUTHENTICATE, warp::http::HeaderValue::from_static("Bearer"));
+    Ok(reply)
+}
+
+/// The client's `X-Request-Id` when it is short, printable ASCII, otherwise a new UUID.
+fn request_id() -> impl Filter<Extract = (String,), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>(REQUEST_ID_HEADER).map(|header: Option<String>| {
+        header
+            .filter(|id| {
+                !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN && id.chars().all(|c| c.is_ascii_graphic())
+            })
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+    })
+}
+
+fn with_request_id(mut reply: warp::reply::Response, request_id: &str) -> warp::reply::Response {
+    if let Ok(value) = request_id.parse() {
+        reply.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    reply
+}
+
+fn accepts_event_stream(accept: &Option<String>) -> bool {
+    accept
+        .as_deref()
+        .is_some_and(|accept| accept.contains(EVENT_STREAM))
+}
+
+fn message_event(message: &Value) -> Event {
+    Event::default().event("message").data(message.to_string())
+}
+
+/// Aborts a request's task when its SSE stream is dropped, i.e. the client went away.
+struct AbortOnDrop(JoinHandle<()>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Answer a request over SSE, sending its notifications before the response.
+fn stream_response(server: McpServer, request: Value, mut context: RequestContext) -> warp::reply::Response {
+    let (outgoing, messages) = mpsc::unbounded_channel();
+    context.notifier = Some(outgoing.clone());
+    let task = AbortOnDrop(tokio::spawn(async move {
+        if let Some(response) = server.handle_request_with(request, context).await {
+            let _ = outgoing.send(response);
+        }
+    }));
+
+    // The stream ends once the request and its progress reporter drop their senders
+    let events = UnboundedReceiverStream::new(messages).map(move |message| {
+        let _ = &task;
+        Ok::<_, Infallible>(message_event(&message))
+    });
+    warp::sse::reply(events).into_response()
+}
+
+fn transport_error(status: StatusCode, message: &str) -> warp::reply::Response {
+    let body = jsonrpc::error(Value::Null, &RpcError::server_error(message));
+    warp::reply::with_status(warp::reply::json(&body), status).into_response()
+}
+
+fn rate_limited(id: Value, retry_after: Duration) -> warp::reply::Response {
+    let seconds = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+    let error = RpcError::rate_limited(format!("Rate limit exceeded, retry in {}s", seconds))
+        .with_data(json!({ "retry_after_secs": seconds }));
+    let mut reply = warp::reply::with_status(
+        warp::reply::json(&jsonrpc::error(id, &error)),
+        StatusCode::TOO_MANY_REQUESTS,
+    )
+    .into_response();
+    reply
+        .headers_mut()
+        .insert(warp::http::header::RETRY_AFTER, warp::http::HeaderValue::from(seconds));
+    reply
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn handle_post(
+    server: McpServer,
+    sessions: SessionStore,
+    limiter: Option<RateLimiter>,
+    client: Option<SocketAddr>,
+    request_id: String,
+    accept: Option<String>,
+    session_id: Option<String>,
+    request: Value,
+) -> Result<warp::reply::Response, Infallible> {
+    let reply = async {
+        if let Some(limiter) = &limiter {
+            let client = client.map_or_else(|| "unknown".to_string(), |address| address.ip().to_string());
+            // Each entry of a batch is a request of its own
+            let cost = request.as_array().map_or(1, Vec::len);
+            if cost > limiter.burst() {
+                warn!(request_id = %request_id, "Batch of {} from {} exceeds the rate limit burst", cost, client);
+                let error = RpcError::rate_limited(format!(
+                    "Batch of {} requests exceeds the rate limit of {} at once; send smaller batches",
+                    cost,
+                    limiter.burst()
+                ));
+                return warp::reply::with_status(
+                    warp::reply::json(&jsonrpc::error(Value::Null, &error)),
+                    StatusCode::TOO_MANY_REQUESTS,
+                )
+                .into_response();
+            }
+            if let Err(retry_after) = limiter.check_n(&client, cost) {
+                warn!(request_id = %request_id, "Rate limit exceeded for {}", client);
+                return rate_limited(request["id"].clone(), retry_after);
+            }
+        }
+
+        let is_initialize = request["method"] == "initialize";
+
+        if let Some(id) = &session_id {
+            if !is_initialize && !sessions.touch(id) {
+                return transport_error(StatusCode::NOT_FOUND, "Session not found");
+            }
+        }
+
+        let mut context = RequestContext {
+            connection: session_id.clone(),
+            notifier: None,
+            request_id: Some(request_id.clone()),
+        };
+
+        // `initialize` is answered in one piece, since its session header depends on the result
+        if accepts_event_stream(&accept) && !is_initialize && request.get("id").is_some() {
+            return stream_response(server, request, context);
+        }
+        // A JSON answer cannot carry notifications, so they go to the session's GET streams
+        if let Some(id) = session_id.as_deref().filter(|_| !is_initialize) {
+            context.notifier = Some(sessions.notifier(id));
+        }
+
+        // Boxed: a tool call's future is large, and moved inline through every warp
+        // layer above it, it overflows a worker thread's stack in debug builds
+        let Some(response) = Box::pin(server.handle_request_with(request, context)).await else {
+            return StatusCode::ACCEPTED.into_response();
+        };
+
+        let new_session = (is_initialize && response.get("result").is_some()).then(|| sessions.create());
+
+        let mut reply = if accepts_event_stream(&accept) {
+            let event = message_event(&response);
+            warp::sse::reply(stream::once(async move { Ok::<_, Infallible>(event) })).into_response()
+        } else {
+            warp::reply::json(&response).into_response()
+        };
+
+        if let Some(id) = new_session {
+            if let Ok(value) = id.parse() {
+                reply.headers_mut().insert(SESSION_HEADER, value);
+            }
+        }
+
+        reply
+    }
+    .await;
+    Ok(with_request_id(reply, &request_id))
+}
+
+/// `GET /health/ready`: every readiness check plus the cache, 503 when any check fails.
+async fn handle_ready(server: McpServer, readiness: Readiness) -> Result<warp::reply::Response, Infallible> {
+    let mut checks = serde_json::Map::new();
+    let mut failures = Vec::new();
+    for (name, result) in readiness.run().await {
+        if !result.is_ok() {
+            warn!("Readiness check {} failed: {}", name, result.detail);
+            failures.push(format!("{}: {}", name, result.detail));
+        }
+        checks.insert(name.to_string(), json!(result));
+    }
+    // In memory, so it cannot fail; reported so probes show whether it is in use
+    let cache = match server.cache() {
+        Some(cache) => CheckResult::ok(format!("enabled, {} cached results", cache.entry_count())),
+        None => CheckResult::ok("disabled"),
+    };
+    checks.insert("cache".to_string(), json!(cache));
+
+    let ready = failures.is_empty();
+    let mut body = json!({
+        "status": if ready { "ready" } else { "degraded" },
+        "service": server.name(),
+        "version": server.version(),
+        "checks": checks
+    });
+    if !ready {
+        body["reason"] = json!(failures.join("; "));
+    }
+    let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    Ok(warp::reply::with_status(warp::reply::json(&body), status).into_response())
+}
+
+async fn handle_get(
+    sessions: SessionStore,
+    accept: Option<String>,
+    session_id: Option<String>,
+) -> Result<warp::reply::Response, Infallible> {
+    if !accepts_event_stream(&accept) {
+        return Ok(transport_error(
+            StatusCode::NOT_ACCEPTABLE,
+            "GET /mcp requires Accept: text/event-stream",
+        ));
+    }
+    let Some(id) = session_id else {
+        return Ok(transport_error(StatusCode::BAD_REQUEST, "Missing Mcp-Session-Id header"));
+    };
+    let Some(receiver) = sessions.subscribe(&id) else {
+        return Ok(transport_error(StatusCode::NOT_FOUND, "Session not found"));
+    };
+
+    let events = BroadcastStream::new(receiver)
+        .filter_map(|message| async move { message.ok() })
+        .map(|message| Ok::<_, Infallible>(message_event(&message)));
+
+    let keep_alive = warp::sse::keep_alive().interval(SSE_KEEP_ALIVE);
+    Ok(warp::sse::reply(keep_alive.stream(events)).into_response())
+}
+
+async fn handle_delete(
+    sessions: SessionStore,
+    session_id: Option<String>,
+) -> Result<warp::reply::Response, Infallible> {
+    let Some(id) = session_id else {
+        return Ok(transport_error(StatusCode::BAD_REQUEST, "Missing Mcp-Session-Id header"));
+    };
+    if sessions.remove(&id) {
+        Ok(StatusCode::NO_CONTENT.into_response())
+    } else {
+        Ok(transport_error(StatusCode::NOT_FOUND, "Session not found"))
+    }
+}
+
+/// Serve `server` over HTTP until Ctrl-C or SIGTERM.
+pub async fn serve(server: McpServer, options: HttpOptions) -> Result<()> {
+    let sessions = SessionStore::new();
+    let log_target = options.log_target;
+    let default_port = options.default_port;
+
+    // Create CORS configuration
+    let cors = warp::cors()
+        .allow_any_origin()
+        .allow_headers(vec![
+            "content-type",
+            "accept",
+            AUTHORIZATION_HEADER,
+            SESSION_HEADER,
+            "mcp-protocol-version",
+            REQUEST_ID_HEADER,
+        ])
+        .expose_headers(vec![SESSION_HEADER, REQUEST_ID_HEADER])
+        .allow_methods(vec!["GET", "POST", "DELETE", "OPTIONS"]);
+
+    // Health check endpoint
+    let health_body = json!({
+        "status": "healthy",
+        "service": server.name(),
+        "version": server.version()
+    });
+    let health = warp::path("health")
+        .and(warp::path::end())
+        .and(warp::get())
+        .map(move || warp::reply::json(&health_body));
+
+    // Readiness probe: upstream and dependency checks
+    let ready = {
+        let server = server.clone();
+        let readiness = options.readiness.clone();
+        warp::path!("health" / "ready")
+            .and(warp::get())
+            .and(warp::any().map(move || (server.clone(), readiness.clone())))
+            .and_then(|(server, readiness)| handle_ready(server, readiness))
+    };
+
+    // Prometheus scrape endpoint
+    let metrics = warp::path("metrics")
+        .and(warp::path::end())
+        .and(warp::get())
+        .map(|| warp::reply::with_header(metrics::render(), "content-type", metrics::CONTENT_TYPE));
+
+    // MCP endpoint (Streamable HTTP)
+    let with_server = {
+        let server = server.clone();
+        warp::any().map(move || server.clone())
+    };
+    let with_sessions = warp::any().map(move || sessions.clone());
+    let limiter = RateLimiter::from_config();
+    let with_limiter = warp::any().map(move || limiter.clone());
+    let accept = warp::header::optional::<String>("accept");
+    let session_id = warp::header::optional::<String>(SESSION_HEADER);
+
+    let mcp_post = warp::post()
+        .and(with_server)
+        .and(with_sessions.clone())
+        .and(with_limiter)
+        .and(warp::addr::remote())
+        .and(request_id())
+        .and(accept)
+        .and(session_id)
+        .and(warp::body::json())
+        .and_then(handle_post);
+    let mcp_get = warp::get()
+        .and(with_sessions.clone())
+        .and(accept)
+        .and(session_id)
+        .and_then(handle_get);
+    let mcp_delete = warp::delete()
+        .and(with_sessions)
+        .and(session_id)
+        .and_then(handle_delete);
+    let auth = BearerAuth::from_config();
+    let auth_enabled = auth.is_some();
+    let mcp = warp::path("mcp")
+        .and(warp::path::end())
+        .and(authorize(auth))
+        .and(mcp_post.or(mcp_get).unify().or(mcp_delete).unify())
+        .recover(handle_unauthorized);
+
+    // Duffel webhook endpoint, only when a signing secret is configured
+    let webhooks_enabled = options.webhooks.is_some();
+    let webhook = warp::path!("webhooks" / "duffel")
+        .and(warp::post())
+        .and(warp::any().map(move || options.webhooks.clone()))
+        .and_then(|receiver: Option<WebhookReceiver>| async move {
+            receiver.ok_or_else(warp::reject::not_found)
+        })
+        .and(warp::header::optional::<String>(webhooks::SIGNATURE_HEADER))
+        .and(warp::body::content_length_limit(MAX_WEBHOOK_BODY))
+        .and(warp::body::bytes())
+        .and_then(webhooks::handle);
+
+    // Root endpoint with info
+    let mut endpoints = json!({
+        "health": "GET /health",
+        "ready": "GET /health/ready",
+        "metrics": "GET /metrics",
+        "mcp": "POST /mcp",
+        "mcp_stream": "GET /mcp",
+        "mcp_session_end": "DELETE /mcp"
+    });
+    if webhooks_enabled {
+        endpoints["duffel_webhooks"] = json!("POST /webhooks/duffel");
+    }
+    let root_body = json!({
+        "service": server.title(),
+        "version": server.version(),
+        "endpoints": endpoints,
+        "authentication": if auth_enabled { "bearer" } else { "none" },
+        "tools": server.tool_names(),
+        "resource_templates": server.resource_templates()
+    });
+    let root = warp::path::end()
+        .and(warp::get())
+        .map(move || warp::reply::json(&root_body));
+
+    let routes = health
+        .or(ready)
+        .or(metrics)
+        .or(mcp)
+        .or(webhook)
+        .or(root)
+        .with(cors)
+        .with(warp::log(log_target));
+
+    let server_config = &config::get().server;
+    let address = SocketAddr::new(server_config.bind_addr, server_config.port.unwrap_or(default_port));
+    if !auth_enabled && !address.ip().is_loopback() {
+        warn!("Listening on {} without MCP_AUTH_TOKEN; anyone who can reach it can use your Duffel token", address);
+    }
+    // Both or neither, checked when the config was loaded
+    let tls = server_config.tls_cert_path.as_ref().zip(server_config.tls_key_path.as_ref());
+    let base_url = format!("{}://{}", if tls.is_some() { "https" } else { "http" }, address);
+
+    info!("Server starting on {}", base_url);
+    info!("MCP endpoint: {}/mcp", base_url);
+    info!("Health check: {}/health", base_url);
+    info!("Readiness check: {}/health/ready", base_url);
+    info!("Metrics: {}/metrics", base_url);
+    if webhooks_enabled {
+        info!("Duffel webhooks: {}/webhooks/duffel", base_url);
+    }
+
+    // Stop accepting connections on the first signal, then let in-flight calls finish
+    let (stop_tx, stop_rx) = oneshot::channel::<()>();
+    let stopped = async {
+        stop_rx.await.ok();
+    };
+    let mut running = match tls {
+        Some((cert_path, key_path)) => {
+            let (_, running) = warp::serve(routes)
+                .tls()
+                .cert_path(cert_path)
+                .key_path(key_path)
+                .try_bind_with_graceful_shutdown(address, stopped)
+                .with_context(|| format!("failed to bind {} with TLS (check TLS_CERT_PATH and TLS_KEY_PATH)", address))?;
+            tokio::spawn(running)
+        }
+        None => {
+            let (_, running) = warp::serve(routes)
+                .try_bind_with_graceful_shutdown(address, stopped)
+                .with_context(|| format!("failed to bind {}", address))?;
+            tokio::spawn(running)
+        }
+    };
+
+    tokio::select! {
+        result = &mut running => {
+            result.context("HTTP server task failed")?;
+        }
+        _ = shutdown_signal() => {
+            info!("Shutdown requested, draining in-flight requests for up to {:?}", SHUTDOWN_GRACE);
+            let _ = stop_tx.send(());
+            // Open SSE streams never finish on their own, so the drain is bounded
+            if tokio::time::timeout(SHUTDOWN_GRACE, running).await.is_err() {
+                warn!("Connections still open after {:?}, exiting anyway", SHUTDOWN_GRACE);
+            }
+        }
+    }
+    info!("Server stopped");
+    Ok(())
+}
+
+/// Resolves on Ctrl-C or, on Unix, SIGTERM.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("Could not listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                warn!("Could not listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
@@ -0,0 +1,192 @@
+//! Idempotency keys for tools that create something in Duffel, so a retried tool
+//! call returns the original result instead of, say, booking twice.
+//!
+//! A tool claims `(tool, key)` with a hash of its arguments before calling Duffel,
+//! and records what it created once the call succeeds. Calling again with the same
+//! key and arguments returns the recorded result; with different arguments it is
+//! refused. A failed call releases its claim so it can be retried. A claim left
+//! behind by a server that stopped mid-call can be taken over after
+//! [`PENDING_TIMEOUT_SECS`]; the `Idempotency-Key` header sent to Duffel keeps that
+//! retry from creating a second order too.
+
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use rusqlite::{params, OptionalExtension};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::jsonrpc::RpcError;
+use crate::store::Store;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS idempotency_keys (
+    tool TEXT NOT NULL,
+    key TEXT NOT NULL,
+    request_hash TEXT NOT NULL,
+    resource_id TEXT,
+    response TEXT,
+    created_at TEXT NOT NULL,
+    completed_at TEXT,
+    PRIMARY KEY (tool, key)
+);
+";
+
+/// Longest idempotency key accepted.
+const MAX_KEY_LEN: usize = 255;
+
+/// How long an unfinished claim blocks other calls with the same key.
+pub const PENDING_TIMEOUT_SECS: i64 = 600;
+
+/// The outcome of claiming a key.
+#[derive(Debug, Clone)]
+pub enum Claim {
+    /// The key is new, or its earlier call was abandoned: go ahead.
+    Acquired,
+    /// A call with this key and these arguments already succeeded.
+    Completed(CompletedCall),
+    /// A call with this key is still running.
+    InProgress,
+    /// The key was already used with different arguments.
+    Mismatch,
+}
+
+/// What an earlier call with the same key created.
+#[derive(Debug, Clone)]
+pub struct CompletedCall {
+    /// ID of what was created, e.g. the Duffel order ID.
+    pub resource_id: String,
+    /// The tool's `structuredContent` at the time.
+    pub response: Value,
+    pub completed_at: String,
+}
+
+/// Check a client-supplied idempotency key: 1-255 letters, digits, `-`, `_`, `.` or `:`.
+pub fn check_key(field: &str, value: &str) -> Result<String, RpcError> {
+    let key = value.trim();
+    let valid = !key.is_empty()
+        && key.len() <= MAX_KEY_LEN
+        && key
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b':'));
+    if !valid {
+        return Err(RpcError::invalid_params(format!(
+            "Invalid parameters: {} must be 1-{} letters, digits, '-', '_', '.' or ':', got '{}'",
+            field, MAX_KEY_LEN, value
+        )));
+    }
+    Ok(key.to_string())
+}
+
+/// Hex SHA-256 of a request's JSON; object keys are sorted, so equal requests hash equally.
+pub fn request_hash(request: &Value) -> String {
+    hex::encode(Sha256::digest(request.to_string().as_bytes()))
+}
+
+/// A row of `idempotency_keys`; the result columns are set once the call succeeded.
+struct StoredKey {
+    request_hash: String,
+    resource_id: Option<String>,
+    response: Option<String>,
+    created_at: String,
+    completed_at: Option<String>,
+}
+
+/// Idempotency keys and the results they produced, in the shared [`Store`].
+#[derive(Debug, Clone)]
+pub struct IdempotencyKeys {
+    store: Store,
+}
+
+impl IdempotencyKeys {
+    pub async fn new(store: Store) -> Result<Self> {
+        store.migrate(SCHEMA).await?;
+        Ok(Self { store })
+    }
+
+    /// Claim `key` for a `tool` call whose arguments hash to `request_hash`.
+    pub async fn claim(&self, tool: &str, key: &str, request_hash: &str) -> Result<Claim> {
+        let (tool, key, request_hash) = (tool.to_string(), key.to_string(), request_hash.to_string());
+        self.store
+            .call(move |connection| {
+                let transaction = connection.transaction()?;
+                let existing = transaction
+                    .query_row(
+                        "SELECT request_hash, resource_id, response, created_at, completed_at
+                         FROM idempotency_keys WHERE tool = ?1 AND key = ?2",
+                        params![tool, key],
+                        |row| {
+                            Ok(StoredKey {
+                                request_hash: row.get(0)?,
+                                resource_id: row.get(1)?,
+                                response: row.get(2)?,
+                                created_at: row.get(3)?,
+                                completed_at: row.get(4)?,
+                            })
+                        },
+                    )
+                    .optional()?;
+
+                let now = Utc::now();
+                let claim = match existing {
+                    Some(stored) if stored.request_hash != request_hash => Claim::Mismatch,
+                    Some(StoredKey {
+                        resource_id: Some(resource_id),
+                        response: Some(response),
+                        completed_at: Some(completed_at),
+                        ..
+                    }) => Claim::Completed(CompletedCall {
+                        resource_id,
+                        response: serde_json::from_str(&response)?,
+                        completed_at,
+                    }),
+                    Some(stored) if !is_stale(&stored.created_at, now) => Claim::InProgress,
+                    _ => {
+                        transaction.execute(
+                            "INSERT INTO idempotency_keys (tool, key, request_hash, created_at) VALUES (?1, ?2, ?3, ?4)
+                             ON CONFLICT (tool, key) DO UPDATE SET created_at = excluded.created_at",
+                            params![tool, key, request_hash, now.to_rfc3339()],
+                        )?;
+                        Claim::Acquired
+                    }
+                };
+                transaction.commit()?;
+                Ok(claim)
+            })
+            .await
+    }
+
+    /// Record what a claimed call created, so replays return it.
+    pub async fn complete(&self, tool: &str, key: &str, resource_id: &str, response: &Value) -> Result<()> {
+        let (tool, key, resource_id, response) =
+            (tool.to_string(), key.to_string(), resource_id.to_string(), response.to_string());
+        self.store
+            .call(move |connection| {
+                connection.execute(
+                    "UPDATE idempotency_keys SET resource_id = ?3, response = ?4, completed_at = ?5
+                     WHERE tool = ?1 AND key = ?2",
+                    params![tool, key, resource_id, response, Utc::now().to_rfc3339()],
+                )?;
+                Ok(())
+            })
+            .await
+    }
+
+    /// Give up a claim after its call failed, so the key can be used again.
+    pub async fn release(&self, tool: &str, key: &str) -> Result<()> {
+        let (tool, key) = (tool.to_string(), key.to_string());
+        self.store
+            .call(move |connection| {
+                connection.execute(
+                    "DELETE FROM idempotency_keys WHERE tool = ?1 AND key = ?2 AND completed_at IS NULL",
+                    params![tool, key],
+                )?;
+                Ok(())
+            })
+            .await
+    }
+}
+
+fn is_stale(created_at: &str, now: chrono::DateTime<Utc>) -> bool {
+    chrono::DateTime::parse_from_rfc3339(created_at)
+        .map_or(true, |created_at| created_at + Duration::seconds(PENDING_TIMEOUT_SECS) < now)
+}
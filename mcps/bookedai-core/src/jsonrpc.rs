@@ -0,0 +1,110 @@
+use std::fmt;
+
+use serde_json::{json, Value};
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const SERVER_ERROR: i64 = -32000;
+/// MCP: `resources/read` was given a URI the server does not know.
+pub const RESOURCE_NOT_FOUND: i64 = -32002;
+/// Implementation-defined: the client exceeded its request rate (sent with HTTP 429).
+pub const RATE_LIMITED: i64 = -32029;
+
+// Implementation-defined: Duffel failures, by kind (see `errors`).
+pub const DUFFEL_VALIDATION_ERROR: i64 = -32010;
+pub const DUFFEL_AUTHENTICATION_ERROR: i64 = -32011;
+pub const DUFFEL_RATE_LIMITED: i64 = -32012;
+pub const DUFFEL_AIRLINE_ERROR: i64 = -32013;
+pub const DUFFEL_NOT_FOUND: i64 = -32014;
+pub const DUFFEL_INVALID_STATE: i64 = -32015;
+pub const DUFFEL_UNAVAILABLE: i64 = -32016;
+
+/// A JSON-RPC error object, returned by tool handlers and method routing.
+#[derive(Debug, Clone)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    pub data: Option<Value>,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    pub fn parse_error(message: impl Into<String>) -> Self {
+        Self::new(PARSE_ERROR, message)
+    }
+
+    pub fn invalid_request(message: impl Into<String>) -> Self {
+        Self::new(INVALID_REQUEST, message)
+    }
+
+    pub fn method_not_found() -> Self {
+        Self::new(METHOD_NOT_FOUND, "Method not found")
+    }
+
+    pub fn invalid_params(message: impl Into<String>) -> Self {
+        Self::new(INVALID_PARAMS, message)
+    }
+
+    pub fn server_error(message: impl Into<String>) -> Self {
+        Self::new(SERVER_ERROR, message)
+    }
+
+    pub fn resource_not_found(uri: &str) -> Self {
+        Self::new(RESOURCE_NOT_FOUND, "Resource not found").with_data(json!({ "uri": uri }))
+    }
+
+    pub fn rate_limited(message: impl Into<String>) -> Self {
+        Self::new(RATE_LIMITED, message)
+    }
+
+    pub fn with_data(mut self, data: Value) -> Self {
+        self.data = Some(data);
+        self
+    }
+
+    fn to_value(&self) -> Value {
+        let mut error = json!({
+            "code": self.code,
+            "message": self.message
+        });
+        if let Some(data) = &self.data {
+            error["data"] = data.clone();
+        }
+        error
+    }
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.message, self.code)
+    }
+}
+
+impl std::error::Error for RpcError {}
+
+/// Build a successful JSON-RPC response.
+pub fn success(id: Value, result: Value) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "result": result,
+        "id": id
+    })
+}
+
+/// Build a JSON-RPC error response.
+pub fn error(id: Value, error: &RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "error": error.to_value(),
+        "id": id
+    })
+}
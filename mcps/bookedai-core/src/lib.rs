@@ -0,0 +1,86 @@
+//! Shared plumbing for the BookedAI MCP servers.
+//!
+//! Every server is a thin binary that owns its domain logic (request types,
+//! response parsing, formatting) and delegates the rest to this crate:
+//!
+//! - [`airports`]: embedded IATA airport and city-code dataset, with distances
+//! - [`audit`]: append-only audit log of tool calls, with `query_audit_log`
+//! - [`auth`]: `MCP_AUTH_TOKEN` bearer authentication for `/mcp`
+//! - [`cache`]: TTL cache for repeated identical tool calls
+//! - [`circuit_breaker`]: fail-fast cut-off for Duffel calls during an outage
+//! - [`config`]: `config.toml` and environment settings, validated at startup
+//! - [`duffel`]: authenticated HTTP client for the Duffel API
+//! - [`duffel_version`]: the `Duffel-Version` sent and per-version payload adaptation
+//! - [`duffel_models`]: typed Duffel offer and stay payloads
+//! - [`errors`]: Duffel failures classified into distinct JSON-RPC errors
+//! - [`fx`]: exchange rates and `display_currency` conversion
+//! - [`idempotency`]: idempotency keys so retried booking calls are not repeated
+//! - [`jsonrpc`]: JSON-RPC 2.0 envelopes and error codes
+//! - [`logging`]: text or JSON log output on stderr
+//! - [`mcp`]: MCP method routing and the tool registry
+//! - [`mcp_client`]: `tools/call` client for orchestrating other MCP servers
+//! - [`metrics`]: Prometheus counters and latency histograms for `/metrics`
+//! - [`mock`]: canned Duffel responses for `DUFFEL_MODE=mock`
+//! - [`notifications`]: booking confirmations by email and webhook
+//! - [`pagination`]: `limit` / `page_token` handling for search tools
+//! - [`price_alerts`]: saved searches with price history and target prices
+//! - [`profiles`]: encrypted traveller profiles for repeat bookings
+//! - [`progress`]: `notifications/progress` reporting from inside tool calls
+//! - [`http`]: the warp Streamable HTTP transport (`/mcp`, `/health`, `/health/ready`, `/metrics`, `/`)
+//! - [`search_results`]: offers saved by `search_id` so later calls can pick "option N"
+//! - [`session`]: `Mcp-Session-Id` sessions for the HTTP transport
+//! - [`rate_limit`]: per-client token buckets for `POST /mcp`
+//! - [`readiness`]: Duffel, database and other dependency checks for `/health/ready`
+//! - [`resources`]: MCP resource templates for `resources/list` and `resources/read`
+//! - [`retry`]: backoff policy and rate-limit headers for Duffel calls
+//! - [`scheduler`]: periodic background jobs
+//! - [`streaming`]: large Duffel lists parsed item by item within a response size limit
+//! - [`stdio`]: newline-delimited JSON-RPC over stdin/stdout
+//! - [`store`]: the SQLite database shared by persistent features
+//! - [`transport`]: `--transport` selection shared by every binary
+//! - [`validation`]: date, time, IATA code and Duffel ID checks for tool arguments
+//! - [`webhooks`]: signed Duffel webhook receiver and event store
+
+pub mod airports;
+pub mod audit;
+pub mod auth;
+pub mod cache;
+pub mod circuit_breaker;
+pub mod config;
+pub mod duffel;
+pub mod duffel_models;
+pub mod duffel_version;
+pub mod errors;
+pub mod fx;
+pub mod http;
+pub mod idempotency;
+pub mod jsonrpc;
+pub mod logging;
+pub mod mcp;
+pub mod mcp_client;
+pub mod metrics;
+pub mod mock;
+pub mod notifications;
+pub mod pagination;
+pub mod price_alerts;
+pub mod profiles;
+pub mod progress;
+pub mod rate_limit;
+pub mod readiness;
+pub mod resources;
+pub mod retry;
+pub mod scheduler;
+pub mod search_results;
+pub mod session;
+pub mod stdio;
+pub mod streaming;
+pub mod store;
+pub mod transport;
+pub mod validation;
+pub mod webhooks;
+
+pub use cache::ResponseCache;
+pub use duffel::DuffelClient;
+pub use jsonrpc::RpcError;
+pub use mcp::{McpServer, RequestContext, Tool};
+pub use transport::Transport;
@@ -0,0 +1,23 @@
+//! Log output shared by every binary.
+//!
+//! Logs go to stderr, since stdout carries JSON-RPC in stdio mode. `LOG_FORMAT=json`
+//! writes one JSON object per line, including the fields of the enclosing spans
+//! (such as `request_id`), for log shippers; the default is human-readable text.
+//! `RUST_LOG` sets the level filter (default `info`).
+
+use tracing_subscriber::EnvFilter;
+
+use crate::config::{self, LogFormat};
+
+/// Install the global subscriber described by `server.log_format` and `RUST_LOG`.
+pub fn init() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+
+    match config::get().server.log_format {
+        LogFormat::Json => builder.json().init(),
+        LogFormat::Text => builder.init(),
+    }
+}
@@ -0,0 +1,622 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use base64::Engine;
+use futures_util::{stream, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, error, info, info_span, warn, Instrument};
+
+use crate::audit::{self, AuditEntry, AuditLog};
+use crate::cache::{ResponseCache, CACHE_ARGUMENT};
+use crate::config;
+use crate::jsonrpc::{self, RpcError};
+use crate::metrics;
+use crate::progress::{self, Progress};
+use crate::resources::{self, RegisteredResources, Resource, ResourceTemplate};
+
+/// Entries of one JSON-RPC batch handled at once; the rest wait their turn.
+const BATCH_CONCURRENCY: usize = 4;
+
+/// Most entries accepted in one batch.
+const MAX_BATCH_LEN: usize = 50;
+
+/// Protocol revisions this server speaks, newest first.
+const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+type ToolFuture = Pin<Box<dyn Future<Output = Result<Value, RpcError>> + Send>>;
+type ToolHandler = Arc<dyn Fn(Value) -> ToolFuture + Send + Sync>;
+
+/// Metadata advertised for a tool in `tools/list`.
+#[derive(Debug, Clone)]
+pub struct Tool {
+    pub name: String,
+    pub description: String,
+    pub input_schema: Value,
+    pub output_schema: Option<Value>,
+    /// Whether results may be served from the server's [`ResponseCache`].
+    pub cacheable: bool,
+}
+
+impl Tool {
+    pub fn new(name: impl Into<String>, description: impl Into<String>, input_schema: Value) -> Self {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            input_schema,
+            output_schema: None,
+            cacheable: false,
+        }
+    }
+
+    /// Allow identical calls to be answered from the response cache.
+    ///
+    /// Also advertises the `cache` argument so clients can opt out per call.
+    pub fn cacheable(mut self) -> Self {
+        self.cacheable = true;
+        if let Some(properties) = self.input_schema["properties"].as_object_mut() {
+            properties.insert(
+                CACHE_ARGUMENT.to_string(),
+                json!({
+                    "type": "boolean",
+                    "description": "Set to false to bypass cached results and query Duffel again (default: true)"
+                }),
+            );
+        }
+        self
+    }
+
+    /// Declare the JSON schema of the tool's `structuredContent`.
+    pub fn with_output_schema(mut self, output_schema: Value) -> Self {
+        self.output_schema = Some(output_schema);
+        self
+    }
+
+    fn to_value(&self) -> Value {
+        let mut tool = json!({
+            "name": self.name,
+            "description": self.description,
+            "inputSchema": self.input_schema
+        });
+        if let Some(output_schema) = &self.output_schema {
+            tool["outputSchema"] = output_schema.clone();
+        }
+        tool
+    }
+}
+
+struct RegisteredTool {
+    tool: Tool,
+    handler: ToolHandler,
+}
+
+/// Where a message arrived from, and how to reach its client before the response.
+#[derive(Debug, Clone, Default)]
+pub struct RequestContext {
+    /// Connection the request IDs belong to: the HTTP session ID, or `None` for stdio
+    /// and sessionless HTTP clients.
+    pub connection: Option<String>,
+    /// Channel for notifications sent while the request runs; `None` when the
+    /// transport can only return the final response.
+    pub notifier: Option<mpsc::UnboundedSender<Value>>,
+    /// Correlation ID for the request's logs; one is generated when `None`.
+    pub request_id: Option<String>,
+}
+
+/// Identifies an in-flight request for `notifications/cancelled`.
+type RequestKey = (Option<String>, String);
+
+/// Cancels in-flight tool calls on request.
+#[derive(Default)]
+struct InFlight {
+    requests: Mutex<HashMap<RequestKey, oneshot::Sender<()>>>,
+}
+
+/// Unregisters a request once it finishes, however it finishes.
+struct InFlightGuard<'a> {
+    in_flight: &'a InFlight,
+    key: RequestKey,
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.in_flight.requests.lock().unwrap().remove(&self.key);
+    }
+}
+
+impl InFlight {
+    fn register(&self, key: RequestKey) -> (InFlightGuard<'_>, oneshot::Receiver<()>) {
+        let (cancel, cancelled) = oneshot::channel();
+        self.requests.lock().unwrap().insert(key.clone(), cancel);
+        (InFlightGuard { in_flight: self, key }, cancelled)
+    }
+
+    /// Signal a request to stop; returns `false` if it is not running.
+    fn cancel(&self, key: &RequestKey) -> bool {
+        match self.requests.lock().unwrap().remove(key) {
+            Some(cancel) => cancel.send(()).is_ok(),
+            None => false,
+        }
+    }
+}
+
+struct Inner {
+    name: String,
+    version: String,
+    title: String,
+    tools: Vec<RegisteredTool>,
+    resources: Vec<RegisteredResources>,
+    cache: Option<ResponseCache>,
+    tool_timeout: Option<Duration>,
+    audit: Option<AuditLog>,
+    in_flight: InFlight,
+}
+
+/// Builder for [`McpServer`]; register every tool before calling [`build`](Self::build).
+pub struct McpServerBuilder {
+    name: String,
+    version: String,
+    title: Option<String>,
+    tools: Vec<RegisteredTool>,
+    resources: Vec<RegisteredResources>,
+    cache: Option<ResponseCache>,
+    tool_timeout: Option<Duration>,
+    audit: Option<AuditLog>,
+}
+
+/// Longest a tool call may run, Duffel retries included (`server.tool_call_timeout_secs`);
+/// `0` means no deadline.
+fn tool_timeout_from_config() -> Option<Duration> {
+    let timeout = Duration::from_secs(config::get().server.tool_call_timeout_secs);
+    (!timeout.is_zero()).then_some(timeout)
+}
+
+impl McpServerBuilder {
+    /// Human-readable service name shown on the HTTP root endpoint.
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Cache results of tools marked [`Tool::cacheable`]; `None` disables caching.
+    pub fn cache(mut self, cache: Option<ResponseCache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Deadline for each tool call, after which it fails with a timeout error; `None`
+    /// lets calls run as long as they take. Defaults to `TOOL_CALL_TIMEOUT_SECS`.
+    pub fn tool_timeout(mut self, tool_timeout: Option<Duration>) -> Self {
+        self.tool_timeout = tool_timeout;
+        self
+    }
+
+    /// Record every tool call in `audit`; `None` leaves calls unaudited.
+    pub fn audit(mut self, audit: Option<AuditLog>) -> Self {
+        self.audit = audit;
+        self
+    }
+
+    /// Register a tool and the async handler invoked with its `arguments`.
+    pub fn tool<F, Fut>(mut self, tool: Tool, handler: F) -> Self
+    where
+        F: Fn(Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Value, RpcError>> + Send + 'static,
+    {
+        let handler: ToolHandler = Arc::new(move |arguments| Box::pin(handler(arguments)));
+        self.tools.push(RegisteredTool { tool, handler });
+        self
+    }
+
+    /// Register a family of resources sharing one URI template.
+    ///
+    /// `list` returns the resources that currently exist; `read` loads one from the part
+    /// of its URI after the template's fixed prefix, returning `Ok(Value::Null)` when
+    /// there is no such resource.
+    pub fn resources<L, LFut, R, RFut>(mut self, template: ResourceTemplate, list: L, read: R) -> Self
+    where
+        L: Fn() -> LFut + Send + Sync + 'static,
+        LFut: Future<Output = Result<Vec<Resource>, RpcError>> + Send + 'static,
+        R: Fn(String) -> RFut + Send + Sync + 'static,
+        RFut: Future<Output = Result<Value, RpcError>> + Send + 'static,
+    {
+        self.resources.push(RegisteredResources::new(template, list, read));
+        self
+    }
+
+    pub fn build(self) -> McpServer {
+        let title = self.title.unwrap_or_else(|| self.name.clone());
+        McpServer {
+            inner: Arc::new(Inner {
+                name: self.name,
+                version: self.version,
+                title,
+                tools: self.tools,
+                resources: self.resources,
+                cache: self.cache,
+                tool_timeout: self.tool_timeout,
+                audit: self.audit,
+                in_flight: InFlight::default(),
+            }),
+        }
+    }
+}
+
+/// Transport-agnostic MCP server: routes JSON-RPC requests to registered tools.
+#[derive(Clone)]
+pub struct McpServer {
+    inner: Arc<Inner>,
+}
+
+impl McpServer {
+    pub fn builder(name: impl Into<String>, version: impl Into<String>) -> McpServerBuilder {
+        McpServerBuilder {
+            name: name.into(),
+            version: version.into(),
+            title: None,
+            tools: Vec::new(),
+            resources: Vec::new(),
+            cache: None,
+            tool_timeout: tool_timeout_from_config(),
+            audit: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.inner.name
+    }
+
+    pub fn version(&self) -> &str {
+        &self.inner.version
+    }
+
+    pub fn title(&self) -> &str {
+        &self.inner.title
+    }
+
+    /// The tool result cache, when caching is enabled.
+    pub fn cache(&self) -> Option<&ResponseCache> {
+        self.inner.cache.as_ref()
+    }
+
+    pub fn tool_names(&self) -> Vec<&str> {
+        self.inner
+            .tools
+            .iter()
+            .map(|registered| registered.tool.name.as_str())
+            .collect()
+    }
+
+    pub fn resource_templates(&self) -> Vec<&str> {
+        self.inner
+            .resources
+            .iter()
+            .map(|registered| registered.template.uri_template.as_str())
+            .collect()
+    }
+
+    /// Handle a JSON-RPC message or batch and return the response envelope.
+    ///
+    /// Notifications (messages without an `id`) produce no response.
+    pub async fn handle_request(&self, request: Value) -> Option<Value> {
+        self.handle_request_with(request, RequestContext::default()).await
+    }
+
+    /// Handle a message from a transport that supports progress and cancellation.
+    ///
+    /// A `tools/call` cancelled by `notifications/cancelled` produces no response.
+    /// Everything logged while handling the message, Duffel calls included, is in a
+    /// `request` span carrying its `request_id`.
+    ///
+    /// A batch (an array of messages) is answered with an array holding one response
+    /// per entry that has an `id`, in the order sent; a failing entry does not affect
+    /// the others. Entries run concurrently, `BATCH_CONCURRENCY` at a time.
+    pub async fn handle_request_with(&self, request: Value, context: RequestContext) -> Option<Value> {
+        match request {
+            Value::Array(requests) => self.handle_batch(requests, context).await,
+            request => self.handle_single(request, context).await,
+        }
+    }
+
+    async fn handle_batch(&self, requests: Vec<Value>, context: RequestContext) -> Option<Value> {
+        if requests.is_empty() {
+            let error = RpcError::invalid_request("Invalid Request: empty batch");
+            return Some(jsonrpc::error(Value::Null, &error));
+        }
+        if requests.len() > MAX_BATCH_LEN {
+            let error = RpcError::invalid_request(format!(
+                "Invalid Request: batch of {} messages, at most {} allowed",
+                requests.len(),
+                MAX_BATCH_LEN
+            ));
+            return Some(jsonrpc::error(Value::Null, &error));
+        }
+
+        debug!("Handling a batch of {} messages", requests.len());
+        let responses: Vec<Value> = stream::iter(requests)
+            .map(|request| {
+                let context = context.clone();
+                async move {
+                    if !request.is_object() {
+                        let error = RpcError::invalid_request("Invalid Request: batch entries must be objects");
+                        return Some(jsonrpc::error(Value::Null, &error));
+                    }
+                    self.handle_single(request, context).await
+                }
+            })
+            .buffered(BATCH_CONCURRENCY)
+            .filter_map(|response| async move { response })
+            .collect()
+            .await;
+
+        // A batch of notifications gets no response at all
+        (!responses.is_empty()).then_some(Value::Array(responses))
+    }
+
+    async fn handle_single(&self, request: Value, mut context: RequestContext) -> Option<Value> {
+        let request_id = context
+            .request_id
+            .get_or_insert_with(|| uuid::Uuid::new_v4().to_string())
+            .clone();
+        let span = info_span!(
+            "request",
+            request_id = %request_id,
+            method = request["method"].as_str().unwrap_or("")
+        );
+        self.dispatch(request, context).instrument(span).await
+    }
+
+    async fn dispatch(&self, request: Value, context: RequestContext) -> Option<Value> {
+        let method = request["method"].as_str().unwrap_or("");
+        let Some(id) = request.get("id").cloned() else {
+            if method == "notifications/cancelled" {
+                self.cancel_request(&request["params"], &context);
+            } else {
+                debug!("Received notification: {}", method);
+            }
+            return None;
+        };
+
+        let result = match method {
+            "initialize" => Ok(self.initialize_result(&request["params"])),
+            "tools/list" => Ok(self.tools_list_result()),
+            "tools/call" => self.call_tool_cancellable(&id, &request["params"], context).await?,
+            "resources/list" => Ok(self.resources_list_result().await),
+            "resources/templates/list" => Ok(self.resource_templates_list_result()),
+            "resources/read" => self.read_resource(&request["params"]).await,
+            _ => Err(RpcError::method_not_found()),
+        };
+
+        Some(match result {
+            Ok(result) => jsonrpc::success(id, result),
+            Err(e) => jsonrpc::error(id, &e),
+        })
+    }
+
+    fn initialize_result(&self, params: &Value) -> Value {
+        // Echo the client's revision when we support it, otherwise offer our latest
+        let requested = params["protocolVersion"].as_str().unwrap_or("");
+        let protocol_version = SUPPORTED_PROTOCOL_VERSIONS
+            .iter()
+            .find(|version| **version == requested)
+            .unwrap_or(&SUPPORTED_PROTOCOL_VERSIONS[0]);
+
+        let mut capabilities = json!({ "tools": {} });
+        if !self.inner.resources.is_empty() {
+            capabilities["resources"] = json!({});
+        }
+
+        json!({
+            "protocolVersion": protocol_version,
+            "capabilities": capabilities,
+            "serverInfo": {
+                "name": self.inner.name,
+                "version": self.inner.version
+            }
+        })
+    }
+
+    fn tools_list_result(&self) -> Value {
+        let tools: Vec<Value> = self
+            .inner
+            .tools
+            .iter()
+            .map(|registered| registered.tool.to_value())
+            .collect();
+        json!({ "tools": tools })
+    }
+
+    async fn resources_list_result(&self) -> Value {
+        let mut resources: Vec<Resource> = Vec::new();
+        for registered in &self.inner.resources {
+            // One unavailable source should not hide the others
+            match (registered.list)().await {
+                Ok(listed) => resources.extend(listed),
+                Err(e) => warn!("Listing {} failed: {}", registered.template.uri_template, e),
+            }
+        }
+        json!({ "resources": resources })
+    }
+
+    fn resource_templates_list_result(&self) -> Value {
+        let templates: Vec<Value> = self
+            .inner
+            .resources
+            .iter()
+            .map(RegisteredResources::template_value)
+            .collect();
+        json!({ "resourceTemplates": templates })
+    }
+
+    async fn read_resource(&self, params: &Value) -> Result<Value, RpcError> {
+        let uri = params["uri"]
+            .as_str()
+            .ok_or_else(|| RpcError::invalid_params("Invalid parameters: uri is required"))?;
+        let (registered, id) = self
+            .inner
+            .resources
+            .iter()
+            .find_map(|registered| {
+                let id = uri.strip_prefix(registered.template.prefix())?;
+                (!id.is_empty()).then_some((registered, id))
+            })
+            .ok_or_else(|| RpcError::resource_not_found(uri))?;
+
+        let document = (registered.read)(id.to_string()).await?;
+        if document.is_null() {
+            return Err(RpcError::resource_not_found(uri));
+        }
+        Ok(resources::read_result(uri, &document))
+    }
+
+    /// Run a tool call until it finishes or the client cancels it (`None`).
+    async fn call_tool_cancellable(
+        &self,
+        id: &Value,
+        params: &Value,
+        context: RequestContext,
+    ) -> Option<Result<Value, RpcError>> {
+        let (_guard, cancelled) = self.inner.in_flight.register((context.connection, id.to_string()));
+        let progress = Progress::for_request(params, context.notifier);
+
+        tokio::select! {
+            result = progress::scope(progress, self.call_tool(params, context.request_id.as_deref())) => Some(result),
+            Ok(()) = cancelled => {
+                info!("Cancelled tools/call request {}", id);
+                None
+            }
+        }
+    }
+
+    fn cancel_request(&self, params: &Value, context: &RequestContext) {
+        let request_id = &params["requestId"];
+        let key = (context.connection.clone(), request_id.to_string());
+        if self.inner.in_flight.cancel(&key) {
+            let reason = params["reason"].as_str().unwrap_or("no reason given");
+            info!("Client cancelled request {}: {}", request_id, reason);
+        } else {
+            debug!("Ignoring cancellation of request {}, which is not running", request_id);
+        }
+    }
+
+    async fn call_tool(&self, params: &Value, request_id: Option<&str>) -> Result<Value, RpcError> {
+        let tool_name = params["name"].as_str().unwrap_or("");
+        let registered = self
+            .inner
+            .tools
+            .iter()
+            .find(|registered| registered.tool.name == tool_name)
+            .ok_or_else(RpcError::method_not_found)?;
+
+        let started = Instant::now();
+        let call = self.run_tool(registered, params["arguments"].clone());
+        let (result, duffel_request_ids) = audit::collect_duffel_requests(async {
+            match self.inner.tool_timeout {
+                Some(limit) => tokio::time::timeout(limit, call).await.unwrap_or_else(|_| {
+                    warn!("Tool {} timed out after {:?}", tool_name, limit);
+                    Err(RpcError::server_error(format!(
+                        "{} timed out after {}s",
+                        tool_name,
+                        limit.as_secs()
+                    )))
+                }),
+                None => call.await,
+            }
+        })
+        .await;
+        metrics::record_tool_call(tool_name, result.is_ok(), started.elapsed());
+
+        // Recorded before answering, so every call the client saw is in the log
+        if let Some(audit) = &self.inner.audit {
+            let entry = AuditEntry::new(
+                &self.inner.name,
+                tool_name,
+                request_id,
+                &params["arguments"],
+                &result,
+                duffel_request_ids,
+                started.elapsed(),
+            );
+            if let Err(e) = audit.record(entry).await {
+                error!("Could not write audit log entry for {}: {:#}", tool_name, e);
+            }
+        }
+        result
+    }
+
+    async fn run_tool(&self, registered: &RegisteredTool, arguments: Value) -> Result<Value, RpcError> {
+        let cache = self.inner.cache.as_ref().filter(|_| registered.tool.cacheable);
+        let Some(cache) = cache else {
+            return (registered.handler)(arguments).await;
+        };
+
+        let key = ResponseCache::key(&registered.tool.name, &arguments);
+        if arguments[CACHE_ARGUMENT].as_bool() == Some(false) {
+            cache.refresh(key, (registered.handler)(arguments)).await
+        } else {
+            cache.get_or_try_insert(key, (registered.handler)(arguments)).await
+        }
+    }
+}
+
+/// Deserialize tool arguments, mapping failures to a `-32602` error.
+pub fn parse_arguments<T: DeserializeOwned>(tool_name: &str, arguments: Value) -> Result<T, RpcError> {
+    serde_json::from_value(arguments).map_err(|e| {
+        error!("Invalid arguments for {}: {}", tool_name, e);
+        RpcError::invalid_params(format!("Invalid parameters: {}", e))
+    })
+}
+
+/// Wrap plain text as an MCP tool result.
+pub fn text_content(text: impl Into<String>) -> Value {
+    json!({
+        "content": [
+            {
+                "type": "text",
+                "text": text.into()
+            }
+        ]
+    })
+}
+
+/// An embedded resource content block carrying a document, base64-encoded, for
+/// tools that return a file (e.g. a calendar) alongside their text.
+pub fn embedded_resource(uri: &str, mime_type: &str, data: &[u8]) -> Value {
+    json!({
+        "type": "resource",
+        "resource": {
+            "uri": uri,
+            "mimeType": mime_type,
+            "blob": base64::engine::general_purpose::STANDARD.encode(data)
+        }
+    })
+}
+
+/// Keep only `fields` in each object of the array `items`, so summary results can
+/// leave detail out of `structuredContent`.
+pub fn retain_fields(items: &mut Value, fields: &[&str]) {
+    for item in items.as_array_mut().into_iter().flatten() {
+        if let Some(item) = item.as_object_mut() {
+            item.retain(|key, _| fields.contains(&key.as_str()));
+        }
+    }
+}
+
+/// Wrap a text rendering plus its machine-readable form as an MCP tool result.
+///
+/// `data` is returned as `structuredContent` and must match the tool's output schema.
+pub fn structured_content<T: Serialize>(text: impl Into<String>, data: &T) -> Result<Value, RpcError> {
+    let structured = serde_json::to_value(data).map_err(|e| {
+        error!("Failed to serialize structured content: {}", e);
+        RpcError::server_error(format!("Failed to serialize tool result: {}", e))
+    })?;
+
+    let mut result = text_content(text);
+    result["structuredContent"] = structured;
+    Ok(result)
+}
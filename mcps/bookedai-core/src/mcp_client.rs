@@ -0,0 +1,119 @@
+//! Minimal client for calling tools on another MCP server over Streamable HTTP.
+//!
+//! Used by servers that orchestrate the others (e.g. the trip planner), so each
+//! domain keeps its validation, filtering and formatting in one place.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+use tracing::debug;
+
+use crate::config;
+use crate::jsonrpc::{RpcError, SERVER_ERROR};
+
+/// Flight searches can take tens of seconds upstream.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Calls `tools/call` on a single MCP endpoint.
+#[derive(Debug, Clone)]
+pub struct McpClient {
+    url: String,
+    auth_token: Option<String>,
+    client: reqwest::Client,
+    next_id: Arc<AtomicU64>,
+}
+
+impl McpClient {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            auth_token: None,
+            client: reqwest::Client::new(),
+            next_id: Arc::new(AtomicU64::new(1)),
+        }
+    }
+
+    /// Build a client for one of the `upstream` URLs in the config.
+    ///
+    /// `upstream.auth_token` (`UPSTREAM_MCP_AUTH_TOKEN`), when set, is sent as a bearer token.
+    pub fn from_config(url: &str) -> Self {
+        let mut client = Self::new(url);
+        client.auth_token = config::get().upstream.auth_token.clone();
+        client
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Ask the server's `GET /health/ready`, on the same host as its MCP endpoint, and
+    /// fail unless it answers with a success status.
+    pub async fn ready(&self) -> anyhow::Result<()> {
+        let url = reqwest::Url::parse(&self.url)?.join("/health/ready")?;
+        let response = self.client.get(url.clone()).send().await.map_err(|e| {
+            // reqwest repeats each cause in the one above it; the innermost says enough
+            let mut cause: &dyn std::error::Error = &e;
+            while let Some(source) = cause.source() {
+                cause = source;
+            }
+            anyhow::anyhow!("cannot reach {}: {}", url, cause)
+        })?;
+        let status = response.status();
+        if !status.is_success() {
+            anyhow::bail!("{} answered {}", url, status);
+        }
+        Ok(())
+    }
+
+    /// Call `name` and return its `structuredContent`.
+    ///
+    /// JSON-RPC errors from the upstream server are passed through unchanged, so
+    /// an invalid argument is still reported as `-32602`.
+    pub async fn call_tool(&self, name: &str, arguments: Value) -> Result<Value, RpcError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "tools/call",
+            "params": { "name": name, "arguments": arguments }
+        });
+        debug!("Calling {} on {}", name, self.url);
+
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header("Accept", "application/json")
+            .timeout(REQUEST_TIMEOUT)
+            .json(&body);
+        if let Some(token) = &self.auth_token {
+            request = request.bearer_auth(token);
+        }
+
+        let upstream_error =
+            |e: &dyn std::fmt::Display| RpcError::server_error(format!("{} at {} failed: {}", name, self.url, e));
+        let response = request.send().await.map_err(|e| upstream_error(&e))?;
+        let status = response.status();
+        let reply: Value = response.json().await.map_err(|e| upstream_error(&e))?;
+
+        if let Some(error) = reply.get("error") {
+            let mut rpc_error = RpcError::new(
+                error["code"].as_i64().unwrap_or(SERVER_ERROR),
+                error["message"].as_str().unwrap_or("Unknown upstream error"),
+            );
+            if let Some(data) = error.get("data") {
+                rpc_error = rpc_error.with_data(data.clone());
+            }
+            return Err(rpc_error);
+        }
+        if !status.is_success() {
+            return Err(upstream_error(&status));
+        }
+
+        reply["result"]
+            .get("structuredContent")
+            .cloned()
+            .ok_or_else(|| upstream_error(&"response has no structuredContent"))
+    }
+}
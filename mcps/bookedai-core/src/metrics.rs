@@ -0,0 +1,134 @@
+//! Prometheus metrics served at `GET /metrics` by the HTTP transport.
+//!
+//! Counters and latency histograms for tool calls and for every Duffel request
+//! attempt, kept in one process-wide registry so any clone of a server or client
+//! reports into it.
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use prometheus::{Encoder, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder};
+
+/// `Content-Type` of [`render`]'s output.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Latency buckets in seconds; Duffel searches routinely take several seconds.
+const LATENCY_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 20.0, 40.0];
+
+struct Metrics {
+    registry: Registry,
+    tool_calls: IntCounterVec,
+    tool_call_duration: HistogramVec,
+    duffel_requests: IntCounterVec,
+    duffel_request_duration: HistogramVec,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+        let tool_calls = IntCounterVec::new(
+            Opts::new("mcp_tool_calls_total", "Tool calls by tool and outcome (ok or error)"),
+            &["tool", "outcome"],
+        )
+        .expect("valid metric");
+        let tool_call_duration = HistogramVec::new(
+            HistogramOpts::new("mcp_tool_call_duration_seconds", "Tool call latency").buckets(LATENCY_BUCKETS.to_vec()),
+            &["tool"],
+        )
+        .expect("valid metric");
+        let duffel_requests = IntCounterVec::new(
+            Opts::new(
+                "duffel_requests_total",
+                "Duffel API request attempts by endpoint and HTTP status (\"error\" when no response arrived)",
+            ),
+            &["method", "endpoint", "status"],
+        )
+        .expect("valid metric");
+        let duffel_request_duration = HistogramVec::new(
+            HistogramOpts::new("duffel_request_duration_seconds", "Duffel API request attempt latency")
+                .buckets(LATENCY_BUCKETS.to_vec()),
+            &["method", "endpoint"],
+        )
+        .expect("valid metric");
+
+        for collector in [
+            Box::new(tool_calls.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(tool_call_duration.clone()),
+            Box::new(duffel_requests.clone()),
+            Box::new(duffel_request_duration.clone()),
+        ] {
+            registry.register(collector).expect("metric registered once");
+        }
+
+        Self {
+            registry,
+            tool_calls,
+            tool_call_duration,
+            duffel_requests,
+            duffel_request_duration,
+        }
+    }
+}
+
+fn metrics() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(Metrics::new)
+}
+
+/// Record a finished tool call.
+pub fn record_tool_call(tool: &str, ok: bool, elapsed: Duration) {
+    let metrics = metrics();
+    let outcome = if ok { "ok" } else { "error" };
+    metrics.tool_calls.with_label_values(&[tool, outcome]).inc();
+    metrics
+        .tool_call_duration
+        .with_label_values(&[tool])
+        .observe(elapsed.as_secs_f64());
+}
+
+/// Record one Duffel request attempt; `status` is `None` when no response arrived.
+pub fn record_duffel_request(method: &str, path: &str, status: Option<u16>, elapsed: Duration) {
+    let metrics = metrics();
+    let endpoint = endpoint(path);
+    let status = status.map_or_else(|| "error".to_string(), |status| status.to_string());
+    metrics
+        .duffel_requests
+        .with_label_values(&[method, &endpoint, &status])
+        .inc();
+    metrics
+        .duffel_request_duration
+        .with_label_values(&[method, &endpoint])
+        .observe(elapsed.as_secs_f64());
+}
+
+/// A Duffel path as a metric label: query dropped and IDs (`ord_0000...`) replaced by
+/// `{id}`, so each endpoint is one series rather than one per order.
+pub fn endpoint(path: &str) -> String {
+    let path = path.split('?').next().unwrap_or(path);
+    path.split('/')
+        .map(|segment| if is_duffel_id(segment) { "{id}" } else { segment })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn is_duffel_id(segment: &str) -> bool {
+    match segment.split_once('_') {
+        Some((prefix, rest)) => {
+            (2..=4).contains(&prefix.len())
+                && prefix.chars().all(|c| c.is_ascii_lowercase())
+                && rest.len() >= 10
+                && rest.chars().all(|c| c.is_ascii_alphanumeric())
+                && rest.chars().any(|c| c.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
+/// Every metric in the Prometheus text exposition format.
+pub fn render() -> String {
+    let mut buffer = Vec::new();
+    if let Err(e) = TextEncoder::new().encode(&metrics().registry.gather(), &mut buffer) {
+        tracing::error!("Failed to encode metrics: {}", e);
+    }
+    String::from_utf8(buffer).unwrap_or_default()
+}
@@ -0,0 +1,235 @@
+//! Canned Duffel responses for `DUFFEL_MODE=mock`.
+//!
+//! The fixtures under `fixtures/duffel` are embedded in the binary and returned in
+//! place of real API responses, so the servers parse them exactly as they would
+//! live data. Searches always return the same London offers and hotels whatever was
+//! asked for, except that stay searches by accommodation ID keep just those hotels,
+//! and accommodation suggestions are the fixture hotels sharing a word with the
+//! query. IDs in request paths are echoed back, offers are made to expire shortly
+//! after they are served, new orders carry the passengers and payment they were
+//! created with, and cancellations are remembered for the life of the process
+//! so a quote can be confirmed.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use chrono::{Duration, SecondsFormat, Utc};
+use serde_json::{json, Value};
+
+use crate::errors::{DuffelError, ErrorKind};
+
+const OFFER_REQUEST: &str = include_str!("../fixtures/duffel/offer_request.json");
+const OFFERS: &str = include_str!("../fixtures/duffel/offers.json");
+const AVAILABLE_SERVICES: &str = include_str!("../fixtures/duffel/available_services.json");
+const SEAT_MAPS: &str = include_str!("../fixtures/duffel/seat_maps.json");
+const ORDER: &str = include_str!("../fixtures/duffel/order.json");
+const ORDER_CANCELLATION: &str = include_str!("../fixtures/duffel/order_cancellation.json");
+const STAYS_SEARCH: &str = include_str!("../fixtures/duffel/stays_search.json");
+const STAY_RATES: &str = include_str!("../fixtures/duffel/stay_rates.json");
+const STAY_BOOKING: &str = include_str!("../fixtures/duffel/stay_booking.json");
+const ACCOMMODATION: &str = include_str!("../fixtures/duffel/accommodation.json");
+
+/// How long a mock offer stays bookable after it is served.
+const OFFER_LIFETIME_MINUTES: i64 = 30;
+
+/// Serves fixture responses for the Duffel endpoints the servers call.
+#[derive(Debug, Clone, Default)]
+pub struct MockDuffel {
+    /// Order cancellations quoted so far, by cancellation ID.
+    cancellations: Arc<Mutex<HashMap<String, Value>>>,
+}
+
+fn fixture(source: &str) -> Value {
+    serde_json::from_str(source).expect("embedded Duffel fixture is valid JSON")
+}
+
+fn now() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// A fresh ID with a Duffel-style prefix, e.g. `orq_0000...`.
+fn new_id(prefix: &str) -> String {
+    format!("{}_0000{}", prefix, uuid::Uuid::new_v4().simple())
+}
+
+/// The fixture's `data` object with its `id` replaced by the one that was asked for.
+fn with_id(source: &str, id: &str) -> Value {
+    let mut response = fixture(source);
+    response["data"]["id"] = json!(id);
+    response
+}
+
+impl MockDuffel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The response Duffel would send to `method path`, or a 404-style error for
+    /// endpoints without a fixture.
+    pub fn respond(&self, method: &str, path: &str, payload: Option<&Value>) -> Result<Value> {
+        let path = path.split('?').next().unwrap_or(path);
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+        let response = match (method, segments.as_slice()) {
+            ("POST", ["air", "offer_requests"]) => with_id(OFFER_REQUEST, &new_id("orq")),
+            ("GET", ["air", "offer_requests", offer_request_id]) => with_id(OFFER_REQUEST, offer_request_id),
+            ("GET", ["air", "offers"]) => self.offers(),
+            ("GET", ["air", "offers", offer_id]) => {
+                let mut offer = json!({ "data": self.offers()["data"][0].clone() });
+                offer["data"]["id"] = json!(offer_id);
+                offer["data"]["available_services"] = fixture(AVAILABLE_SERVICES);
+                offer
+            }
+            ("GET", ["air", "seat_maps"]) => fixture(SEAT_MAPS),
+            ("GET", ["air", "airlines"]) => json!({
+                "data": [{ "id": "arl_00009VME7DAGiJjwomhv32", "name": "British Airways", "iata_code": "BA" }],
+                "meta": { "after": null }
+            }),
+            ("GET", ["air", "orders"]) => json!({
+                "data": [fixture(ORDER)["data"].clone()],
+                "meta": { "after": null }
+            }),
+            ("GET", ["air", "orders", order_id]) => with_id(ORDER, order_id),
+            ("POST", ["air", "orders"]) => self.order(payload),
+            ("POST", ["air", "order_cancellations"]) => {
+                let mut cancellation = with_id(ORDER_CANCELLATION, &new_id("ore"));
+                if let Some(order_id) = payload.and_then(|payload| payload["data"]["order_id"].as_str()) {
+                    cancellation["data"]["order_id"] = json!(order_id);
+                }
+                cancellation["data"]["expires_at"] = json!(
+                    (Utc::now() + Duration::minutes(OFFER_LIFETIME_MINUTES)).to_rfc3339_opts(SecondsFormat::Secs, true)
+                );
+                self.remember(&cancellation);
+                cancellation
+            }
+            ("GET", ["air", "order_cancellations", cancellation_id]) => self.cancellation(cancellation_id),
+            ("POST", ["air", "order_cancellations", cancellation_id, "actions", "confirm"]) => {
+                let mut cancellation = self.cancellation(cancellation_id);
+                cancellation["data"]["confirmed_at"] = json!(now());
+                self.remember(&cancellation);
+                cancellation
+            }
+            ("POST", ["stays", "search"]) => {
+                let mut search = fixture(STAYS_SEARCH);
+                search["meta"]["request_id"] = json!(new_id("mock"));
+                if let Some(ids) = payload.and_then(|payload| payload["data"]["accommodation"]["ids"].as_array()) {
+                    if let Some(results) = search["data"]["results"].as_array_mut() {
+                        results.retain(|result| ids.contains(&result["accommodation"]["id"]));
+                    }
+                }
+                search
+            }
+            ("POST", ["stays", "accommodation", "suggestions"]) => self.suggestions(payload),
+            ("POST", ["stays", "search_results", search_result_id, "actions", "fetch_all_rates"]) => {
+                // Full hotel details, with the rooms and rates from the rates fixture
+                let mut rates = with_id(STAY_RATES, search_result_id);
+                let mut accommodation = fixture(ACCOMMODATION)["data"].clone();
+                accommodation["rooms"] = rates["data"]["accommodation"]["rooms"].take();
+                rates["data"]["accommodation"] = accommodation;
+                rates
+            }
+            ("GET", ["stays", "accommodation", accommodation_id]) => with_id(ACCOMMODATION, accommodation_id),
+            ("GET", ["stays", "bookings"]) => json!({
+                "data": [fixture(STAY_BOOKING)["data"].clone()],
+                "meta": { "after": null }
+            }),
+            ("GET", ["stays", "bookings", booking_id]) => with_id(STAY_BOOKING, booking_id),
+            ("POST", ["stays", "bookings", booking_id, "actions", "cancel"]) => {
+                let mut booking = with_id(STAY_BOOKING, booking_id);
+                booking["data"]["status"] = json!("cancelled");
+                booking["data"]["cancelled_at"] = json!(now());
+                booking
+            }
+            _ => {
+                return Err(DuffelError::new(
+                    ErrorKind::NotFound,
+                    Some(404),
+                    format!("no mock fixture for {} {}", method, path),
+                )
+                .into())
+            }
+        };
+        Ok(response)
+    }
+
+    /// The search fixture's hotels whose names share a word with the query.
+    fn suggestions(&self, payload: Option<&Value>) -> Value {
+        let query = payload
+            .and_then(|payload| payload["data"]["query"].as_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        let words: Vec<&str> = query.split_whitespace().collect();
+        let suggestions: Vec<Value> = fixture(STAYS_SEARCH)["data"]["results"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|result| &result["accommodation"])
+            .filter(|accommodation| {
+                let name = accommodation["name"].as_str().unwrap_or_default().to_lowercase();
+                let chain = accommodation["chain"]["name"].as_str().unwrap_or_default().to_lowercase();
+                words.iter().any(|word| name.contains(word) || chain.contains(word))
+            })
+            .map(|accommodation| {
+                json!({
+                    "accommodation_id": accommodation["id"],
+                    "accommodation_name": accommodation["name"],
+                    "accommodation_location": accommodation["location"]
+                })
+            })
+            .collect();
+        json!({ "data": suggestions })
+    }
+
+    /// The offer fixtures, bookable for the next half hour.
+    fn offers(&self) -> Value {
+        let mut offers = fixture(OFFERS);
+        let expires_at = (Utc::now() + Duration::minutes(OFFER_LIFETIME_MINUTES)).to_rfc3339_opts(SecondsFormat::Secs, true);
+        if let Some(items) = offers["data"].as_array_mut() {
+            for offer in items {
+                offer["expires_at"] = json!(expires_at);
+            }
+        }
+        offers
+    }
+
+    /// A new order for the offer, passengers and payment in `payload`.
+    fn order(&self, payload: Option<&Value>) -> Value {
+        let mut order = with_id(ORDER, &new_id("ord"));
+        order["data"]["created_at"] = json!(now());
+        let Some(request) = payload.map(|payload| &payload["data"]) else {
+            return order;
+        };
+        if let Some(payment) = request["payments"].get(0) {
+            order["data"]["total_amount"] = payment["amount"].clone();
+            order["data"]["total_currency"] = payment["currency"].clone();
+        }
+        if let Some(passengers) = request["passengers"].as_array() {
+            order["data"]["passengers"] = passengers
+                .iter()
+                .map(|passenger| {
+                    json!({
+                        "id": passenger["id"],
+                        "given_name": passenger["given_name"],
+                        "family_name": passenger["family_name"]
+                    })
+                })
+                .collect();
+        }
+        order
+    }
+
+    fn cancellation(&self, cancellation_id: &str) -> Value {
+        let quoted = self.cancellations.lock().unwrap().get(cancellation_id).cloned();
+        quoted.unwrap_or_else(|| with_id(ORDER_CANCELLATION, cancellation_id))
+    }
+
+    fn remember(&self, cancellation: &Value) {
+        if let Some(id) = cancellation["data"]["id"].as_str() {
+            self.cancellations
+                .lock()
+                .unwrap()
+                .insert(id.to_string(), cancellation.clone());
+        }
+    }
+}
@@ -0,0 +1,359 @@
+//! Booking confirmations, delivered by email (SMTP) and webhook.
+//!
+//! Each server turns its own order or booking into a [`Confirmation`]; the
+//! [`Notifier`] renders it with one shared template and hands it to every
+//! configured sink. Sinks are set up in `[notifications]`: `smtp_host` enables
+//! email and `webhook_url` enables webhooks, so with neither nothing is sent.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
+use futures_util::future::{self, BoxFuture};
+use hmac::{Hmac, Mac};
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use tracing::{info, warn};
+
+use crate::{config, RpcError};
+
+/// Header carrying the webhook signature: `t=<unix time>,v1=<hex HMAC-SHA256 of "t.body">`,
+/// the scheme Duffel signs its own webhooks with.
+pub const SIGNATURE_HEADER: &str = "x-bookedai-signature";
+
+/// Longest an SMTP conversation or webhook call may take.
+const SEND_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// What was booked, for the confirmation template.
+#[derive(Debug, Clone, Serialize)]
+pub struct Confirmation {
+    /// `flight_order` or `stay_booking`.
+    pub kind: &'static str,
+    /// Duffel order or booking ID.
+    pub booking_id: String,
+    /// Airline or hotel booking reference.
+    pub reference: Option<String>,
+    /// One line naming the trip, e.g. `Flight LHR to JFK, 15 Mar 2027`.
+    pub title: String,
+    /// Flight segments or hotel dates, one per line.
+    pub details: Vec<String>,
+    /// Email addresses to confirm to; webhooks ignore them.
+    pub recipients: Vec<String>,
+    /// The order or booking as its lookup tool returns it, for webhook consumers.
+    pub booking: Value,
+}
+
+impl Confirmation {
+    pub fn subject(&self) -> String {
+        match &self.reference {
+            Some(reference) => format!("Booking confirmed: {} (ref {})", self.title, reference),
+            None => format!("Booking confirmed: {}", self.title),
+        }
+    }
+
+    /// The plain-text message every sink sends.
+    pub fn text(&self) -> String {
+        let mut text = format!("Your booking is confirmed.\n\n{}\n", self.title);
+        if let Some(reference) = &self.reference {
+            text.push_str(&format!("Booking reference: {}\n", reference));
+        }
+        text.push('\n');
+        for line in &self.details {
+            text.push_str(line);
+            text.push('\n');
+        }
+        text.push_str(&format!(
+            "\nBooking ID: {}\nKeep this reference for check-in and for any changes to your booking.\n",
+            self.booking_id
+        ));
+        text
+    }
+}
+
+/// The outcome of sending one confirmation through one sink.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Delivery {
+    /// `email` or `webhook`.
+    pub channel: String,
+    pub sent: bool,
+    /// Who received it, or why it failed.
+    pub detail: String,
+}
+
+/// Somewhere confirmations are delivered.
+pub trait NotificationSink: fmt::Debug + Send + Sync {
+    /// Channel name reported in [`Delivery::channel`].
+    fn channel(&self) -> &'static str;
+
+    /// Deliver `confirmation`; `Ok` describes where it went.
+    fn send<'a>(&'a self, confirmation: &'a Confirmation) -> BoxFuture<'a, Result<String>>;
+}
+
+/// Confirmations by email, to the booking's travellers.
+pub struct EmailSink {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    bcc: Option<Mailbox>,
+}
+
+impl fmt::Debug for EmailSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EmailSink").field("from", &self.from.to_string()).finish_non_exhaustive()
+    }
+}
+
+impl EmailSink {
+    fn from_config(notifications: &config::NotificationsConfig, host: &str) -> Result<Self> {
+        let builder = match notifications.smtp_security.as_str() {
+            "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(host)?,
+            "starttls" => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?,
+            _ => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host),
+        };
+        let mut builder = builder.port(notifications.smtp_port).timeout(Some(SEND_TIMEOUT));
+        if let (Some(username), Some(password)) = (&notifications.smtp_username, &notifications.smtp_password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        let from = notifications
+            .email_from
+            .as_deref()
+            .ok_or_else(|| anyhow!("notifications.email_from is required with notifications.smtp_host"))?;
+        let bcc = notifications
+            .email_bcc
+            .as_deref()
+            .map(|bcc| bcc.parse().with_context(|| format!("invalid notifications.email_bcc '{}'", bcc)))
+            .transpose()?;
+        Ok(Self {
+            transport: builder.build(),
+            from: from.parse().with_context(|| format!("invalid notifications.email_from '{}'", from))?,
+            bcc,
+        })
+    }
+
+    async fn deliver(&self, confirmation: &Confirmation) -> Result<String> {
+        if confirmation.recipients.is_empty() {
+            return Err(anyhow!("the booking has no email address to send to"));
+        }
+        let mut message = Message::builder().from(self.from.clone());
+        for recipient in &confirmation.recipients {
+            let mailbox: Mailbox = recipient
+                .parse()
+                .with_context(|| format!("invalid email address '{}'", recipient))?;
+            message = message.to(mailbox);
+        }
+        if let Some(bcc) = &self.bcc {
+            message = message.bcc(bcc.clone());
+        }
+        let message = message
+            .subject(confirmation.subject())
+            .header(ContentType::TEXT_PLAIN)
+            .body(confirmation.text())?;
+
+        self.transport.send(message).await?;
+        Ok(format!("sent to {}", confirmation.recipients.join(", ")))
+    }
+}
+
+impl NotificationSink for EmailSink {
+    fn channel(&self) -> &'static str {
+        "email"
+    }
+
+    fn send<'a>(&'a self, confirmation: &'a Confirmation) -> BoxFuture<'a, Result<String>> {
+        Box::pin(self.deliver(confirmation))
+    }
+}
+
+/// Confirmations POSTed as JSON, optionally signed.
+pub struct WebhookSink {
+    client: reqwest::Client,
+    url: String,
+    secret: Option<String>,
+}
+
+impl fmt::Debug for WebhookSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebhookSink").field("url", &self.url).finish_non_exhaustive()
+    }
+}
+
+impl WebhookSink {
+    pub fn new(url: String, secret: Option<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+            secret,
+        }
+    }
+
+    /// `t=...,v1=...` for `body` signed at `timestamp`.
+    fn signature(secret: &str, timestamp: i64, body: &[u8]) -> Result<String> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())?;
+        mac.update(timestamp.to_string().as_bytes());
+        mac.update(b".");
+        mac.update(body);
+        Ok(format!("t={},v1={}", timestamp, hex::encode(mac.finalize().into_bytes())))
+    }
+
+    async fn deliver(&self, confirmation: &Confirmation) -> Result<String> {
+        let body = serde_json::to_vec(&json!({
+            "type": "booking.confirmed",
+            "kind": confirmation.kind,
+            "booking_id": confirmation.booking_id,
+            "reference": confirmation.reference,
+            "subject": confirmation.subject(),
+            "text": confirmation.text(),
+            "booking": confirmation.booking,
+            "sent_at": Utc::now().to_rfc3339()
+        }))?;
+
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .timeout(SEND_TIMEOUT);
+        if let Some(secret) = &self.secret {
+            request = request.header(SIGNATURE_HEADER, Self::signature(secret, Utc::now().timestamp(), &body)?);
+        }
+        let response = request.body(body).send().await?.error_for_status()?;
+        Ok(format!("delivered to {} ({})", self.url, response.status()))
+    }
+}
+
+impl NotificationSink for WebhookSink {
+    fn channel(&self) -> &'static str {
+        "webhook"
+    }
+
+    fn send<'a>(&'a self, confirmation: &'a Confirmation) -> BoxFuture<'a, Result<String>> {
+        Box::pin(self.deliver(confirmation))
+    }
+}
+
+/// The configured sinks; cheap to clone.
+#[derive(Debug, Clone, Default)]
+pub struct Notifier {
+    sinks: Vec<Arc<dyn NotificationSink>>,
+}
+
+impl Notifier {
+    /// Sinks from `[notifications]`; none when neither email nor webhook is set up.
+    pub fn from_config() -> Result<Self> {
+        let notifications = &config::get().notifications;
+        let mut notifier = Self::default();
+        if let Some(host) = &notifications.smtp_host {
+            notifier = notifier.sink(Arc::new(EmailSink::from_config(notifications, host)?));
+        }
+        if let Some(url) = &notifications.webhook_url {
+            notifier = notifier.sink(Arc::new(WebhookSink::new(url.clone(), notifications.webhook_secret.clone())));
+        }
+        Ok(notifier)
+    }
+
+    pub fn sink(mut self, sink: Arc<dyn NotificationSink>) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    pub fn is_configured(&self) -> bool {
+        !self.sinks.is_empty()
+    }
+
+    /// Channels confirmations go to, e.g. `email, webhook`.
+    pub fn channels(&self) -> String {
+        self.sinks.iter().map(|sink| sink.channel()).collect::<Vec<_>>().join(", ")
+    }
+
+    /// Send through every sink concurrently; one failing does not stop the others.
+    pub async fn send(&self, confirmation: &Confirmation) -> Vec<Delivery> {
+        future::join_all(self.sinks.iter().map(|sink| async move {
+            let channel = sink.channel().to_string();
+            match sink.send(confirmation).await {
+                Ok(detail) => {
+                    info!("Confirmation for {} {} by {}", confirmation.booking_id, detail, channel);
+                    Delivery { channel, sent: true, detail }
+                }
+                Err(e) => {
+                    warn!("Confirmation for {} by {} failed: {:#}", confirmation.booking_id, channel, e);
+                    Delivery {
+                        channel,
+                        sent: false,
+                        detail: format!("{:#}", e),
+                    }
+                }
+            }
+        }))
+        .await
+    }
+
+    /// Send for `send_confirmation`: an error when no sink is configured or every one failed.
+    pub async fn send_requested(&self, confirmation: &Confirmation) -> Result<Vec<Delivery>, RpcError> {
+        if !self.is_configured() {
+            return Err(RpcError::server_error(
+                "Confirmations are unavailable: set SMTP_HOST or NOTIFY_WEBHOOK_URL to enable them",
+            ));
+        }
+        let deliveries = self.send(confirmation).await;
+        if deliveries.iter().all(|delivery| !delivery.sent) {
+            let failures: Vec<String> = deliveries
+                .iter()
+                .map(|delivery| format!("{}: {}", delivery.channel, delivery.detail))
+                .collect();
+            return Err(RpcError::server_error(format!(
+                "Confirmation could not be sent ({})",
+                failures.join("; ")
+            )));
+        }
+        Ok(deliveries)
+    }
+
+    /// Send in the background, for confirmations that should not hold up a booking.
+    pub fn spawn_send(&self, confirmation: Confirmation) {
+        let notifier = self.clone();
+        tokio::spawn(async move {
+            notifier.send(&confirmation).await;
+        });
+    }
+}
+
+/// Delivery lines for a tool's text result.
+pub fn describe(deliveries: &[Delivery]) -> String {
+    deliveries
+        .iter()
+        .map(|delivery| {
+            let outcome = if delivery.sent { "sent" } else { "FAILED" };
+            format!("- {}: {} ({})\n", delivery.channel, outcome, delivery.detail)
+        })
+        .collect()
+}
+
+/// `send_confirmation` output schema, with the tool's ID field.
+pub fn deliveries_schema(id_field: &str) -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            id_field: { "type": "string" },
+            "recipients": { "type": "array", "items": { "type": "string" } },
+            "deliveries": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "channel": { "type": "string", "enum": ["email", "webhook"] },
+                        "sent": { "type": "boolean" },
+                        "detail": { "type": "string", "description": "Who received it, or why it failed" }
+                    },
+                    "required": ["channel", "sent", "detail"]
+                }
+            }
+        },
+        "required": [id_field, "recipients", "deliveries"]
+    })
+}
@@ -0,0 +1,22 @@
+//! Result limits shared by the search and list tools.
+
+use crate::jsonrpc::RpcError;
+
+/// Results returned per page when the client does not pass `limit`.
+pub const DEFAULT_LIMIT: usize = 10;
+
+/// Largest `limit` a client may request in one call.
+pub const MAX_LIMIT: usize = 50;
+
+/// Resolve a client-supplied `limit`, rejecting values outside `1..=MAX_LIMIT`.
+pub fn resolve_limit(limit: Option<usize>) -> Result<usize, RpcError> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT);
+    if (1..=MAX_LIMIT).contains(&limit) {
+        Ok(limit)
+    } else {
+        Err(RpcError::invalid_params(format!(
+            "Invalid parameters: limit must be between 1 and {}",
+            MAX_LIMIT
+        )))
+    }
+}
@@ -0,0 +1,278 @@
+//! Saved searches whose price is re-checked in the background.
+//!
+//! Each alert stores the tool arguments needed to repeat its search and a target
+//! price; every check appends to its price history. An alert is `triggered` the
+//! first time the price reaches the target and keeps being tracked until the
+//! search can no longer run (e.g. the travel date has passed), when it `expired`.
+
+use anyhow::Result;
+use chrono::Utc;
+use rusqlite::{params, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::store::Store;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS price_alerts (
+    id TEXT PRIMARY KEY,
+    kind TEXT NOT NULL,
+    description TEXT NOT NULL,
+    search TEXT NOT NULL,
+    target_price REAL NOT NULL,
+    currency TEXT,
+    status TEXT NOT NULL DEFAULT 'active',
+    created_at TEXT NOT NULL,
+    last_checked_at TEXT,
+    last_price REAL,
+    last_currency TEXT,
+    lowest_price REAL,
+    triggered_at TEXT,
+    last_error TEXT
+);
+CREATE TABLE IF NOT EXISTS price_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    alert_id TEXT NOT NULL REFERENCES price_alerts(id) ON DELETE CASCADE,
+    checked_at TEXT NOT NULL,
+    price REAL NOT NULL,
+    currency TEXT NOT NULL,
+    offer_id TEXT
+);
+CREATE INDEX IF NOT EXISTS price_history_alert ON price_history(alert_id, checked_at);
+";
+
+const ALERT_COLUMNS: &str = "id, kind, description, search, target_price, currency, status, created_at, \
+     last_checked_at, last_price, last_currency, lowest_price, triggered_at, last_error";
+
+/// A tracked search.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceAlert {
+    pub id: String,
+    /// What is being tracked, e.g. `flight`.
+    pub kind: String,
+    /// Human-readable summary, e.g. `JFK -> LHR on 2025-03-15`.
+    pub description: String,
+    /// Tool arguments that repeat the search.
+    pub search: Value,
+    pub target_price: f64,
+    /// Currency of `target_price`; `None` means the offers' own currency.
+    pub currency: Option<String>,
+    /// `active`, `triggered`, or `expired`.
+    pub status: String,
+    pub created_at: String,
+    pub last_checked_at: Option<String>,
+    pub last_price: Option<f64>,
+    pub last_currency: Option<String>,
+    pub lowest_price: Option<f64>,
+    pub triggered_at: Option<String>,
+    /// Why the latest check failed, cleared by the next successful one.
+    pub last_error: Option<String>,
+}
+
+/// One observed price.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PricePoint {
+    pub checked_at: String,
+    pub price: f64,
+    pub currency: String,
+    /// Cheapest offer at the time, when the search returned one.
+    pub offer_id: Option<String>,
+}
+
+fn alert_from_row(row: &Row) -> rusqlite::Result<PriceAlert> {
+    let search: String = row.get(3)?;
+    Ok(PriceAlert {
+        id: row.get(0)?,
+        kind: row.get(1)?,
+        description: row.get(2)?,
+        search: serde_json::from_str(&search).unwrap_or(Value::Null),
+        target_price: row.get(4)?,
+        currency: row.get(5)?,
+        status: row.get(6)?,
+        created_at: row.get(7)?,
+        last_checked_at: row.get(8)?,
+        last_price: row.get(9)?,
+        last_currency: row.get(10)?,
+        lowest_price: row.get(11)?,
+        triggered_at: row.get(12)?,
+        last_error: row.get(13)?,
+    })
+}
+
+/// Alert and price-history tables in the shared [`Store`].
+#[derive(Debug, Clone)]
+pub struct PriceAlerts {
+    store: Store,
+}
+
+impl PriceAlerts {
+    pub async fn new(store: Store) -> Result<Self> {
+        store.migrate(SCHEMA).await?;
+        Ok(Self { store })
+    }
+
+    /// Save a new active alert.
+    pub async fn create(
+        &self,
+        kind: &str,
+        description: String,
+        search: Value,
+        target_price: f64,
+        currency: Option<String>,
+    ) -> Result<PriceAlert> {
+        let alert = PriceAlert {
+            id: format!("pal_{}", uuid::Uuid::new_v4().simple()),
+            kind: kind.to_string(),
+            description,
+            search,
+            target_price,
+            currency,
+            status: "active".to_string(),
+            created_at: Utc::now().to_rfc3339(),
+            last_checked_at: None,
+            last_price: None,
+            last_currency: None,
+            lowest_price: None,
+            triggered_at: None,
+            last_error: None,
+        };
+        let saved = alert.clone();
+        self.store
+            .call(move |connection| {
+                connection.execute(
+                    "INSERT INTO price_alerts (id, kind, description, search, target_price, currency, status, created_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    params![
+                        saved.id,
+                        saved.kind,
+                        saved.description,
+                        saved.search.to_string(),
+                        saved.target_price,
+                        saved.currency,
+                        saved.status,
+                        saved.created_at
+                    ],
+                )?;
+                Ok(())
+            })
+            .await?;
+        Ok(alert)
+    }
+
+    pub async fn get(&self, id: &str) -> Result<Option<PriceAlert>> {
+        let id = id.to_string();
+        self.store
+            .call(move |connection| {
+                let sql = format!("SELECT {} FROM price_alerts WHERE id = ?1", ALERT_COLUMNS);
+                Ok(connection.query_row(&sql, [id], alert_from_row).optional()?)
+            })
+            .await
+    }
+
+    /// Alerts of `kind`, newest first, optionally only those with `status`.
+    pub async fn list(&self, kind: &str, status: Option<&str>) -> Result<Vec<PriceAlert>> {
+        let kind = kind.to_string();
+        let status = status.map(str::to_string);
+        self.store
+            .call(move |connection| {
+                let sql = format!(
+                    "SELECT {} FROM price_alerts WHERE kind = ?1 AND (?2 IS NULL OR status = ?2)
+                     ORDER BY created_at DESC",
+                    ALERT_COLUMNS
+                );
+                let mut statement = connection.prepare(&sql)?;
+                let alerts = statement
+                    .query_map(params![kind, status], alert_from_row)?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                Ok(alerts)
+            })
+            .await
+    }
+
+    /// Alerts of `kind` that are still being tracked.
+    pub async fn due(&self, kind: &str) -> Result<Vec<PriceAlert>> {
+        let mut alerts = self.list(kind, None).await?;
+        alerts.retain(|alert| alert.status != "expired");
+        Ok(alerts)
+    }
+
+    /// The latest `limit` prices for an alert, newest first.
+    pub async fn history(&self, id: &str, limit: usize) -> Result<Vec<PricePoint>> {
+        let id = id.to_string();
+        self.store
+            .call(move |connection| {
+                let mut statement = connection.prepare(
+                    "SELECT checked_at, price, currency, offer_id FROM price_history
+                     WHERE alert_id = ?1 ORDER BY id DESC LIMIT ?2",
+                )?;
+                let points = statement
+                    .query_map(params![id, limit as i64], |row| {
+                        Ok(PricePoint {
+                            checked_at: row.get(0)?,
+                            price: row.get(1)?,
+                            currency: row.get(2)?,
+                            offer_id: row.get(3)?,
+                        })
+                    })?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                Ok(points)
+            })
+            .await
+    }
+
+    /// Store a check's price; returns `true` when this check triggered the alert.
+    pub async fn record_price(&self, id: &str, point: PricePoint) -> Result<bool> {
+        let id = id.to_string();
+        self.store
+            .call(move |connection| {
+                let transaction = connection.transaction()?;
+                transaction.execute(
+                    "INSERT INTO price_history (alert_id, checked_at, price, currency, offer_id)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![id, point.checked_at, point.price, point.currency, point.offer_id],
+                )?;
+                let triggered = transaction.execute(
+                    "UPDATE price_alerts SET status = 'triggered', triggered_at = ?2
+                     WHERE id = ?1 AND status = 'active' AND ?3 <= target_price",
+                    params![id, point.checked_at, point.price],
+                )? > 0;
+                transaction.execute(
+                    "UPDATE price_alerts SET last_checked_at = ?2, last_price = ?3, last_currency = ?4,
+                         lowest_price = MIN(COALESCE(lowest_price, ?3), ?3), last_error = NULL
+                     WHERE id = ?1",
+                    params![id, point.checked_at, point.price, point.currency],
+                )?;
+                transaction.commit()?;
+                Ok(triggered)
+            })
+            .await
+    }
+
+    /// Note a failed check without touching the price history.
+    pub async fn record_error(&self, id: &str, message: String) -> Result<()> {
+        let id = id.to_string();
+        self.store
+            .call(move |connection| {
+                connection.execute(
+                    "UPDATE price_alerts SET last_checked_at = ?2, last_error = ?3 WHERE id = ?1",
+                    params![id, Utc::now().to_rfc3339(), message],
+                )?;
+                Ok(())
+            })
+            .await
+    }
+
+    /// Stop tracking an alert whose search can no longer run.
+    pub async fn expire(&self, id: &str, reason: String) -> Result<()> {
+        let id = id.to_string();
+        self.store
+            .call(move |connection| {
+                connection.execute(
+                    "UPDATE price_alerts SET status = 'expired', last_error = ?2 WHERE id = ?1",
+                    params![id, reason],
+                )?;
+                Ok(())
+            })
+            .await
+    }
+}
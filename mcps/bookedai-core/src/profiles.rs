@@ -0,0 +1,330 @@
+//! Traveller profiles saved for repeat bookings, encrypted at rest.
+//!
+//! Profiles hold passport and loyalty numbers, so each one is stored as a single
+//! AES-256-GCM ciphertext under `profiles.encryption_key` (`TRAVELER_PROFILE_KEY`);
+//! only its ID and timestamps are readable in the database. The ID is bound to the
+//! ciphertext as associated data, so a profile cannot be swapped into another row,
+//! and reading with the wrong key fails instead of returning garbage.
+
+use std::fmt;
+use std::sync::Arc;
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use chrono::Utc;
+use rusqlite::{params, OptionalExtension};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+use crate::store::Store;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS traveler_profiles (
+    id TEXT PRIMARY KEY,
+    nonce BLOB NOT NULL,
+    ciphertext BLOB NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+";
+
+/// Prefix of profile IDs, e.g. `tvp_4f0c...`.
+pub const ID_PREFIX: &str = "tvp_";
+
+const KEY_BYTES: usize = 32;
+
+/// Decode a base64 AES-256 key, as generated by `openssl rand -base64 32`.
+pub fn decode_key(encoded: &str) -> Result<Vec<u8>> {
+    let key = base64::engine::general_purpose::STANDARD
+        .decode(encoded.trim())
+        .map_err(|e| anyhow!("not base64: {}", e))?;
+    if key.len() != KEY_BYTES {
+        return Err(anyhow!(
+            "must decode to {} bytes, got {}; generate one with `openssl rand -base64 32`",
+            KEY_BYTES,
+            key.len()
+        ));
+    }
+    Ok(key)
+}
+
+/// A stored profile with its bookkeeping.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedProfile<T> {
+    pub id: String,
+    pub profile: T,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// The encrypted profile table in the shared [`Store`].
+#[derive(Clone)]
+pub struct TravelerProfiles {
+    store: Store,
+    cipher: Arc<Aes256Gcm>,
+}
+
+impl fmt::Debug for TravelerProfiles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TravelerProfiles").finish_non_exhaustive()
+    }
+}
+
+impl TravelerProfiles {
+    pub async fn new(store: Store, key: &[u8]) -> Result<Self> {
+        if key.len() != KEY_BYTES {
+            return Err(anyhow!("profile encryption key must be {} bytes", KEY_BYTES));
+        }
+        store.migrate(SCHEMA).await?;
+        Ok(Self {
+            store,
+            cipher: Arc::new(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key))),
+        })
+    }
+
+    /// Profiles keyed by `profiles.encryption_key`; `None` when no key is configured.
+    pub async fn from_config(store: Store) -> Result<Option<Self>> {
+        match &config::get().profiles.encryption_key {
+            Some(key) => Ok(Some(Self::new(store, &decode_key(key)?).await?)),
+            None => Ok(None),
+        }
+    }
+
+    fn encrypt<T: Serialize>(&self, id: &str, profile: &T) -> Result<(Vec<u8>, Vec<u8>)> {
+        let plaintext = serde_json::to_vec(profile)?;
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, Payload { msg: &plaintext, aad: id.as_bytes() })
+            .map_err(|_| anyhow!("could not encrypt profile {}", id))?;
+        Ok((nonce.to_vec(), ciphertext))
+    }
+
+    fn decrypt<T: DeserializeOwned>(&self, id: &str, nonce: &[u8], ciphertext: &[u8]) -> Result<T> {
+        if nonce.len() != 12 {
+            return Err(anyhow!("profile {} is corrupt", id));
+        }
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: id.as_bytes() })
+            .map_err(|_| {
+                anyhow!("profile {} could not be decrypted; it was saved with a different TRAVELER_PROFILE_KEY", id)
+            })?;
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+
+    /// Save a new profile.
+    pub async fn create<T: Serialize>(&self, profile: T) -> Result<SavedProfile<T>> {
+        let id = format!("{}{}", ID_PREFIX, uuid::Uuid::new_v4().simple());
+        let now = Utc::now().to_rfc3339();
+        let (nonce, ciphertext) = self.encrypt(&id, &profile)?;
+        let (row_id, created_at) = (id.clone(), now.clone());
+        self.store
+            .call(move |connection| {
+                connection.execute(
+                    "INSERT INTO traveler_profiles (id, nonce, ciphertext, created_at, updated_at) \
+                     VALUES (?1, ?2, ?3, ?4, ?4)",
+                    params![row_id, nonce, ciphertext, created_at],
+                )?;
+                Ok(())
+            })
+            .await?;
+        Ok(SavedProfile {
+            id,
+            profile,
+            created_at: now.clone(),
+            updated_at: now,
+        })
+    }
+
+    /// Replace a profile's details; `None` when there is no profile `id`.
+    pub async fn update<T: Serialize>(&self, id: &str, profile: T) -> Result<Option<SavedProfile<T>>> {
+        let now = Utc::now().to_rfc3339();
+        let (nonce, ciphertext) = self.encrypt(id, &profile)?;
+        let (row_id, updated_at) = (id.to_string(), now.clone());
+        let created_at = self
+            .store
+            .call(move |connection| {
+                let updated = connection.execute(
+                    "UPDATE traveler_profiles SET nonce = ?2, ciphertext = ?3, updated_at = ?4 WHERE id = ?1",
+                    params![row_id, nonce, ciphertext, updated_at],
+                )?;
+                if updated == 0 {
+                    return Ok(None);
+                }
+                Ok(connection
+                    .query_row("SELECT created_at FROM traveler_profiles WHERE id = ?1", [&row_id], |row| row.get(0))
+                    .optional()?)
+            })
+            .await?;
+        Ok(created_at.map(|created_at| SavedProfile {
+            id: id.to_string(),
+            profile,
+            created_at,
+            updated_at: now,
+        }))
+    }
+
+    pub async fn get<T: DeserializeOwned>(&self, id: &str) -> Result<Option<SavedProfile<T>>> {
+        let row_id = id.to_string();
+        let row = self
+            .store
+            .call(move |connection| {
+                Ok(connection
+                    .query_row(
+                        "SELECT nonce, ciphertext, created_at, updated_at FROM traveler_profiles WHERE id = ?1",
+                        [&row_id],
+                        |row| Ok((row.get::<_, Vec<u8>>(0)?, row.get::<_, Vec<u8>>(1)?, row.get(2)?, row.get(3)?)),
+                    )
+                    .optional()?)
+            })
+            .await?;
+        row.map(|(nonce, ciphertext, created_at, updated_at)| {
+            Ok(SavedProfile {
+                id: id.to_string(),
+                profile: self.decrypt(id, &nonce, &ciphertext)?,
+                created_at,
+                updated_at,
+            })
+        })
+        .transpose()
+    }
+
+    /// Every profile, most recently updated first.
+    pub async fn list<T: DeserializeOwned>(&self) -> Result<Vec<SavedProfile<T>>> {
+        let rows = self
+            .store
+            .call(|connection| {
+                let mut statement = connection.prepare(
+                    "SELECT id, nonce, ciphertext, created_at, updated_at FROM traveler_profiles \
+                     ORDER BY updated_at DESC",
+                )?;
+                let rows = statement
+                    .query_map([], |row| {
+                        Ok((
+                            row.get::<_, String>(0)?,
+                            row.get::<_, Vec<u8>>(1)?,
+                            row.get::<_, Vec<u8>>(2)?,
+                            row.get(3)?,
+                            row.get(4)?,
+                        ))
+                    })?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                Ok(rows)
+            })
+            .await?;
+        rows.into_iter()
+            .map(|(id, nonce, ciphertext, created_at, updated_at)| {
+                Ok(SavedProfile {
+                    profile: self.decrypt(&id, &nonce, &ciphertext)?,
+                    id,
+                    created_at,
+                    updated_at,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Traveler {
+        name: String,
+        passport: String,
+    }
+
+    fn traveler() -> Traveler {
+        Traveler {
+            name: "Ada Lovelace".to_string(),
+            passport: "X1234567".to_string(),
+        }
+    }
+
+    async fn profiles(store: &Store, key_byte: u8) -> TravelerProfiles {
+        TravelerProfiles::new(store.clone(), &[key_byte; KEY_BYTES]).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn round_trips_profiles_through_the_database() {
+        let store = Store::open(":memory:").unwrap();
+        let profiles = profiles(&store, 7).await;
+        let saved = profiles.create(traveler()).await.unwrap();
+        assert!(saved.id.starts_with(ID_PREFIX));
+
+        let read = profiles.get::<Traveler>(&saved.id).await.unwrap().unwrap();
+        assert_eq!(read.profile, traveler());
+        assert_eq!(profiles.list::<Traveler>().await.unwrap().len(), 1);
+        assert!(profiles.get::<Traveler>("tvp_missing").await.unwrap().is_none());
+
+        let updated = Traveler {
+            passport: "Y7654321".to_string(),
+            ..traveler()
+        };
+        profiles.update(&saved.id, updated).await.unwrap().unwrap();
+        let read = profiles.get::<Traveler>(&saved.id).await.unwrap().unwrap();
+        assert_eq!(read.profile.passport, "Y7654321");
+    }
+
+    #[tokio::test]
+    async fn stores_only_ciphertext() {
+        let store = Store::open(":memory:").unwrap();
+        let saved = profiles(&store, 7).await.create(traveler()).await.unwrap();
+        let ciphertext: Vec<u8> = store
+            .call(move |connection| {
+                Ok(connection.query_row("SELECT ciphertext FROM traveler_profiles WHERE id = ?1", [&saved.id], |row| {
+                    row.get(0)
+                })?)
+            })
+            .await
+            .unwrap();
+        let stored = String::from_utf8_lossy(&ciphertext);
+        assert!(!stored.contains("X1234567") && !stored.contains("Lovelace"));
+    }
+
+    #[tokio::test]
+    async fn a_different_key_cannot_decrypt() {
+        let store = Store::open(":memory:").unwrap();
+        let saved = profiles(&store, 7).await.create(traveler()).await.unwrap();
+
+        let other = profiles(&store, 8).await;
+        let error = other.get::<Traveler>(&saved.id).await.unwrap_err();
+        assert!(error.to_string().contains("different TRAVELER_PROFILE_KEY"), "{}", error);
+        assert!(other.list::<Traveler>().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn a_profile_moved_to_another_row_cannot_decrypt() {
+        let store = Store::open(":memory:").unwrap();
+        let profiles = profiles(&store, 7).await;
+        let first = profiles.create(traveler()).await.unwrap();
+        let second = profiles.create(traveler()).await.unwrap();
+        let (from, to) = (first.id.clone(), second.id.clone());
+        store
+            .call(move |connection| {
+                connection.execute(
+                    "UPDATE traveler_profiles SET (nonce, ciphertext) = \
+                     (SELECT nonce, ciphertext FROM traveler_profiles WHERE id = ?1) WHERE id = ?2",
+                    params![from, to],
+                )?;
+                Ok(())
+            })
+            .await
+            .unwrap();
+        assert!(profiles.get::<Traveler>(&second.id).await.is_err());
+    }
+
+    #[test]
+    fn decodes_only_32_byte_base64_keys() {
+        let key = base64::engine::general_purpose::STANDARD.encode([1u8; KEY_BYTES]);
+        assert_eq!(decode_key(&key).unwrap(), vec![1u8; KEY_BYTES]);
+        assert!(decode_key("not base64!").is_err());
+        let short = base64::engine::general_purpose::STANDARD.encode([1u8; 16]);
+        assert!(decode_key(&short).unwrap_err().to_string().contains("must decode to 32 bytes"));
+    }
+}
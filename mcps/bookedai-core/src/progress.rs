@@ -0,0 +1,69 @@
+//! MCP progress notifications for long-running tool calls.
+//!
+//! When a `tools/call` request carries `_meta.progressToken` and its transport can
+//! push messages mid-request (stdio, or `POST /mcp` answered as an SSE stream), the
+//! call runs with a [`Progress`] reporter in scope. Tool code anywhere below the
+//! handler reports stages with [`report`], which is a no-op otherwise.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+use tokio::sync::mpsc::UnboundedSender;
+
+tokio::task_local! {
+    static PROGRESS: Option<Progress>;
+}
+
+/// Sends `notifications/progress` for one request.
+#[derive(Debug, Clone)]
+pub struct Progress {
+    token: Value,
+    notifier: UnboundedSender<Value>,
+    /// Steps reported so far; progress must only ever increase.
+    steps: Arc<AtomicU64>,
+}
+
+impl Progress {
+    /// A reporter for a request's `params`, if the client asked for progress.
+    pub fn for_request(params: &Value, notifier: Option<UnboundedSender<Value>>) -> Option<Self> {
+        let token = params["_meta"]["progressToken"].clone();
+        if !(token.is_string() || token.is_number()) {
+            return None;
+        }
+        Some(Self {
+            token,
+            notifier: notifier?,
+            steps: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    fn notify(&self, message: String) {
+        let progress = self.steps.fetch_add(1, Ordering::Relaxed) + 1;
+        let notification = json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/progress",
+            "params": {
+                "progressToken": self.token,
+                "progress": progress,
+                "message": message
+            }
+        });
+        // The client may already have gone away; progress is best effort
+        let _ = self.notifier.send(notification);
+    }
+}
+
+/// Run `future` with `progress` as the reporter [`report`] sends to.
+pub async fn scope<F: std::future::Future>(progress: Option<Progress>, future: F) -> F::Output {
+    PROGRESS.scope(progress, future).await
+}
+
+/// Tell the client what the current tool call is doing, e.g. `"Offer request created"`.
+pub fn report(message: impl Into<String>) {
+    let _ = PROGRESS.try_with(|progress| {
+        if let Some(progress) = progress {
+            progress.notify(message.into());
+        }
+    });
+}
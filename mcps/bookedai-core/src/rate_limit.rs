@@ -0,0 +1,93 @@
+//! Per-client token buckets for the HTTP transport.
+//!
+//! Each client IP address gets `RATE_LIMIT_BURST` requests up front, refilled at
+//! `RATE_LIMIT_PER_MINUTE`. Requests over the limit are answered with `429` before
+//! they reach a tool.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tracing::info;
+
+use crate::config;
+
+/// Bucket count above which idle, full buckets are dropped.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Shared token-bucket limiter keyed by client.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    /// Tokens added per second.
+    rate: f64,
+    burst: f64,
+    buckets: Arc<Mutex<HashMap<String, Bucket>>>,
+}
+
+impl RateLimiter {
+    pub fn new(per_minute: u32, burst: u32) -> Self {
+        Self {
+            rate: f64::from(per_minute) / 60.0,
+            burst: f64::from(burst.max(1)),
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Build from `[rate_limit]` in the config (`RATE_LIMIT_PER_MINUTE` and
+    /// `RATE_LIMIT_BURST`); a rate of `0` disables limiting.
+    pub fn from_config() -> Option<Self> {
+        let config::RateLimitConfig { per_minute, burst } = config::get().rate_limit;
+
+        if per_minute == 0 {
+            info!("Rate limiting disabled");
+            return None;
+        }
+        info!("Rate limit: {} requests/minute per client, burst {}", per_minute, burst);
+        Some(Self::new(per_minute, burst))
+    }
+
+    /// Take one token for `client`, or return how long until one is available.
+    pub fn check(&self, client: &str) -> Result<(), Duration> {
+        self.check_n(client, 1)
+    }
+
+    /// Most tokens a client can hold, and so the most one [`check_n`](Self::check_n) can take.
+    pub fn burst(&self) -> usize {
+        self.burst as usize
+    }
+
+    /// Take `n` tokens for `client` at once, e.g. one per entry of a JSON-RPC batch,
+    /// or none and return how long until `n` are available.
+    pub fn check_n(&self, client: &str, n: usize) -> Result<(), Duration> {
+        let cost = n.max(1) as f64;
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= PRUNE_THRESHOLD {
+            let (rate, burst) = (self.rate, self.burst);
+            buckets.retain(|_, bucket| {
+                bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * rate < burst
+            });
+        }
+
+        let bucket = buckets.entry(client.to_string()).or_insert(Bucket {
+            tokens: self.burst,
+            updated: now,
+        });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+        bucket.updated = now;
+
+        if bucket.tokens >= cost {
+            bucket.tokens -= cost;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((cost - bucket.tokens) / self.rate))
+        }
+    }
+}
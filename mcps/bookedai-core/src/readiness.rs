@@ -0,0 +1,188 @@
+//! Dependency checks behind `GET /health/ready`.
+//!
+//! `/health` only says the process is up; `/health/ready` runs every registered
+//! check and answers 503 when one fails, so a load balancer or orchestrator stops
+//! sending traffic to a server that cannot do its job (a revoked Duffel token, no
+//! network, a broken database, a down upstream MCP server). Checks that cost an
+//! upstream call are cached, so frequent probes do not eat into the Duffel rate limit.
+
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use chrono::Utc;
+use futures_util::future::{self, BoxFuture};
+use serde::Serialize;
+use tokio::sync::Mutex;
+
+use crate::duffel::DuffelClient;
+use crate::mcp_client::McpClient;
+use crate::store::Store;
+
+/// How long a Duffel check result is reused.
+pub const DUFFEL_CHECK_TTL: Duration = Duration::from_secs(60);
+
+/// How long an upstream MCP server check result is reused.
+pub const MCP_SERVER_CHECK_TTL: Duration = Duration::from_secs(30);
+
+/// Longest a single check may take before it counts as failed.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+type CheckFn = Arc<dyn Fn() -> BoxFuture<'static, Result<String>> + Send + Sync>;
+
+/// The outcome of one check.
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckResult {
+    /// `ok` or `failed`.
+    pub status: &'static str,
+    /// What was found, or why the check failed.
+    pub detail: String,
+    pub checked_at: String,
+}
+
+impl CheckResult {
+    pub fn ok(detail: impl Into<String>) -> Self {
+        Self::new("ok", detail.into())
+    }
+
+    pub fn failed(detail: impl Into<String>) -> Self {
+        Self::new("failed", detail.into())
+    }
+
+    fn new(status: &'static str, detail: String) -> Self {
+        Self {
+            status,
+            detail,
+            checked_at: Utc::now().to_rfc3339(),
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.status == "ok"
+    }
+}
+
+struct RegisteredCheck {
+    name: &'static str,
+    run: CheckFn,
+    /// Reuse a result this long; `None` runs the check on every probe.
+    ttl: Option<Duration>,
+    last: Mutex<Option<(Instant, CheckResult)>>,
+}
+
+impl RegisteredCheck {
+    async fn result(&self) -> CheckResult {
+        // Held across the check, so concurrent probes share one upstream call
+        let mut last = self.last.lock().await;
+        if let (Some(ttl), Some((checked, result))) = (self.ttl, last.as_ref()) {
+            if checked.elapsed() < ttl {
+                return result.clone();
+            }
+        }
+
+        let result = match tokio::time::timeout(CHECK_TIMEOUT, (self.run)()).await {
+            Ok(Ok(detail)) => CheckResult::ok(detail),
+            Ok(Err(e)) => CheckResult::failed(format!("{:#}", e)),
+            Err(_) => CheckResult::failed(format!("no answer within {}s", CHECK_TIMEOUT.as_secs())),
+        };
+        *last = Some((Instant::now(), result.clone()));
+        result
+    }
+}
+
+/// The checks a server runs for `GET /health/ready`; register them before serving.
+#[derive(Clone, Default)]
+pub struct Readiness {
+    checks: Vec<Arc<RegisteredCheck>>,
+}
+
+impl fmt::Debug for Readiness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.checks.iter().map(|check| check.name)).finish()
+    }
+}
+
+impl Readiness {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a check run on every probe; `Ok` carries a short description of what was found.
+    pub fn check<F, Fut>(self, name: &'static str, check: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<String>> + Send + 'static,
+    {
+        self.register(name, None, check)
+    }
+
+    /// Add a check whose result is reused for `ttl`, for checks that call an upstream API.
+    pub fn cached_check<F, Fut>(self, name: &'static str, ttl: Duration, check: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<String>> + Send + 'static,
+    {
+        self.register(name, Some(ttl), check)
+    }
+
+    /// Check the Duffel token and network with the cheapest authenticated call there is.
+    pub fn duffel(self, duffel: DuffelClient) -> Self {
+        self.cached_check("duffel", DUFFEL_CHECK_TTL, move || {
+            let duffel = duffel.clone();
+            async move {
+                duffel.get("/air/airlines?limit=1").await?;
+                Ok("authenticated request succeeded".to_string())
+            }
+        })
+    }
+
+    /// Check another MCP server this one calls is ready, by its own `/health/ready`.
+    pub fn mcp_server(self, name: &'static str, client: McpClient) -> Self {
+        self.cached_check(name, MCP_SERVER_CHECK_TTL, move || {
+            let client = client.clone();
+            async move {
+                client.ready().await?;
+                Ok(format!("{} is ready", client.url()))
+            }
+        })
+    }
+
+    /// Check the database answers queries; `None` means it could not be opened at startup.
+    pub fn store(self, store: Option<Store>) -> Self {
+        self.check("store", move || {
+            let store = store.clone();
+            async move {
+                let store = store.ok_or_else(|| anyhow::anyhow!("database could not be opened at startup"))?;
+                store.call(|connection| Ok(connection.query_row("SELECT 1", [], |_| Ok(()))?)).await?;
+                Ok("database is answering queries".to_string())
+            }
+        })
+    }
+
+    fn register<F, Fut>(mut self, name: &'static str, ttl: Option<Duration>, check: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<String>> + Send + 'static,
+    {
+        let run: CheckFn = Arc::new(move || Box::pin(check()));
+        self.checks.push(Arc::new(RegisteredCheck {
+            name,
+            run,
+            ttl,
+            last: Mutex::new(None),
+        }));
+        self
+    }
+
+    /// Run every check concurrently, in registration order.
+    pub async fn run(&self) -> Vec<(&'static str, CheckResult)> {
+        future::join_all(
+            self.checks
+                .iter()
+                .map(|check| async move { (check.name, check.result().await) }),
+        )
+        .await
+    }
+}
@@ -0,0 +1,139 @@
+//! MCP resources: read-only JSON documents clients can browse with `resources/list`
+//! and fetch with `resources/read`, alongside the tools.
+//!
+//! Servers register one [`ResourceTemplate`] per kind of document (e.g.
+//! `booking://flights/{order_id}`) with two handlers: one listing the resources that
+//! currently exist, and one reading a resource from the part of its URI after the
+//! template's fixed prefix.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::jsonrpc::RpcError;
+
+/// Every resource is served as JSON.
+pub const MIME_TYPE: &str = "application/json";
+
+/// One entry in `resources/list`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Resource {
+    pub uri: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub mime_type: String,
+}
+
+impl Resource {
+    pub fn new(uri: impl Into<String>, name: impl Into<String>) -> Self {
+        Self {
+            uri: uri.into(),
+            name: name.into(),
+            title: None,
+            description: None,
+            mime_type: MIME_TYPE.to_string(),
+        }
+    }
+
+    /// Human-readable label shown by clients instead of `name`.
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+}
+
+/// A family of resources addressed by one URI template with a single trailing variable.
+#[derive(Debug, Clone)]
+pub struct ResourceTemplate {
+    pub uri_template: String,
+    pub name: String,
+    pub description: String,
+}
+
+impl ResourceTemplate {
+    pub fn new(uri_template: impl Into<String>, name: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            uri_template: uri_template.into(),
+            name: name.into(),
+            description: description.into(),
+        }
+    }
+
+    /// The URI up to the template variable, e.g. `booking://flights/`.
+    pub fn prefix(&self) -> &str {
+        let end = self.uri_template.find('{').unwrap_or(self.uri_template.len());
+        &self.uri_template[..end]
+    }
+
+    /// URI of the resource whose variable is `id`.
+    pub fn uri(&self, id: &str) -> String {
+        format!("{}{}", self.prefix(), id)
+    }
+
+    fn to_value(&self) -> Value {
+        json!({
+            "uriTemplate": self.uri_template,
+            "name": self.name,
+            "description": self.description,
+            "mimeType": MIME_TYPE
+        })
+    }
+}
+
+type ListFuture = Pin<Box<dyn Future<Output = Result<Vec<Resource>, RpcError>> + Send>>;
+type ReadFuture = Pin<Box<dyn Future<Output = Result<Value, RpcError>> + Send>>;
+pub(crate) type ListHandler = Arc<dyn Fn() -> ListFuture + Send + Sync>;
+pub(crate) type ReadHandler = Arc<dyn Fn(String) -> ReadFuture + Send + Sync>;
+
+/// A template with its handlers, as kept by the [`McpServer`](crate::McpServer).
+pub(crate) struct RegisteredResources {
+    pub template: ResourceTemplate,
+    pub list: ListHandler,
+    pub read: ReadHandler,
+}
+
+impl RegisteredResources {
+    pub fn new<L, LFut, R, RFut>(template: ResourceTemplate, list: L, read: R) -> Self
+    where
+        L: Fn() -> LFut + Send + Sync + 'static,
+        LFut: Future<Output = Result<Vec<Resource>, RpcError>> + Send + 'static,
+        R: Fn(String) -> RFut + Send + Sync + 'static,
+        RFut: Future<Output = Result<Value, RpcError>> + Send + 'static,
+    {
+        Self {
+            template,
+            list: Arc::new(move || Box::pin(list())),
+            read: Arc::new(move |id| Box::pin(read(id))),
+        }
+    }
+
+    pub fn template_value(&self) -> Value {
+        self.template.to_value()
+    }
+}
+
+/// Wrap a resource document as a `resources/read` result.
+pub(crate) fn read_result(uri: &str, document: &Value) -> Value {
+    let text = serde_json::to_string_pretty(document).unwrap_or_else(|_| document.to_string());
+    json!({
+        "contents": [
+            {
+                "uri": uri,
+                "mimeType": MIME_TYPE,
+                "text": text
+            }
+        ]
+    })
+}
@@ -0,0 +1,80 @@
+//! Retry policy and rate-limit header handling for outbound Duffel calls.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::DateTime;
+use rand::Rng;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+
+/// Longest wait we will honor from a `Retry-After` / `Ratelimit-Reset` header.
+const MAX_SERVER_DELAY: Duration = Duration::from_secs(60);
+
+/// Exponential backoff with jitter.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; `0` disables retrying.
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `attempt` (0-based), preferring the server's hint.
+    pub fn delay(&self, attempt: u32, server_hint: Option<Duration>) -> Duration {
+        if let Some(hint) = server_hint {
+            return hint.min(MAX_SERVER_DELAY);
+        }
+
+        let exponential = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        // "Equal jitter": keep half the delay, randomize the other half
+        let half = exponential / 2;
+        half + half.mul_f64(rand::thread_rng().gen::<f64>())
+    }
+}
+
+/// How long the server asked us to wait, from `Retry-After` or `Ratelimit-Reset`.
+///
+/// Both headers may hold delta seconds, a Unix timestamp, or an HTTP date.
+pub fn server_delay(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get(RETRY_AFTER)
+        .or_else(|| headers.get("ratelimit-reset"))
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_delay)
+}
+
+/// Whether the response says no requests remain in the current rate-limit window.
+pub fn rate_limit_exhausted(headers: &HeaderMap) -> bool {
+    headers
+        .get("ratelimit-remaining")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        == Some(0)
+}
+
+fn parse_delay(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+
+    if let Ok(number) = value.parse::<u64>() {
+        // Large values are absolute Unix timestamps rather than delta seconds
+        let seconds = if number > 1_000_000_000 { number.saturating_sub(now) } else { number };
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let reset = u64::try_from(DateTime::parse_from_rfc2822(value).ok()?.timestamp()).ok()?;
+    Some(Duration::from_secs(reset.saturating_sub(now)))
+}
@@ -0,0 +1,31 @@
+//! Periodic background jobs.
+
+use std::future::Future;
+use std::time::Duration;
+
+use anyhow::Result;
+use tokio::task::JoinHandle;
+use tokio::time::{self, Instant, MissedTickBehavior};
+use tracing::{error, info};
+
+/// Run `job` every `interval`, starting one interval from now.
+///
+/// A run that overruns the interval delays the next one rather than stacking up, and
+/// a failed run is logged without stopping the schedule.
+pub fn spawn_periodic<F, Fut>(name: &'static str, interval: Duration, mut job: F) -> JoinHandle<()>
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<()>> + Send,
+{
+    info!("Scheduled {} every {:?}", name, interval);
+    tokio::spawn(async move {
+        let mut ticks = time::interval_at(Instant::now() + interval, interval);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticks.tick().await;
+            if let Err(e) = job().await {
+                error!("{} failed: {}", name, e);
+            }
+        }
+    })
+}
@@ -0,0 +1,286 @@
+//! Search results saved by `search_id`, so later tool calls can refer back to them.
+//!
+//! Every page of offers a search tool returns is appended to its search and numbered
+//! from 1, which lets a client turn "option 3" into a concrete offer ID after the
+//! conversation has moved on. Saved searches are dropped `SEARCH_RESULTS_TTL_SECS`
+//! after their last page was saved.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::info;
+
+use crate::config;
+use crate::jsonrpc::RpcError;
+use crate::store::Store;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS searches (
+    search_id TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL,
+    PRIMARY KEY (kind, search_id)
+);
+CREATE TABLE IF NOT EXISTS search_offers (
+    kind TEXT NOT NULL,
+    search_id TEXT NOT NULL,
+    option INTEGER NOT NULL,
+    offer_id TEXT NOT NULL,
+    expires_at TEXT,
+    offer TEXT NOT NULL,
+    PRIMARY KEY (kind, search_id, option),
+    FOREIGN KEY (kind, search_id) REFERENCES searches(kind, search_id) ON DELETE CASCADE
+);
+";
+
+/// One offer as it was returned to the client.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedOffer {
+    /// 1-based position across every saved page of the search.
+    pub option: usize,
+    pub offer_id: String,
+    /// When Duffel stops accepting the offer, if it said.
+    pub expires_at: Option<String>,
+    /// `true` once `expires_at` has passed.
+    pub expired: bool,
+    /// The offer exactly as the search tool returned it.
+    pub offer: Value,
+}
+
+/// A saved search and its offers, in option order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub search_id: String,
+    pub created_at: String,
+    /// When the saved results are dropped.
+    pub expires_at: String,
+    pub offers: Vec<SavedOffer>,
+}
+
+/// A saved search without its offers, for listings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchSummary {
+    pub search_id: String,
+    pub created_at: String,
+    pub expires_at: String,
+    pub offer_count: usize,
+}
+
+/// An offer to save: its ID, Duffel's expiry, and the offer as returned.
+#[derive(Debug, Clone)]
+pub struct NewOffer {
+    pub offer_id: String,
+    pub expires_at: Option<String>,
+    pub offer: Value,
+}
+
+fn has_passed(timestamp: Option<&str>, now: DateTime<Utc>) -> bool {
+    timestamp
+        .and_then(|timestamp| DateTime::parse_from_rfc3339(timestamp).ok())
+        .is_some_and(|timestamp| timestamp < now)
+}
+
+/// Saved search results in the shared [`Store`].
+#[derive(Debug, Clone)]
+pub struct SearchResults {
+    store: Store,
+    ttl: Duration,
+}
+
+impl SearchResults {
+    /// Create the tables, keeping results for `storage.search_results_ttl_secs`
+    /// (`SEARCH_RESULTS_TTL_SECS`, default: 1800).
+    pub async fn new(store: Store) -> Result<Self> {
+        let ttl = config::get().storage.search_results_ttl_secs;
+        store.migrate(SCHEMA).await?;
+        info!("Search results kept for {}s", ttl);
+        Ok(Self {
+            store,
+            ttl: Duration::from_secs(ttl),
+        })
+    }
+
+    /// Append a page of offers to a search; returns the option number of the first one.
+    pub async fn append(&self, kind: &str, search_id: &str, offers: Vec<NewOffer>) -> Result<usize> {
+        let (kind, search_id) = (kind.to_string(), search_id.to_string());
+        let now = Utc::now();
+        let expires_at = now + chrono::Duration::from_std(self.ttl)?;
+        self.store
+            .call(move |connection| {
+                let transaction = connection.transaction()?;
+                // Each page pushes the whole search's expiry back
+                transaction.execute(
+                    "INSERT INTO searches (search_id, kind, created_at, expires_at) VALUES (?1, ?2, ?3, ?4)
+                     ON CONFLICT (kind, search_id) DO UPDATE SET expires_at = excluded.expires_at",
+                    params![search_id, kind, now.to_rfc3339(), expires_at.to_rfc3339()],
+                )?;
+                let saved: i64 = transaction.query_row(
+                    "SELECT COUNT(*) FROM search_offers WHERE kind = ?1 AND search_id = ?2",
+                    params![kind, search_id],
+                    |row| row.get(0),
+                )?;
+                let mut next_option = saved as usize + 1;
+                let mut first_option = None;
+                for offer in offers {
+                    // A page fetched twice keeps the option numbers it was first given
+                    let existing: Option<i64> = transaction
+                        .query_row(
+                            "SELECT option FROM search_offers WHERE kind = ?1 AND search_id = ?2 AND offer_id = ?3",
+                            params![kind, search_id, offer.offer_id],
+                            |row| row.get(0),
+                        )
+                        .optional()?;
+                    let option = match existing {
+                        Some(option) => option as usize,
+                        None => {
+                            transaction.execute(
+                                "INSERT INTO search_offers (kind, search_id, option, offer_id, expires_at, offer)
+                                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                                params![
+                                    kind,
+                                    search_id,
+                                    next_option as i64,
+                                    offer.offer_id,
+                                    offer.expires_at,
+                                    offer.offer.to_string()
+                                ],
+                            )?;
+                            next_option += 1;
+                            next_option - 1
+                        }
+                    };
+                    first_option.get_or_insert(option);
+                }
+                transaction.commit()?;
+                Ok(first_option.unwrap_or(next_option))
+            })
+            .await
+    }
+
+    /// A saved search, or `None` when it is unknown or has been dropped.
+    pub async fn get(&self, kind: &str, search_id: &str) -> Result<Option<SavedSearch>> {
+        let (kind, search_id) = (kind.to_string(), search_id.to_string());
+        let now = Utc::now();
+        self.store
+            .call(move |connection| {
+                let search = connection
+                    .query_row(
+                        "SELECT created_at, expires_at FROM searches WHERE kind = ?1 AND search_id = ?2",
+                        params![kind, search_id],
+                        |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+                    )
+                    .optional()?;
+                let Some((created_at, expires_at)) = search else {
+                    return Ok(None);
+                };
+                if has_passed(Some(&expires_at), now) {
+                    return Ok(None);
+                }
+
+                let mut statement = connection.prepare(
+                    "SELECT option, offer_id, expires_at, offer FROM search_offers
+                     WHERE kind = ?1 AND search_id = ?2 ORDER BY option",
+                )?;
+                let offers = statement
+                    .query_map(params![kind, search_id], |row| {
+                        let expires_at: Option<String> = row.get(2)?;
+                        let offer: String = row.get(3)?;
+                        Ok(SavedOffer {
+                            option: row.get::<_, i64>(0)? as usize,
+                            offer_id: row.get(1)?,
+                            expired: has_passed(expires_at.as_deref(), now),
+                            expires_at,
+                            offer: serde_json::from_str(&offer).unwrap_or(Value::Null),
+                        })
+                    })?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+
+                Ok(Some(SavedSearch {
+                    search_id,
+                    created_at,
+                    expires_at,
+                    offers,
+                }))
+            })
+            .await
+    }
+
+    /// The `limit` most recent searches still saved, newest first.
+    pub async fn recent(&self, kind: &str, limit: usize) -> Result<Vec<SearchSummary>> {
+        let kind = kind.to_string();
+        let now = Utc::now().to_rfc3339();
+        self.store
+            .call(move |connection| {
+                let mut statement = connection.prepare(
+                    "SELECT s.search_id, s.created_at, s.expires_at, COUNT(o.option) FROM searches s
+                     LEFT JOIN search_offers o ON o.kind = s.kind AND o.search_id = s.search_id
+                     WHERE s.kind = ?1 AND s.expires_at >= ?2
+                     GROUP BY s.search_id ORDER BY s.created_at DESC LIMIT ?3",
+                )?;
+                let searches = statement
+                    .query_map(params![kind, now, limit as i64], |row| {
+                        Ok(SearchSummary {
+                            search_id: row.get(0)?,
+                            created_at: row.get(1)?,
+                            expires_at: row.get(2)?,
+                            offer_count: row.get::<_, i64>(3)? as usize,
+                        })
+                    })?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                Ok(searches)
+            })
+            .await
+    }
+
+    /// A saved search for a tool call, with errors a client can act on.
+    pub async fn search(&self, kind: &str, search_id: &str) -> Result<SavedSearch, RpcError> {
+        self.get(kind, search_id)
+            .await
+            .map_err(|e| RpcError::server_error(format!("Saved search lookup failed: {}", e)))?
+            .ok_or_else(|| {
+                RpcError::invalid_params(format!(
+                    "Invalid parameters: no saved results for search_id {}; results are kept for {} minutes, so run the search again",
+                    search_id,
+                    self.ttl.as_secs() / 60
+                ))
+            })
+    }
+
+    /// Resolve "option N" of a saved search to its offer, refusing offers Duffel has expired.
+    pub async fn option(&self, kind: &str, search_id: &str, option: usize) -> Result<SavedOffer, RpcError> {
+        let search = self.search(kind, search_id).await?;
+        let count = search.offers.len();
+        let offer = search
+            .offers
+            .into_iter()
+            .find(|offer| offer.option == option)
+            .ok_or_else(|| {
+                RpcError::invalid_params(format!(
+                    "Invalid parameters: option must be between 1 and {} for search_id {}",
+                    count, search_id
+                ))
+            })?;
+        if offer.expired {
+            return Err(RpcError::invalid_params(format!(
+                "Invalid parameters: offer {} (option {}) expired at {}; run the search again",
+                offer.offer_id,
+                option,
+                offer.expires_at.as_deref().unwrap_or("an unknown time")
+            )));
+        }
+        Ok(offer)
+    }
+
+    /// Delete searches past their expiry; returns how many were removed.
+    pub async fn purge_expired(&self) -> Result<usize> {
+        let now = Utc::now().to_rfc3339();
+        self.store
+            .call(move |connection| Ok(connection.execute("DELETE FROM searches WHERE expires_at < ?1", [now])?))
+            .await
+    }
+}
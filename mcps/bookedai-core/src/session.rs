@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+use tokio::sync::{broadcast, mpsc};
+use tracing::info;
+
+/// Header carrying the session ID in the MCP Streamable HTTP transport.
+pub const SESSION_HEADER: &str = "mcp-session-id";
+
+/// Sessions untouched for this long are dropped when the next one is created.
+const SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// Buffered server-initiated messages per session before slow listeners start lagging.
+const SESSION_CHANNEL_CAPACITY: usize = 64;
+
+struct Session {
+    last_seen: Instant,
+    sender: broadcast::Sender<Value>,
+}
+
+/// In-memory registry of Streamable HTTP sessions.
+///
+/// Each session owns a broadcast channel feeding its `GET /mcp` event streams,
+/// so server-initiated messages can be pushed to the client outside a request.
+#[derive(Clone, Default)]
+pub struct SessionStore {
+    sessions: Arc<Mutex<HashMap<String, Session>>>,
+}
+
+impl SessionStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start a new session and return its ID.
+    pub fn create(&self) -> String {
+        let id = uuid::Uuid::new_v4().to_string();
+        let (sender, _) = broadcast::channel(SESSION_CHANNEL_CAPACITY);
+
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|_, session| session.last_seen.elapsed() < SESSION_IDLE_TIMEOUT);
+        sessions.insert(
+            id.clone(),
+            Session {
+                last_seen: Instant::now(),
+                sender,
+            },
+        );
+
+        info!("Created MCP session {} ({} active)", id, sessions.len());
+        id
+    }
+
+    /// Mark a session as active; returns `false` if it does not exist.
+    pub fn touch(&self, id: &str) -> bool {
+        match self.sessions.lock().unwrap().get_mut(id) {
+            Some(session) => {
+                session.last_seen = Instant::now();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Subscribe to server-initiated messages for a session.
+    pub fn subscribe(&self, id: &str) -> Option<broadcast::Receiver<Value>> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions.get_mut(id)?;
+        session.last_seen = Instant::now();
+        Some(session.sender.subscribe())
+    }
+
+    /// Push a message to every open `GET` stream of a session.
+    ///
+    /// Returns `false` if the session does not exist or nobody is listening.
+    pub fn send(&self, id: &str, message: Value) -> bool {
+        match self.sessions.lock().unwrap().get(id) {
+            Some(session) => session.sender.send(message).is_ok(),
+            None => false,
+        }
+    }
+
+    /// A sender whose messages go out on the session's `GET` streams, for requests
+    /// answered as plain JSON that still have notifications to send.
+    ///
+    /// Messages sent while no stream is open are dropped, like any other [`send`](Self::send).
+    pub fn notifier(&self, id: &str) -> mpsc::UnboundedSender<Value> {
+        let (outgoing, mut messages) = mpsc::unbounded_channel();
+        let (sessions, id) = (self.clone(), id.to_string());
+        // Ends once the request drops its senders
+        tokio::spawn(async move {
+            while let Some(message) = messages.recv().await {
+                sessions.send(&id, message);
+            }
+        });
+        outgoing
+    }
+
+    /// Terminate a session; returns `false` if it did not exist.
+    pub fn remove(&self, id: &str) -> bool {
+        let removed = self.sessions.lock().unwrap().remove(id).is_some();
+        if removed {
+            info!("Terminated MCP session {}", id);
+        }
+        removed
+    }
+}
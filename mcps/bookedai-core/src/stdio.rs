@@ -0,0 +1,74 @@
+use anyhow::Result;
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+use tracing::{error, info};
+
+use crate::jsonrpc::{self, RpcError};
+use crate::mcp::{McpServer, RequestContext};
+
+/// Serve `server` over stdio: newline-delimited JSON-RPC in on stdin, responses out on stdout.
+///
+/// Messages are handled concurrently, so progress notifications stream out while a
+/// search runs and a `notifications/cancelled` can reach it. Returns once stdin is
+/// closed and every in-flight request has answered. Logs must not go to stdout in
+/// this mode.
+pub async fn serve(server: McpServer) -> Result<()> {
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+
+    // A single writer, so concurrent responses and notifications never interleave mid-line
+    let (outgoing, mut messages) = mpsc::unbounded_channel::<Value>();
+    let writer = tokio::spawn(async move {
+        let mut stdout = tokio::io::stdout();
+        while let Some(message) = messages.recv().await {
+            let mut output = serde_json::to_vec(&message)?;
+            output.push(b'\n');
+            stdout.write_all(&output).await?;
+            stdout.flush().await?;
+        }
+        anyhow::Ok(())
+    });
+
+    info!("Reading JSON-RPC messages from stdin");
+
+    let mut requests = JoinSet::new();
+    while let Some(line) = lines.next_line().await? {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        match serde_json::from_str::<Value>(line) {
+            Ok(request) => {
+                let server = server.clone();
+                let outgoing = outgoing.clone();
+                requests.spawn(async move {
+                    let context = RequestContext {
+                        connection: None,
+                        notifier: Some(outgoing.clone()),
+                        request_id: None,
+                    };
+                    if let Some(response) = server.handle_request_with(request, context).await {
+                        let _ = outgoing.send(response);
+                    }
+                });
+            }
+            Err(e) => {
+                error!("Failed to parse JSON-RPC message: {}", e);
+                let error = RpcError::parse_error(format!("Parse error: {}", e));
+                let _ = outgoing.send(jsonrpc::error(Value::Null, &error));
+            }
+        }
+
+        // Reap finished requests so the set does not grow for the whole session
+        while requests.try_join_next().is_some() {}
+    }
+
+    info!("stdin closed, finishing {} in-flight requests", requests.len());
+    while requests.join_next().await.is_some() {}
+    drop(outgoing);
+    writer.await??;
+    info!("Shutting down");
+    Ok(())
+}
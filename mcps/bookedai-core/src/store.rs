@@ -0,0 +1,62 @@
+//! SQLite persistence shared by the servers.
+//!
+//! One database file (`BOOKEDAI_DB_PATH`, default `bookedai.db`) holds every table;
+//! each feature registers its schema through [`Store::migrate`]. Queries run on
+//! tokio's blocking pool so a slow disk never stalls the async runtime.
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+use tracing::info;
+
+use crate::config;
+
+/// Shared handle to the SQLite database.
+#[derive(Debug, Clone)]
+pub struct Store {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl Store {
+    /// Open (or create) the database at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let connection =
+            Connection::open(path).with_context(|| format!("failed to open database {}", path.display()))?;
+        // WAL lets the background jobs write while tools read
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.pragma_update(None, "foreign_keys", "ON")?;
+        info!("Database: {}", path.display());
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    /// Open the database at `storage.db_path` (`BOOKEDAI_DB_PATH`, default: `bookedai.db`).
+    pub fn from_config() -> Result<Self> {
+        Self::open(&config::get().storage.db_path)
+    }
+
+    /// Apply a feature's schema; statements must be idempotent (`IF NOT EXISTS`).
+    pub async fn migrate(&self, schema: &'static str) -> Result<()> {
+        self.call(move |connection| Ok(connection.execute_batch(schema)?)).await
+    }
+
+    /// Run `f` against the connection on the blocking pool.
+    pub async fn call<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+    {
+        let connection = self.connection.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut connection = connection
+                .lock()
+                .map_err(|_| anyhow::anyhow!("database connection poisoned"))?;
+            f(&mut connection)
+        })
+        .await?
+    }
+}
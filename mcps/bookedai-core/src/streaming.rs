@@ -0,0 +1,160 @@
+//! Large Duffel lists parsed item by item, within `duffel.max_response_bytes`.
+//!
+//! An offer request can answer with hundreds of offers, each carrying full segment
+//! data. Instead of building a `serde_json::Value` of the whole body, [`parse_list`]
+//! walks to the list and reads it one [`RawValue`] at a time, deserializing each item
+//! into its typed model before reading the next. A body cut off at the size limit
+//! still yields every item that arrived whole, flagged as [`ListPage::truncated`].
+
+use std::fmt;
+
+use anyhow::{bail, Result};
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use serde_json::Value;
+
+use crate::duffel_models;
+
+/// The items of one Duffel list response.
+#[derive(Debug, Clone)]
+pub struct ListPage<T> {
+    pub items: Vec<T>,
+    /// One warning per item that did not match `T`, as [`duffel_models::parse_each`] words them.
+    pub skipped: Vec<String>,
+    /// Items read, parsed or skipped.
+    pub received: usize,
+    /// The top-level `meta` object, or `Null` when the body was cut off before it.
+    pub meta: Value,
+    /// Whether the body exceeded the size limit, so later items were dropped.
+    pub truncated: bool,
+}
+
+/// Rewrites one raw list item before it is parsed; `None` leaves it as it is.
+pub type ItemAdapter<'a> = &'a dyn Fn(&RawValue) -> Option<Value>;
+
+struct Collector<'a, T> {
+    page: ListPage<T>,
+    kind: &'a str,
+    adapt: ItemAdapter<'a>,
+    found: bool,
+}
+
+impl<T: DeserializeOwned> Collector<'_, T> {
+    fn push(&mut self, raw: &RawValue) {
+        self.page.received += 1;
+        let parsed = match (self.adapt)(raw) {
+            Some(adapted) => T::deserialize(&adapted),
+            None => serde_json::from_str(raw.get()),
+        };
+        match parsed {
+            Ok(item) => self.page.items.push(item),
+            Err(e) => {
+                #[derive(Deserialize)]
+                struct Id {
+                    id: Option<String>,
+                }
+                let id = serde_json::from_str::<Id>(raw.get()).ok().and_then(|item| item.id);
+                self.page
+                    .skipped
+                    .push(duffel_models::skipped_item(self.kind, id.as_deref(), &e));
+            }
+        }
+    }
+}
+
+/// Walks `path` (object keys) down to the list, collecting its items on the way.
+struct Walk<'a, 'c, T> {
+    path: &'a [&'a str],
+    top_level: bool,
+    collector: &'a mut Collector<'c, T>,
+}
+
+impl<'de, T: DeserializeOwned> DeserializeSeed<'de> for Walk<'_, '_, T> {
+    type Value = ();
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        if self.path.is_empty() {
+            deserializer.deserialize_seq(self)
+        } else {
+            deserializer.deserialize_map(self)
+        }
+    }
+}
+
+impl<'de, T: DeserializeOwned> Visitor<'de> for Walk<'_, '_, T> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.path.first() {
+            Some(key) => write!(f, "an object with '{}'", key),
+            None => write!(f, "a list"),
+        }
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        while let Some(key) = map.next_key::<String>()? {
+            if key == self.path[0] {
+                map.next_value_seed(Walk {
+                    path: &self.path[1..],
+                    top_level: false,
+                    collector: &mut *self.collector,
+                })?;
+            } else if self.top_level && key == "meta" {
+                self.collector.page.meta = map.next_value()?;
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        self.collector.found = true;
+        while let Some(raw) = seq.next_element::<&RawValue>()? {
+            self.collector.push(raw);
+        }
+        Ok(())
+    }
+}
+
+/// Parse the list at `path` in `body` (e.g. `["data", "results"]`) into `T`s, skipping
+/// items that do not match as [`duffel_models::parse_each`] does.
+///
+/// `truncated` says `body` was cut off at the size limit: running out of input then
+/// ends the list instead of failing, keeping the items read in full.
+pub fn parse_list<T: DeserializeOwned>(
+    body: &[u8],
+    path: &[&str],
+    kind: &str,
+    adapt: ItemAdapter,
+    truncated: bool,
+) -> Result<ListPage<T>> {
+    let mut collector = Collector {
+        page: ListPage {
+            items: Vec::new(),
+            skipped: Vec::new(),
+            received: 0,
+            meta: Value::Null,
+            truncated,
+        },
+        kind,
+        adapt,
+        found: false,
+    };
+    let mut deserializer = serde_json::Deserializer::from_slice(body);
+    let walk = Walk {
+        path,
+        top_level: true,
+        collector: &mut collector,
+    };
+    match walk.deserialize(&mut deserializer).and_then(|_| deserializer.end()) {
+        Ok(()) => {}
+        Err(e) if truncated && e.is_eof() => {}
+        Err(e) => bail!("Unreadable {} list from Duffel: {}", kind, e),
+    }
+    if !collector.found && !truncated {
+        bail!("No {} list in Duffel response", kind);
+    }
+    Ok(collector.page)
+}
@@ -0,0 +1,76 @@
+use std::env;
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::Result;
+use tracing::warn;
+
+use crate::config;
+use crate::http::{self, HttpOptions};
+use crate::mcp::McpServer;
+use crate::stdio;
+
+/// How a server talks to its MCP client, selected with `--transport <http|stdio>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    Http,
+    Stdio,
+}
+
+impl Transport {
+    /// Read `--transport <name>` (or `--transport=<name>`) from the process arguments.
+    ///
+    /// Defaults to HTTP when the flag is absent.
+    pub fn from_args() -> Result<Self> {
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            if arg == "--transport" {
+                let value = args
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("--transport requires a value (http or stdio)"))?;
+                return value.parse();
+            }
+            if let Some(value) = arg.strip_prefix("--transport=") {
+                return value.parse();
+            }
+        }
+        Ok(Transport::Http)
+    }
+}
+
+impl FromStr for Transport {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "http" => Ok(Transport::Http),
+            "stdio" => Ok(Transport::Stdio),
+            other => Err(anyhow::anyhow!("Unknown transport '{}', expected http or stdio", other)),
+        }
+    }
+}
+
+impl fmt::Display for Transport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Transport::Http => write!(f, "http"),
+            Transport::Stdio => write!(f, "stdio"),
+        }
+    }
+}
+
+/// Run `server` on the selected transport until it shuts down.
+pub async fn serve(server: McpServer, transport: Transport, http_options: HttpOptions) -> Result<()> {
+    match transport {
+        Transport::Http => http::serve(server, http_options).await,
+        Transport::Stdio => {
+            if http_options.webhooks.is_some() {
+                warn!("Webhooks are only received over the HTTP transport");
+            }
+            if config::get().server.auth_token.is_some() {
+                warn!("MCP_AUTH_TOKEN only applies to the HTTP transport");
+            }
+            stdio::serve(server).await
+        }
+    }
+}
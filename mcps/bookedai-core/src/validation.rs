@@ -0,0 +1,215 @@
+//! Argument checks shared by the search tools.
+//!
+//! Everything here returns `-32602` errors that tell the caller how to fix the
+//! request, so bad input never reaches Duffel and comes back as an opaque API error.
+
+use chrono::{Days, NaiveDate, NaiveDateTime, Utc};
+
+use crate::airports;
+use crate::jsonrpc::RpcError;
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const DATE_TIME_FORMATS: &[&str] = &["%Y-%m-%dT%H:%M", "%Y-%m-%dT%H:%M:%S"];
+
+fn invalid(message: String) -> RpcError {
+    RpcError::invalid_params(format!("Invalid parameters: {}", message))
+}
+
+/// Parse a `YYYY-MM-DD` date.
+pub fn date(field: &str, value: &str) -> Result<NaiveDate, RpcError> {
+    NaiveDate::parse_from_str(value.trim(), DATE_FORMAT).map_err(|_| {
+        invalid(format!(
+            "{} must be a calendar date in YYYY-MM-DD format (e.g. 2025-07-14), got '{}'",
+            field, value
+        ))
+    })
+}
+
+/// Parse a `YYYY-MM-DD` date that must not be in the past.
+///
+/// "Today" is taken one day early so callers west of UTC can still search their own today.
+pub fn upcoming_date(field: &str, value: &str) -> Result<NaiveDate, RpcError> {
+    let parsed = date(field, value)?;
+    not_past(field, parsed)?;
+    Ok(parsed)
+}
+
+fn not_past(field: &str, parsed: NaiveDate) -> Result<(), RpcError> {
+    let today = Utc::now().date_naive();
+    let earliest = today.checked_sub_days(Days::new(1)).unwrap_or(today);
+    if parsed < earliest {
+        return Err(invalid(format!(
+            "{} {} is in the past; today is {}",
+            field, parsed, today
+        )));
+    }
+    Ok(())
+}
+
+/// Parse a local `YYYY-MM-DDTHH:MM` date and time (seconds optional).
+pub fn date_time(field: &str, value: &str) -> Result<NaiveDateTime, RpcError> {
+    DATE_TIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value.trim(), format).ok())
+        .ok_or_else(|| {
+            invalid(format!(
+                "{} must be a local date and time in YYYY-MM-DDTHH:MM format (e.g. 2025-07-14T09:30), got '{}'",
+                field, value
+            ))
+        })
+}
+
+/// Parse a local `YYYY-MM-DDTHH:MM` date and time whose date is not in the past.
+pub fn upcoming_date_time(field: &str, value: &str) -> Result<NaiveDateTime, RpcError> {
+    let parsed = date_time(field, value)?;
+    not_past(field, parsed.date())?;
+    Ok(parsed)
+}
+
+/// Require `later` to fall on or after `earlier`.
+pub fn on_or_after(
+    later_field: &str,
+    later: NaiveDate,
+    earlier_field: &str,
+    earlier: NaiveDate,
+) -> Result<(), RpcError> {
+    if later < earlier {
+        return Err(invalid(format!(
+            "{} ({}) must not be before {} ({})",
+            later_field, later, earlier_field, earlier
+        )));
+    }
+    Ok(())
+}
+
+/// Require `later` to fall strictly after `earlier`.
+pub fn after(
+    later_field: &str,
+    later: NaiveDate,
+    earlier_field: &str,
+    earlier: NaiveDate,
+) -> Result<(), RpcError> {
+    if later <= earlier {
+        return Err(invalid(format!(
+            "{} ({}) must be after {} ({})",
+            later_field, later, earlier_field, earlier
+        )));
+    }
+    Ok(())
+}
+
+/// Check a 3-letter IATA airport or city code and return it uppercased.
+///
+/// Only the format is checked: the embedded airport list is not complete, so codes
+/// it lacks still go to Duffel, which knows every airport.
+pub fn iata_code(field: &str, value: &str) -> Result<String, RpcError> {
+    let code = value.trim().to_ascii_uppercase();
+    if code.len() != 3 || !code.bytes().all(|b| b.is_ascii_alphabetic()) {
+        return Err(invalid(format!(
+            "{} must be a 3-letter IATA airport or city code (e.g. 'JFK', 'LON'), got '{}'",
+            field, value
+        )));
+    }
+    Ok(code)
+}
+
+/// Like [`iata_code`], but also accepts a city name such as `London` and returns its code.
+pub fn airport_or_city(field: &str, value: &str) -> Result<String, RpcError> {
+    if value.trim().chars().count() <= 3 {
+        return iata_code(field, value);
+    }
+    airports::city_code(value).map(str::to_string).ok_or_else(|| {
+        invalid(format!(
+            "{} must be an IATA airport or city code (e.g. 'JFK', 'LON') or a known city name (e.g. 'London'), got '{}'",
+            field, value
+        ))
+    })
+}
+
+/// Check that `value` looks like an email address (`name@domain.tld`).
+pub fn email(field: &str, value: &str) -> Result<String, RpcError> {
+    let email = value.trim();
+    let valid = email
+        .split_once('@')
+        .is_some_and(|(local, domain)| !local.is_empty() && domain.contains('.') && !email.contains(char::is_whitespace));
+    if !valid {
+        return Err(invalid(format!("{} must be an email address, got '{}'", field, email)));
+    }
+    Ok(email.to_string())
+}
+
+/// Check that `value` looks like a Duffel ID with the given prefix (e.g. `off_`).
+pub fn duffel_id(field: &str, value: &str, prefix: &str) -> Result<String, RpcError> {
+    let id = value.trim();
+    let suffix = id.strip_prefix(prefix).unwrap_or_default();
+    if suffix.is_empty() || !suffix.bytes().all(|b| b.is_ascii_alphanumeric()) {
+        return Err(invalid(format!(
+            "{} must be a Duffel ID starting with '{}', got '{}'",
+            field, prefix, value
+        )));
+    }
+    Ok(id.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iata_code_uppercases_and_trims() {
+        assert_eq!(iata_code("origin", " jfk ").unwrap(), "JFK");
+        assert_eq!(iata_code("origin", "LON").unwrap(), "LON");
+    }
+
+    #[test]
+    fn iata_code_accepts_airports_missing_from_the_embedded_list() {
+        for code in ["ALB", "SDF", "OMA", "MSN", "DSM", "GRR", "DAY", "MHT", "PWM", "KOA", "SRQ", "PIE", "INV"] {
+            assert_eq!(iata_code("origin", code).unwrap(), code);
+        }
+    }
+
+    #[test]
+    fn iata_code_rejects_malformed_codes() {
+        for value in ["", "JF", "JFKX", "J1K", "JF-"] {
+            let error = iata_code("origin", value).unwrap_err();
+            assert!(error.message.contains("origin must be a 3-letter IATA"), "{}", error.message);
+        }
+    }
+
+    #[test]
+    fn upcoming_date_accepts_today_and_later() {
+        let today = Utc::now().date_naive();
+        assert_eq!(upcoming_date("departure_date", &today.to_string()).unwrap(), today);
+        let next_year = today.checked_add_days(Days::new(365)).unwrap();
+        assert_eq!(upcoming_date("departure_date", &next_year.to_string()).unwrap(), next_year);
+    }
+
+    #[test]
+    fn upcoming_date_allows_yesterday_for_callers_west_of_utc() {
+        let yesterday = Utc::now().date_naive().checked_sub_days(Days::new(1)).unwrap();
+        assert!(upcoming_date("departure_date", &yesterday.to_string()).is_ok());
+    }
+
+    #[test]
+    fn upcoming_date_rejects_past_and_malformed_dates() {
+        let last_week = Utc::now().date_naive().checked_sub_days(Days::new(7)).unwrap();
+        let error = upcoming_date("departure_date", &last_week.to_string()).unwrap_err();
+        assert!(error.message.contains("is in the past"), "{}", error.message);
+
+        for value in ["2025-13-01", "14/07/2025", "tomorrow"] {
+            let error = upcoming_date("departure_date", value).unwrap_err();
+            assert!(error.message.contains("YYYY-MM-DD"), "{}", error.message);
+        }
+    }
+
+    #[test]
+    fn after_requires_a_strictly_later_date() {
+        let check_in = NaiveDate::from_ymd_opt(2030, 6, 1).unwrap();
+        let check_out = NaiveDate::from_ymd_opt(2030, 6, 3).unwrap();
+        assert!(after("check_out_date", check_out, "check_in_date", check_in).is_ok());
+
+        let error = after("check_out_date", check_in, "check_in_date", check_in).unwrap_err();
+        assert!(error.message.contains("check_out_date (2030-06-01) must be after check_in_date"), "{}", error.message);
+        assert!(after("check_out_date", check_in, "check_in_date", check_out).is_err());
+    }
+}
@@ -0,0 +1,275 @@
+//! Receiver for Duffel webhook events.
+//!
+//! Duffel signs every delivery with `X-Duffel-Signature: t=<unix time>,v1=<hex HMAC>`,
+//! where the HMAC-SHA256 covers `"<t>.<raw body>"` under the webhook secret. Verified
+//! events are kept in a bounded in-memory store that tools can query.
+
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use tracing::{info, warn};
+use warp::http::StatusCode;
+use warp::hyper::body::Bytes;
+use warp::Reply;
+
+use crate::config;
+
+/// Header carrying Duffel's webhook signature.
+pub const SIGNATURE_HEADER: &str = "x-duffel-signature";
+
+/// Most events kept in memory; the oldest are dropped first.
+const MAX_EVENTS: usize = 1000;
+
+/// Deliveries signed longer ago than this are rejected as replays.
+const SIGNATURE_TOLERANCE_SECS: i64 = 300;
+
+/// A verified webhook delivery.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookEvent {
+    pub id: String,
+    /// Duffel event type, e.g. `order.airline_initiated_change_detected`.
+    pub event_type: String,
+    /// Order the event concerns, when it concerns one.
+    pub order_id: Option<String>,
+    pub created_at: Option<String>,
+    pub received_at: String,
+    pub live_mode: bool,
+    /// The event's `data.object` as sent by Duffel.
+    pub object: Value,
+}
+
+impl WebhookEvent {
+    fn from_payload(payload: &Value) -> Option<Self> {
+        let object = payload["data"]["object"].clone();
+        // Order events carry the order itself; cancellation events reference it
+        let order_id = object["id"]
+            .as_str()
+            .filter(|id| id.starts_with("ord_"))
+            .or_else(|| object["order_id"].as_str())
+            .map(|s| s.to_string());
+
+        Some(Self {
+            id: payload["id"].as_str()?.to_string(),
+            event_type: payload["type"].as_str()?.to_string(),
+            order_id,
+            created_at: payload["created_at"].as_str().map(|s| s.to_string()),
+            received_at: Utc::now().to_rfc3339(),
+            live_mode: payload["live_mode"].as_bool().unwrap_or(false),
+            object,
+        })
+    }
+}
+
+/// Bounded, shared log of received events, newest last.
+#[derive(Debug, Clone, Default)]
+pub struct EventStore {
+    events: Arc<Mutex<VecDeque<WebhookEvent>>>,
+}
+
+impl EventStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an event, ignoring redeliveries of one already stored.
+    pub fn push(&self, event: WebhookEvent) -> bool {
+        let mut events = self.events.lock().unwrap();
+        if events.iter().any(|existing| existing.id == event.id) {
+            return false;
+        }
+        if events.len() >= MAX_EVENTS {
+            events.pop_front();
+        }
+        events.push_back(event);
+        true
+    }
+
+    /// Events matching the filters, newest first.
+    pub fn list(&self, order_id: Option<&str>, event_type: Option<&str>, limit: usize) -> Vec<WebhookEvent> {
+        self.events
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .filter(|event| order_id.is_none_or(|id| event.order_id.as_deref() == Some(id)))
+            .filter(|event| event_type.is_none_or(|kind| event.event_type == kind))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Verifies and stores deliveries to `POST /webhooks/duffel`.
+#[derive(Clone)]
+pub struct WebhookReceiver {
+    secret: Arc<str>,
+    events: EventStore,
+}
+
+impl fmt::Debug for WebhookReceiver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebhookReceiver").finish_non_exhaustive()
+    }
+}
+
+impl WebhookReceiver {
+    pub fn new(secret: impl Into<String>) -> Self {
+        Self {
+            secret: secret.into().into(),
+            events: EventStore::new(),
+        }
+    }
+
+    /// Build a receiver from `duffel.webhook_secret` (`DUFFEL_WEBHOOK_SECRET`); `None`
+    /// leaves webhooks disabled.
+    pub fn from_config() -> Option<Self> {
+        let secret = config::get().duffel.webhook_secret.clone();
+        if secret.is_none() {
+            info!("DUFFEL_WEBHOOK_SECRET not set, webhook endpoint disabled");
+        }
+        secret.map(Self::new)
+    }
+
+    /// The store verified events are written to.
+    pub fn events(&self) -> EventStore {
+        self.events.clone()
+    }
+
+    /// Check a `X-Duffel-Signature` header against the raw request body.
+    pub fn verify(&self, signature: &str, body: &[u8]) -> bool {
+        let mut timestamp = None;
+        let mut expected = None;
+        for part in signature.split(',') {
+            match part.trim().split_once('=') {
+                Some(("t", value)) => timestamp = Some(value),
+                Some(("v1", value)) => expected = hex::decode(value).ok(),
+                _ => {}
+            }
+        }
+        let (Some(timestamp), Some(expected)) = (timestamp, expected) else {
+            return false;
+        };
+
+        let fresh = timestamp
+            .parse::<i64>()
+            .ok()
+            .and_then(|t| DateTime::from_timestamp(t, 0))
+            .is_some_and(|signed_at| (Utc::now() - signed_at).num_seconds().abs() <= SIGNATURE_TOLERANCE_SECS);
+        if !fresh {
+            return false;
+        }
+
+        let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(self.secret.as_bytes()) else {
+            return false;
+        };
+        mac.update(timestamp.as_bytes());
+        mac.update(b".");
+        mac.update(body);
+        mac.verify_slice(&expected).is_ok()
+    }
+}
+
+/// Warp handler for `POST /webhooks/duffel`.
+pub(crate) async fn handle(
+    receiver: WebhookReceiver,
+    signature: Option<String>,
+    body: Bytes,
+) -> Result<warp::reply::Response, Infallible> {
+    let reply = |status: StatusCode, message: &str| {
+        warp::reply::with_status(warp::reply::json(&json!({ "message": message })), status).into_response()
+    };
+
+    if !signature.is_some_and(|signature| receiver.verify(&signature, &body)) {
+        warn!("Rejected webhook delivery with a missing or invalid signature");
+        return Ok(reply(StatusCode::UNAUTHORIZED, "invalid signature"));
+    }
+
+    let Some(event) = serde_json::from_slice::<Value>(&body)
+        .ok()
+        .as_ref()
+        .and_then(WebhookEvent::from_payload)
+    else {
+        return Ok(reply(StatusCode::BAD_REQUEST, "unrecognized event payload"));
+    };
+
+    info!(
+        "Received Duffel webhook {} ({}) for order {}",
+        event.id,
+        event.event_type,
+        event.order_id.as_deref().unwrap_or("-")
+    );
+    receiver.events.push(event);
+    Ok(reply(StatusCode::OK, "ok"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "whsec_test";
+    const BODY: &[u8] = br#"{"id":"wev_1","type":"order.updated","data":{"object":{"id":"ord_1"}}}"#;
+
+    fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(format!("{}.", timestamp).as_bytes());
+        mac.update(body);
+        format!("t={},v1={}", timestamp, hex::encode(mac.finalize().into_bytes()))
+    }
+
+    #[test]
+    fn accepts_a_valid_signature() {
+        let receiver = WebhookReceiver::new(SECRET);
+        assert!(receiver.verify(&sign(SECRET, Utc::now().timestamp(), BODY), BODY));
+    }
+
+    #[test]
+    fn rejects_a_tampered_body() {
+        let receiver = WebhookReceiver::new(SECRET);
+        let signature = sign(SECRET, Utc::now().timestamp(), BODY);
+        let tampered = br#"{"id":"wev_1","type":"order.updated","data":{"object":{"id":"ord_2"}}}"#;
+        assert!(!receiver.verify(&signature, tampered));
+    }
+
+    #[test]
+    fn rejects_a_signature_made_with_another_secret() {
+        let receiver = WebhookReceiver::new(SECRET);
+        assert!(!receiver.verify(&sign("whsec_other", Utc::now().timestamp(), BODY), BODY));
+    }
+
+    #[test]
+    fn rejects_malformed_headers() {
+        let receiver = WebhookReceiver::new(SECRET);
+        let now = Utc::now().timestamp();
+        let valid = sign(SECRET, now, BODY);
+        let v1 = valid.split_once(",v1=").unwrap().1;
+        for header in [
+            "",
+            "garbage",
+            &format!("t={}", now),
+            &format!("v1={}", v1),
+            &format!("t=yesterday,v1={}", v1),
+            &format!("t={},v1=not-hex", now),
+            &format!("t={},v1={}", now, &v1[..10]),
+        ] {
+            assert!(!receiver.verify(header, BODY), "accepted {:?}", header);
+        }
+    }
+
+    #[test]
+    fn rejects_stale_and_future_timestamps() {
+        let receiver = WebhookReceiver::new(SECRET);
+        let now = Utc::now().timestamp();
+        let stale = now - SIGNATURE_TOLERANCE_SECS - 60;
+        let future = now + SIGNATURE_TOLERANCE_SECS + 60;
+        assert!(!receiver.verify(&sign(SECRET, stale, BODY), BODY));
+        assert!(!receiver.verify(&sign(SECRET, future, BODY), BODY));
+        assert!(receiver.verify(&sign(SECRET, now - SIGNATURE_TOLERANCE_SECS + 30, BODY), BODY));
+    }
+}
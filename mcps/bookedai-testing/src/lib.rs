@@ -0,0 +1,329 @@
+//! End-to-end test harness for the BookedAI MCP servers.
+//!
+//! [`FakeDuffel`] is a wiremock server that answers the Duffel API from the recorded
+//! fixtures in `bookedai-core/fixtures/duffel`, and can be told to fail a route the
+//! way Duffel does. [`TestServer`] runs a server binary against it over HTTP, with
+//! its own port and database, and speaks JSON-RPC to its `/mcp` endpoint:
+//!
+//! ```ignore
+//! let duffel = FakeDuffel::start().await;
+//! let mut server = TestServer::start(env!("CARGO_BIN_EXE_mcp_duffel_flights"), &duffel, &[]).await;
+//! server.initialize().await;
+//! let result = server.call_tool("search_flights", json!({ ... })).await;
+//! ```
+
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+use tokio::process::{Child, Command};
+use wiremock::matchers::{method, path, path_regex};
+use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+const OFFER_REQUEST: &str = include_str!("../../bookedai-core/fixtures/duffel/offer_request.json");
+const OFFERS: &str = include_str!("../../bookedai-core/fixtures/duffel/offers.json");
+const STAYS_SEARCH: &str = include_str!("../../bookedai-core/fixtures/duffel/stays_search.json");
+const STAY_RATES: &str = include_str!("../../bookedai-core/fixtures/duffel/stay_rates.json");
+const ORDER: &str = include_str!("../../bookedai-core/fixtures/duffel/order.json");
+
+/// API token the servers are started with; a test token, so nothing is live.
+pub const API_TOKEN: &str = "duffel_test_integration";
+
+/// `x-request-id` the fake Duffel sends with every answer.
+pub const DUFFEL_REQUEST_ID: &str = "req_fake_duffel";
+
+/// Longest wait for a server to start answering `/health`.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(15);
+
+fn fixture(json: &str) -> Value {
+    serde_json::from_str(json).expect("Duffel fixtures are valid JSON")
+}
+
+fn respond(status: u16, body: Value) -> ResponseTemplate {
+    ResponseTemplate::new(status)
+        .insert_header("x-request-id", DUFFEL_REQUEST_ID)
+        .set_body_json(body)
+}
+
+/// A Duffel error response body, as Duffel sends it.
+pub fn duffel_error(error_type: &str, code: &str, message: &str) -> Value {
+    json!({
+        "errors": [{
+            "type": error_type,
+            "title": message,
+            "message": message,
+            "code": code,
+            "documentation_url": "https://duffel.com/docs/api/overview/errors"
+        }],
+        "meta": { "status": 0, "request_id": DUFFEL_REQUEST_ID }
+    })
+}
+
+/// A fake Duffel API serving flight and stay searches from recorded fixtures.
+pub struct FakeDuffel {
+    server: MockServer,
+}
+
+impl FakeDuffel {
+    /// Start the fake with every fixture route mounted:
+    ///
+    /// - `POST /air/offer_requests` and `GET /air/offer_requests/{id}`
+    /// - `GET /air/offers`, and `GET /air/offers/{id}` answering with the first of them
+    /// - `POST /air/orders`
+    /// - `POST /stays/search` and `POST /stays/search_results/{id}/actions/fetch_all_rates`
+    pub async fn start() -> Self {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/air/offer_requests"))
+            .respond_with(respond(201, fixture(OFFER_REQUEST)))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path_regex(r"^/air/offer_requests/[^/]+$"))
+            .respond_with(respond(200, fixture(OFFER_REQUEST)))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/air/offers"))
+            .respond_with(respond(200, fixture(OFFERS)))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path_regex(r"^/air/offers/[^/]+$"))
+            .respond_with(respond(200, json!({ "data": fixture(OFFERS)["data"][0] })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/air/orders"))
+            .respond_with(respond(201, fixture(ORDER)))
+            .mount(&server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/stays/search"))
+            .respond_with(respond(201, fixture(STAYS_SEARCH)))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path_regex(r"^/stays/search_results/[^/]+/actions/fetch_all_rates$"))
+            .respond_with(respond(201, fixture(STAY_RATES)))
+            .mount(&server)
+            .await;
+        Self { server }
+    }
+
+    /// Base URL to set as `DUFFEL_API_BASE_URL`.
+    pub fn uri(&self) -> String {
+        self.server.uri()
+    }
+
+    /// Answer `method_name path_exact` with `status` and `body` from now on, ahead of
+    /// the fixture routes; e.g. [`duffel_error`] for Duffel's error cases.
+    pub async fn fail(&self, method_name: &str, path_exact: &str, status: u16, body: Value) {
+        Mock::given(method(method_name))
+            .and(path(path_exact))
+            .respond_with(respond(status, body))
+            .with_priority(1)
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Like [`fail`](Self::fail), but only for the next request; later ones get the fixtures.
+    pub async fn fail_once(&self, method_name: &str, path_exact: &str, status: u16, body: Value) {
+        Mock::given(method(method_name))
+            .and(path(path_exact))
+            .respond_with(respond(status, body))
+            .up_to_n_times(1)
+            .with_priority(1)
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Requests received so far for `path_exact`, oldest first.
+    pub async fn requests_to(&self, path_exact: &str) -> Vec<Request> {
+        self.server
+            .received_requests()
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|request| request.url.path() == path_exact)
+            .collect()
+    }
+}
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .map(|address| address.port())
+        .expect("a free local port")
+}
+
+/// A server binary running over HTTP against a [`FakeDuffel`].
+///
+/// The process is killed and its working directory, holding its database, removed
+/// when this is dropped.
+pub struct TestServer {
+    child: Child,
+    dir: PathBuf,
+    base_url: String,
+    client: reqwest::Client,
+    session_id: Option<String>,
+    next_id: u64,
+}
+
+impl TestServer {
+    /// Start `binary` (e.g. `env!("CARGO_BIN_EXE_mcp_duffel_flights")`) pointed at
+    /// `duffel`, and wait until it answers `/health`.
+    ///
+    /// The process gets a clean environment: a Duffel test token, no retries, its own
+    /// port and database, and `env` on top of that.
+    pub async fn start(binary: &str, duffel: &FakeDuffel, env: &[(&str, &str)]) -> Self {
+        let dir = std::env::temp_dir().join(format!("bookedai-test-{}", uuid::Uuid::new_v4().simple()));
+        std::fs::create_dir_all(&dir).expect("test directory can be created");
+        let port = free_port();
+
+        let mut command = Command::new(binary);
+        command
+            .args(["--transport", "http"])
+            .current_dir(&dir)
+            .env_clear()
+            .env("BIND_ADDR", "127.0.0.1")
+            .env("PORT", port.to_string())
+            .env("DUFFEL_API_BASE_URL", duffel.uri())
+            .env("DUFFEL_API_TOKEN", API_TOKEN)
+            .env("DUFFEL_MAX_RETRIES", "0")
+            .env("BOOKEDAI_DB_PATH", dir.join("bookedai.db"))
+            .envs(env.iter().copied())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .kill_on_drop(true);
+        let child = command.spawn().unwrap_or_else(|e| panic!("cannot start {}: {}", binary, e));
+
+        let mut server = Self {
+            child,
+            dir,
+            base_url: format!("http://127.0.0.1:{}", port),
+            client: reqwest::Client::new(),
+            session_id: None,
+            next_id: 1,
+        };
+        server.wait_until_healthy().await;
+        server
+    }
+
+    async fn wait_until_healthy(&mut self) {
+        let deadline = tokio::time::Instant::now() + STARTUP_TIMEOUT;
+        loop {
+            if let Ok(Some(status)) = self.child.try_wait() {
+                panic!("server exited during startup with {}", status);
+            }
+            let health = self.client.get(format!("{}/health", self.base_url)).send().await;
+            if health.is_ok_and(|response| response.status().is_success()) {
+                return;
+            }
+            assert!(tokio::time::Instant::now() < deadline, "server did not become healthy in time");
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    /// Send one JSON-RPC request to `POST /mcp` and return the whole response.
+    pub async fn request(&mut self, rpc_method: &str, params: Value) -> Value {
+        let id = self.next_id;
+        self.next_id += 1;
+        let mut request = self
+            .client
+            .post(format!("{}/mcp", self.base_url))
+            .header("Accept", "application/json")
+            .json(&json!({ "jsonrpc": "2.0", "id": id, "method": rpc_method, "params": params }));
+        if let Some(session_id) = &self.session_id {
+            request = request.header("Mcp-Session-Id", session_id);
+        }
+        let response = request.send().await.expect("POST /mcp succeeds");
+        if let Some(session_id) = response.headers().get("mcp-session-id") {
+            self.session_id = Some(session_id.to_str().expect("session ID is ASCII").to_string());
+        }
+        let body: Value = response.json().await.expect("POST /mcp answers with JSON");
+        assert_eq!(body["id"], json!(id), "response to request {}", id);
+        body
+    }
+
+    /// Send `requests` to `POST /mcp` as one JSON-RPC batch; returns the HTTP status and body.
+    pub async fn batch(&mut self, requests: &[(&str, Value)]) -> (u16, Value) {
+        let batch: Vec<Value> = requests
+            .iter()
+            .map(|(rpc_method, params)| {
+                let id = self.next_id;
+                self.next_id += 1;
+                json!({ "jsonrpc": "2.0", "id": id, "method": rpc_method, "params": params })
+            })
+            .collect();
+        let mut request = self
+            .client
+            .post(format!("{}/mcp", self.base_url))
+            .header("Accept", "application/json")
+            .json(&batch);
+        if let Some(session_id) = &self.session_id {
+            request = request.header("Mcp-Session-Id", session_id);
+        }
+        let response = request.send().await.expect("POST /mcp succeeds");
+        let status = response.status().as_u16();
+        (status, response.json().await.expect("POST /mcp answers with JSON"))
+    }
+
+    /// Open the session's `GET /mcp` event stream; call [`initialize`](Self::initialize) first.
+    pub async fn event_stream(&self) -> reqwest::Response {
+        let session_id = self.session_id.as_deref().expect("a session is open");
+        let response = self
+            .client
+            .get(format!("{}/mcp", self.base_url))
+            .header("Accept", "text/event-stream")
+            .header("Mcp-Session-Id", session_id)
+            .send()
+            .await
+            .expect("GET /mcp succeeds");
+        assert!(response.status().is_success(), "GET /mcp answered {}", response.status());
+        response
+    }
+
+    /// `initialize`, keeping the session it opens; returns the result.
+    pub async fn initialize(&mut self) -> Value {
+        let response = self
+            .request(
+                "initialize",
+                json!({
+                    "protocolVersion": "2025-06-18",
+                    "capabilities": {},
+                    "clientInfo": { "name": "bookedai-testing", "version": "0.1.0" }
+                }),
+            )
+            .await;
+        assert!(self.session_id.is_some(), "initialize opens a session");
+        response["result"].clone()
+    }
+
+    /// Names of the tools `tools/list` advertises.
+    pub async fn tool_names(&mut self) -> Vec<String> {
+        let response = self.request("tools/list", json!({})).await;
+        response["result"]["tools"]
+            .as_array()
+            .expect("tools/list returns tools")
+            .iter()
+            .filter_map(|tool| tool["name"].as_str().map(str::to_string))
+            .collect()
+    }
+
+    /// `tools/call`, returning the whole JSON-RPC response so errors can be checked too.
+    pub async fn call_tool(&mut self, name: &str, arguments: Value) -> Value {
+        self.request("tools/call", json!({ "name": name, "arguments": arguments })).await
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        let _ = self.child.start_kill();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
@@ -0,0 +1,449 @@
+//! Flight price alerts: saved searches re-run in the background until their price
+//! reaches a target.
+
+use bookedai_core::price_alerts::{PriceAlert, PriceAlerts, PricePoint};
+use bookedai_core::{errors, mcp, pagination, RpcError, Tool};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{error, info, warn};
+
+use crate::filters::SortBy;
+use crate::{with_search_options, DuffelFlightServer, FlightSearchRequest};
+
+/// `kind` of the alerts this server owns in the shared store.
+const FLIGHT_ALERT: &str = "flight";
+
+const STATUSES: [&str; 3] = ["active", "triggered", "expired"];
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TrackFlightPriceRequest {
+    /// Alert when the cheapest offer costs this much or less.
+    target_price: f64,
+    #[serde(flatten)]
+    search: FlightSearchRequest,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ListPriceAlertsRequest {
+    alert_id: Option<String>,
+    status: Option<String>,
+    /// Price checks to include per alert, newest first.
+    history_limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TrackFlightPriceResponse {
+    alert: PriceAlert,
+    /// Result of the first check, made while creating the alert.
+    current_price: Option<PricePoint>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AlertWithHistory {
+    #[serde(flatten)]
+    alert: PriceAlert,
+    history: Vec<PricePoint>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ListPriceAlertsResponse {
+    alerts: Vec<AlertWithHistory>,
+}
+
+fn describe_search(search: &FlightSearchRequest) -> String {
+    let mut description = format!("{} -> {} on {}", search.origin, search.destination, search.departure_date);
+    if let Some(return_date) = &search.return_date {
+        description.push_str(&format!(", returning {}", return_date));
+    }
+    description
+}
+
+fn alerts_error(e: anyhow::Error) -> RpcError {
+    error!("Price alert error: {}", e);
+    RpcError::server_error(format!("Price alert storage failed: {}", e))
+}
+
+impl DuffelFlightServer {
+    /// Price of the cheapest offer for a saved search, or `None` when nothing matches.
+    ///
+    /// The price is in the search's `display_currency` when it has one.
+    async fn cheapest_price(&self, mut search: FlightSearchRequest) -> Result<Option<PricePoint>, RpcError> {
+        search.validate()?;
+        let passengers = search.options.passenger_mix.to_duffel()?;
+        search.options.programmes.validate(&passengers)?;
+        search.options.filters.validate()?;
+        search.options.filters.sort_by = Some(SortBy::Price);
+        let display = self.fx.display_currency(search.options.display_currency.as_deref()).await?;
+        let converting = display.is_some();
+        let slices = search.slices();
+
+        let response = match search.fanout() {
+            Some(fanout) => self.search_fanout(fanout, search.options, pagination::MAX_LIMIT, display).await,
+            None => self.search_flights(slices, search.options, pagination::MAX_LIMIT, None, display).await,
+        }
+        .map_err(|e| errors::rpc_error("Flight search failed", &e))?;
+        let Some(offer) = response.offers.into_iter().next() else {
+            return Ok(None);
+        };
+
+        let (amount, currency) = match (offer.converted_amount, converting) {
+            (Some(converted), _) => (converted.amount, converted.currency),
+            (None, false) => (offer.price, offer.currency),
+            (None, true) => {
+                return Err(RpcError::server_error(format!(
+                    "No exchange rate to convert {} prices",
+                    offer.currency
+                )))
+            }
+        };
+        let price = amount
+            .parse()
+            .map_err(|_| RpcError::server_error(format!("Unreadable offer price: {}", amount)))?;
+        Ok(Some(PricePoint {
+            checked_at: Utc::now().to_rfc3339(),
+            price,
+            currency,
+            offer_id: Some(offer.id),
+        }))
+    }
+}
+
+/// Re-run every tracked flight search once and record its price.
+pub async fn check_price_alerts(flights: DuffelFlightServer, alerts: PriceAlerts) -> anyhow::Result<()> {
+    let due = alerts.due(FLIGHT_ALERT).await?;
+    info!("Checking {} flight price alerts", due.len());
+
+    // One search at a time, so background checks never crowd out live tool calls
+    for alert in due {
+        let mut search: FlightSearchRequest = match serde_json::from_value(alert.search.clone()) {
+            Ok(search) => search,
+            Err(e) => {
+                alerts.expire(&alert.id, format!("Saved search is unreadable: {}", e)).await?;
+                continue;
+            }
+        };
+        // A departure date in the past can never be searched again
+        if let Err(e) = search.validate() {
+            let reason = e.message.trim_start_matches("Invalid parameters: ").to_string();
+            info!("Expiring price alert {}: {}", alert.id, reason);
+            alerts.expire(&alert.id, reason).await?;
+            continue;
+        }
+
+        match flights.cheapest_price(search).await {
+            Ok(Some(point)) => {
+                let (price, currency) = (point.price, point.currency.clone());
+                if alerts.record_price(&alert.id, point).await? {
+                    info!(
+                        "Price alert {} triggered: {} is now {:.2} {} (target {:.2})",
+                        alert.id, alert.description, price, currency, alert.target_price
+                    );
+                }
+            }
+            Ok(None) => {
+                alerts
+                    .record_error(&alert.id, "No offers matched the search".to_string())
+                    .await?
+            }
+            Err(e) => {
+                warn!("Price alert {} check failed: {}", alert.id, e.message);
+                alerts.record_error(&alert.id, e.message).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn format_price(point: &PricePoint) -> String {
+    format!("{:.2} {}", point.price, point.currency)
+}
+
+fn format_tracked(response: &TrackFlightPriceResponse) -> String {
+    let alert = &response.alert;
+    let currency = alert.currency.as_deref().unwrap_or("(offer currency)");
+    let mut result = format!(
+        "Tracking flights {} with a target of {:.2} {}.\n",
+        alert.description, alert.target_price, currency
+    );
+    match &response.current_price {
+        Some(point) => {
+            result.push_str(&format!("Cheapest now: {}", format_price(point)));
+            if let Some(offer_id) = &point.offer_id {
+                result.push_str(&format!(" (offer {})", offer_id));
+            }
+            result.push('\n');
+            if alert.status == "triggered" {
+                result.push_str("This is already at or below the target.\n");
+            }
+        }
+        None => result.push_str("No offers match the search yet.\n"),
+    }
+    result.push_str(&format!("Alert ID: {}", alert.id));
+    result
+}
+
+fn format_alerts(response: &ListPriceAlertsResponse) -> String {
+    if response.alerts.is_empty() {
+        return "No price alerts found.".to_string();
+    }
+
+    let mut result = format!("{} price alerts:\n\n", response.alerts.len());
+    for (i, entry) in response.alerts.iter().enumerate() {
+        let alert = &entry.alert;
+        result.push_str(&format!(
+            "{}. {} [{}]\n   Target: {:.2} {}\n",
+            i + 1,
+            alert.description,
+            alert.status,
+            alert.target_price,
+            alert.currency.as_deref().unwrap_or("(offer currency)")
+        ));
+        if let (Some(price), Some(currency)) = (alert.last_price, &alert.last_currency) {
+            result.push_str(&format!("   Latest: {:.2} {}", price, currency));
+            if let Some(lowest) = alert.lowest_price {
+                result.push_str(&format!(" (lowest seen: {:.2})", lowest));
+            }
+            result.push('\n');
+        }
+        if let Some(triggered_at) = &alert.triggered_at {
+            result.push_str(&format!("   Reached target at {}\n", triggered_at));
+        }
+        if let Some(last_error) = &alert.last_error {
+            result.push_str(&format!("   Last check: {}\n", last_error));
+        }
+        for point in &entry.history {
+            result.push_str(&format!("   - {}: {}\n", point.checked_at, format_price(point)));
+        }
+        result.push_str(&format!("   Alert ID: {}\n\n", alert.id));
+    }
+    result.trim_end().to_string()
+}
+
+pub fn track_flight_price_tool() -> Tool {
+    let mut properties = with_search_options(json!({
+        "origin": {
+            "type": "string",
+            "description": "Origin IATA airport or city code (e.g., 'JFK', 'NYC')"
+        },
+        "destination": {
+            "type": "string",
+            "description": "Destination IATA airport or city code (e.g., 'LHR', 'LON')"
+        },
+        "departure_date": {
+            "type": "string",
+            "description": "Departure date in YYYY-MM-DD format"
+        },
+        "return_date": {
+            "type": "string",
+            "description": "Return date in YYYY-MM-DD format (optional, for round-trip)"
+        },
+        "target_price": {
+            "type": "number",
+            "description": "Alert once the cheapest offer costs this much or less (in display_currency when set, otherwise the offer currency)"
+        }
+    }));
+    // The saved search always looks for the single cheapest offer
+    if let Some(properties) = properties.as_object_mut() {
+        for option in ["limit", "page_token", "sort_by"] {
+            properties.remove(option);
+        }
+    }
+
+    Tool::new(
+        "track_flight_price",
+        "Track the price of a flight search and record when the cheapest offer drops to a target price",
+        json!({
+            "type": "object",
+            "properties": properties,
+            "required": ["origin", "destination", "departure_date", "target_price"]
+        }),
+    )
+    .with_output_schema(json!({
+        "type": "object",
+        "properties": {
+            "alert": price_alert_schema(),
+            "current_price": {
+                "anyOf": [price_point_schema(), { "type": "null" }],
+                "description": "Cheapest offer when the alert was created; null when nothing matched"
+            }
+        },
+        "required": ["alert"]
+    }))
+}
+
+pub fn list_price_alerts_tool() -> Tool {
+    Tool::new(
+        "list_price_alerts",
+        "List tracked flight prices with their latest checks and whether they reached their target",
+        json!({
+            "type": "object",
+            "properties": {
+                "alert_id": {
+                    "type": "string",
+                    "description": "Only this alert (starts with 'pal_')"
+                },
+                "status": {
+                    "type": "string",
+                    "enum": STATUSES,
+                    "description": "Only alerts with this status"
+                },
+                "history_limit": {
+                    "type": "integer",
+                    "description": format!(
+                        "Price checks to include per alert, newest first (1-{}, default: {})",
+                        pagination::MAX_LIMIT,
+                        pagination::DEFAULT_LIMIT
+                    )
+                }
+            }
+        }),
+    )
+    .with_output_schema(json!({
+        "type": "object",
+        "properties": {
+            "alerts": {
+                "type": "array",
+                "items": {
+                    "allOf": [price_alert_schema()],
+                    "properties": {
+                        "history": { "type": "array", "items": price_point_schema() }
+                    },
+                    "required": ["history"]
+                }
+            }
+        },
+        "required": ["alerts"]
+    }))
+}
+
+fn price_alert_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "id": { "type": "string", "description": "Alert ID, starting with 'pal_'" },
+            "kind": { "type": "string" },
+            "description": { "type": "string" },
+            "search": { "type": "object", "description": "search_flights arguments re-run on every check" },
+            "target_price": { "type": "number" },
+            "currency": { "type": ["string", "null"], "description": "Currency of target_price; null for the offer currency" },
+            "status": {
+                "type": "string",
+                "enum": STATUSES,
+                "description": "triggered once the target was reached; expired when the departure date has passed"
+            },
+            "created_at": { "type": "string" },
+            "last_checked_at": { "type": ["string", "null"] },
+            "last_price": { "type": ["number", "null"] },
+            "last_currency": { "type": ["string", "null"] },
+            "lowest_price": { "type": ["number", "null"] },
+            "triggered_at": { "type": ["string", "null"] },
+            "last_error": { "type": ["string", "null"], "description": "Why the latest check failed" }
+        },
+        "required": ["id", "kind", "description", "search", "target_price", "status", "created_at"]
+    })
+}
+
+fn price_point_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "checked_at": { "type": "string" },
+            "price": { "type": "number" },
+            "currency": { "type": "string" },
+            "offer_id": { "type": ["string", "null"] }
+        },
+        "required": ["checked_at", "price", "currency"]
+    })
+}
+
+fn unavailable() -> RpcError {
+    RpcError::server_error("Price alerts are unavailable: the database at BOOKEDAI_DB_PATH could not be opened")
+}
+
+pub async fn track_flight_price(
+    flights: DuffelFlightServer,
+    alerts: Option<PriceAlerts>,
+    arguments: Value,
+) -> Result<Value, RpcError> {
+    let mut request: TrackFlightPriceRequest = mcp::parse_arguments("track_flight_price", arguments)?;
+    if !request.target_price.is_finite() || request.target_price <= 0.0 {
+        return Err(RpcError::invalid_params(
+            "Invalid parameters: target_price must be greater than 0",
+        ));
+    }
+    let Some(alerts) = alerts else {
+        return Err(unavailable());
+    };
+
+    let search = &mut request.search;
+    search.validate()?;
+    search.options.limit = None;
+    search.options.page_token = None;
+    search.options.filters.sort_by = None;
+    let saved = serde_json::to_value(&*search).map_err(|e| RpcError::server_error(format!("Failed to save search: {}", e)))?;
+
+    // Check once up front, so a search that cannot run is never saved
+    let current_price = flights.cheapest_price(mcp::parse_arguments("track_flight_price", saved.clone())?).await?;
+
+    let alert = alerts
+        .create(
+            FLIGHT_ALERT,
+            describe_search(search),
+            saved,
+            request.target_price,
+            search.options.display_currency.as_ref().map(|code| code.to_uppercase()),
+        )
+        .await
+        .map_err(alerts_error)?;
+    if let Some(point) = &current_price {
+        alerts.record_price(&alert.id, point.clone()).await.map_err(alerts_error)?;
+    }
+    let alert = alerts.get(&alert.id).await.map_err(alerts_error)?.unwrap_or(alert);
+    info!("Tracking flight price {}: {}", alert.id, alert.description);
+
+    let response = TrackFlightPriceResponse { alert, current_price };
+    mcp::structured_content(format_tracked(&response), &response)
+}
+
+pub async fn list_price_alerts(alerts: Option<PriceAlerts>, arguments: Value) -> Result<Value, RpcError> {
+    let request: ListPriceAlertsRequest = mcp::parse_arguments("list_price_alerts", arguments)?;
+    let history_limit = pagination::resolve_limit(request.history_limit)?;
+    if let Some(status) = &request.status {
+        if !STATUSES.contains(&status.as_str()) {
+            return Err(RpcError::invalid_params(format!(
+                "Invalid parameters: status must be one of {}",
+                STATUSES.join(", ")
+            )));
+        }
+    }
+    let Some(alerts) = alerts else {
+        return Err(unavailable());
+    };
+
+    let found = match &request.alert_id {
+        Some(alert_id) => {
+            let alert = alerts.get(alert_id).await.map_err(alerts_error)?;
+            match alert.filter(|alert| alert.kind == FLIGHT_ALERT) {
+                Some(alert) => vec![alert],
+                None => {
+                    return Err(RpcError::invalid_params(format!(
+                        "Invalid parameters: no price alert with alert_id {}",
+                        alert_id
+                    )))
+                }
+            }
+        }
+        None => alerts
+            .list(FLIGHT_ALERT, request.status.as_deref())
+            .await
+            .map_err(alerts_error)?,
+    };
+
+    let mut response = ListPriceAlertsResponse { alerts: Vec::with_capacity(found.len()) };
+    for alert in found {
+        let history = alerts.history(&alert.id, history_limit).await.map_err(alerts_error)?;
+        response.alerts.push(AlertWithHistory { alert, history });
+    }
+    mcp::structured_content(format_alerts(&response), &response)
+}
@@ -0,0 +1,446 @@
+//! Seat maps and extra services (bags, seats, cancellation cover) for a flight offer.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use bookedai_core::{errors, mcp, validation, RpcError, Tool};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::error;
+
+use crate::DuffelFlightServer;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OfferLookupRequest {
+    offer_id: String,
+}
+
+/// A bookable seat, or one that is already taken.
+#[derive(Debug, Serialize, Deserialize)]
+struct Seat {
+    designator: String,
+    available: bool,
+    /// Cheapest price across passengers; `None` when the seat is taken.
+    price: Option<String>,
+    currency: Option<String>,
+    /// Seat service IDs, one per passenger who may select the seat.
+    service_ids: Vec<String>,
+    disclosures: Vec<String>,
+}
+
+/// Seats on one segment of the offer.
+#[derive(Debug, Serialize, Deserialize)]
+struct SegmentSeatMap {
+    seat_map_id: String,
+    segment_id: String,
+    slice_id: Option<String>,
+    cabin_class: Option<String>,
+    seats: Vec<Seat>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SeatMapResponse {
+    offer_id: String,
+    seat_maps: Vec<SegmentSeatMap>,
+}
+
+/// An optional extra that can be added to the order, such as a checked bag.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Ancillary {
+    pub service_id: String,
+    pub service_type: String,
+    pub description: String,
+    pub price: String,
+    pub currency: String,
+    pub maximum_quantity: u32,
+    pub passenger_ids: Vec<String>,
+    pub segment_ids: Vec<String>,
+}
+
+/// Price range of selectable seats on one segment.
+#[derive(Debug, Serialize, Deserialize)]
+struct SeatSummary {
+    segment_id: String,
+    available_seats: usize,
+    min_price: Option<String>,
+    max_price: Option<String>,
+    currency: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AncillariesResponse {
+    offer_id: String,
+    services: Vec<Ancillary>,
+    seats: Vec<SeatSummary>,
+}
+
+fn amount(value: &str) -> f64 {
+    value.parse().unwrap_or(f64::MAX)
+}
+
+impl DuffelFlightServer {
+    async fn get_seat_maps(&self, offer_id: &str) -> Result<SeatMapResponse> {
+        let response = self
+            .duffel
+            .get_with_query("/air/seat_maps", &[("offer_id", offer_id)])
+            .await?;
+        let seat_maps = response["data"]
+            .as_array()
+            .ok_or_else(|| anyhow::anyhow!("No seat map data in response"))?
+            .iter()
+            .filter_map(parse_seat_map)
+            .collect();
+
+        Ok(SeatMapResponse {
+            offer_id: offer_id.to_string(),
+            seat_maps,
+        })
+    }
+
+    async fn list_ancillaries(&self, offer_id: &str) -> Result<AncillariesResponse> {
+        let path = format!("/air/offers/{}", offer_id);
+        let offer = self
+            .duffel
+            .get_with_query(&path, &[("return_available_services", "true")])
+            .await?;
+        let services = offer["data"]["available_services"]
+            .as_array()
+            .map(|services| services.iter().filter_map(parse_service).collect())
+            .unwrap_or_default();
+
+        let seats = self
+            .get_seat_maps(offer_id)
+            .await?
+            .seat_maps
+            .iter()
+            .map(summarize_seats)
+            .collect();
+
+        Ok(AncillariesResponse {
+            offer_id: offer_id.to_string(),
+            services,
+            seats,
+        })
+    }
+
+    fn format_seat_maps(&self, response: &SeatMapResponse) -> String {
+        if response.seat_maps.is_empty() {
+            return format!(
+                "No seat maps are available for offer {}; the airline may not support advance seat selection.",
+                response.offer_id
+            );
+        }
+
+        let mut result = format!("Seat maps for offer {}:\n\n", response.offer_id);
+        for seat_map in &response.seat_maps {
+            let available: Vec<&Seat> = seat_map.seats.iter().filter(|seat| seat.available).collect();
+            result.push_str(&format!(
+                "Segment {} ({}): {} of {} seats available\n",
+                seat_map.segment_id,
+                seat_map.cabin_class.as_deref().unwrap_or("unknown cabin"),
+                available.len(),
+                seat_map.seats.len()
+            ));
+
+            // Group available seats by price so the list stays readable
+            let mut by_price: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+            for seat in &available {
+                let label = match (&seat.price, &seat.currency) {
+                    (Some(price), _) if amount(price) == 0.0 => "Free".to_string(),
+                    (Some(price), Some(currency)) => format!("{} {}", price, currency),
+                    _ => "Price unknown".to_string(),
+                };
+                by_price.entry(label).or_default().push(&seat.designator);
+            }
+            for (price, designators) in by_price {
+                result.push_str(&format!("   {}: {}\n", price, designators.join(", ")));
+            }
+            result.push('\n');
+        }
+
+        result.push_str("Add a seat to a booking with its service ID.");
+        result
+    }
+
+    fn format_ancillaries(&self, response: &AncillariesResponse) -> String {
+        let mut result = format!("Extras for offer {}:\n\n", response.offer_id);
+
+        if response.services.is_empty() {
+            result.push_str("No bags or other services can be added to this offer.\n");
+        }
+        for (i, service) in response.services.iter().enumerate() {
+            result.push_str(&format!(
+                "{}. {} - {} {} (service ID: {})\n",
+                i + 1,
+                service.description,
+                service.price,
+                service.currency,
+                service.service_id
+            ));
+            result.push_str(&format!(
+                "   Up to {} per passenger, {} passenger(s), {} segment(s)\n",
+                service.maximum_quantity,
+                service.passenger_ids.len(),
+                service.segment_ids.len()
+            ));
+        }
+
+        if !response.seats.is_empty() {
+            result.push_str("\nSeat selection:\n");
+            for summary in &response.seats {
+                match (&summary.min_price, &summary.max_price, &summary.currency) {
+                    (Some(min), Some(max), Some(currency)) => result.push_str(&format!(
+                        "   Segment {}: {} seats, {}-{} {}\n",
+                        summary.segment_id, summary.available_seats, min, max, currency
+                    )),
+                    _ => result.push_str(&format!(
+                        "   Segment {}: {} seats\n",
+                        summary.segment_id, summary.available_seats
+                    )),
+                }
+            }
+            result.push_str("Use get_seat_map for individual seats.");
+        }
+        result
+    }
+}
+
+fn parse_seat_map(seat_map: &Value) -> Option<SegmentSeatMap> {
+    let cabins = seat_map["cabins"].as_array()?;
+    let seats = cabins
+        .iter()
+        .filter_map(|cabin| cabin["rows"].as_array())
+        .flatten()
+        .filter_map(|row| row["sections"].as_array())
+        .flatten()
+        .filter_map(|section| section["elements"].as_array())
+        .flatten()
+        .filter(|element| element["type"] == "seat")
+        .filter_map(parse_seat)
+        .collect();
+
+    Some(SegmentSeatMap {
+        seat_map_id: seat_map["id"].as_str()?.to_string(),
+        segment_id: seat_map["segment_id"].as_str()?.to_string(),
+        slice_id: seat_map["slice_id"].as_str().map(|s| s.to_string()),
+        cabin_class: cabins
+            .first()
+            .and_then(|cabin| cabin["cabin_class"].as_str())
+            .map(|s| s.to_string()),
+        seats,
+    })
+}
+
+fn parse_seat(element: &Value) -> Option<Seat> {
+    let designator = element["designator"].as_str()?.to_string();
+    let services = element["available_services"].as_array().cloned().unwrap_or_default();
+
+    // A seat is bookable when at least one passenger is offered a service for it
+    let cheapest = services
+        .iter()
+        .filter_map(|service| service["total_amount"].as_str().map(|price| (price, service)))
+        .min_by(|(a, _), (b, _)| amount(a).total_cmp(&amount(b)));
+
+    Some(Seat {
+        designator,
+        available: !services.is_empty(),
+        price: cheapest.map(|(price, _)| price.to_string()),
+        currency: cheapest.and_then(|(_, service)| service["total_currency"].as_str().map(|s| s.to_string())),
+        service_ids: services
+            .iter()
+            .filter_map(|service| service["id"].as_str().map(|s| s.to_string()))
+            .collect(),
+        disclosures: element["disclosures"]
+            .as_array()
+            .map(|items| items.iter().filter_map(|d| d.as_str().map(|s| s.to_string())).collect())
+            .unwrap_or_default(),
+    })
+}
+
+pub fn parse_service(service: &Value) -> Option<Ancillary> {
+    let service_type = service["type"].as_str()?.to_string();
+    let metadata = &service["metadata"];
+    let description = match service_type.as_str() {
+        "baggage" => {
+            let kind = match metadata["type"].as_str() {
+                Some("carry_on") => "Carry-on bag",
+                _ => "Checked bag",
+            };
+            match metadata["maximum_weight_kg"].as_f64() {
+                Some(weight) => format!("{} (up to {} kg)", kind, weight),
+                None => kind.to_string(),
+            }
+        }
+        "cancel_for_any_reason" => "Cancel for any reason cover".to_string(),
+        other => other.replace('_', " "),
+    };
+
+    Some(Ancillary {
+        service_id: service["id"].as_str()?.to_string(),
+        service_type,
+        description,
+        price: service["total_amount"].as_str()?.to_string(),
+        currency: service["total_currency"].as_str()?.to_string(),
+        maximum_quantity: service["maximum_quantity"].as_u64().unwrap_or(1) as u32,
+        passenger_ids: string_array(&service["passenger_ids"]),
+        segment_ids: string_array(&service["segment_ids"]),
+    })
+}
+
+fn string_array(value: &Value) -> Vec<String> {
+    value
+        .as_array()
+        .map(|items| items.iter().filter_map(|item| item.as_str().map(|s| s.to_string())).collect())
+        .unwrap_or_default()
+}
+
+fn summarize_seats(seat_map: &SegmentSeatMap) -> SeatSummary {
+    let priced: Vec<&Seat> = seat_map
+        .seats
+        .iter()
+        .filter(|seat| seat.available && seat.price.is_some())
+        .collect();
+    let price = |seat: &&Seat| amount(seat.price.as_deref().unwrap_or_default());
+    let min = priced.iter().min_by(|a, b| price(a).total_cmp(&price(b)));
+    let max = priced.iter().max_by(|a, b| price(a).total_cmp(&price(b)));
+
+    SeatSummary {
+        segment_id: seat_map.segment_id.clone(),
+        available_seats: seat_map.seats.iter().filter(|seat| seat.available).count(),
+        min_price: min.and_then(|seat| seat.price.clone()),
+        max_price: max.and_then(|seat| seat.price.clone()),
+        currency: min.and_then(|seat| seat.currency.clone()),
+    }
+}
+
+fn offer_id_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "offer_id": {
+                "type": "string",
+                "description": "Offer ID from search_flights or search_multi_city (e.g., 'off_0000AEdGRhTrsmIL8Lbw3K')"
+            }
+        },
+        "required": ["offer_id"]
+    })
+}
+
+pub fn ancillary_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "service_id": { "type": "string" },
+            "service_type": { "type": "string" },
+            "description": { "type": "string" },
+            "price": { "type": "string" },
+            "currency": { "type": "string" },
+            "maximum_quantity": { "type": "integer" },
+            "passenger_ids": { "type": "array", "items": { "type": "string" } },
+            "segment_ids": { "type": "array", "items": { "type": "string" } }
+        },
+        "required": [
+            "service_id", "service_type", "description", "price", "currency",
+            "maximum_quantity", "passenger_ids", "segment_ids"
+        ]
+    })
+}
+
+pub fn get_seat_map_tool() -> Tool {
+    Tool::new(
+        "get_seat_map",
+        "Get the seat map for each segment of a flight offer, with seat availability and prices",
+        offer_id_schema(),
+    )
+    .with_output_schema(json!({
+        "type": "object",
+        "properties": {
+            "offer_id": { "type": "string" },
+            "seat_maps": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "seat_map_id": { "type": "string" },
+                        "segment_id": { "type": "string" },
+                        "slice_id": { "type": ["string", "null"] },
+                        "cabin_class": { "type": ["string", "null"] },
+                        "seats": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "designator": { "type": "string" },
+                                    "available": { "type": "boolean" },
+                                    "price": { "type": ["string", "null"] },
+                                    "currency": { "type": ["string", "null"] },
+                                    "service_ids": { "type": "array", "items": { "type": "string" } },
+                                    "disclosures": { "type": "array", "items": { "type": "string" } }
+                                },
+                                "required": ["designator", "available", "service_ids", "disclosures"]
+                            }
+                        }
+                    },
+                    "required": ["seat_map_id", "segment_id", "seats"]
+                }
+            }
+        },
+        "required": ["offer_id", "seat_maps"]
+    }))
+}
+
+pub fn list_ancillaries_tool() -> Tool {
+    Tool::new(
+        "list_ancillaries",
+        "List extra bags, seat selection prices and other services that can be added to a flight offer",
+        offer_id_schema(),
+    )
+    .with_output_schema(json!({
+        "type": "object",
+        "properties": {
+            "offer_id": { "type": "string" },
+            "services": { "type": "array", "items": ancillary_schema() },
+            "seats": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "segment_id": { "type": "string" },
+                        "available_seats": { "type": "integer" },
+                        "min_price": { "type": ["string", "null"] },
+                        "max_price": { "type": ["string", "null"] },
+                        "currency": { "type": ["string", "null"] }
+                    },
+                    "required": ["segment_id", "available_seats"]
+                }
+            }
+        },
+        "required": ["offer_id", "services", "seats"]
+    }))
+}
+
+pub async fn get_seat_map(flights: DuffelFlightServer, arguments: Value) -> Result<Value, RpcError> {
+    let request: OfferLookupRequest = mcp::parse_arguments("get_seat_map", arguments)?;
+    let offer_id = validation::duffel_id("offer_id", &request.offer_id, "off_")?;
+
+    let response = flights.get_seat_maps(&offer_id).await.map_err(|e| {
+        error!("Seat map error: {}", e);
+        errors::rpc_error("Seat map lookup failed", &e)
+    })?;
+
+    let formatted_results = flights.format_seat_maps(&response);
+    mcp::structured_content(formatted_results, &response)
+}
+
+pub async fn list_ancillaries(flights: DuffelFlightServer, arguments: Value) -> Result<Value, RpcError> {
+    let request: OfferLookupRequest = mcp::parse_arguments("list_ancillaries", arguments)?;
+    let offer_id = validation::duffel_id("offer_id", &request.offer_id, "off_")?;
+
+    let response = flights.list_ancillaries(&offer_id).await.map_err(|e| {
+        error!("Ancillaries error: {}", e);
+        errors::rpc_error("Ancillary lookup failed", &e)
+    })?;
+
+    let formatted_results = flights.format_ancillaries(&response);
+    mcp::structured_content(formatted_results, &response)
+}
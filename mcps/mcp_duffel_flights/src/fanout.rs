@@ -0,0 +1,252 @@
+//! Searches fanned out over nearby airports or surrounding dates: one offer request
+//! per variant, run concurrently and merged into a single list.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use bookedai_core::fx::DisplayCurrency;
+use bookedai_core::progress;
+use futures_util::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::filters::{self, SortBy};
+use crate::{DuffelFlightServer, FlightOffer, FlightSearchResponse, SearchOptions, SliceRequest};
+
+/// Offer requests in flight at once for one fanned-out search.
+const VARIANT_SEARCH_CONCURRENCY: usize = 4;
+
+/// What varies between the searches of a [`Fanout`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FanoutKind {
+    /// Origin and destination airports; offers are tagged with their pair.
+    AirportPairs,
+    /// Travel dates; the response gets a cheapest-price calendar.
+    Dates,
+}
+
+/// One search of a [`Fanout`], with the slices sent to Duffel for it.
+#[derive(Debug)]
+pub struct SearchVariant {
+    pub origin: String,
+    pub destination: String,
+    pub departure_date: String,
+    pub return_date: Option<String>,
+    pub slices: Vec<SliceRequest>,
+}
+
+/// Several variants of one search, merged into a single page of results.
+#[derive(Debug)]
+pub struct Fanout {
+    pub kind: FanoutKind,
+    pub variants: Vec<SearchVariant>,
+}
+
+/// The cheapest offer found for one pair of travel dates.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DatePrice {
+    pub departure_date: String,
+    pub return_date: Option<String>,
+    /// In `display_currency` when one was requested; `None` when nothing matched or the search failed.
+    pub cheapest_price: Option<String>,
+    pub currency: Option<String>,
+    pub offer_id: Option<String>,
+    /// Matching offers fetched for these dates.
+    pub offers: usize,
+}
+
+impl DatePrice {
+    fn new(variant: &SearchVariant, offers: &[FlightOffer]) -> Self {
+        let cheapest = offers
+            .iter()
+            .min_by(|a, b| filters::price(a).total_cmp(&filters::price(b)));
+        let (price, currency) = match cheapest {
+            Some(offer) => match &offer.converted_amount {
+                Some(converted) => (Some(converted.amount.clone()), Some(converted.currency.clone())),
+                None => (Some(offer.price.clone()), Some(offer.currency.clone())),
+            },
+            None => (None, None),
+        };
+        Self {
+            departure_date: variant.departure_date.clone(),
+            return_date: variant.return_date.clone(),
+            cheapest_price: price,
+            currency,
+            offer_id: cheapest.map(|offer| offer.id.clone()),
+            offers: offers.len(),
+        }
+    }
+}
+
+/// Flights, airline and departure times of an offer; equal keys mean the same trip.
+fn itinerary_key(offer: &FlightOffer) -> String {
+    let mut key = offer.airline_code.clone().unwrap_or_else(|| offer.airline.clone());
+    for segment in offer.slices.iter().flat_map(|slice| &slice.segments) {
+        key.push_str(&format!(
+            "|{}{}@{}",
+            segment.airline_code.as_deref().unwrap_or_default(),
+            segment.flight_number,
+            segment.departure_time
+        ));
+    }
+    key
+}
+
+/// Keep only the cheapest offer for each itinerary, preserving the order of the rest.
+fn dedupe(offers: Vec<FlightOffer>) -> Vec<FlightOffer> {
+    let mut kept: Vec<FlightOffer> = Vec::with_capacity(offers.len());
+    let mut index_by_key: HashMap<String, usize> = HashMap::new();
+    for offer in offers {
+        let key = itinerary_key(&offer);
+        match index_by_key.get(&key) {
+            Some(&index) => {
+                if filters::price(&offer) < filters::price(&kept[index]) {
+                    kept[index] = offer;
+                }
+            }
+            None => {
+                index_by_key.insert(key, kept.len());
+                kept.push(offer);
+            }
+        }
+    }
+    kept
+}
+
+/// How a variant is named in warnings, e.g. `LGW to JFK` or `2025-07-14`.
+fn describe(kind: FanoutKind, variant: &SearchVariant) -> String {
+    match (kind, &variant.return_date) {
+        (FanoutKind::AirportPairs, _) => format!("{} to {}", variant.origin, variant.destination),
+        (FanoutKind::Dates, Some(return_date)) => format!("{} to {}", variant.departure_date, return_date),
+        (FanoutKind::Dates, None) => variant.departure_date.clone(),
+    }
+}
+
+impl DuffelFlightServer {
+    /// Run every variant of a search and merge the offers into one page of at most `limit`.
+    ///
+    /// Variants that fail become warnings; the search only fails when every variant does.
+    pub async fn search_fanout(
+        &self,
+        fanout: Fanout,
+        options: SearchOptions,
+        limit: usize,
+        display: Option<DisplayCurrency>,
+    ) -> Result<FlightSearchResponse> {
+        let kind = fanout.kind;
+        let mut variant_options = options.clone();
+        if kind == FanoutKind::Dates {
+            // Each date's cheapest offers, whatever order the merged list ends up in
+            variant_options.filters.sort_by = Some(SortBy::Price);
+        }
+
+        let total = fanout.variants.len();
+        let mut done = 0;
+        let results: Vec<(SearchVariant, Result<FlightSearchResponse>)> = stream::iter(fanout.variants)
+            .map(|variant| {
+                let options = variant_options.clone();
+                let display = display.clone();
+                async move {
+                    let slices = variant.slices.clone();
+                    let response = self.search_flights(slices, options, limit, None, display).await;
+                    (variant, response)
+                }
+            })
+            .buffered(VARIANT_SEARCH_CONCURRENCY)
+            .inspect(|_| {
+                done += 1;
+                progress::report(format!("Ran {} of {} searches", done, total));
+            })
+            .collect()
+            .await;
+
+        let mut offers = Vec::new();
+        let mut total_results = 0;
+        let mut available_results = Some(0);
+        let mut skipped_results = 0;
+        let mut warnings = Vec::new();
+        let mut calendar = Vec::new();
+        let mut search_id = None;
+        let mut first_error = None;
+        for (variant, response) in results {
+            let name = describe(kind, &variant);
+            match response {
+                Ok(response) => {
+                    search_id.get_or_insert(response.search_id);
+                    total_results += response.total_results;
+                    available_results = available_results.zip(response.available_results).map(|(a, b)| a + b);
+                    skipped_results += response.skipped_results;
+                    warnings.extend(response.warnings.into_iter().map(|warning| format!("{}: {}", name, warning)));
+                    if kind == FanoutKind::Dates {
+                        calendar.push(DatePrice::new(&variant, &response.offers));
+                    }
+                    for mut offer in response.offers {
+                        if kind == FanoutKind::AirportPairs {
+                            offer.origin_airport = Some(variant.origin.clone());
+                            offer.destination_airport = Some(variant.destination.clone());
+                        }
+                        offers.push(offer);
+                    }
+                }
+                Err(e) => {
+                    let warning = format!("Search for {} failed: {}", name, e);
+                    warn!("{}", warning);
+                    warnings.push(warning);
+                    if kind == FanoutKind::Dates {
+                        calendar.push(DatePrice::new(&variant, &[]));
+                    }
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        let Some(search_id) = search_id else {
+            return Err(first_error.unwrap_or_else(|| anyhow::anyhow!("Nothing to search")));
+        };
+
+        // Each variant came back sorted on its own; order the merged list, cheapest first by default
+        let mut filters = options.filters;
+        filters.sort_by.get_or_insert(SortBy::Price);
+        let mut offers = filters.apply(dedupe(offers));
+        offers.truncate(limit);
+
+        Ok(FlightSearchResponse {
+            offers,
+            total_results,
+            search_id,
+            next_page_token: None,
+            skipped_results,
+            warnings,
+            price_calendar: (kind == FanoutKind::Dates).then_some(calendar),
+            available_results,
+        })
+    }
+}
+
+/// Cheapest price per date, marking the cheapest of all.
+pub fn describe_calendar(result: &mut String, calendar: &[DatePrice]) {
+    let price = |entry: &DatePrice| entry.cheapest_price.as_deref().and_then(|price| price.parse::<f64>().ok());
+    let lowest = calendar.iter().filter_map(price).min_by(f64::total_cmp);
+    let day = |date: &str| {
+        date.parse::<chrono::NaiveDate>()
+            .map_or_else(|_| date.to_string(), |parsed| parsed.format("%a %Y-%m-%d").to_string())
+    };
+
+    result.push_str("Cheapest by date:\n");
+    for entry in calendar {
+        let dates = match &entry.return_date {
+            Some(return_date) => format!("{} to {}", day(&entry.departure_date), day(return_date)),
+            None => day(&entry.departure_date),
+        };
+        match (&entry.cheapest_price, &entry.currency) {
+            (Some(amount), Some(currency)) => {
+                result.push_str(&format!("   {}: {} {}", dates, amount, currency));
+                if price(entry).is_some() && price(entry) == lowest {
+                    result.push_str(" (cheapest)");
+                }
+                result.push('\n');
+            }
+            _ => result.push_str(&format!("   {}: no flights found\n", dates)),
+        }
+    }
+    result.push('\n');
+}
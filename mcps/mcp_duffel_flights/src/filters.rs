@@ -0,0 +1,186 @@
+//! Post-fetch filtering and sorting of flight offers.
+
+use std::cmp::Ordering;
+
+use bookedai_core::RpcError;
+use chrono::{NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+use crate::FlightOffer;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortBy {
+    Price,
+    Duration,
+    Departure,
+}
+
+/// Local departure time range, inclusive, as `HH:MM` strings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeWindow {
+    pub earliest: Option<String>,
+    pub latest: Option<String>,
+}
+
+/// Optional constraints applied to offers after they are fetched from Duffel.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FlightFilters {
+    pub max_price: Option<f64>,
+    pub max_stops: Option<i32>,
+    pub preferred_airlines: Option<Vec<String>>,
+    pub departure_time_window: Option<TimeWindow>,
+    pub sort_by: Option<SortBy>,
+}
+
+fn parse_time(value: &str, field: &str) -> Result<NaiveTime, RpcError> {
+    NaiveTime::parse_from_str(value, "%H:%M").map_err(|_| {
+        RpcError::invalid_params(format!(
+            "Invalid parameters: departure_time_window.{} must be HH:MM, got '{}'",
+            field, value
+        ))
+    })
+}
+
+/// Convert an ISO 8601 duration such as `PT7H30M` or `P1DT2H` to minutes.
+pub fn duration_minutes(duration: &str) -> Option<i64> {
+    let rest = duration.strip_prefix('P')?;
+    let (days, time) = match rest.split_once('T') {
+        Some((days, time)) => (days, time),
+        None => (rest, ""),
+    };
+
+    let mut minutes = 0;
+    if !days.is_empty() {
+        minutes += days.strip_suffix('D')?.parse::<i64>().ok()? * 24 * 60;
+    }
+
+    let mut number = String::new();
+    for c in time.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'H' => minutes += number.parse::<i64>().ok()? * 60,
+            'M' => minutes += number.parse::<i64>().ok()?,
+            'S' => {}
+            _ => return None,
+        }
+        if c.is_ascii_alphabetic() {
+            number.clear();
+        }
+    }
+    Some(minutes)
+}
+
+fn departure_time(offer: &FlightOffer) -> Option<NaiveDateTime> {
+    offer.outbound().departure()
+}
+
+/// Total price, in the display currency when one was requested.
+pub fn price(offer: &FlightOffer) -> f64 {
+    let amount = offer
+        .converted_amount
+        .as_ref()
+        .map_or(&offer.price, |converted| &converted.amount);
+    amount.parse().unwrap_or(f64::MAX)
+}
+
+impl FlightFilters {
+    /// Check parameters that serde cannot, before any Duffel call is made.
+    pub fn validate(&self) -> Result<(), RpcError> {
+        if let Some(max_price) = self.max_price {
+            if max_price <= 0.0 {
+                return Err(RpcError::invalid_params("Invalid parameters: max_price must be positive"));
+            }
+        }
+        if let Some(max_stops) = self.max_stops {
+            if max_stops < 0 {
+                return Err(RpcError::invalid_params("Invalid parameters: max_stops cannot be negative"));
+            }
+        }
+        self.time_bounds().map(|_| ())
+    }
+
+    fn time_bounds(&self) -> Result<(Option<NaiveTime>, Option<NaiveTime>), RpcError> {
+        let Some(window) = &self.departure_time_window else {
+            return Ok((None, None));
+        };
+        let earliest = window
+            .earliest
+            .as_deref()
+            .map(|value| parse_time(value, "earliest"))
+            .transpose()?;
+        let latest = window
+            .latest
+            .as_deref()
+            .map(|value| parse_time(value, "latest"))
+            .transpose()?;
+        Ok((earliest, latest))
+    }
+
+    /// Duffel's own `sort` value for the list-offers endpoint, when it has one.
+    pub fn duffel_sort(&self) -> Option<&'static str> {
+        match self.sort_by? {
+            SortBy::Price => Some("total_amount"),
+            SortBy::Duration => Some("total_duration"),
+            SortBy::Departure => None,
+        }
+    }
+
+    fn matches(&self, offer: &FlightOffer, bounds: (Option<NaiveTime>, Option<NaiveTime>)) -> bool {
+        if let Some(max_price) = self.max_price {
+            if price(offer) > max_price {
+                return false;
+            }
+        }
+
+        if let Some(max_stops) = self.max_stops {
+            if offer.max_stops() as i32 > max_stops {
+                return false;
+            }
+        }
+
+        if let Some(airlines) = self.preferred_airlines.as_ref().filter(|airlines| !airlines.is_empty()) {
+            let preferred = airlines.iter().any(|airline| {
+                airline.eq_ignore_ascii_case(&offer.airline)
+                    || offer
+                        .airline_code
+                        .as_deref()
+                        .is_some_and(|code| airline.eq_ignore_ascii_case(code))
+            });
+            if !preferred {
+                return false;
+            }
+        }
+
+        if let (None, None) = bounds {
+            return true;
+        }
+        let Some(departure) = departure_time(offer).map(|departure| departure.time()) else {
+            return false;
+        };
+        bounds.0.is_none_or(|earliest| departure >= earliest)
+            && bounds.1.is_none_or(|latest| departure <= latest)
+    }
+
+    /// Drop offers that fail any constraint, then order what is left.
+    pub fn apply(&self, offers: Vec<FlightOffer>) -> Vec<FlightOffer> {
+        // Bounds were checked in `validate`, so a parse failure here cannot happen
+        let bounds = self.time_bounds().unwrap_or((None, None));
+        let mut offers: Vec<FlightOffer> = offers
+            .into_iter()
+            .filter(|offer| self.matches(offer, bounds))
+            .collect();
+
+        match self.sort_by {
+            Some(SortBy::Price) => {
+                offers.sort_by(|a, b| price(a).partial_cmp(&price(b)).unwrap_or(Ordering::Equal))
+            }
+            Some(SortBy::Duration) => {
+                offers.sort_by_key(|offer| offer.total_duration_minutes().unwrap_or(i64::MAX))
+            }
+            Some(SortBy::Departure) => offers.sort_by_key(departure_time),
+            None => {}
+        }
+        offers
+    }
+}
@@ -0,0 +1,208 @@
+//! Per-slice itinerary details: every segment, plus the layovers between them.
+
+use bookedai_core::duffel_models;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::filters::duration_minutes;
+
+const DUFFEL_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// One flight within a slice.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlightSegment {
+    pub origin: String,
+    pub destination: String,
+    pub departure_time: String,
+    pub arrival_time: String,
+    /// IANA time zones the departure and arrival times are local to, when Duffel gives them.
+    #[serde(default)]
+    pub departure_time_zone: Option<String>,
+    #[serde(default)]
+    pub arrival_time_zone: Option<String>,
+    pub duration: Option<String>,
+    pub airline: String,
+    pub airline_code: Option<String>,
+    pub flight_number: String,
+    /// Set when another airline flies the segment (codeshare).
+    pub operating_airline: Option<String>,
+    pub aircraft: Option<String>,
+}
+
+/// Time on the ground between two consecutive segments.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Layover {
+    pub arrival_airport: String,
+    /// Differs from `arrival_airport` when the connection needs an airport change.
+    pub departure_airport: String,
+    pub duration_minutes: Option<i64>,
+}
+
+/// One leg of the journey, e.g. the outbound or the return.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlightSlice {
+    pub origin: String,
+    pub destination: String,
+    pub departure_time: String,
+    pub arrival_time: String,
+    pub duration: Option<String>,
+    pub stops: usize,
+    pub segments: Vec<FlightSegment>,
+    pub layovers: Vec<Layover>,
+}
+
+fn parse_time(value: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value, DUFFEL_TIME_FORMAT).ok()
+}
+
+impl FlightSlice {
+    /// Build a slice from Duffel's; `None` when it has no segments.
+    pub fn from_duffel(slice: &duffel_models::Slice) -> Option<Self> {
+        let first = slice.segments.first()?;
+        let last = slice.segments.last()?;
+
+        let segments = slice
+            .segments
+            .iter()
+            .map(|segment| FlightSegment {
+                origin: segment.origin.iata_code.clone(),
+                destination: segment.destination.iata_code.clone(),
+                departure_time: segment.departing_at.clone(),
+                arrival_time: segment.arriving_at.clone(),
+                departure_time_zone: segment.origin.time_zone.clone(),
+                arrival_time_zone: segment.destination.time_zone.clone(),
+                duration: segment.duration.clone(),
+                airline: segment.marketing_carrier.name.clone(),
+                airline_code: segment.marketing_carrier.iata_code.clone(),
+                flight_number: segment.marketing_carrier_flight_number.clone(),
+                operating_airline: segment
+                    .operating_carrier
+                    .as_ref()
+                    .filter(|operating| operating.name != segment.marketing_carrier.name)
+                    .map(|operating| operating.name.clone()),
+                aircraft: segment.aircraft.as_ref().map(|aircraft| aircraft.name.clone()),
+            })
+            .collect();
+
+        // Both times are local to the connecting airport, so the naive difference is exact
+        let layovers = slice
+            .segments
+            .windows(2)
+            .map(|pair| Layover {
+                arrival_airport: pair[0].destination.iata_code.clone(),
+                departure_airport: pair[1].origin.iata_code.clone(),
+                duration_minutes: parse_time(&pair[0].arriving_at)
+                    .zip(parse_time(&pair[1].departing_at))
+                    .map(|(arrived, departs)| (departs - arrived).num_minutes()),
+            })
+            .collect();
+
+        Some(Self {
+            origin: slice.origin.iata_code.clone(),
+            destination: slice.destination.iata_code.clone(),
+            departure_time: first.departing_at.clone(),
+            arrival_time: last.arriving_at.clone(),
+            duration: slice.duration.clone(),
+            stops: slice.segments.len() - 1,
+            segments,
+            layovers,
+        })
+    }
+
+    pub fn duration_minutes(&self) -> Option<i64> {
+        self.duration.as_deref().and_then(duration_minutes)
+    }
+
+    pub fn departure(&self) -> Option<NaiveDateTime> {
+        parse_time(&self.departure_time)
+    }
+}
+
+/// Render minutes as `7h 05m`.
+pub fn format_minutes(minutes: i64) -> String {
+    format!("{}h {:02}m", minutes / 60, minutes % 60)
+}
+
+/// `2025-03-15T18:05:00` as `2025-03-15 18:05`.
+pub fn format_time(value: &str) -> String {
+    match parse_time(value) {
+        Some(time) => time.format("%Y-%m-%d %H:%M").to_string(),
+        None => value.to_string(),
+    }
+}
+
+/// `Flight`, `Outbound` / `Return` for a round trip, or `Leg N`, for slice `n` of `slices`.
+pub fn slice_label(slices: &[FlightSlice], n: usize) -> String {
+    let round_trip = slices.len() == 2
+        && slices[0].origin == slices[1].destination
+        && slices[0].destination == slices[1].origin;
+    match (slices.len(), round_trip, n) {
+        (1, _, _) => "Flight".to_string(),
+        (_, true, 0) => "Outbound".to_string(),
+        (_, true, _) => "Return".to_string(),
+        _ => format!("Leg {}", n + 1),
+    }
+}
+
+/// Append a slice's one-line overview to a text summary: airports, times, duration and stops.
+pub fn summarize_slice(result: &mut String, label: &str, slice: &FlightSlice) {
+    let duration = slice
+        .duration_minutes()
+        .map(format_minutes)
+        .unwrap_or_else(|| "duration unknown".to_string());
+    let stops = match slice.stops {
+        0 => "direct".to_string(),
+        1 => "1 stop".to_string(),
+        n => format!("{} stops", n),
+    };
+    result.push_str(&format!(
+        "   {}: {} -> {}, {} -> {} ({}, {})\n",
+        label,
+        slice.origin,
+        slice.destination,
+        format_time(&slice.departure_time),
+        format_time(&slice.arrival_time),
+        duration,
+        stops
+    ));
+}
+
+/// Append a slice and its segments to a text summary.
+pub fn describe_slice(result: &mut String, label: &str, slice: &FlightSlice) {
+    summarize_slice(result, label, slice);
+    for (i, segment) in slice.segments.iter().enumerate() {
+        let mut line = format!(
+            "      {}{} {} {} -> {} {}",
+            segment.airline_code.as_deref().unwrap_or(""),
+            segment.flight_number,
+            segment.origin,
+            format_time(&segment.departure_time),
+            segment.destination,
+            format_time(&segment.arrival_time)
+        );
+        line.push_str(&format!(" ({}", segment.airline));
+        if let Some(operating) = &segment.operating_airline {
+            line.push_str(&format!(", operated by {}", operating));
+        }
+        if let Some(aircraft) = &segment.aircraft {
+            line.push_str(&format!(", {}", aircraft));
+        }
+        line.push_str(")\n");
+        result.push_str(&line);
+
+        if let Some(layover) = slice.layovers.get(i) {
+            let wait = layover
+                .duration_minutes
+                .map(format_minutes)
+                .unwrap_or_else(|| "unknown".to_string());
+            if layover.arrival_airport == layover.departure_airport {
+                result.push_str(&format!("      Layover: {} in {}\n", wait, layover.arrival_airport));
+            } else {
+                result.push_str(&format!(
+                    "      Layover: {}, change airports from {} to {}\n",
+                    wait, layover.arrival_airport, layover.departure_airport
+                ));
+            }
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! Frequent-flyer accounts and negotiated (corporate) fare codes for offer requests, so
+//! airlines can return the fares those travellers are entitled to.
+
+use std::collections::BTreeMap;
+
+use bookedai_core::RpcError;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// A frequent-flyer account held by one of the adults searching.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoyaltyAccount {
+    pub airline_iata_code: String,
+    pub account_number: String,
+    /// Duffel requires the holder's name alongside their account.
+    pub given_name: String,
+    pub family_name: String,
+    /// Which adult holds the account, starting at 1 (default: 1).
+    pub adult: Option<u32>,
+}
+
+/// A negotiated fare agreement with an airline.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrivateFare {
+    pub airline_iata_code: String,
+    pub corporate_code: Option<String>,
+    pub tracking_reference: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FareProgrammes {
+    pub loyalty_programme_accounts: Option<Vec<LoyaltyAccount>>,
+    pub private_fares: Option<Vec<PrivateFare>>,
+}
+
+fn invalid(message: String) -> RpcError {
+    RpcError::invalid_params(format!("Invalid parameters: {}", message))
+}
+
+/// Check a 2-character IATA airline code and return it uppercased.
+fn airline_code(field: &str, value: &str) -> Result<String, RpcError> {
+    let code = value.trim().to_ascii_uppercase();
+    if code.len() != 2 || !code.bytes().all(|b| b.is_ascii_alphanumeric()) {
+        return Err(invalid(format!(
+            "{} must be a 2-character IATA airline code (e.g. 'BA'), got '{}'",
+            field, value
+        )));
+    }
+    Ok(code)
+}
+
+fn required(field: &str, value: &str) -> Result<String, RpcError> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(invalid(format!("{} must not be empty", field)));
+    }
+    Ok(value.to_string())
+}
+
+fn optional(value: &Option<String>) -> Option<String> {
+    value.as_deref().map(str::trim).filter(|value| !value.is_empty()).map(str::to_string)
+}
+
+impl FareProgrammes {
+    /// Check both lists against the passengers built by `PassengerMix::to_duffel`.
+    pub fn validate(&self, passengers: &[Value]) -> Result<(), RpcError> {
+        self.add_loyalty_accounts(&mut passengers.to_vec())?;
+        self.private_fares()?;
+        Ok(())
+    }
+
+    /// Add each account, with its holder's name, to the adult it belongs to.
+    pub fn add_loyalty_accounts(&self, passengers: &mut [Value]) -> Result<(), RpcError> {
+        let accounts = self.loyalty_programme_accounts.as_deref().unwrap_or_default();
+        let mut adults: Vec<&mut Value> = passengers
+            .iter_mut()
+            .filter(|passenger| passenger["type"] == "adult")
+            .collect();
+
+        for (index, account) in accounts.iter().enumerate() {
+            let field = |name: &str| format!("loyalty_programme_accounts[{}].{}", index, name);
+            let airline_iata_code = airline_code(&field("airline_iata_code"), &account.airline_iata_code)?;
+            let account_number = required(&field("account_number"), &account.account_number)?;
+            let given_name = required(&field("given_name"), &account.given_name)?;
+            let family_name = required(&field("family_name"), &account.family_name)?;
+
+            let adult = account.adult.unwrap_or(1);
+            let adult_count = adults.len();
+            let Some(passenger) = adult.checked_sub(1).and_then(|i| adults.get_mut(i as usize)) else {
+                return Err(invalid(format!(
+                    "{} is {}, but the search has {} adult(s)",
+                    field("adult"),
+                    adult,
+                    adult_count
+                )));
+            };
+
+            // One adult can hold several accounts, but only under one name
+            let named = passenger["given_name"].as_str().zip(passenger["family_name"].as_str());
+            if named.is_some_and(|(given, family)| given != given_name || family != family_name) {
+                return Err(invalid(format!(
+                    "{} gives a different name for adult {} than an earlier account",
+                    field("given_name"),
+                    adult
+                )));
+            }
+            passenger["given_name"] = json!(given_name);
+            passenger["family_name"] = json!(family_name);
+            if !passenger["loyalty_programme_accounts"].is_array() {
+                passenger["loyalty_programme_accounts"] = json!([]);
+            }
+            if let Some(accounts) = passenger["loyalty_programme_accounts"].as_array_mut() {
+                accounts.push(json!({
+                    "airline_iata_code": airline_iata_code,
+                    "account_number": account_number
+                }));
+            }
+        }
+        Ok(())
+    }
+
+    /// Duffel's `private_fares` object, keyed by airline; `None` when no codes were given.
+    pub fn private_fares(&self) -> Result<Option<Value>, RpcError> {
+        let fares = self.private_fares.as_deref().unwrap_or_default();
+        if fares.is_empty() {
+            return Ok(None);
+        }
+
+        let mut by_airline: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+        for (index, fare) in fares.iter().enumerate() {
+            let field = |name: &str| format!("private_fares[{}].{}", index, name);
+            let airline_iata_code = airline_code(&field("airline_iata_code"), &fare.airline_iata_code)?;
+            let corporate_code = optional(&fare.corporate_code);
+            let tracking_reference = optional(&fare.tracking_reference);
+            if corporate_code.is_none() && tracking_reference.is_none() {
+                return Err(invalid(format!(
+                    "private_fares[{}] needs a corporate_code or a tracking_reference",
+                    index
+                )));
+            }
+
+            let mut code = json!({});
+            if let Some(corporate_code) = corporate_code {
+                code["corporate_code"] = json!(corporate_code);
+            }
+            if let Some(tracking_reference) = tracking_reference {
+                code["tracking_reference"] = json!(tracking_reference);
+            }
+            by_airline.entry(airline_iata_code).or_default().push(code);
+        }
+        Ok(Some(json!(by_airline)))
+    }
+}
+
+/// Mask account numbers and traveller names in an offer request before it is logged.
+pub fn redact(payload: &mut Value) {
+    let Some(passengers) = payload["data"]["passengers"].as_array_mut() else {
+        return;
+    };
+    for passenger in passengers {
+        for field in ["given_name", "family_name"] {
+            if passenger[field].is_string() {
+                passenger[field] = json!("***");
+            }
+        }
+        if let Some(accounts) = passenger["loyalty_programme_accounts"].as_array_mut() {
+            for account in accounts {
+                account["account_number"] = json!("***");
+            }
+        }
+    }
+}
@@ -1,12 +1,101 @@
-use std::env;
-use std::collections::HashMap;
-use std::convert::Infallible;
+use std::time::Duration;
 
 use anyhow::Result;
+use bookedai_core::audit::{self, AuditLog};
+use bookedai_core::config::{self, Verbosity};
+use bookedai_core::duffel_models::Offer;
+use bookedai_core::fx::{ConvertedAmount, CurrencyConverter, DisplayCurrency};
+use bookedai_core::http::HttpOptions;
+use bookedai_core::idempotency::IdempotencyKeys;
+use bookedai_core::notifications::Notifier;
+use bookedai_core::price_alerts::PriceAlerts;
+use bookedai_core::profiles::TravelerProfiles;
+use bookedai_core::readiness::Readiness;
+use bookedai_core::search_results::{NewOffer, SearchResults};
+use bookedai_core::store::Store;
+use bookedai_core::webhooks::WebhookReceiver;
+use bookedai_core::{airports, errors, mcp, pagination, progress, scheduler, transport, validation};
+use bookedai_core::{DuffelClient, McpServer, ResponseCache, RpcError, Tool, Transport};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use tracing::{error, info};
-use warp::Filter;
+use tracing::{error, info, warn};
+
+mod alerts;
+mod ancillaries;
+mod fanout;
+mod filters;
+mod itinerary;
+mod loyalty;
+mod offers;
+mod orders;
+mod passengers;
+mod profiles;
+mod results;
+
+use fanout::{DatePrice, Fanout, FanoutKind, SearchVariant};
+use filters::FlightFilters;
+use itinerary::FlightSlice;
+use loyalty::FareProgrammes;
+use passengers::PassengerMix;
+
+/// Most slices accepted in a single multi-city search.
+const MAX_SLICES: usize = 6;
+
+/// Most days `date_flexibility_days` may move a search either way.
+const MAX_DATE_FLEXIBILITY_DAYS: i64 = 3;
+
+/// How often expired saved searches are deleted.
+const SEARCH_RESULTS_PURGE_INTERVAL: Duration = Duration::from_secs(600);
+
+/// First wait between checks on an offer request; doubles up to [`OFFER_POLL_MAX_DELAY`].
+const OFFER_POLL_INITIAL_DELAY: Duration = Duration::from_millis(250);
+const OFFER_POLL_MAX_DELAY: Duration = Duration::from_secs(2);
+
+/// One leg of an itinerary, mapped directly onto a Duffel offer request slice.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SliceRequest {
+    origin: String,
+    destination: String,
+    departure_date: String,
+}
+
+impl SliceRequest {
+    /// Normalize the airport codes and check the leg's endpoints and date.
+    ///
+    /// `prefix` names the leg in error messages (e.g. `slices[1].`).
+    fn validate(&mut self, prefix: &str) -> Result<chrono::NaiveDate, RpcError> {
+        self.origin = validation::airport_or_city(&format!("{}origin", prefix), &self.origin)?;
+        self.destination = validation::airport_or_city(&format!("{}destination", prefix), &self.destination)?;
+        if self.origin == self.destination {
+            return Err(RpcError::invalid_params(format!(
+                "Invalid parameters: {}origin and {}destination are both {}",
+                prefix, prefix, self.origin
+            )));
+        }
+        let departure_date = validation::upcoming_date(&format!("{}departure_date", prefix), &self.departure_date)?;
+        self.departure_date = departure_date.to_string();
+        Ok(departure_date)
+    }
+}
+
+/// Parameters shared by every flight search tool.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SearchOptions {
+    #[serde(flatten)]
+    passenger_mix: PassengerMix,
+    cabin_class: Option<String>,
+    /// ISO 4217 code to convert prices into, alongside the original amounts.
+    display_currency: Option<String>,
+    limit: Option<usize>,
+    page_token: Option<String>,
+    /// `summary` leaves segments and layovers out of the results.
+    verbosity: Option<Verbosity>,
+    #[serde(flatten)]
+    filters: FlightFilters,
+    /// Frequent-flyer accounts and corporate codes sent with the offer request.
+    #[serde(flatten)]
+    programmes: FareProgrammes,
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 struct FlightSearchRequest {
@@ -14,22 +103,224 @@ struct FlightSearchRequest {
     destination: String,
     departure_date: String,
     return_date: Option<String>,
-    passengers: Option<i32>,
-    cabin_class: Option<String>,
+    /// Also search the other airports of the origin's and destination's cities.
+    include_nearby_airports: Option<bool>,
+    /// Also search this many days before and after the travel dates.
+    date_flexibility_days: Option<i64>,
+    #[serde(flatten)]
+    options: SearchOptions,
+}
+
+impl FlightSearchRequest {
+    /// Check airports and dates before anything is sent to Duffel.
+    fn validate(&mut self) -> Result<(), RpcError> {
+        let mut outbound = SliceRequest {
+            origin: self.origin.clone(),
+            destination: self.destination.clone(),
+            departure_date: self.departure_date.clone(),
+        };
+        let departure_date = outbound.validate("")?;
+        self.origin = outbound.origin;
+        self.destination = outbound.destination;
+        self.departure_date = outbound.departure_date;
+
+        if let Some(return_date) = &mut self.return_date {
+            let parsed = validation::date("return_date", return_date)?;
+            validation::on_or_after("return_date", parsed, "departure_date", departure_date)?;
+            *return_date = parsed.to_string();
+        }
+
+        if let Some(days) = self.date_flexibility_days {
+            if !(0..=MAX_DATE_FLEXIBILITY_DAYS).contains(&days) {
+                return Err(RpcError::invalid_params(format!(
+                    "Invalid parameters: date_flexibility_days must be between 0 and {}, got {}",
+                    MAX_DATE_FLEXIBILITY_DAYS, days
+                )));
+            }
+            if days > 0 && self.include_nearby_airports.unwrap_or(false) {
+                return Err(RpcError::invalid_params(
+                    "Invalid parameters: date_flexibility_days cannot be combined with include_nearby_airports",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The outbound slice, plus a return slice for round trips.
+    fn slices(&self) -> Vec<SliceRequest> {
+        self.variant(&self.origin, &self.destination, &self.departure_date, self.return_date.as_deref())
+            .slices
+    }
+
+    fn variant(
+        &self,
+        origin: &str,
+        destination: &str,
+        departure_date: &str,
+        return_date: Option<&str>,
+    ) -> SearchVariant {
+        let mut slices = vec![SliceRequest {
+            origin: origin.to_string(),
+            destination: destination.to_string(),
+            departure_date: departure_date.to_string(),
+        }];
+
+        // Add return slice if return_date is provided
+        if let Some(return_date) = return_date {
+            slices.push(SliceRequest {
+                origin: destination.to_string(),
+                destination: origin.to_string(),
+                departure_date: return_date.to_string(),
+            });
+        }
+        SearchVariant {
+            origin: origin.to_string(),
+            destination: destination.to_string(),
+            departure_date: departure_date.to_string(),
+            return_date: return_date.map(str::to_string),
+            slices,
+        }
+    }
+
+    /// The searches to run instead of a single one, when `include_nearby_airports`
+    /// or `date_flexibility_days` asks for more than one.
+    fn fanout(&self) -> Option<Fanout> {
+        let fanout = if self.include_nearby_airports.unwrap_or(false) {
+            Fanout {
+                kind: FanoutKind::AirportPairs,
+                variants: self.nearby_airport_variants(),
+            }
+        } else {
+            Fanout {
+                kind: FanoutKind::Dates,
+                variants: self.flexible_date_variants(),
+            }
+        };
+        (fanout.variants.len() > 1).then_some(fanout)
+    }
+
+    /// Every pairing of nearby origin and destination airports; a code missing from
+    /// the airport list pairs as itself.
+    fn nearby_airport_variants(&self) -> Vec<SearchVariant> {
+        let nearby = |code: &str| {
+            let airports: Vec<String> = airports::nearby(code).into_iter().map(str::to_string).collect();
+            if airports.is_empty() {
+                vec![code.to_string()]
+            } else {
+                airports
+            }
+        };
+        let destinations = nearby(&self.destination);
+        nearby(&self.origin)
+            .iter()
+            .flat_map(|origin| destinations.iter().map(move |destination| (origin, destination)))
+            .filter(|(origin, destination)| origin != destination)
+            .map(|(origin, destination)| {
+                self.variant(origin, destination, &self.departure_date, self.return_date.as_deref())
+            })
+            .collect()
+    }
+
+    /// The travel dates moved by up to `date_flexibility_days` either way, keeping
+    /// the trip's length and skipping departures in the past.
+    fn flexible_date_variants(&self) -> Vec<SearchVariant> {
+        let days = self.date_flexibility_days.unwrap_or(0);
+        // Both dates were normalized in `validate`
+        let Ok(departure_date) = self.departure_date.parse::<chrono::NaiveDate>() else {
+            return Vec::new();
+        };
+        let return_date = self
+            .return_date
+            .as_deref()
+            .and_then(|date| date.parse::<chrono::NaiveDate>().ok());
+        (-days..=days)
+            .filter_map(|offset| {
+                let shift = chrono::Duration::days(offset);
+                let departure = (departure_date + shift).to_string();
+                validation::upcoming_date("departure_date", &departure).ok()?;
+                let return_date = return_date.map(|date| (date + shift).to_string());
+                Some(self.variant(&self.origin, &self.destination, &departure, return_date.as_deref()))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MultiCitySearchRequest {
+    slices: Vec<SliceRequest>,
+    #[serde(flatten)]
+    options: SearchOptions,
+}
+
+impl MultiCitySearchRequest {
+    /// Check every leg, and that legs are listed in travel order.
+    fn validate(&mut self) -> Result<(), RpcError> {
+        if self.slices.is_empty() || self.slices.len() > MAX_SLICES {
+            return Err(RpcError::invalid_params(format!(
+                "Invalid parameters: slices must contain between 1 and {} legs",
+                MAX_SLICES
+            )));
+        }
+
+        let mut previous: Option<chrono::NaiveDate> = None;
+        for (index, slice) in self.slices.iter_mut().enumerate() {
+            let departure_date = slice.validate(&format!("slices[{}].", index))?;
+            if let Some(previous) = previous {
+                validation::on_or_after(
+                    &format!("slices[{}].departure_date", index),
+                    departure_date,
+                    &format!("slices[{}].departure_date", index - 1),
+                    previous,
+                )?;
+            }
+            previous = Some(departure_date);
+        }
+        Ok(())
+    }
 }
 
+/// Offer fields `structuredContent` keeps at `summary` verbosity.
+const SUMMARY_OFFER_FIELDS: &[&str] = &["id", "price", "currency", "airline", "converted_amount", "slices"];
+
+/// Slice fields kept at `summary` verbosity; segments and layovers are left out.
+const SUMMARY_SLICE_FIELDS: &[&str] = &["origin", "destination", "departure_time", "arrival_time", "duration", "stops"];
+
 #[derive(Debug, Serialize, Deserialize)]
 struct FlightOffer {
     id: String,
     price: String,
     currency: String,
-    departure_time: String,
-    arrival_time: String,
-    duration: String,
+    /// Airline selling the ticket.
     airline: String,
-    flight_number: String,
-    aircraft: Option<String>,
-    stops: i32,
+    airline_code: Option<String>,
+    /// `price` in the requested `display_currency`.
+    converted_amount: Option<ConvertedAmount>,
+    /// When Duffel stops accepting the offer for booking.
+    expires_at: Option<String>,
+    /// One entry per requested slice, in travel order.
+    slices: Vec<FlightSlice>,
+    /// Airports searched for this offer, set when nearby airports were included.
+    #[serde(default)]
+    origin_airport: Option<String>,
+    #[serde(default)]
+    destination_airport: Option<String>,
+}
+
+impl FlightOffer {
+    fn outbound(&self) -> &FlightSlice {
+        // Offers without slices are skipped while parsing
+        &self.slices[0]
+    }
+
+    /// Most stops on any slice.
+    fn max_stops(&self) -> usize {
+        self.slices.iter().map(|slice| slice.stops).max().unwrap_or(0)
+    }
+
+    /// Time in the air and on the ground across every slice.
+    fn total_duration_minutes(&self) -> Option<i64> {
+        self.slices.iter().map(FlightSlice::duration_minutes).sum()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -37,434 +328,1004 @@ struct FlightSearchResponse {
     offers: Vec<FlightOffer>,
     total_results: i32,
     search_id: String,
+    next_page_token: Option<String>,
+    /// Offers dropped because Duffel returned them in an unexpected shape.
+    skipped_results: usize,
+    /// Parts of the search that failed; the offers returned are unaffected.
+    #[serde(default)]
+    warnings: Vec<String>,
+    /// Cheapest offer for each pair of dates, when `date_flexibility_days` was used.
+    #[serde(default)]
+    price_calendar: Option<Vec<DatePrice>>,
+    /// Offers Duffel found for the whole search, before filters and paging; new searches only.
+    #[serde(default)]
+    available_results: Option<usize>,
+}
+
+/// Position in the offer list of an existing offer request, encoded as a page token.
+#[derive(Debug)]
+struct OffersCursor {
+    offer_request_id: String,
+    after: String,
+}
+
+impl OffersCursor {
+    fn encode(&self) -> String {
+        format!("{}:{}", self.offer_request_id, self.after)
+    }
+
+    fn decode(token: &str) -> Result<Self, RpcError> {
+        let (offer_request_id, after) = token
+            .split_once(':')
+            .filter(|(id, after)| !id.is_empty() && !after.is_empty())
+            .ok_or_else(|| RpcError::invalid_params("Invalid parameters: unrecognized page_token"))?;
+
+        Ok(Self {
+            offer_request_id: offer_request_id.to_string(),
+            after: after.to_string(),
+        })
+    }
 }
 
 #[derive(Debug, Clone)]
 struct DuffelFlightServer {
-    api_token: String,
-    client: reqwest::Client,
+    duffel: DuffelClient,
+    fx: CurrencyConverter,
+    /// Where returned offers are saved for `select_offer`; `None` without a database.
+    results: Option<SearchResults>,
+    /// Email and webhook channels for booking confirmations.
+    notifier: Notifier,
 }
 
 impl DuffelFlightServer {
-    fn new() -> Result<Self> {
-        let api_token = env::var("DUFFEL_API_TOKEN")
-            .map_err(|_| anyhow::anyhow!("DUFFEL_API_TOKEN environment variable must be set"))?;
+    fn new(results: Option<SearchResults>) -> Result<Self> {
+        Ok(Self {
+            duffel: DuffelClient::from_config()?,
+            fx: CurrencyConverter::from_config(),
+            results,
+            notifier: Notifier::from_config()?,
+        })
+    }
 
-        let client = reqwest::Client::new();
+    async fn search_flights(
+        &self,
+        slices: Vec<SliceRequest>,
+        options: SearchOptions,
+        limit: usize,
+        cursor: Option<OffersCursor>,
+        display: Option<DisplayCurrency>,
+    ) -> Result<FlightSearchResponse> {
+        // Continue an earlier offer request when paging, otherwise start a new one
+        let (offer_request_id, after, created) = match cursor {
+            Some(cursor) => (cursor.offer_request_id, Some(cursor.after), None),
+            None => {
+                progress::report("Creating offer request");
+                let (offer_request_id, created) = self.create_offer_request(&slices, &options).await?;
+                progress::report(format!("Offer request {} created", offer_request_id));
+                (offer_request_id, None, Some(created))
+            }
+        };
+        let filters = options.filters;
+        let mut warnings = Vec::new();
 
-        Ok(Self { api_token, client })
-    }
+        // Give slower airlines a chance to answer before reading any offers
+        let offer_request = match created {
+            Some(created) => {
+                let (complete, offer_request) = self.wait_for_offers(&offer_request_id, created).await?;
+                if !complete {
+                    warnings.push(format!(
+                        "Airlines were still responding after {}s; searching again may find more offers",
+                        config::get().duffel.offer_poll_timeout_secs
+                    ));
+                }
+                Some(offer_request)
+            }
+            None => None,
+        };
 
-    async fn search_flights(&self, request: FlightSearchRequest) -> Result<FlightSearchResponse> {
-        // Prepare the request payload for Duffel API
-        let mut passengers = Vec::new();
-        let passenger_count = request.passengers.unwrap_or(1);
-        
-        for _ in 0..passenger_count {
-            passengers.push(json!({
-                "type": "adult"
-            }));
+        // Fetch one page of offers
+        let mut query = vec![
+            ("offer_request_id", offer_request_id.clone()),
+            ("limit", limit.to_string()),
+        ];
+        if let Some(after) = after {
+            query.push(("after", after));
+        }
+        // Let Duffel sort and cap connections too, so every page is consistent
+        if let Some(sort) = filters.duffel_sort() {
+            query.push(("sort", sort.to_string()));
+        }
+        if let Some(max_stops) = filters.max_stops {
+            query.push(("max_connections", max_stops.to_string()));
+        }
+        progress::report("Fetching offers");
+        let page = self
+            .duffel
+            .get_list::<Offer, _>("/air/offers", &query, &["data"], "offer")
+            .await?;
+        if page.truncated {
+            warnings.push(format!(
+                "Duffel's answer was larger than {} bytes, so only the first {} offers were read; search with a smaller limit to see the rest",
+                config::get().duffel.max_response_bytes,
+                page.received
+            ));
         }
+        // A first page that is the only one holds every offer; otherwise count the
+        // offers the offer request came back with, so nothing more is downloaded
+        let available_results = offer_request.and_then(|offer_request| {
+            if !page.truncated && page.meta["after"].is_null() {
+                Some(page.received)
+            } else {
+                count_offers(&offer_request, filters.max_stops)
+            }
+        });
 
-        let mut slices = vec![json!({
-            "origin": request.origin,
-            "destination": request.destination,
-            "departure_date": request.departure_date
-        })];
+        // Parse offers into our format, counting any Duffel sent in an unexpected shape
+        let (offers, mut skipped) = (page.items, page.skipped);
+        let mut flight_offers = Vec::with_capacity(offers.len());
+        for offer in &offers {
+            match self.parse_flight_offer(offer) {
+                Some(flight_offer) => flight_offers.push(flight_offer),
+                None => {
+                    let warning = format!("Skipped offer {}: no slices or segments", offer.id);
+                    warn!("{}", warning);
+                    skipped.push(warning);
+                }
+            }
+        }
+        let skipped_results = skipped.len();
+        warnings.extend(skipped);
 
-        // Add return slice if return_date is provided
-        if let Some(return_date) = &request.return_date {
-            slices.push(json!({
-                "origin": request.destination,
-                "destination": request.origin,
-                "departure_date": return_date
-            }));
+        // Convert before filtering so max_price and price sorting compare like with like
+        if let Some(display) = &display {
+            let mut unconverted = 0;
+            for offer in &mut flight_offers {
+                offer.converted_amount = display.convert(&offer.price, &offer.currency);
+                if offer.converted_amount.is_none() {
+                    unconverted += 1;
+                }
+            }
+            if unconverted > 0 {
+                warnings.push(format!(
+                    "{} offers could not be converted to {} and show their original price only",
+                    unconverted, display.currency
+                ));
+            }
+        }
+
+        let fetched = flight_offers.len();
+        let flight_offers = filters.apply(flight_offers);
+        progress::report(format!("Fetched {} offers, {} match the filters", fetched, flight_offers.len()));
+
+        let next_page_token = page.meta["after"].as_str().map(|after| {
+            OffersCursor {
+                offer_request_id: offer_request_id.clone(),
+                after: after.to_string(),
+            }
+            .encode()
+        });
+
+        Ok(FlightSearchResponse {
+            offers: flight_offers,
+            total_results: page.received as i32,
+            search_id: offer_request_id,
+            next_page_token,
+            skipped_results,
+            warnings,
+            price_calendar: None,
+            available_results,
+        })
+    }
+
+    /// Poll an offer request, starting from the `created` response, until every airline
+    /// has answered, for at most `offer_poll_timeout_secs`; returns whether it finished
+    /// in time and the latest offer request.
+    async fn wait_for_offers(&self, offer_request_id: &str, created: Value) -> Result<(bool, Value)> {
+        let timeout = Duration::from_secs(config::get().duffel.offer_poll_timeout_secs);
+        if timeout.is_zero() {
+            return Ok((true, created));
+        }
+        let deadline = tokio::time::Instant::now() + timeout;
+        let mut delay = OFFER_POLL_INITIAL_DELAY;
+        let mut response = created;
+        loop {
+            // Requests without a status were answered in full when they were created
+            let status = response["data"]["status"].as_str();
+            if status.is_none_or(|status| status == "complete") {
+                return Ok((true, response));
+            }
+
+            let now = tokio::time::Instant::now();
+            if now >= deadline {
+                warn!("Offer request {} still {} after {:?}", offer_request_id, status.unwrap_or_default(), timeout);
+                return Ok((false, response));
+            }
+            progress::report("Waiting for airlines to respond");
+            tokio::time::sleep(delay.min(deadline - now)).await;
+            delay = (delay * 2).min(OFFER_POLL_MAX_DELAY);
+            response = self
+                .duffel
+                .get(&format!("/air/offer_requests/{}", offer_request_id))
+                .await?;
         }
+    }
 
-        let payload = json!({
+    /// Create a Duffel offer request and return its ID and Duffel's response.
+    async fn create_offer_request(&self, slices: &[SliceRequest], options: &SearchOptions) -> Result<(String, Value)> {
+        // Prepare the request payload for Duffel API
+        let mut passengers = options.passenger_mix.to_duffel()?;
+        options.programmes.add_loyalty_accounts(&mut passengers)?;
+
+        let mut payload = json!({
             "data": {
                 "slices": slices,
                 "passengers": passengers,
-                "cabin_class": request.cabin_class.unwrap_or_else(|| "economy".to_string())
+                "cabin_class": options.cabin_class.as_deref().unwrap_or("economy")
             }
         });
+        if let Some(private_fares) = options.programmes.private_fares()? {
+            payload["data"]["private_fares"] = private_fares;
+        }
 
-        info!("Searching flights with payload: {}", serde_json::to_string_pretty(&payload)?);
+        let mut logged = payload.clone();
+        loyalty::redact(&mut logged);
+        info!("Searching flights with payload: {}", serde_json::to_string_pretty(&logged)?);
 
         // Make the API request
-        let response = self
-            .client
-            .post("https://api.duffel.com/air/offer_requests")
-            .header("Authorization", format!("Bearer {}", self.api_token))
-            .header("Content-Type", "application/json")
-            .header("Accept", "application/json")
-            .header("Duffel-Version", "v2")
-            .json(&payload)
-            .send()
-            .await?;
+        let response_data = self.duffel.post("/air/offer_requests", &payload).await?;
 
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!("Duffel API error: {}", error_text));
-        }
-
-        let response_data: Value = response.json().await?;
-        
         // Extract offer request ID
         let offer_request_id = response_data["data"]["id"]
             .as_str()
-            .ok_or_else(|| anyhow::anyhow!("No offer request ID in response"))?;
-
-        // Fetch the actual offers
-        let offers_response = self
-            .client
-            .get(&format!("https://api.duffel.com/air/offers?offer_request_id={}", offer_request_id))
-            .header("Authorization", format!("Bearer {}", self.api_token))
-            .header("Accept", "application/json")
-            .header("Duffel-Version", "v2")
-            .send()
-            .await?;
-
-        if !offers_response.status().is_success() {
-            let error_text = offers_response.text().await?;
-            return Err(anyhow::anyhow!("Duffel offers API error: {}", error_text));
-        }
-
-        let offers_data: Value = offers_response.json().await?;
-        let offers_array = offers_data["data"]
-            .as_array()
-            .ok_or_else(|| anyhow::anyhow!("No offers data in response"))?;
-
-        // Parse offers into our format
-        let mut flight_offers = Vec::new();
-        
-        for offer in offers_array.iter().take(10) { // Limit to 10 results
-            if let Some(flight_offer) = self.parse_flight_offer(offer) {
-                flight_offers.push(flight_offer);
-            }
-        }
+            .ok_or_else(|| anyhow::anyhow!("No offer request ID in response"))?
+            .to_string();
 
-        Ok(FlightSearchResponse {
-            offers: flight_offers,
-            total_results: offers_array.len() as i32,
-            search_id: offer_request_id.to_string(),
-        })
+        Ok((offer_request_id, response_data))
     }
 
-    fn parse_flight_offer(&self, offer: &Value) -> Option<FlightOffer> {
-        let id = offer["id"].as_str()?.to_string();
-        let total_amount = offer["total_amount"].as_str()?.to_string();
-        let currency = offer["total_currency"].as_str()?.to_string();
-        
-        // Get the first slice for departure info
-        let slices = offer["slices"].as_array()?;
-        let first_slice = &slices[0];
-        let segments = first_slice["segments"].as_array()?;
-        let first_segment = &segments[0];
-        
-        let departure_time = first_segment["departing_at"].as_str()?.to_string();
-        let arrival_time = first_segment["arriving_at"].as_str()?.to_string();
-        let duration = first_slice["duration"].as_str()?.to_string();
-        
-        // Get airline info
-        let marketing_carrier = &first_segment["marketing_carrier"];
-        let airline = marketing_carrier["name"].as_str()?.to_string();
-        let flight_number = first_segment["marketing_carrier_flight_number"].as_str()?.to_string();
-        
-        let aircraft = first_segment["aircraft"]["name"].as_str().map(|s| s.to_string());
-        let stops = segments.len() as i32 - 1; // Number of segments minus 1 = number of stops
+    fn parse_flight_offer(&self, offer: &Offer) -> Option<FlightOffer> {
+        let slices = offer
+            .slices
+            .iter()
+            .map(FlightSlice::from_duffel)
+            .collect::<Option<Vec<_>>>()
+            .filter(|slices| !slices.is_empty())?;
+
+        // Prefer the ticketing airline, falling back to the first marketing carrier
+        let first_carrier = &offer.slices[0].segments[0].marketing_carrier;
+        let carrier = offer.owner.as_ref().unwrap_or(first_carrier);
 
         Some(FlightOffer {
-            id,
-            price: total_amount,
-            currency,
-            departure_time,
-            arrival_time,
-            duration,
-            airline,
-            flight_number,
-            aircraft,
-            stops,
+            id: offer.id.clone(),
+            price: offer.total_amount.clone(),
+            currency: offer.total_currency.clone(),
+            airline: carrier.name.clone(),
+            airline_code: carrier.iata_code.clone(),
+            converted_amount: None,
+            expires_at: offer.expires_at.clone(),
+            slices,
+            origin_airport: None,
+            destination_airport: None,
         })
     }
 
-    fn format_flight_results(&self, response: &FlightSearchResponse) -> String {
+    /// Save a page of offers under its search ID; returns the option number of the first.
+    async fn save_results(&self, response: &FlightSearchResponse) -> usize {
+        let Some(results) = &self.results else {
+            return 1;
+        };
+        let offers = response
+            .offers
+            .iter()
+            .map(|offer| NewOffer {
+                offer_id: offer.id.clone(),
+                expires_at: offer.expires_at.clone(),
+                offer: serde_json::to_value(offer).unwrap_or(Value::Null),
+            })
+            .collect();
+        results
+            .append(results::FLIGHT_SEARCH, &response.search_id, offers)
+            .await
+            .unwrap_or_else(|e| {
+                warn!("Could not save results of search {}: {}", response.search_id, e);
+                1
+            })
+    }
+
+    /// Numbered offer list; `first_option` is the number of the first offer on this page.
+    fn format_flight_results(&self, response: &FlightSearchResponse, first_option: usize, verbosity: Verbosity) -> String {
         if response.offers.is_empty() {
-            return "No flights found for the specified criteria.".to_string();
+            let mut result = "No flights found for the specified criteria.".to_string();
+            errors::append_warnings(&mut result, &response.warnings);
+            return result;
+        }
+
+        let mut result = match response.available_results {
+            Some(available) => format!(
+                "Found {} flight offers, showing {}:\n\n",
+                available,
+                response.offers.len()
+            ),
+            None => format!("Found {} flight offers:\n\n", response.total_results),
+        };
+        if let Some(calendar) = &response.price_calendar {
+            fanout::describe_calendar(&mut result, calendar);
         }
 
-        let mut result = format!("Found {} flight offers:\n\n", response.total_results);
-        
         for (i, offer) in response.offers.iter().enumerate() {
             result.push_str(&format!(
-                "{}. {} {} - {} {}\n",
-                i + 1,
+                "{}. {} - {} {}",
+                first_option + i,
                 offer.airline,
-                offer.flight_number,
                 offer.price,
                 offer.currency
             ));
-            
-            result.push_str(&format!(
-                "   Departure: {}\n",
-                offer.departure_time
-            ));
-            
-            result.push_str(&format!(
-                "   Arrival: {}\n",
-                offer.arrival_time
-            ));
-            
-            result.push_str(&format!(
-                "   Duration: {}\n",
-                offer.duration
-            ));
-            
-            if offer.stops > 0 {
-                result.push_str(&format!(
-                    "   Stops: {}\n",
-                    offer.stops
-                ));
-            } else {
-                result.push_str("   Direct flight\n");
+            if let Some(converted) = &offer.converted_amount {
+                result.push_str(&format!(" (~{} {})", converted.amount, converted.currency));
             }
-            
-            if let Some(aircraft) = &offer.aircraft {
-                result.push_str(&format!(
-                    "   Aircraft: {}\n",
-                    aircraft
-                ));
+            result.push('\n');
+            if verbosity == Verbosity::Full {
+                if let (Some(origin), Some(destination)) = (&offer.origin_airport, &offer.destination_airport) {
+                    result.push_str(&format!("   Airports: {} to {}\n", origin, destination));
+                }
             }
-            
-            result.push_str("\n");
+
+            for (n, slice) in offer.slices.iter().enumerate() {
+                let label = itinerary::slice_label(&offer.slices, n);
+                match verbosity {
+                    Verbosity::Summary => itinerary::summarize_slice(&mut result, &label, slice),
+                    Verbosity::Full => itinerary::describe_slice(&mut result, &label, slice),
+                }
+            }
+
+            result.push_str(&format!("   Offer ID: {}\n\n", offer.id));
         }
 
         result.push_str(&format!("Search ID: {}", response.search_id));
+        errors::append_warnings(&mut result, &response.warnings);
+        if let Some(token) = &response.next_page_token {
+            result.push_str(&format!("\nMore offers available (page_token: {})", token));
+        }
+        if self.results.is_some() {
+            result.push_str("\nUse select_offer with this Search ID and an option number to pick an offer");
+        }
         result
     }
 }
 
-async fn handle_mcp_request(
-    server: DuffelFlightServer,
-    request: Value,
-) -> Result<impl warp::Reply, Infallible> {
-    let response = handle_request(&server, request).await;
-    Ok(warp::reply::json(&response))
+/// Offers in an offer request's response with at most `max_connections` on every slice,
+/// or `None` when it came back without them.
+fn count_offers(offer_request: &Value, max_connections: Option<i32>) -> Option<usize> {
+    let offers = offer_request["data"]["offers"].as_array()?;
+    let within = |offer: &&Value| match max_connections {
+        Some(max_connections) => offer["slices"].as_array().is_some_and(|slices| {
+            slices.iter().all(|slice| {
+                let segments = slice["segments"].as_array().map_or(0, Vec::len);
+                segments.saturating_sub(1) <= max_connections.max(0) as usize
+            })
+        }),
+        None => true,
+    };
+    Some(offers.iter().filter(within).count())
 }
 
-async fn handle_request(server: &DuffelFlightServer, request: Value) -> Value {
-    let method = request["method"].as_str().unwrap_or("");
-    let id = request["id"].clone();
-
-    match method {
-        "initialize" => {
-            json!({
-                "jsonrpc": "2.0",
-                "result": {
-                    "protocolVersion": "2024-11-05",
-                    "capabilities": {
-                        "tools": {}
-                    },
-                    "serverInfo": {
-                        "name": "duffel-flights-mcp",
-                        "version": "0.1.0"
-                    }
+/// The tool result for a page of offers, cut down to [`SUMMARY_OFFER_FIELDS`] at `summary` verbosity.
+fn search_results_content(
+    flights: &DuffelFlightServer,
+    response: &FlightSearchResponse,
+    first_option: usize,
+    verbosity: Verbosity,
+) -> Result<Value, RpcError> {
+    let text = flights.format_flight_results(response, first_option, verbosity);
+    if verbosity == Verbosity::Full {
+        return mcp::structured_content(text, response);
+    }
+    let mut summary = serde_json::to_value(response)
+        .map_err(|e| RpcError::server_error(format!("Failed to serialize tool result: {}", e)))?;
+    mcp::retain_fields(&mut summary["offers"], SUMMARY_OFFER_FIELDS);
+    for offer in summary["offers"].as_array_mut().into_iter().flatten() {
+        mcp::retain_fields(&mut offer["slices"], SUMMARY_SLICE_FIELDS);
+    }
+    mcp::structured_content(text, &summary)
+}
+
+fn search_flights_tool() -> Tool {
+    Tool::new(
+        "search_flights",
+        "Search for one-way or round-trip flights using the Duffel API",
+        json!({
+            "type": "object",
+            "properties": with_search_options(json!({
+                "origin": {
+                    "type": "string",
+                    "description": "Origin IATA airport or city code, or a city name (e.g., 'JFK', 'NYC', 'New York')"
                 },
-                "id": id
-            })
-        }
-        "tools/list" => {
-            json!({
-                "jsonrpc": "2.0",
-                "result": {
-                    "tools": [
-                        {
-                            "name": "search_flights",
-                            "description": "Search for flights using the Duffel API",
-                            "inputSchema": {
-                                "type": "object",
-                                "properties": {
-                                    "origin": {
-                                        "type": "string",
-                                        "description": "Origin airport code (e.g., 'JFK', 'LAX')"
-                                    },
-                                    "destination": {
-                                        "type": "string", 
-                                        "description": "Destination airport code (e.g., 'LHR', 'CDG')"
-                                    },
-                                    "departure_date": {
-                                        "type": "string",
-                                        "description": "Departure date in YYYY-MM-DD format"
-                                    },
-                                    "return_date": {
-                                        "type": "string",
-                                        "description": "Return date in YYYY-MM-DD format (optional, for round-trip)"
-                                    },
-                                    "passengers": {
-                                        "type": "integer",
-                                        "description": "Number of passengers (default: 1)"
-                                    },
-                                    "cabin_class": {
-                                        "type": "string",
-                                        "description": "Cabin class: economy, premium_economy, business, first (default: economy)"
-                                    }
-                                },
-                                "required": ["origin", "destination", "departure_date"]
+                "destination": {
+                    "type": "string",
+                    "description": "Destination IATA airport or city code, or a city name (e.g., 'LHR', 'LON', 'London')"
+                },
+                "departure_date": {
+                    "type": "string",
+                    "description": "Departure date in YYYY-MM-DD format"
+                },
+                "return_date": {
+                    "type": "string",
+                    "description": "Return date in YYYY-MM-DD format (optional, for round-trip)"
+                },
+                "include_nearby_airports": {
+                    "type": "boolean",
+                    "description": format!(
+                        "Also search nearby airports (e.g. 'LON' or 'London' searches LHR, LGW, STN and LCY; up to {} per end), merging the results into one page without a page_token (default: false)",
+                        airports::MAX_NEARBY_AIRPORTS
+                    )
+                },
+                "date_flexibility_days": {
+                    "type": "integer",
+                    "description": format!(
+                        "Also search up to this many days (0-{}) before and after the travel dates, keeping the trip's length; returns a price_calendar with the cheapest offer per date and the best offers overall in one page without a page_token (default: 0)",
+                        MAX_DATE_FLEXIBILITY_DAYS
+                    )
+                }
+            })),
+            "required": ["origin", "destination", "departure_date"]
+        }),
+    )
+    .with_output_schema(flight_search_output_schema())
+    .cacheable()
+}
+
+fn search_multi_city_tool() -> Tool {
+    Tool::new(
+        "search_multi_city",
+        "Search multi-city flight itineraries (e.g. JFK→LHR→CDG→JFK) in a single Duffel offer request",
+        json!({
+            "type": "object",
+            "properties": with_search_options(json!({
+                "slices": {
+                    "type": "array",
+                    "description": format!("Itinerary legs in travel order (1-{})", MAX_SLICES),
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "origin": {
+                                "type": "string",
+                                "description": "Origin airport code (e.g., 'JFK')"
+                            },
+                            "destination": {
+                                "type": "string",
+                                "description": "Destination airport code (e.g., 'LHR')"
+                            },
+                            "departure_date": {
+                                "type": "string",
+                                "description": "Departure date in YYYY-MM-DD format"
                             }
-                        }
-                    ]
+                        },
+                        "required": ["origin", "destination", "departure_date"]
+                    }
+                }
+            })),
+            "required": ["slices"]
+        }),
+    )
+    .with_output_schema(flight_search_output_schema())
+    .cacheable()
+}
+
+/// Input schema properties for [`SearchOptions`], shared by every search tool.
+fn search_option_properties() -> Value {
+    json!({
+        "adults": {
+            "type": "integer",
+            "description": "Number of adult passengers (default: 1)"
+        },
+        "children_ages": {
+            "type": "array",
+            "items": { "type": "integer" },
+            "description": format!(
+                "Age of each child passenger ({}-{}), one entry per child",
+                passengers::MIN_CHILD_AGE,
+                passengers::MAX_CHILD_AGE
+            )
+        },
+        "infants": {
+            "type": "integer",
+            "description": format!(
+                "Number of lap infants under {} (at most one per adult, default: 0)",
+                passengers::MIN_CHILD_AGE
+            )
+        },
+        "passengers": {
+            "type": "integer",
+            "description": "Deprecated alias for adults"
+        },
+        "cabin_class": {
+            "type": "string",
+            "description": "Cabin class: economy, premium_economy, business, first (default: economy)"
+        },
+        "max_price": {
+            "type": "number",
+            "description": "Only return offers at or below this total price (in display_currency when set, otherwise the offer currency)"
+        },
+        "max_stops": {
+            "type": "integer",
+            "description": "Maximum number of stops (0 for direct flights only)"
+        },
+        "preferred_airlines": {
+            "type": "array",
+            "items": { "type": "string" },
+            "description": "Only return offers marketed by these airlines (IATA codes like 'BA' or names)"
+        },
+        "departure_time_window": {
+            "type": "object",
+            "properties": {
+                "earliest": { "type": "string", "description": "Earliest local departure time, HH:MM" },
+                "latest": { "type": "string", "description": "Latest local departure time, HH:MM" }
+            },
+            "description": "Only return offers departing within this local time range"
+        },
+        "display_currency": {
+            "type": "string",
+            "description": "ISO 4217 code (e.g. 'USD', 'GBP') to also show every price in, using ECB daily rates"
+        },
+        "sort_by": {
+            "type": "string",
+            "enum": ["price", "duration", "departure"],
+            "description": "Order results by total price, journey duration, or departure time"
+        },
+        "limit": {
+            "type": "integer",
+            "description": format!(
+                "Maximum offers to return (1-{}, default: {})",
+                pagination::MAX_LIMIT,
+                pagination::DEFAULT_LIMIT
+            )
+        },
+        "page_token": {
+            "type": "string",
+            "description": "next_page_token from a previous search to fetch more offers from the same search"
+        },
+        "verbosity": verbosity_schema(),
+        "loyalty_programme_accounts": {
+            "type": "array",
+            "items": {
+                "type": "object",
+                "properties": {
+                    "airline_iata_code": { "type": "string", "description": "Airline running the programme, e.g. 'BA'" },
+                    "account_number": { "type": "string" },
+                    "given_name": { "type": "string", "description": "Account holder's given name" },
+                    "family_name": { "type": "string", "description": "Account holder's family name" },
+                    "adult": { "type": "integer", "description": "Which adult holds the account, starting at 1 (default: 1)" }
                 },
-                "id": id
-            })
+                "required": ["airline_iata_code", "account_number", "given_name", "family_name"]
+            },
+            "description": "Frequent-flyer accounts, so airlines can return member fares"
+        },
+        "private_fares": {
+            "type": "array",
+            "items": {
+                "type": "object",
+                "properties": {
+                    "airline_iata_code": { "type": "string", "description": "Airline the agreement is with, e.g. 'BA'" },
+                    "corporate_code": { "type": "string" },
+                    "tracking_reference": { "type": "string" }
+                },
+                "required": ["airline_iata_code"]
+            },
+            "description": "Negotiated fare agreements; each needs a corporate_code or a tracking_reference"
         }
-        "tools/call" => {
-            let params = &request["params"];
-            let tool_name = params["name"].as_str().unwrap_or("");
-            let arguments = &params["arguments"];
-
-            match tool_name {
-                "search_flights" => {
-                    match serde_json::from_value::<FlightSearchRequest>(arguments.clone()) {
-                        Ok(search_request) => {
-                            match server.search_flights(search_request).await {
-                                Ok(search_response) => {
-                                    let formatted_results = server.format_flight_results(&search_response);
-                                    json!({
-                                        "jsonrpc": "2.0",
-                                        "result": {
-                                            "content": [
-                                                {
-                                                    "type": "text",
-                                                    "text": formatted_results
-                                                }
-                                            ]
-                                        },
-                                        "id": id
-                                    })
-                                }
-                                Err(e) => {
-                                    error!("Flight search error: {}", e);
-                                    json!({
-                                        "jsonrpc": "2.0",
-                                        "error": {
-                                            "code": -32000,
-                                            "message": format!("Flight search failed: {}", e)
-                                        },
-                                        "id": id
-                                    })
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            error!("Invalid arguments for search_flights: {}", e);
-                            json!({
-                                "jsonrpc": "2.0",
-                                "error": {
-                                    "code": -32602,
-                                    "message": format!("Invalid parameters: {}", e)
-                                },
-                                "id": id
-                            })
+    })
+}
+
+fn verbosity_schema() -> Value {
+    json!({
+        "type": "string",
+        "enum": ["summary", "full"],
+        "description": "'summary' returns only price, airline and times per offer, to save context; 'full' adds every segment and layover (default: the server's RESPONSE_VERBOSITY, normally 'full')"
+    })
+}
+
+/// Add the shared [`SearchOptions`] properties to a tool's own properties.
+fn with_search_options(mut properties: Value) -> Value {
+    if let (Some(properties), Value::Object(options)) = (properties.as_object_mut(), search_option_properties()) {
+        properties.extend(options);
+    }
+    properties
+}
+
+fn flight_slice_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "origin": { "type": "string" },
+            "destination": { "type": "string" },
+            "departure_time": { "type": "string", "description": "Local departure time of the first segment (ISO 8601)" },
+            "arrival_time": { "type": "string", "description": "Local arrival time of the last segment (ISO 8601)" },
+            "duration": { "type": ["string", "null"], "description": "Slice duration (ISO 8601 duration)" },
+            "stops": { "type": "integer" },
+            "segments": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "origin": { "type": "string" },
+                        "destination": { "type": "string" },
+                        "departure_time": { "type": "string" },
+                        "arrival_time": { "type": "string" },
+                        "departure_time_zone": {
+                            "type": ["string", "null"],
+                            "description": "IANA time zone departure_time is local to, e.g. Europe/London"
+                        },
+                        "arrival_time_zone": {
+                            "type": ["string", "null"],
+                            "description": "IANA time zone arrival_time is local to"
+                        },
+                        "duration": { "type": ["string", "null"] },
+                        "airline": { "type": "string", "description": "Marketing carrier" },
+                        "airline_code": { "type": ["string", "null"] },
+                        "flight_number": { "type": "string" },
+                        "operating_airline": { "type": ["string", "null"], "description": "Set for codeshares" },
+                        "aircraft": { "type": ["string", "null"] }
+                    },
+                    "required": [
+                        "origin", "destination", "departure_time", "arrival_time",
+                        "airline", "flight_number"
+                    ]
+                }
+            },
+            "layovers": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "arrival_airport": { "type": "string" },
+                        "departure_airport": {
+                            "type": "string",
+                            "description": "Differs from arrival_airport when the connection changes airports"
+                        },
+                        "duration_minutes": { "type": ["integer", "null"] }
+                    },
+                    "required": ["arrival_airport", "departure_airport"]
+                }
+            }
+        },
+        "required": ["origin", "destination", "departure_time", "arrival_time", "stops", "segments", "layovers"]
+    })
+}
+
+fn converted_amount_schema() -> Value {
+    json!({
+        "type": ["object", "null"],
+        "description": "Price in display_currency; null when display_currency was not requested",
+        "properties": {
+            "amount": { "type": "string", "description": "Converted total, rounded to 2 decimals" },
+            "currency": { "type": "string" },
+            "exchange_rate": { "type": "number", "description": "Units of currency per unit of the original currency" },
+            "rates_date": { "type": "string", "description": "Publication date of the ECB rate used" }
+        },
+        "required": ["amount", "currency", "exchange_rate", "rates_date"]
+    })
+}
+
+fn flight_search_output_schema() -> Value {
+    // Summary results leave out segments and layovers
+    let mut slice = flight_slice_schema();
+    slice["required"] = json!(["origin", "destination", "departure_time", "arrival_time", "stops"]);
+    json!({
+        "type": "object",
+        "properties": {
+            "offers": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "id": { "type": "string", "description": "Duffel offer ID" },
+                        "price": { "type": "string", "description": "Total price as a decimal string" },
+                        "currency": { "type": "string", "description": "ISO 4217 currency code" },
+                        "airline": { "type": "string", "description": "Airline selling the ticket" },
+                        "airline_code": { "type": ["string", "null"], "description": "IATA code of the selling airline" },
+                        "converted_amount": converted_amount_schema(),
+                        "expires_at": { "type": ["string", "null"], "description": "When the offer can no longer be booked (ISO 8601)" },
+                        "slices": {
+                            "type": "array",
+                            "description": "One entry per requested slice, in travel order; segments and layovers are left out at summary verbosity",
+                            "items": slice
+                        },
+                        "origin_airport": {
+                            "type": ["string", "null"],
+                            "description": "Origin airport searched for this offer; set when nearby airports were included"
+                        },
+                        "destination_airport": {
+                            "type": ["string", "null"],
+                            "description": "Destination airport searched for this offer; set when nearby airports were included"
                         }
-                    }
+                    },
+                    "required": ["id", "price", "currency", "airline", "slices"]
                 }
-                _ => {
-                    json!({
-                        "jsonrpc": "2.0",
-                        "error": {
-                            "code": -32601,
-                            "message": "Method not found"
+            },
+            "total_results": { "type": "integer", "description": "Offers returned by Duffel for this page" },
+            "available_results": {
+                "type": ["integer", "null"],
+                "description": "Offers Duffel found for the whole search, before filters and paging; null on later pages"
+            },
+            "search_id": {
+                "type": "string",
+                "description": "Duffel offer request ID; the first search's when nearby airports or flexible dates were searched"
+            },
+            "next_page_token": {
+                "type": ["string", "null"],
+                "description": "Pass as page_token to fetch the next page; null when there are no more offers"
+            },
+            "skipped_results": {
+                "type": "integer",
+                "description": "Offers on this page that Duffel returned in an unexpected shape and were left out"
+            },
+            "warnings": {
+                "type": "array",
+                "items": { "type": "string" },
+                "description": "Parts of the search that failed, e.g. skipped offers; the offers returned are still valid"
+            },
+            "price_calendar": {
+                "type": ["array", "null"],
+                "description": "Cheapest offer per pair of travel dates, when date_flexibility_days was used",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "departure_date": { "type": "string" },
+                        "return_date": { "type": ["string", "null"] },
+                        "cheapest_price": {
+                            "type": ["string", "null"],
+                            "description": "In display_currency when set; null when nothing matched or the search failed"
                         },
-                        "id": id
-                    })
+                        "currency": { "type": ["string", "null"] },
+                        "offer_id": { "type": ["string", "null"], "description": "The cheapest offer for these dates" },
+                        "offers": { "type": "integer", "description": "Matching offers fetched for these dates" }
+                    },
+                    "required": ["departure_date", "offers"]
                 }
             }
-        }
-        _ => {
-            json!({
-                "jsonrpc": "2.0",
-                "error": {
-                    "code": -32601,
-                    "message": "Method not found"
-                },
-                "id": id
-            })
-        }
+        },
+        "required": ["offers", "total_results", "search_id", "skipped_results"]
+    })
+}
+
+async fn search_flights(flights: DuffelFlightServer, arguments: Value) -> Result<Value, RpcError> {
+    let mut search_request: FlightSearchRequest = mcp::parse_arguments("search_flights", arguments)?;
+    search_request.validate()?;
+    let slices = search_request.slices();
+    let fanout = search_request.fanout();
+    run_search(flights, slices, fanout, search_request.options).await
+}
+
+async fn search_multi_city(flights: DuffelFlightServer, arguments: Value) -> Result<Value, RpcError> {
+    let mut search_request: MultiCitySearchRequest = mcp::parse_arguments("search_multi_city", arguments)?;
+    search_request.validate()?;
+    run_search(flights, search_request.slices, None, search_request.options).await
+}
+
+/// Run a search; a `fanout`, when given, replaces `slices` with several merged searches.
+async fn run_search(
+    flights: DuffelFlightServer,
+    slices: Vec<SliceRequest>,
+    fanout: Option<Fanout>,
+    options: SearchOptions,
+) -> Result<Value, RpcError> {
+    let limit = pagination::resolve_limit(options.limit)?;
+    let verbosity = Verbosity::resolve(options.verbosity);
+    let passengers = options.passenger_mix.to_duffel()?;
+    options.programmes.validate(&passengers)?;
+    options.filters.validate()?;
+    let cursor = options
+        .page_token
+        .as_deref()
+        .map(OffersCursor::decode)
+        .transpose()?;
+    if fanout.is_some() && cursor.is_some() {
+        return Err(RpcError::invalid_params(
+            "Invalid parameters: page_token cannot be used with include_nearby_airports or date_flexibility_days, which return a single merged page",
+        ));
     }
+    let display = flights.fx.display_currency(options.display_currency.as_deref()).await?;
+
+    let search_response = match fanout {
+        Some(fanout) => flights.search_fanout(fanout, options, limit, display).await,
+        None => flights.search_flights(slices, options, limit, cursor, display).await,
+    }
+    .map_err(|e| {
+        error!("Flight search error: {}", e);
+        errors::rpc_error("Flight search failed", &e)
+    })?;
+
+    let first_option = flights.save_results(&search_response).await;
+    search_results_content(&flights, &search_response, first_option, verbosity)
+}
+
+/// A database-backed feature, or `None` (with a warning) when its tables could not be set up.
+fn open<T>(opened: Result<T>, feature: &str) -> Option<T> {
+    opened.map_err(|e| warn!("{} disabled: {:#}", feature, e)).ok()
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize logging
-    tracing_subscriber::fmt::init();
-    info!("Starting Duffel Flights MCP HTTP Server");
+    let transport = Transport::from_args()?;
+    // config.toml plus environment overrides; `--print-config` prints it and exits
+    config::init()?;
+
+    // Initialize logging (stderr, so stdout stays free for the stdio transport)
+    bookedai_core::logging::init();
+    info!("Starting Duffel Flights MCP Server ({} transport)", transport);
 
     // Initialize the server
-    let server = DuffelFlightServer::new()?;
-    info!("Duffel API token loaded successfully");
-
-    // Create CORS configuration
-    let cors = warp::cors()
-        .allow_any_origin()
-        .allow_headers(vec!["content-type"])
-        .allow_methods(vec!["GET", "POST", "OPTIONS"]);
-
-    // Health check endpoint
-    let health = warp::path("health")
-        .and(warp::get())
-        .map(|| {
-            warp::reply::json(&json!({
-                "status": "healthy",
-                "service": "duffel-flights-mcp",
-                "version": "0.1.0"
-            }))
+    let store = Store::from_config().map_err(|e| warn!("Database unavailable: {:#}", e)).ok();
+    let search_results = match &store {
+        Some(store) => open(SearchResults::new(store.clone()).await, "Saved search results"),
+        None => None,
+    };
+    let price_alerts = match &store {
+        Some(store) => open(PriceAlerts::new(store.clone()).await, "Price alerts"),
+        None => None,
+    };
+    let idempotency_keys = match &store {
+        Some(store) => open(IdempotencyKeys::new(store.clone()).await, "Booking idempotency keys"),
+        None => None,
+    };
+    // Off unless an encryption key is configured
+    let traveler_profiles = match &store {
+        Some(store) => open(TravelerProfiles::from_config(store.clone()).await, "Traveler profiles").flatten(),
+        None => None,
+    };
+    // On unless AUDIT_LOG_ENABLED=false
+    let audit_log = match &store {
+        Some(store) => open(AuditLog::from_config(store.clone()).await, "Audit log").flatten(),
+        None => None,
+    };
+    let flights = DuffelFlightServer::new(search_results.clone())?;
+    info!("Duffel mode: {}", config::get().duffel.mode);
+    let readiness = Readiness::new().duffel(flights.duffel.clone()).store(store);
+    let webhooks = WebhookReceiver::from_config();
+    let order_events = webhooks.as_ref().map(WebhookReceiver::events);
+    if let Some(search_results) = search_results {
+        scheduler::spawn_periodic("Saved search cleanup", SEARCH_RESULTS_PURGE_INTERVAL, move || {
+            let search_results = search_results.clone();
+            async move {
+                let purged = search_results.purge_expired().await?;
+                if purged > 0 {
+                    info!("Deleted {} expired saved searches", purged);
+                }
+                Ok(())
+            }
         });
-
-    // MCP endpoint
-    let server_clone = server.clone();
-    let mcp = warp::path("mcp")
-        .and(warp::post())
-        .and(warp::body::json())
-        .and_then(move |request: Value| {
-            let server = server_clone.clone();
+    }
+    if let Some(audit_log) = audit_log.clone() {
+        scheduler::spawn_periodic("Audit log rotation", audit::PRUNE_INTERVAL, move || {
+            let audit_log = audit_log.clone();
             async move {
-                handle_mcp_request(server, request).await
+                let pruned = audit_log.prune_from_config().await?;
+                if pruned > 0 {
+                    info!("Deleted {} old audit log entries", pruned);
+                }
+                Ok(())
             }
         });
-
-    // Root endpoint with info
-    let root = warp::path::end()
-        .and(warp::get())
-        .map(|| {
-            warp::reply::json(&json!({
-                "service": "Duffel Flights MCP Server",
-                "version": "0.1.0",
-                "endpoints": {
-                    "health": "GET /health",
-                    "mcp": "POST /mcp"
-                },
-                "tools": ["search_flights"]
-            }))
+    }
+    if let Some(price_alerts) = &price_alerts {
+        let interval = config::get().price_alerts.interval_secs;
+        let (flights, price_alerts) = (flights.clone(), price_alerts.clone());
+        scheduler::spawn_periodic("Price alert check", Duration::from_secs(interval), move || {
+            alerts::check_price_alerts(flights.clone(), price_alerts.clone())
         });
+    }
 
-    let routes = health
-        .or(mcp)
-        .or(root)
-        .with(cors)
-        .with(warp::log("duffel_flights"));
-
-    let port = env::var("PORT")
-        .unwrap_or_else(|_| "3001".to_string())
-        .parse::<u16>()
-        .unwrap_or(3001);
-
-    info!("Server starting on http://localhost:{}", port);
-    info!("MCP endpoint: http://localhost:{}/mcp", port);
-    info!("Health check: http://localhost:{}/health", port);
-
-    warp::serve(routes)
-        .run(([127, 0, 0, 1], port))
-        .await;
+    let mut server = McpServer::builder("duffel-flights-mcp", env!("CARGO_PKG_VERSION"))
+        .title("Duffel Flights MCP Server")
+        .cache(ResponseCache::from_config())
+        .audit(audit_log.clone())
+        .tool(search_flights_tool(), {
+            let flights = flights.clone();
+            move |arguments| search_flights(flights.clone(), arguments)
+        })
+        .tool(search_multi_city_tool(), {
+            let flights = flights.clone();
+            move |arguments| search_multi_city(flights.clone(), arguments)
+        })
+        .tool(offers::get_flight_offer_tool(), {
+            let flights = flights.clone();
+            move |arguments| offers::get_flight_offer(flights.clone(), arguments)
+        })
+        .tool(ancillaries::get_seat_map_tool(), {
+            let flights = flights.clone();
+            move |arguments| ancillaries::get_seat_map(flights.clone(), arguments)
+        })
+        .tool(ancillaries::list_ancillaries_tool(), {
+            let flights = flights.clone();
+            move |arguments| ancillaries::list_ancillaries(flights.clone(), arguments)
+        })
+        .tool(orders::create_order_tool(), {
+            let flights = flights.clone();
+            let traveler_profiles = traveler_profiles.clone();
+            move |arguments| {
+                orders::create_order(flights.clone(), idempotency_keys.clone(), traveler_profiles.clone(), arguments)
+            }
+        })
+        .tool(orders::get_order_tool(), {
+            let flights = flights.clone();
+            move |arguments| orders::get_order(flights.clone(), arguments)
+        })
+        .tool(orders::list_orders_tool(), {
+            let flights = flights.clone();
+            move |arguments| orders::list_orders(flights.clone(), arguments)
+        })
+        .tool(orders::cancel_order_tool(), {
+            let flights = flights.clone();
+            move |arguments| orders::cancel_order(flights.clone(), arguments)
+        })
+        .tool(orders::send_confirmation_tool(), {
+            let flights = flights.clone();
+            move |arguments| orders::send_confirmation(flights.clone(), arguments)
+        })
+        .tool(profiles::save_traveler_profile_tool(), {
+            let traveler_profiles = traveler_profiles.clone();
+            move |arguments| profiles::save_traveler_profile(traveler_profiles.clone(), arguments)
+        })
+        .tool(profiles::list_traveler_profiles_tool(), move |arguments| {
+            profiles::list_traveler_profiles(traveler_profiles.clone(), arguments)
+        })
+        .tool(orders::list_order_events_tool(), move |arguments| {
+            orders::list_order_events(order_events.clone(), arguments)
+        })
+        .tool(results::get_search_results_tool(), {
+            let flights = flights.clone();
+            move |arguments| results::get_search_results(flights.clone(), arguments)
+        })
+        .tool(results::select_offer_tool(), {
+            let flights = flights.clone();
+            move |arguments| results::select_offer(flights.clone(), arguments)
+        })
+        .resources(
+            results::search_resource_template(),
+            {
+                let flights = flights.clone();
+                move || results::list_search_resources(flights.clone())
+            },
+            {
+                let flights = flights.clone();
+                move |search_id| results::read_search_resource(flights.clone(), search_id)
+            },
+        )
+        .resources(
+            orders::order_resource_template(),
+            {
+                let flights = flights.clone();
+                move || orders::list_order_resources(flights.clone())
+            },
+            {
+                let flights = flights.clone();
+                move |order_id| orders::read_order_resource(flights.clone(), order_id)
+            },
+        )
+        .tool(alerts::track_flight_price_tool(), {
+            let price_alerts = price_alerts.clone();
+            move |arguments| alerts::track_flight_price(flights.clone(), price_alerts.clone(), arguments)
+        })
+        .tool(alerts::list_price_alerts_tool(), move |arguments| {
+            alerts::list_price_alerts(price_alerts.clone(), arguments)
+        });
+    // Admin only: every client could read every recorded call
+    if config::get().audit.query_tool {
+        server = server.tool(audit::query_audit_log_tool(), move |arguments| {
+            audit::query_audit_log(audit_log.clone(), arguments)
+        });
+    }
+    let server = server.build();
 
-    Ok(())
-} 
\ No newline at end of file
+    transport::serve(
+        server,
+        transport,
+        HttpOptions {
+            default_port: 3001,
+            log_target: "duffel_flights",
+            webhooks,
+            readiness,
+        },
+    )
+    .await
+}
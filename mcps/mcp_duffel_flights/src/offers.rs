@@ -0,0 +1,332 @@
+//! A single flight offer with its fare rules: brand, change and refund conditions, and
+//! the baggage each passenger gets without paying extra.
+
+use anyhow::Result;
+use bookedai_core::duffel_models::{self, Offer};
+use bookedai_core::{errors, mcp, validation, RpcError, Tool};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::error;
+
+use crate::ancillaries::{self, Ancillary};
+use crate::itinerary::{self, FlightSlice};
+use crate::DuffelFlightServer;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OfferLookupRequest {
+    offer_id: String,
+}
+
+/// Whether a change or refund is allowed before departure, and at what cost.
+#[derive(Debug, Serialize, Deserialize)]
+struct FareCondition {
+    allowed: bool,
+    penalty_amount: Option<String>,
+    penalty_currency: Option<String>,
+}
+
+impl FareCondition {
+    fn from_duffel(condition: &duffel_models::Condition) -> Self {
+        Self {
+            allowed: condition.allowed,
+            penalty_amount: condition.penalty_amount.clone(),
+            penalty_currency: condition.penalty_currency.clone(),
+        }
+    }
+}
+
+/// Bags included in one passenger's fare for a slice.
+#[derive(Debug, Serialize, Deserialize)]
+struct PassengerBaggage {
+    passenger_id: String,
+    /// `adult`, `child`, or `infant_without_seat`.
+    passenger_type: Option<String>,
+    age: Option<u32>,
+    /// Airline's name for the cabin, e.g. `Economy Light`.
+    cabin: Option<String>,
+    checked_bags: u32,
+    carry_on_bags: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FareSlice {
+    fare_brand_name: Option<String>,
+    /// Overrides the offer-wide rule for this slice when set.
+    change_before_departure: Option<FareCondition>,
+    baggage: Vec<PassengerBaggage>,
+    itinerary: FlightSlice,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FlightOfferDetails {
+    offer_id: String,
+    price: String,
+    currency: String,
+    airline: String,
+    airline_code: Option<String>,
+    expires_at: Option<String>,
+    /// `None` when the airline does not say; treat as unknown rather than allowed.
+    change_before_departure: Option<FareCondition>,
+    refund_before_departure: Option<FareCondition>,
+    slices: Vec<FareSlice>,
+    /// Extras that can be bought on top of the fare, such as more checked bags.
+    services: Vec<Ancillary>,
+}
+
+/// The bags a passenger keeps for the whole slice: the fewest included on any segment.
+fn slice_baggage(offer: &Offer, slice: &duffel_models::Slice) -> Vec<PassengerBaggage> {
+    let allowance = |segment: &duffel_models::Segment, passenger_id: &str, baggage_type: &str| {
+        segment
+            .passengers
+            .iter()
+            .find(|passenger| passenger.passenger_id == passenger_id)
+            .map(|passenger| {
+                passenger
+                    .baggages
+                    .iter()
+                    .filter(|baggage| baggage.baggage_type == baggage_type)
+                    .map(|baggage| baggage.quantity)
+                    .sum::<u32>()
+            })
+            .unwrap_or(0)
+    };
+
+    offer
+        .passengers
+        .iter()
+        .map(|passenger| {
+            let minimum = |baggage_type: &str| {
+                slice
+                    .segments
+                    .iter()
+                    .map(|segment| allowance(segment, &passenger.id, baggage_type))
+                    .min()
+                    .unwrap_or(0)
+            };
+            PassengerBaggage {
+                passenger_id: passenger.id.clone(),
+                passenger_type: passenger.passenger_type.clone(),
+                age: passenger.age,
+                cabin: slice
+                    .segments
+                    .first()
+                    .and_then(|segment| segment.passengers.iter().find(|p| p.passenger_id == passenger.id))
+                    .and_then(|p| p.cabin_class_marketing_name.clone().or_else(|| p.cabin_class.clone())),
+                checked_bags: minimum("checked"),
+                carry_on_bags: minimum("carry_on"),
+            }
+        })
+        .collect()
+}
+
+fn describe_condition(condition: Option<&FareCondition>) -> String {
+    match condition {
+        None => "not stated by the airline".to_string(),
+        Some(condition) if !condition.allowed => "not allowed".to_string(),
+        Some(condition) => match (&condition.penalty_amount, &condition.penalty_currency) {
+            (Some(amount), _) if amount.parse::<f64>() == Ok(0.0) => "allowed, free".to_string(),
+            (Some(amount), Some(currency)) => format!("allowed, penalty {} {}", amount, currency),
+            _ => "allowed".to_string(),
+        },
+    }
+}
+
+impl DuffelFlightServer {
+    async fn get_flight_offer(&self, offer_id: &str) -> Result<FlightOfferDetails> {
+        let path = format!("/air/offers/{}", offer_id);
+        let response = self
+            .duffel
+            .get_with_query(&path, &[("return_available_services", "true")])
+            .await?;
+        let offer = Offer::deserialize(&response["data"])?;
+
+        let slices = offer
+            .slices
+            .iter()
+            .map(|slice| {
+                Some(FareSlice {
+                    fare_brand_name: slice.fare_brand_name.clone(),
+                    change_before_departure: slice
+                        .conditions
+                        .as_ref()
+                        .and_then(|conditions| conditions.change_before_departure.as_ref())
+                        .map(FareCondition::from_duffel),
+                    baggage: slice_baggage(&offer, slice),
+                    itinerary: FlightSlice::from_duffel(slice)?,
+                })
+            })
+            .collect::<Option<Vec<_>>>()
+            .filter(|slices| !slices.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("Offer {} has no slices or segments", offer.id))?;
+
+        // Prefer the ticketing airline, falling back to the first marketing carrier
+        let carrier = offer
+            .owner
+            .as_ref()
+            .unwrap_or(&offer.slices[0].segments[0].marketing_carrier);
+        let conditions = offer.conditions.as_ref();
+
+        Ok(FlightOfferDetails {
+            offer_id: offer.id.clone(),
+            price: offer.total_amount.clone(),
+            currency: offer.total_currency.clone(),
+            airline: carrier.name.clone(),
+            airline_code: carrier.iata_code.clone(),
+            expires_at: offer.expires_at.clone(),
+            change_before_departure: conditions
+                .and_then(|conditions| conditions.change_before_departure.as_ref())
+                .map(FareCondition::from_duffel),
+            refund_before_departure: conditions
+                .and_then(|conditions| conditions.refund_before_departure.as_ref())
+                .map(FareCondition::from_duffel),
+            slices,
+            services: response["data"]["available_services"]
+                .as_array()
+                .map(|services| services.iter().filter_map(ancillaries::parse_service).collect())
+                .unwrap_or_default(),
+        })
+    }
+
+    fn format_flight_offer(&self, details: &FlightOfferDetails) -> String {
+        let mut result = format!("{} - {} {}\n", details.airline, details.price, details.currency);
+        if let Some(expires_at) = &details.expires_at {
+            result.push_str(&format!("Bookable until {}\n", expires_at));
+        }
+
+        result.push_str("\nFare rules:\n");
+        result.push_str(&format!(
+            "   Changes before departure: {}\n",
+            describe_condition(details.change_before_departure.as_ref())
+        ));
+        result.push_str(&format!(
+            "   Refunds before departure: {}\n",
+            describe_condition(details.refund_before_departure.as_ref())
+        ));
+
+        for (n, slice) in details.slices.iter().enumerate() {
+            result.push('\n');
+            let label = match details.slices.len() {
+                1 => "Flight".to_string(),
+                _ => format!("Leg {}", n + 1),
+            };
+            itinerary::describe_slice(&mut result, &label, &slice.itinerary);
+            if let Some(brand) = &slice.fare_brand_name {
+                result.push_str(&format!("   Fare: {}\n", brand));
+            }
+            // Only worth repeating when legs can differ from each other
+            if let Some(change) = slice.change_before_departure.as_ref().filter(|_| details.slices.len() > 1) {
+                result.push_str(&format!("   Changes on this leg: {}\n", describe_condition(Some(change))));
+            }
+            for baggage in &slice.baggage {
+                let traveller = match (&baggage.passenger_type, baggage.age) {
+                    (Some(passenger_type), _) => passenger_type.replace('_', " "),
+                    (None, Some(age)) => format!("age {}", age),
+                    (None, None) => "passenger".to_string(),
+                };
+                result.push_str(&format!(
+                    "   Included baggage ({}, {}): {} checked, {} carry-on\n",
+                    traveller, baggage.passenger_id, baggage.checked_bags, baggage.carry_on_bags
+                ));
+            }
+        }
+
+        if !details.services.is_empty() {
+            result.push_str("\nExtras for sale:\n");
+            for service in &details.services {
+                result.push_str(&format!(
+                    "   {} - {} {}\n",
+                    service.description, service.price, service.currency
+                ));
+            }
+            result.push_str("Use list_ancillaries for service IDs and seat prices.\n");
+        }
+
+        result.push_str(&format!("\nOffer ID: {}", details.offer_id));
+        result
+    }
+}
+
+fn fare_condition_schema() -> Value {
+    json!({
+        "type": ["object", "null"],
+        "description": "null when the airline does not state the rule",
+        "properties": {
+            "allowed": { "type": "boolean" },
+            "penalty_amount": { "type": ["string", "null"] },
+            "penalty_currency": { "type": ["string", "null"] }
+        },
+        "required": ["allowed"]
+    })
+}
+
+pub fn get_flight_offer_tool() -> Tool {
+    Tool::new(
+        "get_flight_offer",
+        "Get the fare rules of a flight offer before booking: fare brand, whether changes and refunds are allowed and their penalties, and the checked and carry-on bags included for each passenger",
+        json!({
+            "type": "object",
+            "properties": {
+                "offer_id": {
+                    "type": "string",
+                    "description": "Offer ID from search_flights, search_multi_city or select_offer (e.g., 'off_0000AEdGRhTrsmIL8Lbw3K')"
+                }
+            },
+            "required": ["offer_id"]
+        }),
+    )
+    .with_output_schema(json!({
+        "type": "object",
+        "properties": {
+            "offer_id": { "type": "string" },
+            "price": { "type": "string" },
+            "currency": { "type": "string" },
+            "airline": { "type": "string" },
+            "airline_code": { "type": ["string", "null"] },
+            "expires_at": { "type": ["string", "null"] },
+            "change_before_departure": fare_condition_schema(),
+            "refund_before_departure": fare_condition_schema(),
+            "slices": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "fare_brand_name": { "type": ["string", "null"] },
+                        "change_before_departure": fare_condition_schema(),
+                        "baggage": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "passenger_id": { "type": "string" },
+                                    "passenger_type": { "type": ["string", "null"] },
+                                    "age": { "type": ["integer", "null"] },
+                                    "cabin": { "type": ["string", "null"] },
+                                    "checked_bags": { "type": "integer" },
+                                    "carry_on_bags": { "type": "integer" }
+                                },
+                                "required": ["passenger_id", "checked_bags", "carry_on_bags"]
+                            }
+                        },
+                        "itinerary": crate::flight_slice_schema()
+                    },
+                    "required": ["baggage", "itinerary"]
+                }
+            },
+            "services": { "type": "array", "items": ancillaries::ancillary_schema() }
+        },
+        "required": ["offer_id", "price", "currency", "airline", "slices", "services"]
+    }))
+}
+
+pub async fn get_flight_offer(flights: DuffelFlightServer, arguments: Value) -> Result<Value, RpcError> {
+    let request: OfferLookupRequest = mcp::parse_arguments("get_flight_offer", arguments)?;
+    let offer_id = validation::duffel_id("offer_id", &request.offer_id, "off_")?;
+
+    let details = flights.get_flight_offer(&offer_id).await.map_err(|e| {
+        error!("Flight offer error: {}", e);
+        errors::rpc_error("Flight offer lookup failed", &e)
+    })?;
+
+    let formatted_results = flights.format_flight_offer(&details);
+    mcp::structured_content(formatted_results, &details)
+}
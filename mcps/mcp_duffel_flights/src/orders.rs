@@ -0,0 +1,1119 @@
+//! Flight orders: idempotent booking, lookup, listing, two-step cancellation,
+//! confirmations, webhook events, and `booking://flights/{order_id}` resources.
+
+use anyhow::Result;
+use bookedai_core::duffel_models::{self, Offer};
+use bookedai_core::idempotency::{self, Claim, CompletedCall, IdempotencyKeys};
+use bookedai_core::notifications::{self, Confirmation, Delivery};
+use bookedai_core::profiles::TravelerProfiles;
+use bookedai_core::resources::{Resource, ResourceTemplate};
+use bookedai_core::webhooks::{EventStore, WebhookEvent};
+use bookedai_core::{config, errors, mcp, pagination, validation, RpcError, Tool};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{error, info, warn};
+
+use crate::itinerary::{self, FlightSlice};
+use crate::profiles::{self, TravelerDetails};
+use crate::DuffelFlightServer;
+
+/// Confirmed orders listed by `resources/list`.
+const RECENT_ORDER_RESOURCES: usize = 20;
+
+/// Tool name idempotency keys are recorded under.
+const CREATE_ORDER: &str = "create_order";
+
+/// Most passengers Duffel books on one order.
+const MAX_PASSENGERS: usize = 9;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CreateOrderRequest {
+    offer_id: String,
+    passengers: Vec<PassengerDetails>,
+    /// Replaying a key returns the order it booked instead of booking again.
+    idempotency_key: Option<String>,
+}
+
+/// A passenger to book: a saved profile, the traveller's details, or both,
+/// with details given here taking precedence over the profile's.
+#[derive(Debug, Serialize, Deserialize)]
+struct PassengerDetails {
+    /// Offer passenger ID (`pas_`); the offer's passengers are taken in order when omitted.
+    id: Option<String>,
+    /// Saved traveller profile (`tvp_`) to take the details from.
+    profile_id: Option<String>,
+    #[serde(flatten)]
+    traveller: TravelerDetails,
+}
+
+impl PassengerDetails {
+    /// Normalize the details; `prefix` names the passenger in errors (e.g. `passengers[0].`).
+    fn validate(&mut self, prefix: &str) -> Result<(), RpcError> {
+        if let Some(id) = &self.id {
+            self.id = Some(validation::duffel_id(&format!("{}id", prefix), id, "pas_")?);
+        }
+        self.traveller.validate(prefix)
+    }
+}
+
+impl CreateOrderRequest {
+    /// Fill in the details of passengers booked by `profile_id`.
+    async fn resolve_profiles(&mut self, profiles: Option<&TravelerProfiles>) -> Result<(), RpcError> {
+        for (index, passenger) in self.passengers.iter_mut().enumerate() {
+            if let Some(profile_id) = &passenger.profile_id {
+                let field = format!("passengers[{}].profile_id", index);
+                let saved = profiles::resolve(profiles, &field, profile_id).await?;
+                passenger.traveller.fill_from(&saved);
+            }
+        }
+        Ok(())
+    }
+
+    fn validate(&mut self) -> Result<(), RpcError> {
+        self.offer_id = validation::duffel_id("offer_id", &self.offer_id, "off_")?;
+        if self.passengers.is_empty() || self.passengers.len() > MAX_PASSENGERS {
+            return Err(RpcError::invalid_params(format!(
+                "Invalid parameters: passengers must list between 1 and {} travellers",
+                MAX_PASSENGERS
+            )));
+        }
+        for (index, passenger) in self.passengers.iter_mut().enumerate() {
+            passenger.validate(&format!("passengers[{}].", index))?;
+        }
+        if let Some(key) = &self.idempotency_key {
+            self.idempotency_key = Some(idempotency::check_key("idempotency_key", key)?);
+        }
+        Ok(())
+    }
+
+    /// Hash of everything but the key, so a replay can be told from a different booking.
+    fn hash(&self) -> String {
+        idempotency::request_hash(&json!({
+            "offer_id": self.offer_id,
+            "passengers": self.passengers
+        }))
+    }
+}
+
+/// A booked order, with the key that protects it from being booked twice.
+#[derive(Debug, Serialize, Deserialize)]
+struct OrderConfirmation {
+    #[serde(flatten)]
+    order: Order,
+    idempotency_key: String,
+    /// `true` when an earlier call with the same key had already booked this order.
+    replayed: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OrderLookupRequest {
+    order_id: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ListOrdersRequest {
+    booking_reference: Option<String>,
+    awaiting_payment: Option<bool>,
+    limit: Option<usize>,
+    page_token: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CancelOrderRequest {
+    order_id: String,
+    /// Quote returned by an earlier call; confirming it cancels the order.
+    cancellation_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SendConfirmationRequest {
+    order_id: String,
+}
+
+/// Where an order's confirmation went.
+#[derive(Debug, Serialize, Deserialize)]
+struct ConfirmationReceipt {
+    order_id: String,
+    recipients: Vec<String>,
+    deliveries: Vec<Delivery>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ListOrderEventsRequest {
+    order_id: Option<String>,
+    event_type: Option<String>,
+    limit: Option<usize>,
+}
+
+/// Whether a change or refund is allowed before departure, and at what cost.
+#[derive(Debug, Serialize, Deserialize)]
+struct OrderCondition {
+    allowed: bool,
+    penalty_amount: Option<String>,
+    penalty_currency: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Order {
+    id: String,
+    booking_reference: Option<String>,
+    /// `confirmed`, `awaiting_payment`, or `cancelled`.
+    status: String,
+    total_amount: String,
+    currency: String,
+    created_at: Option<String>,
+    airline: Option<String>,
+    /// One line per slice, e.g. `JFK -> LHR departing 2025-03-15T08:00:00`.
+    itinerary: Vec<String>,
+    /// Every slice with its flights, for calendars and detailed itineraries.
+    #[serde(default)]
+    slices: Vec<FlightSlice>,
+    passengers: Vec<String>,
+    /// Passengers' email addresses, for confirmations; not part of the tool output.
+    #[serde(skip)]
+    emails: Vec<String>,
+    refund_before_departure: Option<OrderCondition>,
+    change_before_departure: Option<OrderCondition>,
+    /// Deadline to pay for a held order before it is released.
+    payment_required_by: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OrderEventsResponse {
+    events: Vec<WebhookEvent>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OrderListResponse {
+    orders: Vec<Order>,
+    next_page_token: Option<String>,
+}
+
+/// A refund quote for cancelling an order, or the confirmed cancellation.
+#[derive(Debug, Serialize, Deserialize)]
+struct OrderCancellation {
+    cancellation_id: String,
+    order_id: String,
+    refund_amount: Option<String>,
+    refund_currency: Option<String>,
+    /// Where the refund goes, e.g. `original_form_of_payment` or `voucher`.
+    refund_to: Option<String>,
+    /// The quote must be confirmed before this time.
+    expires_at: Option<String>,
+    confirmed: bool,
+    confirmed_at: Option<String>,
+}
+
+impl DuffelFlightServer {
+    /// Book `request.offer_id` at its current price, paying from the Duffel balance.
+    async fn create_order(&self, request: &CreateOrderRequest, idempotency_key: &str) -> Result<Order, RpcError> {
+        // The price to pay and the passenger IDs come from the offer itself
+        let response = self
+            .duffel
+            .get(&format!("/air/offers/{}", request.offer_id))
+            .await
+            .map_err(booking_error)?;
+        let offer = Offer::deserialize(&response["data"]).map_err(|e| booking_error(e.into()))?;
+        let passengers = passenger_payload(&offer, &request.passengers)?;
+
+        let payload = json!({
+            "data": {
+                "type": "instant",
+                "selected_offers": [offer.id],
+                "passengers": passengers,
+                "payments": [{
+                    "type": "balance",
+                    "amount": offer.total_amount,
+                    "currency": offer.total_currency
+                }]
+            }
+        });
+        info!("Booking offer {} (idempotency key {})", offer.id, idempotency_key);
+        let response = self
+            .duffel
+            .post_idempotent("/air/orders", &payload, idempotency_key)
+            .await
+            .map_err(booking_error)?;
+        parse_order(&response["data"]).ok_or_else(|| RpcError::server_error("Booking failed: no order data in response"))
+    }
+
+    async fn get_order(&self, order_id: &str) -> Result<Order> {
+        let response = self.duffel.get(&format!("/air/orders/{}", order_id)).await?;
+        parse_order(&response["data"]).ok_or_else(|| anyhow::anyhow!("No order data in response"))
+    }
+
+    async fn list_orders(&self, request: &ListOrdersRequest, limit: usize) -> Result<OrderListResponse> {
+        let mut query = vec![("limit", limit.to_string())];
+        if let Some(after) = &request.page_token {
+            query.push(("after", after.clone()));
+        }
+        if let Some(booking_reference) = &request.booking_reference {
+            query.push(("booking_reference", booking_reference.clone()));
+        }
+        if let Some(awaiting_payment) = request.awaiting_payment {
+            query.push(("awaiting_payment", awaiting_payment.to_string()));
+        }
+
+        let response = self.duffel.get_with_query("/air/orders", &query).await?;
+        let orders = response["data"]
+            .as_array()
+            .ok_or_else(|| anyhow::anyhow!("No orders data in response"))?
+            .iter()
+            .filter_map(parse_order)
+            .collect();
+
+        Ok(OrderListResponse {
+            orders,
+            next_page_token: response["meta"]["after"].as_str().map(|s| s.to_string()),
+        })
+    }
+
+    /// Ask Duffel how much would be refunded if the order were cancelled now.
+    async fn quote_cancellation(&self, order_id: &str) -> Result<OrderCancellation> {
+        let payload = json!({ "data": { "order_id": order_id } });
+        let response = self.duffel.post("/air/order_cancellations", &payload).await?;
+        parse_cancellation(&response["data"])
+            .ok_or_else(|| anyhow::anyhow!("No order cancellation data in response"))
+    }
+
+    /// Confirm a previously quoted cancellation, after checking it belongs to `order_id`.
+    async fn confirm_cancellation(&self, order_id: &str, cancellation_id: &str) -> Result<OrderCancellation> {
+        let path = format!("/air/order_cancellations/{}", cancellation_id);
+        let quote = self.duffel.get(&path).await?;
+        let quote = parse_cancellation(&quote["data"])
+            .ok_or_else(|| anyhow::anyhow!("No order cancellation data in response"))?;
+        if quote.order_id != order_id {
+            anyhow::bail!(
+                "cancellation {} was quoted for order {}, not {}",
+                cancellation_id,
+                quote.order_id,
+                order_id
+            );
+        }
+        if quote.confirmed {
+            return Ok(quote);
+        }
+
+        info!("Confirming cancellation {} for order {}", cancellation_id, order_id);
+        let response = self
+            .duffel
+            .post(&format!("{}/actions/confirm", path), &json!({}))
+            .await?;
+        parse_cancellation(&response["data"])
+            .ok_or_else(|| anyhow::anyhow!("No order cancellation data in response"))
+    }
+
+    fn format_order(&self, order: &Order) -> String {
+        let mut result = format!(
+            "Order {} ({}) - {} {}\n",
+            order.booking_reference.as_deref().unwrap_or(&order.id),
+            order.status,
+            order.total_amount,
+            order.currency
+        );
+        if let Some(airline) = &order.airline {
+            result.push_str(&format!("   Airline: {}\n", airline));
+        }
+        for line in &order.itinerary {
+            result.push_str(&format!("   {}\n", line));
+        }
+        if !order.passengers.is_empty() {
+            result.push_str(&format!("   Passengers: {}\n", order.passengers.join(", ")));
+        }
+        if let Some(deadline) = &order.payment_required_by {
+            result.push_str(&format!("   Payment required by: {}\n", deadline));
+        }
+        if let Some(refund) = &order.refund_before_departure {
+            result.push_str(&format!("   Refund before departure: {}\n", describe_condition(refund)));
+        }
+        if let Some(change) = &order.change_before_departure {
+            result.push_str(&format!("   Change before departure: {}\n", describe_condition(change)));
+        }
+        result.push_str(&format!("   Order ID: {}\n", order.id));
+        result
+    }
+
+    fn format_confirmation(&self, confirmation: &OrderConfirmation) -> String {
+        let mut result = if confirmation.replayed {
+            format!(
+                "Already booked by an earlier call with idempotency_key \"{}\"; no new order was created.\n\n",
+                confirmation.idempotency_key
+            )
+        } else {
+            "Booked.\n\n".to_string()
+        };
+        result.push_str(&self.format_order(&confirmation.order));
+        if !confirmation.replayed {
+            result.push_str(&format!(
+                "   Idempotency key: {} (retrying with it returns this order instead of booking again)\n",
+                confirmation.idempotency_key
+            ));
+        }
+        result
+    }
+
+    fn format_order_list(&self, response: &OrderListResponse) -> String {
+        if response.orders.is_empty() {
+            return "No orders found.".to_string();
+        }
+
+        let mut result = format!("Found {} orders:\n\n", response.orders.len());
+        for (i, order) in response.orders.iter().enumerate() {
+            result.push_str(&format!("{}. {}\n", i + 1, self.format_order(order)));
+        }
+        if let Some(token) = &response.next_page_token {
+            result.push_str(&format!("More orders available (page_token: {})", token));
+        }
+        result.trim_end().to_string()
+    }
+
+    fn format_cancellation(&self, cancellation: &OrderCancellation) -> String {
+        let refund = match (&cancellation.refund_amount, &cancellation.refund_currency) {
+            (Some(amount), Some(currency)) => format!("{} {}", amount, currency),
+            _ => "no refund".to_string(),
+        };
+        let refund_to = cancellation
+            .refund_to
+            .as_deref()
+            .map(|to| format!(" to {}", to.replace('_', " ")))
+            .unwrap_or_default();
+
+        if cancellation.confirmed {
+            return format!(
+                "Order {} cancelled{}. Refund: {}{}.",
+                cancellation.order_id,
+                cancellation
+                    .confirmed_at
+                    .as_deref()
+                    .map(|at| format!(" at {}", at))
+                    .unwrap_or_default(),
+                refund,
+                refund_to
+            );
+        }
+
+        let mut result = format!(
+            "Cancelling order {} now would refund {}{}.\n",
+            cancellation.order_id, refund, refund_to
+        );
+        if let Some(expires_at) = &cancellation.expires_at {
+            result.push_str(&format!("This quote expires at {}.\n", expires_at));
+        }
+        result.push_str(&format!(
+            "The order is NOT cancelled yet. Call cancel_order again with cancellation_id \"{}\" to confirm.",
+            cancellation.cancellation_id
+        ));
+        result
+    }
+}
+
+/// Duffel's passenger list for an order: the offer's passenger IDs with each
+/// traveller's details, and every lap infant assigned to an adult.
+fn passenger_payload(offer: &Offer, details: &[PassengerDetails]) -> Result<Vec<Value>, RpcError> {
+    if details.len() != offer.passengers.len() {
+        return Err(RpcError::invalid_params(format!(
+            "Invalid parameters: offer {} is for {} passengers, but {} were given",
+            offer.id,
+            offer.passengers.len(),
+            details.len()
+        )));
+    }
+
+    // Travellers given an ID keep it; the rest take the offer's remaining IDs in order
+    let mut unused: Vec<&str> = offer.passengers.iter().map(|passenger| passenger.id.as_str()).collect();
+    for id in details.iter().filter_map(|passenger| passenger.id.as_deref()) {
+        let Some(index) = unused.iter().position(|unused| *unused == id) else {
+            return Err(RpcError::invalid_params(format!(
+                "Invalid parameters: passenger {} is not on offer {}, or is listed twice",
+                id, offer.id
+            )));
+        };
+        unused.remove(index);
+    }
+    let mut unused = unused.into_iter();
+    let ids: Vec<&str> = details
+        .iter()
+        .map(|passenger| passenger.id.as_deref().or_else(|| unused.next()).unwrap_or_default())
+        .collect();
+
+    let passenger_type = |id: &str| {
+        offer
+            .passengers
+            .iter()
+            .find(|passenger| passenger.id == id)
+            .and_then(|passenger| passenger.passenger_type.as_deref())
+    };
+    let mut infants = ids
+        .iter()
+        .filter(|id| passenger_type(id) == Some("infant_without_seat"))
+        .copied();
+
+    Ok(details
+        .iter()
+        .zip(&ids)
+        .map(|(passenger, id)| {
+            let traveller = &passenger.traveller;
+            let mut payload = json!({
+                "id": id,
+                "title": traveller.title,
+                "given_name": traveller.given_name,
+                "family_name": traveller.family_name,
+                "born_on": traveller.born_on,
+                "gender": traveller.gender,
+                "email": traveller.email,
+                "phone_number": traveller.phone_number
+            });
+            traveller.add_documents(&mut payload);
+            if passenger_type(id) == Some("adult") {
+                if let Some(infant) = infants.next() {
+                    payload["infant_passenger_id"] = json!(infant);
+                }
+            }
+            payload
+        })
+        .collect())
+}
+
+fn format_order_events(response: &OrderEventsResponse) -> String {
+    if response.events.is_empty() {
+        return "No order events received yet.".to_string();
+    }
+
+    let mut result = format!("{} order events, newest first:\n\n", response.events.len());
+    for (i, event) in response.events.iter().enumerate() {
+        result.push_str(&format!(
+            "{}. {} - order {} (received {})\n",
+            i + 1,
+            event.event_type,
+            event.order_id.as_deref().unwrap_or("n/a"),
+            event.received_at
+        ));
+        if event.event_type == "order.airline_initiated_change_detected" {
+            result.push_str("   The airline changed the schedule; review the order with get_order.\n");
+        }
+    }
+    result.trim_end().to_string()
+}
+
+fn describe_condition(condition: &OrderCondition) -> String {
+    if !condition.allowed {
+        return "not allowed".to_string();
+    }
+    match (&condition.penalty_amount, &condition.penalty_currency) {
+        (Some(amount), Some(currency)) => format!("allowed, penalty {} {}", amount, currency),
+        _ => "allowed".to_string(),
+    }
+}
+
+fn parse_condition(condition: &Value) -> Option<OrderCondition> {
+    Some(OrderCondition {
+        allowed: condition["allowed"].as_bool()?,
+        penalty_amount: condition["penalty_amount"].as_str().map(|s| s.to_string()),
+        penalty_currency: condition["penalty_currency"].as_str().map(|s| s.to_string()),
+    })
+}
+
+fn parse_order(order: &Value) -> Option<Order> {
+    let payment_status = &order["payment_status"];
+    let status = if !order["cancelled_at"].is_null() {
+        "cancelled"
+    } else if payment_status["awaiting_payment"].as_bool() == Some(true) {
+        "awaiting_payment"
+    } else {
+        "confirmed"
+    };
+
+    let itinerary = order["slices"]
+        .as_array()
+        .map(|slices| {
+            slices
+                .iter()
+                .filter_map(|slice| {
+                    let origin = slice["origin"]["iata_code"].as_str()?;
+                    let destination = slice["destination"]["iata_code"].as_str()?;
+                    let departing_at = slice["segments"][0]["departing_at"].as_str().unwrap_or("unknown");
+                    Some(format!("{} -> {} departing {}", origin, destination, departing_at))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // Orders Duffel returns in an unexpected shape still get their summary lines
+    let slices = serde_json::from_value::<Vec<duffel_models::Slice>>(order["slices"].clone())
+        .map(|slices| slices.iter().filter_map(FlightSlice::from_duffel).collect())
+        .unwrap_or_default();
+
+    let passengers = order["passengers"]
+        .as_array()
+        .map(|passengers| {
+            passengers
+                .iter()
+                .filter_map(|passenger| {
+                    let given_name = passenger["given_name"].as_str()?;
+                    let family_name = passenger["family_name"].as_str()?;
+                    Some(format!("{} {}", given_name, family_name))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut emails: Vec<String> = order["passengers"]
+        .as_array()
+        .map(|passengers| {
+            passengers
+                .iter()
+                .filter_map(|passenger| passenger["email"].as_str().map(|email| email.to_string()))
+                .collect()
+        })
+        .unwrap_or_default();
+    emails.sort();
+    emails.dedup();
+
+    Some(Order {
+        id: order["id"].as_str()?.to_string(),
+        booking_reference: order["booking_reference"].as_str().map(|s| s.to_string()),
+        status: status.to_string(),
+        total_amount: order["total_amount"].as_str()?.to_string(),
+        currency: order["total_currency"].as_str()?.to_string(),
+        created_at: order["created_at"].as_str().map(|s| s.to_string()),
+        airline: order["owner"]["name"].as_str().map(|s| s.to_string()),
+        itinerary,
+        slices,
+        passengers,
+        emails,
+        refund_before_departure: parse_condition(&order["conditions"]["refund_before_departure"]),
+        change_before_departure: parse_condition(&order["conditions"]["change_before_departure"]),
+        payment_required_by: payment_status["payment_required_by"].as_str().map(|s| s.to_string()),
+    })
+}
+
+fn parse_cancellation(cancellation: &Value) -> Option<OrderCancellation> {
+    let confirmed_at = cancellation["confirmed_at"].as_str().map(|s| s.to_string());
+    Some(OrderCancellation {
+        cancellation_id: cancellation["id"].as_str()?.to_string(),
+        order_id: cancellation["order_id"].as_str()?.to_string(),
+        refund_amount: cancellation["refund_amount"].as_str().map(|s| s.to_string()),
+        refund_currency: cancellation["refund_currency"].as_str().map(|s| s.to_string()),
+        refund_to: cancellation["refund_to"].as_str().map(|s| s.to_string()),
+        expires_at: cancellation["expires_at"].as_str().map(|s| s.to_string()),
+        confirmed: confirmed_at.is_some(),
+        confirmed_at,
+    })
+}
+
+fn order_condition_schema() -> Value {
+    json!({
+        "type": ["object", "null"],
+        "properties": {
+            "allowed": { "type": "boolean" },
+            "penalty_amount": { "type": ["string", "null"] },
+            "penalty_currency": { "type": ["string", "null"] }
+        },
+        "required": ["allowed"]
+    })
+}
+
+fn order_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "id": { "type": "string" },
+            "booking_reference": { "type": ["string", "null"] },
+            "status": { "type": "string", "enum": ["confirmed", "awaiting_payment", "cancelled"] },
+            "total_amount": { "type": "string" },
+            "currency": { "type": "string" },
+            "created_at": { "type": ["string", "null"] },
+            "airline": { "type": ["string", "null"] },
+            "itinerary": { "type": "array", "items": { "type": "string" } },
+            "slices": { "type": "array", "items": crate::flight_slice_schema() },
+            "passengers": { "type": "array", "items": { "type": "string" } },
+            "refund_before_departure": order_condition_schema(),
+            "change_before_departure": order_condition_schema(),
+            "payment_required_by": { "type": ["string", "null"] }
+        },
+        "required": ["id", "status", "total_amount", "currency", "itinerary", "passengers"]
+    })
+}
+
+/// `create_order` passenger properties: the traveller's details and how to match them to the offer.
+fn passenger_properties() -> Value {
+    let mut properties = profiles::passenger_properties();
+    properties["id"] = json!({
+        "type": "string",
+        "description": "Offer passenger ID (starts with 'pas_'); omit to take the offer's passengers in order"
+    });
+    properties["profile_id"] = json!({
+        "type": "string",
+        "description": "Saved traveler profile (starts with 'tvp_') from list_traveler_profiles or save_traveler_profile"
+    });
+    properties
+}
+
+pub fn create_order_tool() -> Tool {
+    Tool::new(
+        "create_order",
+        "Book a flight offer for the given passengers, paying its current price from the Duffel balance. \
+         Retrying with the same idempotency_key returns the original order instead of booking again",
+        json!({
+            "type": "object",
+            "properties": {
+                "offer_id": {
+                    "type": "string",
+                    "description": "Offer to book (starts with 'off_'), from search_flights or select_offer"
+                },
+                "passengers": {
+                    "type": "array",
+                    "description": format!("One entry per passenger on the offer (1-{}), infants included", MAX_PASSENGERS),
+                    "items": {
+                        "type": "object",
+                        "description": "Give the traveller's details, a saved profile_id, or a profile_id with details that override it",
+                        "properties": passenger_properties()
+                    }
+                },
+                "idempotency_key": {
+                    "type": "string",
+                    "description": "Unique key for this booking (letters, digits, '-', '_', '.', ':'); reuse it when retrying. Defaults to one derived from the offer and passengers"
+                }
+            },
+            "required": ["offer_id", "passengers"]
+        }),
+    )
+    .with_output_schema({
+        let mut schema = order_schema();
+        schema["properties"]["idempotency_key"] = json!({ "type": "string" });
+        schema["properties"]["replayed"] = json!({
+            "type": "boolean",
+            "description": "true when an earlier call with the same idempotency_key had already booked this order"
+        });
+        schema["required"] = json!(["id", "status", "total_amount", "currency", "itinerary", "passengers", "idempotency_key", "replayed"]);
+        schema
+    })
+}
+
+pub fn get_order_tool() -> Tool {
+    Tool::new(
+        "get_order",
+        "Get a booked flight order, including its refund and change conditions",
+        json!({
+            "type": "object",
+            "properties": {
+                "order_id": {
+                    "type": "string",
+                    "description": "Duffel order ID (starts with 'ord_')"
+                }
+            },
+            "required": ["order_id"]
+        }),
+    )
+    .with_output_schema(order_schema())
+}
+
+pub fn list_orders_tool() -> Tool {
+    Tool::new(
+        "list_orders",
+        "List booked flight orders, newest first",
+        json!({
+            "type": "object",
+            "properties": {
+                "booking_reference": {
+                    "type": "string",
+                    "description": "Only return the order with this airline booking reference (e.g., 'RZPVKH')"
+                },
+                "awaiting_payment": {
+                    "type": "boolean",
+                    "description": "Only return held orders that still need payment (true) or paid orders (false)"
+                },
+                "limit": {
+                    "type": "integer",
+                    "description": "Maximum number of orders to return (1-50, default: 10)"
+                },
+                "page_token": {
+                    "type": "string",
+                    "description": "next_page_token from a previous list_orders call"
+                }
+            }
+        }),
+    )
+    .with_output_schema(json!({
+        "type": "object",
+        "properties": {
+            "orders": { "type": "array", "items": order_schema() },
+            "next_page_token": {
+                "type": ["string", "null"],
+                "description": "Pass as page_token to fetch the next page; null when there are no more orders"
+            }
+        },
+        "required": ["orders"]
+    }))
+}
+
+pub fn cancel_order_tool() -> Tool {
+    Tool::new(
+        "cancel_order",
+        "Cancel a flight order in two steps: first call returns a refund quote with its deadline; \
+         call again with the quote's cancellation_id to actually cancel",
+        json!({
+            "type": "object",
+            "properties": {
+                "order_id": {
+                    "type": "string",
+                    "description": "Duffel order ID (starts with 'ord_')"
+                },
+                "cancellation_id": {
+                    "type": "string",
+                    "description": "cancellation_id from a previous quote; omit to get a quote without cancelling"
+                }
+            },
+            "required": ["order_id"]
+        }),
+    )
+    .with_output_schema(json!({
+        "type": "object",
+        "properties": {
+            "cancellation_id": { "type": "string" },
+            "order_id": { "type": "string" },
+            "refund_amount": { "type": ["string", "null"] },
+            "refund_currency": { "type": ["string", "null"] },
+            "refund_to": { "type": ["string", "null"] },
+            "expires_at": { "type": ["string", "null"] },
+            "confirmed": {
+                "type": "boolean",
+                "description": "true once the order has actually been cancelled"
+            },
+            "confirmed_at": { "type": ["string", "null"] }
+        },
+        "required": ["cancellation_id", "order_id", "confirmed"]
+    }))
+}
+
+pub fn send_confirmation_tool() -> Tool {
+    Tool::new(
+        "send_confirmation",
+        "Send a booking confirmation for a flight order (segments, booking reference, passengers) \
+         by the email and webhook channels the server is configured with",
+        json!({
+            "type": "object",
+            "properties": {
+                "order_id": {
+                    "type": "string",
+                    "description": "Duffel order ID (starts with 'ord_')"
+                }
+            },
+            "required": ["order_id"]
+        }),
+    )
+    .with_output_schema(notifications::deliveries_schema("order_id"))
+}
+
+pub fn list_order_events_tool() -> Tool {
+    Tool::new(
+        "list_order_events",
+        "List order events pushed by Duffel webhooks, such as airline schedule changes and cancellations",
+        json!({
+            "type": "object",
+            "properties": {
+                "order_id": {
+                    "type": "string",
+                    "description": "Only events for this order (starts with 'ord_')"
+                },
+                "event_type": {
+                    "type": "string",
+                    "description": "Only events of this type (e.g., 'order.airline_initiated_change_detected', 'order.updated')"
+                },
+                "limit": {
+                    "type": "integer",
+                    "description": "Maximum number of events to return (1-50, default: 10)"
+                }
+            }
+        }),
+    )
+    .with_output_schema(json!({
+        "type": "object",
+        "properties": {
+            "events": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "id": { "type": "string" },
+                        "event_type": { "type": "string" },
+                        "order_id": { "type": ["string", "null"] },
+                        "created_at": { "type": ["string", "null"] },
+                        "received_at": { "type": "string" },
+                        "live_mode": { "type": "boolean" },
+                        "object": { "type": "object" }
+                    },
+                    "required": ["id", "event_type", "received_at", "live_mode", "object"]
+                }
+            }
+        },
+        "required": ["events"]
+    }))
+}
+
+pub fn order_resource_template() -> ResourceTemplate {
+    ResourceTemplate::new(
+        "booking://flights/{order_id}",
+        "flight-booking",
+        "A booked Duffel flight order, as returned by get_order",
+    )
+}
+
+/// The most recent confirmed orders, as resources.
+pub async fn list_order_resources(flights: DuffelFlightServer) -> Result<Vec<Resource>, RpcError> {
+    let template = order_resource_template();
+    let response = flights
+        .list_orders(&ListOrdersRequest::default(), RECENT_ORDER_RESOURCES)
+        .await
+        .map_err(order_error)?;
+
+    Ok(response
+        .orders
+        .into_iter()
+        .filter(|order| order.status == "confirmed")
+        .map(|order| {
+            let mut description = format!("{} {}", order.total_amount, order.currency);
+            if let Some(first) = order.itinerary.first() {
+                description = format!("{}; {}", first, description);
+            }
+            Resource::new(template.uri(&order.id), order.id.clone())
+                .with_title(format!(
+                    "Flight booking {}",
+                    order.booking_reference.as_deref().unwrap_or(&order.id)
+                ))
+                .with_description(description)
+        })
+        .collect())
+}
+
+pub async fn read_order_resource(flights: DuffelFlightServer, order_id: String) -> Result<Value, RpcError> {
+    // Anything that is not an order ID cannot name a resource
+    let Ok(order_id) = validation::duffel_id("order_id", &order_id, "ord_") else {
+        return Ok(Value::Null);
+    };
+    let order = flights.get_order(&order_id).await.map_err(order_error)?;
+    serde_json::to_value(order).map_err(|e| RpcError::server_error(format!("Failed to serialize order: {}", e)))
+}
+
+fn booking_error(e: anyhow::Error) -> RpcError {
+    error!("Booking error: {}", e);
+    errors::rpc_error("Booking failed", &e)
+}
+
+/// The order as a confirmation message, addressed to `recipients`.
+fn confirmation_message(order: &Order, recipients: Vec<String>) -> Confirmation {
+    let mut route = order.slices.first().map(|slice| slice.origin.clone()).unwrap_or_default();
+    for slice in &order.slices {
+        route.push_str(&format!(" - {}", slice.destination));
+    }
+    let mut title = match &order.airline {
+        Some(airline) => format!("{} flight {}", airline, route),
+        None => format!("Flight {}", route),
+    };
+    if let Some(first) = order.slices.first() {
+        title.push_str(&format!(", departing {}", itinerary::format_time(&first.departure_time)));
+    }
+
+    let mut details = String::new();
+    for (n, slice) in order.slices.iter().enumerate() {
+        itinerary::describe_slice(&mut details, &itinerary::slice_label(&order.slices, n), slice);
+    }
+    let mut details: Vec<String> = details.lines().map(|line| line.trim_start_matches("   ").to_string()).collect();
+    if order.slices.is_empty() {
+        details.extend(order.itinerary.iter().cloned());
+    }
+    if !order.passengers.is_empty() {
+        details.push(format!("Passengers: {}", order.passengers.join(", ")));
+    }
+    details.push(format!("Total: {} {}", order.total_amount, order.currency));
+    if let Some(deadline) = &order.payment_required_by {
+        details.push(format!("Payment required by: {}", deadline));
+    }
+
+    Confirmation {
+        kind: "flight_order",
+        booking_id: order.id.clone(),
+        reference: order.booking_reference.clone(),
+        title,
+        details,
+        recipients,
+        booking: serde_json::to_value(order).unwrap_or_default(),
+    }
+}
+
+fn format_receipt(receipt: &ConfirmationReceipt) -> String {
+    format!(
+        "Confirmation for order {}:\n{}",
+        receipt.order_id,
+        notifications::describe(&receipt.deliveries)
+    )
+}
+
+fn order_error(e: anyhow::Error) -> RpcError {
+    error!("Order error: {}", e);
+    errors::rpc_error("Order request failed", &e)
+}
+
+/// Return the order an earlier call with the same key booked.
+fn replay(flights: &DuffelFlightServer, call: CompletedCall) -> Result<Value, RpcError> {
+    info!("Replaying order {} booked at {}", call.resource_id, call.completed_at);
+    let mut confirmation: OrderConfirmation = serde_json::from_value(call.response)
+        .map_err(|e| RpcError::server_error(format!("Saved booking of order {} is unreadable: {}", call.resource_id, e)))?;
+    confirmation.replayed = true;
+    mcp::structured_content(flights.format_confirmation(&confirmation), &confirmation)
+}
+
+pub async fn create_order(
+    flights: DuffelFlightServer,
+    idempotency_keys: Option<IdempotencyKeys>,
+    profiles: Option<TravelerProfiles>,
+    arguments: Value,
+) -> Result<Value, RpcError> {
+    let mut request: CreateOrderRequest = mcp::parse_arguments(CREATE_ORDER, arguments)?;
+    request.resolve_profiles(profiles.as_ref()).await?;
+    request.validate()?;
+    let hash = request.hash();
+    let key = match &request.idempotency_key {
+        Some(key) => key.clone(),
+        // Identical retries without a key still find the first booking
+        None => format!("auto:{}", &hash[..32]),
+    };
+
+    if let Some(keys) = &idempotency_keys {
+        let claim = keys.claim(CREATE_ORDER, &key, &hash).await.map_err(|e| {
+            error!("Idempotency key error: {}", e);
+            RpcError::server_error(format!("Could not check idempotency_key: {}", e))
+        })?;
+        match claim {
+            Claim::Acquired => {}
+            Claim::Completed(call) => return replay(&flights, call),
+            Claim::InProgress => {
+                return Err(RpcError::server_error(format!(
+                    "A booking with idempotency_key \"{}\" is still in progress; retry with the same key shortly",
+                    key
+                ))
+                .with_data(json!({ "retryable": true })))
+            }
+            Claim::Mismatch => {
+                return Err(RpcError::invalid_params(format!(
+                    "Invalid parameters: idempotency_key \"{}\" was already used for a different booking",
+                    key
+                )))
+            }
+        }
+    } else {
+        warn!("No database: booking without replay protection beyond Duffel's Idempotency-Key");
+    }
+
+    let order = match flights.create_order(&request, &key).await {
+        Ok(order) => order,
+        Err(e) => {
+            if let Some(keys) = &idempotency_keys {
+                if let Err(release_error) = keys.release(CREATE_ORDER, &key).await {
+                    warn!("Could not release idempotency key {}: {}", key, release_error);
+                }
+            }
+            return Err(e);
+        }
+    };
+    let confirmation = OrderConfirmation {
+        order,
+        idempotency_key: key,
+        replayed: false,
+    };
+    if let Some(keys) = &idempotency_keys {
+        let saved = match serde_json::to_value(&confirmation) {
+            Ok(response) => {
+                keys.complete(CREATE_ORDER, &confirmation.idempotency_key, &confirmation.order.id, &response)
+                    .await
+            }
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = saved {
+            warn!(
+                "Could not record order {} under idempotency key {}: {}",
+                confirmation.order.id, confirmation.idempotency_key, e
+            );
+        }
+    }
+    let mut text = flights.format_confirmation(&confirmation);
+    if config::get().notifications.send_on_booking && flights.notifier.is_configured() {
+        // The addresses just given, which Duffel's order may not echo back
+        let mut recipients: Vec<String> = request.passengers.iter().map(|passenger| passenger.traveller.email.clone()).collect();
+        recipients.sort();
+        recipients.dedup();
+        text.push_str(&format!("   Confirmation: sending by {}\n", flights.notifier.channels()));
+        flights.notifier.spawn_send(confirmation_message(&confirmation.order, recipients));
+    }
+    mcp::structured_content(text, &confirmation)
+}
+
+pub async fn get_order(flights: DuffelFlightServer, arguments: Value) -> Result<Value, RpcError> {
+    let request: OrderLookupRequest = mcp::parse_arguments("get_order", arguments)?;
+    let order_id = validation::duffel_id("order_id", &request.order_id, "ord_")?;
+
+    let order = flights.get_order(&order_id).await.map_err(order_error)?;
+    mcp::structured_content(flights.format_order(&order), &order)
+}
+
+pub async fn list_orders(flights: DuffelFlightServer, arguments: Value) -> Result<Value, RpcError> {
+    let request: ListOrdersRequest = mcp::parse_arguments("list_orders", arguments)?;
+    let limit = pagination::resolve_limit(request.limit)?;
+
+    let response = flights.list_orders(&request, limit).await.map_err(order_error)?;
+    mcp::structured_content(flights.format_order_list(&response), &response)
+}
+
+pub async fn cancel_order(flights: DuffelFlightServer, arguments: Value) -> Result<Value, RpcError> {
+    let request: CancelOrderRequest = mcp::parse_arguments("cancel_order", arguments)?;
+    let order_id = validation::duffel_id("order_id", &request.order_id, "ord_")?;
+
+    let cancellation = match &request.cancellation_id {
+        Some(cancellation_id) => {
+            let cancellation_id = validation::duffel_id("cancellation_id", cancellation_id, "ore_")?;
+            flights.confirm_cancellation(&order_id, &cancellation_id).await
+        }
+        None => flights.quote_cancellation(&order_id).await,
+    }
+    .map_err(order_error)?;
+
+    mcp::structured_content(flights.format_cancellation(&cancellation), &cancellation)
+}
+
+pub async fn send_confirmation(flights: DuffelFlightServer, arguments: Value) -> Result<Value, RpcError> {
+    let request: SendConfirmationRequest = mcp::parse_arguments("send_confirmation", arguments)?;
+    let order_id = validation::duffel_id("order_id", &request.order_id, "ord_")?;
+
+    let order = flights.get_order(&order_id).await.map_err(order_error)?;
+    if order.status == "cancelled" {
+        return Err(RpcError::invalid_params(format!(
+            "Invalid parameters: order {} is cancelled; there is no booking to confirm",
+            order.id
+        )));
+    }
+    // Only the addresses on the order, so the tool cannot send passenger details elsewhere
+    let recipients = order.emails.clone();
+    let deliveries = flights.notifier.send_requested(&confirmation_message(&order, recipients.clone())).await?;
+
+    let receipt = ConfirmationReceipt {
+        order_id: order.id,
+        recipients,
+        deliveries,
+    };
+    mcp::structured_content(format_receipt(&receipt), &receipt)
+}
+
+pub async fn list_order_events(events: Option<EventStore>, arguments: Value) -> Result<Value, RpcError> {
+    let request: ListOrderEventsRequest = mcp::parse_arguments("list_order_events", arguments)?;
+    let limit = pagination::resolve_limit(request.limit)?;
+    let Some(events) = events else {
+        return Err(RpcError::server_error(
+            "Order events are unavailable: set DUFFEL_WEBHOOK_SECRET and run with the HTTP transport",
+        ));
+    };
+
+    let response = OrderEventsResponse {
+        events: events.list(request.order_id.as_deref(), request.event_type.as_deref(), limit),
+    };
+    mcp::structured_content(format_order_events(&response), &response)
+}
@@ -0,0 +1,88 @@
+//! Passenger mix for Duffel offer requests.
+
+use bookedai_core::RpcError;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Most passengers Duffel accepts in one offer request.
+pub const MAX_PASSENGERS: u32 = 9;
+
+/// Youngest age booked as a child with a seat; younger passengers are infants.
+pub const MIN_CHILD_AGE: u8 = 2;
+
+/// Oldest age booked as a child; older passengers are adults.
+pub const MAX_CHILD_AGE: u8 = 17;
+
+/// Adults, children (by age), and lap infants travelling together.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PassengerMix {
+    /// Legacy all-adult passenger count, kept as an alias for `adults`.
+    pub passengers: Option<u32>,
+    pub adults: Option<u32>,
+    pub children_ages: Option<Vec<u8>>,
+    pub infants: Option<u32>,
+}
+
+fn invalid(message: String) -> RpcError {
+    RpcError::invalid_params(format!("Invalid parameters: {}", message))
+}
+
+impl PassengerMix {
+    fn adults(&self) -> Result<u32, RpcError> {
+        match (self.adults, self.passengers) {
+            (Some(adults), Some(passengers)) if adults != passengers => Err(invalid(format!(
+                "passengers ({}) and adults ({}) disagree; use adults only",
+                passengers, adults
+            ))),
+            (Some(adults), _) | (None, Some(adults)) => Ok(adults),
+            (None, None) => Ok(1),
+        }
+    }
+
+    /// Validate the mix and build Duffel's `passengers` array.
+    ///
+    /// Children are sent with their age, as Duffel requires; infants travel on an adult's lap.
+    pub fn to_duffel(&self) -> Result<Vec<Value>, RpcError> {
+        let adults = self.adults()?;
+        let children_ages = self.children_ages.as_deref().unwrap_or_default();
+        let infants = self.infants.unwrap_or(0);
+
+        if adults == 0 {
+            return Err(invalid("at least one adult is required".to_string()));
+        }
+        if infants > adults {
+            return Err(invalid(format!(
+                "each infant must travel with an adult ({} infants, {} adults)",
+                infants, adults
+            )));
+        }
+        if let Some(age) = children_ages
+            .iter()
+            .find(|age| !(MIN_CHILD_AGE..=MAX_CHILD_AGE).contains(*age))
+        {
+            return Err(invalid(format!(
+                "child age {} is outside {}-{}; use infants for passengers under {}",
+                age, MIN_CHILD_AGE, MAX_CHILD_AGE, MIN_CHILD_AGE
+            )));
+        }
+        let total = adults + children_ages.len() as u32 + infants;
+        if total > MAX_PASSENGERS {
+            return Err(invalid(format!(
+                "at most {} passengers per search, got {}",
+                MAX_PASSENGERS, total
+            )));
+        }
+
+        let mut passengers = Vec::with_capacity(total as usize);
+        for _ in 0..adults {
+            passengers.push(json!({ "type": "adult" }));
+        }
+        for age in children_ages {
+            passengers.push(json!({ "age": age }));
+        }
+        for _ in 0..infants {
+            passengers.push(json!({ "type": "infant_without_seat" }));
+        }
+        Ok(passengers)
+    }
+}
@@ -0,0 +1,523 @@
+//! Traveller profiles: passenger details entered once and booked again by
+//! `profile_id`, kept in the encrypted store of [`bookedai_core::profiles`].
+
+use bookedai_core::profiles::{self, SavedProfile, TravelerProfiles};
+use bookedai_core::{errors, mcp, validation, RpcError, Tool};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{error, info};
+
+/// Passenger titles Duffel accepts.
+pub const TITLES: &[&str] = &["mr", "ms", "mrs", "miss", "dr"];
+
+/// Loyalty accounts kept per traveller.
+const MAX_LOYALTY_ACCOUNTS: usize = 10;
+
+/// A traveller's details, as the airline needs them for ticketing.
+///
+/// Every field may be left out when the details come from a saved profile;
+/// [`TravelerDetails::validate`] then checks the merged result.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TravelerDetails {
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub given_name: String,
+    #[serde(default)]
+    pub family_name: String,
+    #[serde(default)]
+    pub born_on: String,
+    /// `m` or `f`, as on the passport.
+    #[serde(default)]
+    pub gender: String,
+    #[serde(default)]
+    pub email: String,
+    /// E.164 format, e.g. `+442080160509`.
+    #[serde(default)]
+    pub phone_number: String,
+    pub passport: Option<Passport>,
+    /// Frequent flyer numbers, credited when booking with that airline.
+    #[serde(default)]
+    pub loyalty_accounts: Vec<LoyaltyAccount>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Passport {
+    pub number: String,
+    /// ISO 3166-1 alpha-2 code of the issuing country, e.g. `GB`.
+    pub issuing_country_code: String,
+    pub expires_on: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoyaltyAccount {
+    /// Airline running the programme, e.g. `BA`.
+    pub airline_iata_code: String,
+    pub account_number: String,
+}
+
+/// `ABCD1234` as `****1234`, for output that should not carry the full number.
+fn mask(number: &str) -> String {
+    let visible = number.chars().count().saturating_sub(4);
+    number
+        .chars()
+        .enumerate()
+        .map(|(i, c)| if i < visible { '*' } else { c })
+        .collect()
+}
+
+impl TravelerDetails {
+    /// Normalize the details; `prefix` names the traveller in errors (e.g. `passengers[0].`).
+    pub fn validate(&mut self, prefix: &str) -> Result<(), RpcError> {
+        let invalid = |message: String| RpcError::invalid_params(format!("Invalid parameters: {}{}", prefix, message));
+        self.title = self.title.trim().to_ascii_lowercase();
+        if !TITLES.contains(&self.title.as_str()) {
+            return Err(invalid(format!("title must be one of {}, got '{}'", TITLES.join(", "), self.title)));
+        }
+        for (field, name) in [("given_name", &mut self.given_name), ("family_name", &mut self.family_name)] {
+            *name = name.trim().to_string();
+            if name.is_empty() {
+                return Err(invalid(format!("{} must not be empty", field)));
+            }
+        }
+        let born_on = validation::date(&format!("{}born_on", prefix), &self.born_on)?;
+        if born_on > Utc::now().date_naive() {
+            return Err(invalid(format!("born_on {} is in the future", born_on)));
+        }
+        self.born_on = born_on.to_string();
+        self.gender = self.gender.trim().to_ascii_lowercase();
+        if self.gender != "m" && self.gender != "f" {
+            return Err(invalid(format!("gender must be 'm' or 'f', got '{}'", self.gender)));
+        }
+        self.email = validation::email(&format!("{}email", prefix), &self.email)?;
+        self.phone_number = self.phone_number.replace([' ', '-'], "");
+        let digits = self.phone_number.strip_prefix('+').unwrap_or_default();
+        if !(7..=15).contains(&digits.len()) || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid(format!(
+                "phone_number must be in E.164 format (e.g. '+442080160509'), got '{}'",
+                self.phone_number
+            )));
+        }
+
+        if let Some(passport) = &mut self.passport {
+            passport.number = passport.number.replace(' ', "").to_ascii_uppercase();
+            if !(5..=20).contains(&passport.number.len()) || !passport.number.bytes().all(|b| b.is_ascii_alphanumeric()) {
+                return Err(invalid("passport.number must be 5-20 letters and digits".to_string()));
+            }
+            passport.issuing_country_code = passport.issuing_country_code.trim().to_ascii_uppercase();
+            if passport.issuing_country_code.len() != 2
+                || !passport.issuing_country_code.bytes().all(|b| b.is_ascii_alphabetic())
+            {
+                return Err(invalid(format!(
+                    "passport.issuing_country_code must be a two-letter country code (e.g. 'GB'), got '{}'",
+                    passport.issuing_country_code
+                )));
+            }
+            let expires_on = validation::date(&format!("{}passport.expires_on", prefix), &passport.expires_on)?;
+            if expires_on < Utc::now().date_naive() {
+                return Err(invalid(format!("passport expired on {}", expires_on)));
+            }
+            passport.expires_on = expires_on.to_string();
+        }
+
+        if self.loyalty_accounts.len() > MAX_LOYALTY_ACCOUNTS {
+            return Err(invalid(format!("at most {} loyalty_accounts can be given", MAX_LOYALTY_ACCOUNTS)));
+        }
+        for (index, account) in self.loyalty_accounts.iter_mut().enumerate() {
+            account.airline_iata_code = account.airline_iata_code.trim().to_ascii_uppercase();
+            if account.airline_iata_code.len() != 2
+                || !account.airline_iata_code.bytes().all(|b| b.is_ascii_alphanumeric())
+            {
+                return Err(invalid(format!(
+                    "loyalty_accounts[{}].airline_iata_code must be a two-character airline code (e.g. 'BA'), got '{}'",
+                    index, account.airline_iata_code
+                )));
+            }
+            account.account_number = account.account_number.trim().to_string();
+            if account.account_number.is_empty() || account.account_number.len() > 30 {
+                return Err(invalid(format!(
+                    "loyalty_accounts[{}].account_number must be 1-30 characters",
+                    index
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Take every field left out here from `saved`.
+    pub fn fill_from(&mut self, saved: &TravelerDetails) {
+        for (field, saved) in [
+            (&mut self.title, &saved.title),
+            (&mut self.given_name, &saved.given_name),
+            (&mut self.family_name, &saved.family_name),
+            (&mut self.born_on, &saved.born_on),
+            (&mut self.gender, &saved.gender),
+            (&mut self.email, &saved.email),
+            (&mut self.phone_number, &saved.phone_number),
+        ] {
+            if field.trim().is_empty() {
+                field.clone_from(saved);
+            }
+        }
+        if self.passport.is_none() {
+            self.passport.clone_from(&saved.passport);
+        }
+        if self.loyalty_accounts.is_empty() {
+            self.loyalty_accounts.clone_from(&saved.loyalty_accounts);
+        }
+    }
+
+    /// These details with passport and loyalty numbers masked, and the date of birth
+    /// cut to its year.
+    fn masked(&self) -> TravelerDetails {
+        let mut masked = self.clone();
+        if let Some((year, _)) = masked.born_on.split_once('-') {
+            masked.born_on = format!("{}-**-**", year);
+        }
+        if let Some(passport) = &mut masked.passport {
+            passport.number = mask(&passport.number);
+        }
+        for account in &mut masked.loyalty_accounts {
+            account.account_number = mask(&account.account_number);
+        }
+        masked
+    }
+
+    /// `identity_documents` and `loyalty_programme_accounts` for a Duffel order passenger.
+    pub fn add_documents(&self, payload: &mut Value) {
+        if let Some(passport) = &self.passport {
+            payload["identity_documents"] = json!([{
+                "type": "passport",
+                "unique_identifier": passport.number,
+                "issuing_country_code": passport.issuing_country_code,
+                "expires_on": passport.expires_on
+            }]);
+        }
+        if !self.loyalty_accounts.is_empty() {
+            payload["loyalty_programme_accounts"] = json!(self
+                .loyalty_accounts
+                .iter()
+                .map(|account| json!({
+                    "airline_iata_code": account.airline_iata_code,
+                    "account_number": account.account_number
+                }))
+                .collect::<Vec<_>>());
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SaveProfileRequest {
+    /// Replace this profile instead of creating one.
+    profile_id: Option<String>,
+    #[serde(flatten)]
+    details: TravelerDetails,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ListProfilesRequest {
+    /// Only travellers whose name contains this, case-insensitively.
+    name: Option<String>,
+}
+
+/// A profile as tools return it: numbers masked, ID first.
+#[derive(Debug, Serialize, Deserialize)]
+struct ProfileSummary {
+    profile_id: String,
+    #[serde(flatten)]
+    details: TravelerDetails,
+    created_at: String,
+    updated_at: String,
+}
+
+impl ProfileSummary {
+    fn new(saved: &SavedProfile<TravelerDetails>) -> Self {
+        Self {
+            profile_id: saved.id.clone(),
+            details: saved.profile.masked(),
+            created_at: saved.created_at.clone(),
+            updated_at: saved.updated_at.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ProfileListResponse {
+    profiles: Vec<ProfileSummary>,
+}
+
+fn format_profile(summary: &ProfileSummary) -> String {
+    let details = &summary.details;
+    let mut result = format!(
+        "{} {} {} {}, born {}\n   Contact: {}, {}\n",
+        summary.profile_id,
+        capitalize(&details.title),
+        details.given_name,
+        details.family_name,
+        details.born_on,
+        details.email,
+        details.phone_number
+    );
+    if let Some(passport) = &details.passport {
+        result.push_str(&format!(
+            "   Passport: {} ({}), expires {}\n",
+            passport.number, passport.issuing_country_code, passport.expires_on
+        ));
+    }
+    for account in &details.loyalty_accounts {
+        result.push_str(&format!("   Loyalty: {} {}\n", account.airline_iata_code, account.account_number));
+    }
+    result
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+pub fn unavailable() -> RpcError {
+    RpcError::server_error(
+        "Traveler profiles are unavailable: set TRAVELER_PROFILE_KEY, and check the database at BOOKEDAI_DB_PATH can be opened",
+    )
+}
+
+fn profiles_error(e: anyhow::Error) -> RpcError {
+    error!("Traveler profile error: {:#}", e);
+    errors::rpc_error("Traveler profile request failed", &e)
+}
+
+/// The saved details of `profile_id`; `field` names it in errors.
+pub async fn resolve(
+    profiles: Option<&TravelerProfiles>,
+    field: &str,
+    profile_id: &str,
+) -> Result<TravelerDetails, RpcError> {
+    let profile_id = validation::duffel_id(field, profile_id, profiles::ID_PREFIX)?;
+    let profiles = profiles.ok_or_else(unavailable)?;
+    let saved = profiles
+        .get::<TravelerDetails>(&profile_id)
+        .await
+        .map_err(profiles_error)?
+        .ok_or_else(|| {
+            RpcError::invalid_params(format!(
+                "Invalid parameters: {} {} is not a saved traveler profile; see list_traveler_profiles",
+                field, profile_id
+            ))
+        })?;
+    Ok(saved.profile)
+}
+
+fn details_schema() -> Value {
+    json!({
+        "title": { "type": "string", "enum": TITLES },
+        "given_name": { "type": "string" },
+        "family_name": { "type": "string" },
+        "born_on": { "type": "string", "description": "Date of birth in YYYY-MM-DD format" },
+        "gender": { "type": "string", "enum": ["m", "f"] },
+        "email": { "type": "string" },
+        "phone_number": { "type": "string", "description": "E.164 format, e.g. '+442080160509'" },
+        "passport": {
+            "type": "object",
+            "properties": {
+                "number": { "type": "string" },
+                "issuing_country_code": { "type": "string", "description": "Two-letter country code, e.g. 'GB'" },
+                "expires_on": { "type": "string", "description": "YYYY-MM-DD" }
+            },
+            "required": ["number", "issuing_country_code", "expires_on"]
+        },
+        "loyalty_accounts": {
+            "type": "array",
+            "description": "Frequent flyer accounts",
+            "items": {
+                "type": "object",
+                "properties": {
+                    "airline_iata_code": { "type": "string", "description": "Airline running the programme, e.g. 'BA'" },
+                    "account_number": { "type": "string" }
+                },
+                "required": ["airline_iata_code", "account_number"]
+            }
+        }
+    })
+}
+
+/// Passenger properties for `create_order`: the traveller's details, all optional with a profile.
+pub fn passenger_properties() -> Value {
+    details_schema()
+}
+
+fn profile_schema() -> Value {
+    let mut properties = details_schema();
+    properties["profile_id"] = json!({ "type": "string" });
+    properties["created_at"] = json!({ "type": "string" });
+    properties["updated_at"] = json!({ "type": "string" });
+    json!({
+        "type": "object",
+        "description": "Passport and loyalty numbers are masked to their last 4 characters, and born_on to its year (e.g. 1985-**-**)",
+        "properties": properties,
+        "required": ["profile_id", "title", "given_name", "family_name", "born_on", "gender", "email", "phone_number", "created_at", "updated_at"]
+    })
+}
+
+pub fn save_traveler_profile_tool() -> Tool {
+    let mut properties = details_schema();
+    properties["profile_id"] = json!({
+        "type": "string",
+        "description": "Profile to replace (starts with 'tvp_'); omit to create a new one"
+    });
+    Tool::new(
+        "save_traveler_profile",
+        "Save a traveler's details (name, date of birth, contact, passport, loyalty numbers) so later bookings \
+         can use them by profile_id. Stored encrypted",
+        json!({
+            "type": "object",
+            "properties": properties,
+            "required": ["title", "given_name", "family_name", "born_on", "gender", "email", "phone_number"]
+        }),
+    )
+    .with_output_schema(profile_schema())
+}
+
+pub fn list_traveler_profiles_tool() -> Tool {
+    Tool::new(
+        "list_traveler_profiles",
+        "List saved traveler profiles, most recently updated first, to book with their profile_id",
+        json!({
+            "type": "object",
+            "properties": {
+                "name": {
+                    "type": "string",
+                    "description": "Only travelers whose name contains this (case-insensitive)"
+                }
+            }
+        }),
+    )
+    .with_output_schema(json!({
+        "type": "object",
+        "properties": {
+            "profiles": { "type": "array", "items": profile_schema() }
+        },
+        "required": ["profiles"]
+    }))
+}
+
+pub async fn save_traveler_profile(profiles: Option<TravelerProfiles>, arguments: Value) -> Result<Value, RpcError> {
+    let mut request: SaveProfileRequest = mcp::parse_arguments("save_traveler_profile", arguments)?;
+    request.details.validate("")?;
+    let profile_id = request
+        .profile_id
+        .as_deref()
+        .map(|id| validation::duffel_id("profile_id", id, profiles::ID_PREFIX))
+        .transpose()?;
+    let Some(profiles) = profiles else {
+        return Err(unavailable());
+    };
+
+    let saved = match &profile_id {
+        Some(profile_id) => profiles
+            .update(profile_id, request.details)
+            .await
+            .map_err(profiles_error)?
+            .ok_or_else(|| {
+                RpcError::invalid_params(format!(
+                    "Invalid parameters: profile_id {} is not a saved traveler profile",
+                    profile_id
+                ))
+            })?,
+        None => profiles.create(request.details).await.map_err(profiles_error)?,
+    };
+    info!("Saved traveler profile {}", saved.id);
+
+    let summary = ProfileSummary::new(&saved);
+    let text = format!(
+        "Saved traveler profile:\n\n{}\nBook with {{\"profile_id\": \"{}\"}} as a create_order passenger.",
+        format_profile(&summary),
+        summary.profile_id
+    );
+    mcp::structured_content(text, &summary)
+}
+
+pub async fn list_traveler_profiles(profiles: Option<TravelerProfiles>, arguments: Value) -> Result<Value, RpcError> {
+    let request: ListProfilesRequest = mcp::parse_arguments("list_traveler_profiles", arguments)?;
+    let Some(profiles) = profiles else {
+        return Err(unavailable());
+    };
+
+    let name = request.name.map(|name| name.trim().to_lowercase()).filter(|name| !name.is_empty());
+    let saved = profiles.list::<TravelerDetails>().await.map_err(profiles_error)?;
+    let response = ProfileListResponse {
+        profiles: saved
+            .iter()
+            .filter(|saved| {
+                let full_name = format!("{} {}", saved.profile.given_name, saved.profile.family_name).to_lowercase();
+                name.as_ref().is_none_or(|name| full_name.contains(name.as_str()))
+            })
+            .map(ProfileSummary::new)
+            .collect(),
+    };
+
+    let text = if response.profiles.is_empty() {
+        "No traveler profiles found. Save one with save_traveler_profile.".to_string()
+    } else {
+        let mut text = format!("{} traveler profiles:\n\n", response.profiles.len());
+        for summary in &response.profiles {
+            text.push_str(&format_profile(summary));
+            text.push('\n');
+        }
+        text
+    };
+    mcp::structured_content(text, &response)
+}
+
+#[cfg(test)]
+mod tests {
+    use bookedai_core::store::Store;
+
+    use super::*;
+
+    fn ada() -> Value {
+        json!({
+            "title": "ms",
+            "given_name": "Ada",
+            "family_name": "Lovelace",
+            "born_on": "1985-12-10",
+            "gender": "f",
+            "email": "ada@example.com",
+            "phone_number": "+442080160509",
+            "passport": { "number": "X1234567", "issuing_country_code": "GB", "expires_on": "2099-01-01" },
+            "loyalty_accounts": [{ "airline_iata_code": "BA", "account_number": "12345678" }]
+        })
+    }
+
+    #[tokio::test]
+    async fn lists_profiles_with_passport_loyalty_and_birth_date_masked() {
+        let store = Store::open(":memory:").unwrap();
+        let profiles = TravelerProfiles::new(store, &[7; 32]).await.unwrap();
+        save_traveler_profile(Some(profiles.clone()), ada()).await.unwrap();
+
+        let result = list_traveler_profiles(Some(profiles.clone()), json!({})).await.unwrap();
+        let listed = &result["structuredContent"]["profiles"][0];
+        assert_eq!(listed["passport"]["number"], "****4567");
+        assert_eq!(listed["passport"]["issuing_country_code"], "GB");
+        assert_eq!(listed["loyalty_accounts"][0]["account_number"], "****5678");
+        assert_eq!(listed["born_on"], "1985-**-**");
+        let text = result.to_string();
+        for secret in ["X1234567", "12345678", "1985-12-10"] {
+            assert!(!text.contains(secret), "{} leaked: {}", secret, text);
+        }
+
+        // The stored details are whole, for booking
+        let saved = profiles.list::<TravelerDetails>().await.unwrap();
+        assert_eq!(saved[0].profile.born_on, "1985-12-10");
+        assert_eq!(saved[0].profile.passport.as_ref().unwrap().number, "X1234567");
+    }
+
+    #[tokio::test]
+    async fn refuses_profile_tools_without_a_key() {
+        let error = list_traveler_profiles(None, json!({})).await.unwrap_err();
+        assert!(error.message.contains("TRAVELER_PROFILE_KEY"), "{}", error.message);
+    }
+}
@@ -0,0 +1,196 @@
+//! `/mcp` end to end: the flights server over HTTP against a fake Duffel.
+
+use bookedai_testing::{duffel_error, FakeDuffel, TestServer, API_TOKEN};
+use serde_json::{json, Value};
+
+const BINARY: &str = env!("CARGO_BIN_EXE_mcp_duffel_flights");
+
+fn search() -> Value {
+    json!({ "origin": "LHR", "destination": "JFK", "departure_date": "2030-06-01", "adults": 1 })
+}
+
+async fn started(duffel: &FakeDuffel) -> TestServer {
+    let mut server = TestServer::start(BINARY, duffel, &[]).await;
+    let result = server.initialize().await;
+    assert!(result["serverInfo"]["name"].is_string(), "initialize result: {}", result);
+    server
+}
+
+#[tokio::test]
+async fn lists_tools_and_searches_flights() {
+    let duffel = FakeDuffel::start().await;
+    let mut server = started(&duffel).await;
+
+    let tools = server.tool_names().await;
+    assert!(tools.iter().any(|name| name == "search_flights"), "tools: {:?}", tools);
+
+    let response = server.call_tool("search_flights", search()).await;
+    let results = &response["result"]["structuredContent"];
+    let offers = results["offers"].as_array().unwrap_or_else(|| panic!("search response: {}", response));
+    assert!(!offers.is_empty());
+    assert!(results["search_id"].is_string());
+    // The only page holds every offer, so counting them takes no further requests
+    assert_eq!(results["available_results"], json!(offers.len()));
+    assert_eq!(duffel.requests_to("/air/offers").await.len(), 1);
+
+    let sent = duffel.requests_to("/air/offer_requests").await;
+    assert_eq!(sent.len(), 1);
+    let headers = &sent[0].headers;
+    assert_eq!(headers.get("authorization").unwrap(), &format!("Bearer {}", API_TOKEN));
+    assert_eq!(headers.get("duffel-version").unwrap(), "v2");
+}
+
+async fn error_code(status: u16, error_type: &str, code: &str) -> Value {
+    let duffel = FakeDuffel::start().await;
+    duffel
+        .fail("POST", "/air/offer_requests", status, duffel_error(error_type, code, "Rejected by the fake"))
+        .await;
+    let mut server = started(&duffel).await;
+    let response = server.call_tool("search_flights", search()).await;
+    assert!(response["result"].is_null(), "expected an error: {}", response);
+    response["error"]["code"].clone()
+}
+
+#[tokio::test]
+async fn maps_duffel_validation_errors() {
+    assert_eq!(error_code(422, "validation_error", "invalid_field").await, json!(-32010));
+}
+
+#[tokio::test]
+async fn maps_duffel_rate_limits() {
+    assert_eq!(error_code(429, "rate_limit_error", "rate_limit_exceeded").await, json!(-32012));
+}
+
+#[tokio::test]
+async fn maps_duffel_outages() {
+    assert_eq!(error_code(500, "api_error", "internal_server_error").await, json!(-32016));
+}
+
+#[tokio::test]
+async fn offers_the_audit_log_only_when_enabled() {
+    let duffel = FakeDuffel::start().await;
+    let mut server = started(&duffel).await;
+    assert!(!server.tool_names().await.iter().any(|name| name == "query_audit_log"));
+    let response = server.call_tool("query_audit_log", json!({})).await;
+    assert!(response["error"].is_object(), "{}", response);
+
+    let mut admin = TestServer::start(BINARY, &duffel, &[("AUDIT_QUERY_TOOL_ENABLED", "true")]).await;
+    admin.initialize().await;
+    assert!(admin.tool_names().await.iter().any(|name| name == "query_audit_log"));
+    admin.call_tool("search_flights", search()).await;
+    let response = admin.call_tool("query_audit_log", json!({ "tool": "search_flights" })).await;
+    let entries = response["result"]["structuredContent"]["entries"].as_array().unwrap_or_else(|| panic!("{}", response));
+    assert_eq!(entries.len(), 1);
+}
+
+#[tokio::test]
+async fn sends_progress_of_json_answers_to_the_session_stream() {
+    let duffel = FakeDuffel::start().await;
+    let mut server = started(&duffel).await;
+    let mut stream = server.event_stream().await;
+
+    let response = server
+        .request(
+            "tools/call",
+            json!({ "name": "search_flights", "arguments": search(), "_meta": { "progressToken": "search-1" } }),
+        )
+        .await;
+    assert!(response["result"].is_object(), "{}", response);
+
+    let received = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+        let mut received = String::new();
+        while !received.contains("notifications/progress") {
+            let chunk = stream.chunk().await.unwrap().expect("the stream stays open");
+            received.push_str(&String::from_utf8_lossy(&chunk));
+        }
+        received
+    })
+    .await
+    .expect("progress arrives on the GET stream");
+    assert!(received.contains("search-1"), "{}", received);
+}
+
+fn booking(idempotency_key: &str) -> Value {
+    json!({
+        "offer_id": "off_0000AmockOfferBritish01",
+        "passengers": [{
+            "title": "ms",
+            "given_name": "Amelia",
+            "family_name": "Earhart",
+            "born_on": "1987-07-24",
+            "gender": "f",
+            "email": "amelia@example.com",
+            "phone_number": "+442080160509"
+        }],
+        "idempotency_key": idempotency_key
+    })
+}
+
+#[tokio::test]
+async fn replays_a_booking_made_with_the_same_key() {
+    let duffel = FakeDuffel::start().await;
+    let mut server = started(&duffel).await;
+
+    let first = server.call_tool("create_order", booking("trip-42")).await;
+    let booked = &first["result"]["structuredContent"];
+    assert_eq!(booked["replayed"], json!(false), "{}", first);
+    let second = server.call_tool("create_order", booking("trip-42")).await;
+    let replayed = &second["result"]["structuredContent"];
+    assert_eq!(replayed["replayed"], json!(true), "{}", second);
+    assert_eq!(replayed["id"], booked["id"]);
+
+    let sent = duffel.requests_to("/air/orders").await;
+    assert_eq!(sent.len(), 1, "the replay books nothing");
+    assert_eq!(sent[0].headers.get("idempotency-key").unwrap(), "trip-42");
+}
+
+#[tokio::test]
+async fn failed_bookings_release_their_key() {
+    let duffel = FakeDuffel::start().await;
+    duffel
+        .fail_once("POST", "/air/orders", 422, duffel_error("validation_error", "offer_no_longer_available", "Gone"))
+        .await;
+    let mut server = started(&duffel).await;
+
+    let failed = server.call_tool("create_order", booking("trip-43")).await;
+    assert!(failed["result"].is_null(), "expected an error: {}", failed);
+    // Neither "in progress" nor a replay: the retry books
+    let retried = server.call_tool("create_order", booking("trip-43")).await;
+    assert_eq!(retried["result"]["structuredContent"]["replayed"], json!(false), "{}", retried);
+
+    let sent = duffel.requests_to("/air/orders").await;
+    assert_eq!(sent.len(), 2);
+    assert!(sent.iter().all(|request| request.headers.get("idempotency-key").unwrap() == "trip-43"));
+}
+
+#[tokio::test]
+async fn charges_the_rate_limit_per_batch_entry() {
+    let duffel = FakeDuffel::start().await;
+    let limits = [("RATE_LIMIT_PER_MINUTE", "1"), ("RATE_LIMIT_BURST", "4")];
+    let mut server = TestServer::start(BINARY, &duffel, &limits).await;
+    server.initialize().await;
+
+    let (status, body) = server.batch(&vec![("tools/list", json!({})); 5]).await;
+    assert_eq!(status, 429, "a batch over the burst is refused: {}", body);
+    let (status, body) = server.batch(&vec![("tools/list", json!({})); 3]).await;
+    assert_eq!(status, 200, "{}", body);
+    // `initialize` and the three entries used the whole burst
+    let response = server.request("tools/list", json!({})).await;
+    assert_eq!(response["error"]["code"], json!(-32029), "{}", response);
+}
+
+#[tokio::test]
+async fn reads_error_bodies_only_up_to_the_response_limit() {
+    let duffel = FakeDuffel::start().await;
+    let padding = "x".repeat(64 * 1024);
+    duffel
+        .fail("POST", "/air/offer_requests", 422, duffel_error("validation_error", "invalid_field", &padding))
+        .await;
+    let mut server = TestServer::start(BINARY, &duffel, &[("DUFFEL_MAX_RESPONSE_BYTES", "1024")]).await;
+    server.initialize().await;
+
+    let response = server.call_tool("search_flights", search()).await;
+    // The cut body is no longer JSON, so the status alone sets the code
+    assert_eq!(response["error"]["code"], json!(-32010), "{}", response);
+    assert!(response.to_string().len() < 8 * 1024, "the error repeats the whole body: {}", response);
+}
@@ -0,0 +1,111 @@
+//! Searching specific hotels by name or chain: Duffel's accommodation suggestions
+//! turn the name into accommodation IDs, which the stay search then prices alone.
+
+use bookedai_core::duffel_models::{self, AccommodationSuggestion};
+use bookedai_core::{errors, progress, RpcError};
+use serde_json::json;
+use tracing::{error, info};
+
+use crate::{DuffelStayServer, StaySearchRequest, DEFAULT_RADIUS_KM};
+
+/// Most hotels of one chain searched at once.
+const MAX_CHAIN_HOTELS: usize = 20;
+
+/// Hotels to search instead of the area around `location`.
+#[derive(Debug, Clone)]
+pub struct HotelSelection {
+    pub suggestions: Vec<AccommodationSuggestion>,
+    /// What was looked up, for messages: a hotel name or a chain.
+    pub query: String,
+}
+
+impl HotelSelection {
+    pub fn ids(&self) -> Vec<&str> {
+        self.suggestions
+            .iter()
+            .map(|suggestion| suggestion.accommodation_id.as_str())
+            .collect()
+    }
+
+    /// Where the search looked, as result messages show it.
+    pub fn describe(&self, request: &StaySearchRequest) -> String {
+        match (&request.accommodation_name, self.suggestions.first()) {
+            (Some(_), Some(hotel)) => {
+                let city = hotel
+                    .accommodation_location
+                    .as_ref()
+                    .and_then(|location| location.address.as_ref())
+                    .and_then(|address| address.city_name.as_deref());
+                match city {
+                    Some(city) => format!("{}, {}", hotel.accommodation_name, city),
+                    None => hotel.accommodation_name.clone(),
+                }
+            }
+            _ => format!("{} hotels in {}", self.query, request.location),
+        }
+    }
+}
+
+/// The hotels `accommodation_name` or `chain` names, or `None` for an area search.
+///
+/// A name resolves to its best match alone; a chain to its hotels near `location`,
+/// which `StayFilters` then holds to the chain.
+pub async fn resolve(
+    stays: &DuffelStayServer,
+    request: &StaySearchRequest,
+) -> Result<Option<HotelSelection>, RpcError> {
+    let (query, limit) = match (&request.accommodation_name, &request.filters.chain) {
+        (Some(name), _) => (name.trim(), 1),
+        (None, Some(chain)) => (chain.trim(), MAX_CHAIN_HOTELS),
+        (None, None) => return Ok(None),
+    };
+
+    let mut data = json!({ "query": query });
+    if !request.location.trim().is_empty() {
+        let (latitude, longitude) = stays.geocode_location(&request.location)?;
+        data["location"] = json!({
+            "radius": request.radius_km.unwrap_or(DEFAULT_RADIUS_KM),
+            "geographic_coordinates": { "latitude": latitude, "longitude": longitude }
+        });
+    }
+
+    progress::report(format!("Looking up hotels matching '{}'", query));
+    let response = stays
+        .duffel
+        .post("/stays/accommodation/suggestions", &json!({ "data": data }))
+        .await
+        .map_err(|e| {
+            error!("Accommodation suggestions error: {}", e);
+            errors::rpc_error("Hotel lookup failed", &e)
+        })?;
+    let items = response["data"].as_array().map(Vec::as_slice).unwrap_or_default();
+    let (suggestions, _) = duffel_models::parse_each::<AccommodationSuggestion>(items, "accommodation suggestion");
+    let suggestions: Vec<AccommodationSuggestion> = suggestions.into_iter().take(limit).collect();
+    if suggestions.is_empty() {
+        let field = if request.accommodation_name.is_some() { "accommodation_name" } else { "chain" };
+        let near = if request.location.trim().is_empty() {
+            String::new()
+        } else {
+            format!(" near {}", request.location)
+        };
+        return Err(RpcError::invalid_params(format!(
+            "Invalid parameters: no hotel matching {} '{}' was found{}; check the spelling, or search by location alone",
+            field, query, near
+        )));
+    }
+    info!(
+        "'{}' matched {}",
+        query,
+        suggestions
+            .iter()
+            .map(|suggestion| format!("{} ({})", suggestion.accommodation_name, suggestion.accommodation_id))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    Ok(Some(HotelSelection {
+        suggestions,
+        query: query.to_string(),
+    }))
+}
+
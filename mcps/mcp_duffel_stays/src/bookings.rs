@@ -0,0 +1,497 @@
+//! Booked stays: lookup, confirmations, and cancellation with the refund the guest
+//! would receive, and `booking://stays/{booking_id}` resources.
+
+use anyhow::Result;
+use bookedai_core::notifications::{self, Confirmation, Delivery};
+use bookedai_core::resources::{Resource, ResourceTemplate};
+use bookedai_core::{errors, mcp, validation, RpcError, Tool};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{error, info};
+
+use crate::{filters, DuffelStayServer};
+
+/// Bookings listed by `resources/list`.
+const RECENT_BOOKING_RESOURCES: usize = 20;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BookingLookupRequest {
+    booking_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CancelBookingRequest {
+    booking_id: String,
+    /// Without this, only the refund that would apply is reported.
+    confirm: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SendConfirmationRequest {
+    booking_id: String,
+}
+
+/// Where a booking's confirmation went.
+#[derive(Debug, Serialize, Deserialize)]
+struct ConfirmationReceipt {
+    booking_id: String,
+    recipients: Vec<String>,
+    deliveries: Vec<Delivery>,
+}
+
+/// Refund available when cancelling before a deadline.
+#[derive(Debug, Serialize, Deserialize)]
+struct RefundWindow {
+    refund_amount: String,
+    currency: String,
+    before: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StayBooking {
+    id: String,
+    reference: Option<String>,
+    status: String,
+    hotel_name: String,
+    check_in_date: String,
+    check_out_date: String,
+    total_amount: Option<String>,
+    currency: Option<String>,
+    guests: Vec<String>,
+    cancellation_timeline: Vec<RefundWindow>,
+    cancelled_at: Option<String>,
+    /// The lead guest's email address, for confirmations; not part of the tool output.
+    #[serde(skip)]
+    email: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BookingCancellation {
+    booking: StayBooking,
+    /// `false` when this is only a preview of the refund.
+    cancelled: bool,
+    refund_amount: Option<String>,
+    refund_currency: Option<String>,
+    /// Cancel before this time to receive `refund_amount`.
+    refund_deadline: Option<String>,
+}
+
+impl StayBooking {
+    /// The refund window that applies right now, if any.
+    fn current_refund(&self) -> Option<&RefundWindow> {
+        let now = Utc::now();
+        self.cancellation_timeline
+            .iter()
+            .filter_map(|window| {
+                let before = DateTime::parse_from_rfc3339(&window.before).ok()?;
+                (before > now).then_some((before, window))
+            })
+            .min_by_key(|(before, _)| *before)
+            .map(|(_, window)| window)
+    }
+}
+
+impl DuffelStayServer {
+    async fn get_booking(&self, booking_id: &str) -> Result<StayBooking> {
+        let response = self.duffel.get(&format!("/stays/bookings/{}", booking_id)).await?;
+        parse_booking(&response["data"]).ok_or_else(|| anyhow::anyhow!("No booking data in response"))
+    }
+
+    /// The most recent bookings, newest first.
+    async fn list_bookings(&self, limit: usize) -> Result<Vec<StayBooking>> {
+        let query = [("limit", limit.to_string())];
+        let response = self.duffel.get_with_query("/stays/bookings", &query).await?;
+        Ok(response["data"]
+            .as_array()
+            .ok_or_else(|| anyhow::anyhow!("No bookings data in response"))?
+            .iter()
+            .filter_map(parse_booking)
+            .collect())
+    }
+
+    async fn cancel_booking(&self, booking_id: &str, confirm: bool) -> Result<BookingCancellation> {
+        let booking = self.get_booking(booking_id).await?;
+        let refund = booking.current_refund();
+        let refund_amount = refund.map(|window| window.refund_amount.clone());
+        let refund_currency = refund.map(|window| window.currency.clone());
+        let refund_deadline = refund.map(|window| window.before.clone());
+
+        if !confirm || booking.status == "cancelled" {
+            return Ok(BookingCancellation {
+                cancelled: booking.status == "cancelled",
+                booking,
+                refund_amount,
+                refund_currency,
+                refund_deadline,
+            });
+        }
+
+        info!("Cancelling stay booking {}", booking_id);
+        let path = format!("/stays/bookings/{}/actions/cancel", booking_id);
+        let response = self.duffel.post(&path, &json!({})).await?;
+        let booking = parse_booking(&response["data"])
+            .ok_or_else(|| anyhow::anyhow!("No booking data in response"))?;
+
+        Ok(BookingCancellation {
+            cancelled: booking.status == "cancelled",
+            booking,
+            refund_amount,
+            refund_currency,
+            refund_deadline,
+        })
+    }
+
+    fn format_booking(&self, booking: &StayBooking) -> String {
+        let mut result = format!(
+            "Booking {} ({}) - {}\n",
+            booking.reference.as_deref().unwrap_or(&booking.id),
+            booking.status,
+            booking.hotel_name
+        );
+        result.push_str(&format!(
+            "   Dates: {} to {}\n",
+            booking.check_in_date, booking.check_out_date
+        ));
+        if let (Some(amount), Some(currency)) = (&booking.total_amount, &booking.currency) {
+            result.push_str(&format!("   Total: {} {}\n", amount, currency));
+        }
+        if !booking.guests.is_empty() {
+            result.push_str(&format!("   Guests: {}\n", booking.guests.join(", ")));
+        }
+        if let Some(cancelled_at) = &booking.cancelled_at {
+            result.push_str(&format!("   Cancelled at: {}\n", cancelled_at));
+        } else if booking.cancellation_timeline.is_empty() {
+            result.push_str("   Non-refundable\n");
+        } else {
+            for window in &booking.cancellation_timeline {
+                result.push_str(&format!(
+                    "   Refund {} {} if cancelled before {}\n",
+                    window.refund_amount, window.currency, window.before
+                ));
+            }
+        }
+        result.push_str(&format!("   Booking ID: {}\n", booking.id));
+        result
+    }
+
+    fn format_cancellation(&self, cancellation: &BookingCancellation) -> String {
+        let refund = match (&cancellation.refund_amount, &cancellation.refund_currency) {
+            (Some(amount), Some(currency)) => format!("{} {}", amount, currency),
+            _ => "no refund".to_string(),
+        };
+
+        if cancellation.cancelled {
+            return format!(
+                "Booking {} is cancelled. Refund: {}.\n\n{}",
+                cancellation.booking.id,
+                refund,
+                self.format_booking(&cancellation.booking)
+            );
+        }
+
+        let mut result = format!(
+            "Cancelling booking {} now would refund {}.\n",
+            cancellation.booking.id, refund
+        );
+        if let Some(deadline) = &cancellation.refund_deadline {
+            result.push_str(&format!("This refund applies until {}.\n", deadline));
+        }
+        result.push_str("The booking is NOT cancelled yet. Call cancel_booking again with confirm: true to cancel.");
+        result
+    }
+}
+
+fn parse_booking(booking: &Value) -> Option<StayBooking> {
+    let accommodation = &booking["accommodation"];
+    let rates: Vec<&Value> = accommodation["rooms"]
+        .as_array()
+        .map(|rooms| {
+            rooms
+                .iter()
+                .filter_map(|room| room["rates"].as_array())
+                .flatten()
+                .collect()
+        })
+        .unwrap_or_default();
+    let rate = rates.first();
+
+    let cancellation_timeline = rate
+        .and_then(|rate| rate["cancellation_timeline"].as_array())
+        .map(|timeline| {
+            timeline
+                .iter()
+                .filter_map(|window| {
+                    Some(RefundWindow {
+                        refund_amount: window["refund_amount"].as_str()?.to_string(),
+                        currency: window["currency"].as_str()?.to_string(),
+                        before: window["before"].as_str()?.to_string(),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let guests = booking["guests"]
+        .as_array()
+        .map(|guests| {
+            guests
+                .iter()
+                .filter_map(|guest| {
+                    let given_name = guest["given_name"].as_str()?;
+                    let family_name = guest["family_name"].as_str()?;
+                    Some(format!("{} {}", given_name, family_name))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Some(StayBooking {
+        id: booking["id"].as_str()?.to_string(),
+        reference: booking["reference"].as_str().map(|s| s.to_string()),
+        status: booking["status"].as_str().unwrap_or("confirmed").to_string(),
+        hotel_name: accommodation["name"].as_str().unwrap_or("Unknown Hotel").to_string(),
+        check_in_date: booking["check_in_date"].as_str()?.to_string(),
+        check_out_date: booking["check_out_date"].as_str()?.to_string(),
+        total_amount: rate.and_then(|rate| rate["total_amount"].as_str()).map(|s| s.to_string()),
+        currency: rate.and_then(|rate| rate["total_currency"].as_str()).map(|s| s.to_string()),
+        guests,
+        cancellation_timeline,
+        cancelled_at: booking["cancelled_at"].as_str().map(|s| s.to_string()),
+        email: booking["email"].as_str().map(|s| s.to_string()),
+    })
+}
+
+fn booking_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "id": { "type": "string" },
+            "reference": { "type": ["string", "null"] },
+            "status": { "type": "string" },
+            "hotel_name": { "type": "string" },
+            "check_in_date": { "type": "string" },
+            "check_out_date": { "type": "string" },
+            "total_amount": { "type": ["string", "null"] },
+            "currency": { "type": ["string", "null"] },
+            "guests": { "type": "array", "items": { "type": "string" } },
+            "cancellation_timeline": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "refund_amount": { "type": "string" },
+                        "currency": { "type": "string" },
+                        "before": { "type": "string" }
+                    },
+                    "required": ["refund_amount", "currency", "before"]
+                }
+            },
+            "cancelled_at": { "type": ["string", "null"] }
+        },
+        "required": [
+            "id", "status", "hotel_name", "check_in_date", "check_out_date",
+            "guests", "cancellation_timeline"
+        ]
+    })
+}
+
+fn booking_id_property() -> Value {
+    json!({
+        "type": "string",
+        "description": "Duffel Stays booking ID (starts with 'bok_')"
+    })
+}
+
+pub fn get_booking_tool() -> Tool {
+    Tool::new(
+        "get_booking",
+        "Get a booked stay, including its cancellation refund timeline",
+        json!({
+            "type": "object",
+            "properties": {
+                "booking_id": booking_id_property()
+            },
+            "required": ["booking_id"]
+        }),
+    )
+    .with_output_schema(booking_schema())
+}
+
+pub fn cancel_booking_tool() -> Tool {
+    Tool::new(
+        "cancel_booking",
+        "Cancel a booked stay. Without confirm: true this only reports the refund that would apply",
+        json!({
+            "type": "object",
+            "properties": {
+                "booking_id": booking_id_property(),
+                "confirm": {
+                    "type": "boolean",
+                    "description": "Set to true to actually cancel (default: false, preview only)"
+                }
+            },
+            "required": ["booking_id"]
+        }),
+    )
+    .with_output_schema(json!({
+        "type": "object",
+        "properties": {
+            "booking": booking_schema(),
+            "cancelled": { "type": "boolean" },
+            "refund_amount": { "type": ["string", "null"] },
+            "refund_currency": { "type": ["string", "null"] },
+            "refund_deadline": { "type": ["string", "null"] }
+        },
+        "required": ["booking", "cancelled"]
+    }))
+}
+
+pub fn send_confirmation_tool() -> Tool {
+    Tool::new(
+        "send_confirmation",
+        "Send a booking confirmation for a stay (hotel, dates, booking reference, guests) \
+         by the email and webhook channels the server is configured with",
+        json!({
+            "type": "object",
+            "properties": {
+                "booking_id": booking_id_property()
+            },
+            "required": ["booking_id"]
+        }),
+    )
+    .with_output_schema(notifications::deliveries_schema("booking_id"))
+}
+
+pub fn booking_resource_template() -> ResourceTemplate {
+    ResourceTemplate::new(
+        "booking://stays/{booking_id}",
+        "stay-booking",
+        "A booked Duffel stay, as returned by get_booking",
+    )
+}
+
+/// The most recent confirmed bookings, as resources.
+pub async fn list_booking_resources(stays: DuffelStayServer) -> Result<Vec<Resource>, RpcError> {
+    let template = booking_resource_template();
+    let bookings = stays
+        .list_bookings(RECENT_BOOKING_RESOURCES)
+        .await
+        .map_err(booking_error)?;
+
+    Ok(bookings
+        .into_iter()
+        .filter(|booking| booking.status == "confirmed")
+        .map(|booking| {
+            Resource::new(template.uri(&booking.id), booking.id.clone())
+                .with_title(format!(
+                    "Stay booking {}",
+                    booking.reference.as_deref().unwrap_or(&booking.id)
+                ))
+                .with_description(format!(
+                    "{}, {} to {}",
+                    booking.hotel_name, booking.check_in_date, booking.check_out_date
+                ))
+        })
+        .collect())
+}
+
+pub async fn read_booking_resource(stays: DuffelStayServer, booking_id: String) -> Result<Value, RpcError> {
+    // Anything that is not a booking ID cannot name a resource
+    let Ok(booking_id) = validation::duffel_id("booking_id", &booking_id, "bok_") else {
+        return Ok(Value::Null);
+    };
+    let booking = stays.get_booking(&booking_id).await.map_err(booking_error)?;
+    serde_json::to_value(booking).map_err(|e| RpcError::server_error(format!("Failed to serialize booking: {}", e)))
+}
+
+/// The booking as a confirmation message, addressed to `recipients`.
+fn confirmation_message(booking: &StayBooking, recipients: Vec<String>) -> Confirmation {
+    let nights = filters::nights(&booking.check_in_date, &booking.check_out_date);
+    let mut details = vec![
+        format!("Check-in: {}", booking.check_in_date),
+        format!(
+            "Check-out: {} ({} night{})",
+            booking.check_out_date,
+            nights,
+            if nights == 1 { "" } else { "s" }
+        ),
+    ];
+    if !booking.guests.is_empty() {
+        details.push(format!("Guests: {}", booking.guests.join(", ")));
+    }
+    if let (Some(amount), Some(currency)) = (&booking.total_amount, &booking.currency) {
+        details.push(format!("Total: {} {}", amount, currency));
+    }
+    if let Some(window) = booking.current_refund() {
+        details.push(format!(
+            "Free cancellation: {} {} refunded if cancelled before {}",
+            window.refund_amount, window.currency, window.before
+        ));
+    }
+
+    Confirmation {
+        kind: "stay_booking",
+        booking_id: booking.id.clone(),
+        reference: booking.reference.clone(),
+        title: format!(
+            "{}, {} to {}",
+            booking.hotel_name, booking.check_in_date, booking.check_out_date
+        ),
+        details,
+        recipients,
+        booking: serde_json::to_value(booking).unwrap_or_default(),
+    }
+}
+
+fn booking_error(e: anyhow::Error) -> RpcError {
+    error!("Booking error: {}", e);
+    errors::rpc_error("Booking request failed", &e)
+}
+
+pub async fn get_booking(stays: DuffelStayServer, arguments: Value) -> Result<Value, RpcError> {
+    let request: BookingLookupRequest = mcp::parse_arguments("get_booking", arguments)?;
+    let booking_id = validation::duffel_id("booking_id", &request.booking_id, "bok_")?;
+
+    let booking = stays.get_booking(&booking_id).await.map_err(booking_error)?;
+    mcp::structured_content(stays.format_booking(&booking), &booking)
+}
+
+pub async fn cancel_booking(stays: DuffelStayServer, arguments: Value) -> Result<Value, RpcError> {
+    let request: CancelBookingRequest = mcp::parse_arguments("cancel_booking", arguments)?;
+    let booking_id = validation::duffel_id("booking_id", &request.booking_id, "bok_")?;
+
+    let cancellation = stays
+        .cancel_booking(&booking_id, request.confirm.unwrap_or(false))
+        .await
+        .map_err(booking_error)?;
+    mcp::structured_content(stays.format_cancellation(&cancellation), &cancellation)
+}
+
+pub async fn send_confirmation(stays: DuffelStayServer, arguments: Value) -> Result<Value, RpcError> {
+    let request: SendConfirmationRequest = mcp::parse_arguments("send_confirmation", arguments)?;
+    let booking_id = validation::duffel_id("booking_id", &request.booking_id, "bok_")?;
+
+    let booking = stays.get_booking(&booking_id).await.map_err(booking_error)?;
+    if booking.status == "cancelled" {
+        return Err(RpcError::invalid_params(format!(
+            "Invalid parameters: booking {} is cancelled; there is no stay to confirm",
+            booking.id
+        )));
+    }
+    // Only the address on the booking, so the tool cannot send guest details elsewhere
+    let recipients: Vec<String> = booking.email.clone().into_iter().collect();
+    let deliveries = stays.notifier.send_requested(&confirmation_message(&booking, recipients.clone())).await?;
+
+    let receipt = ConfirmationReceipt {
+        booking_id: booking.id,
+        recipients,
+        deliveries,
+    };
+    let text = format!(
+        "Confirmation for booking {}:\n{}",
+        receipt.booking_id,
+        notifications::describe(&receipt.deliveries)
+    );
+    mcp::structured_content(text, &receipt)
+}
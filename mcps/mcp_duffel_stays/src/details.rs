@@ -0,0 +1,243 @@
+//! Full accommodation details: description, photos, check-in times, every amenity,
+//! and contact details, which search results leave out.
+
+use anyhow::Result;
+use bookedai_core::duffel_models::{Accommodation, StaysSearchResult};
+use bookedai_core::{errors, mcp, RpcError, Tool};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::error;
+
+use crate::DuffelStayServer;
+
+/// Photo URLs listed in the text response; all of them are in `structuredContent`.
+const PHOTOS_SHOWN: usize = 5;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StayDetailsRequest {
+    /// An accommodation ID (`acc_`) or a search result ID (`srr_`).
+    id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StayAddress {
+    line_one: Option<String>,
+    city_name: Option<String>,
+    region: Option<String>,
+    postal_code: Option<String>,
+    country_code: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StayDetails {
+    accommodation_id: Option<String>,
+    /// Set when looked up by search result ID.
+    search_result_id: Option<String>,
+    name: String,
+    description: Option<String>,
+    rating: Option<f64>,
+    review_score: Option<f64>,
+    photos: Vec<String>,
+    /// Local time from which guests can check in, e.g. `15:00`.
+    check_in_after: Option<String>,
+    check_in_before: Option<String>,
+    check_out_before: Option<String>,
+    amenities: Vec<String>,
+    address: Option<StayAddress>,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+    phone_number: Option<String>,
+    email: Option<String>,
+}
+
+impl StayDetails {
+    fn from_duffel(accommodation: Accommodation, search_result_id: Option<String>) -> Self {
+        let location = accommodation.location.as_ref();
+        let coordinates = location.and_then(|location| location.geographic_coordinates.as_ref());
+        let check_in = accommodation.check_in_information.as_ref();
+
+        Self {
+            accommodation_id: accommodation.id.clone(),
+            search_result_id,
+            name: accommodation.name.clone(),
+            description: accommodation.description.clone(),
+            rating: accommodation.rating,
+            review_score: accommodation.review_score,
+            photos: accommodation.photos.iter().map(|photo| photo.url.clone()).collect(),
+            check_in_after: check_in.and_then(|info| info.check_in_after_time.clone()),
+            check_in_before: check_in.and_then(|info| info.check_in_before_time.clone()),
+            check_out_before: check_in.and_then(|info| info.check_out_before_time.clone()),
+            amenities: accommodation
+                .amenities
+                .iter()
+                .filter_map(|amenity| amenity.description.clone().or_else(|| amenity.amenity_type.clone()))
+                .collect(),
+            address: location
+                .and_then(|location| location.address.as_ref())
+                .map(|address| StayAddress {
+                    line_one: address.line_one.clone(),
+                    city_name: address.city_name.clone(),
+                    region: address.region.clone(),
+                    postal_code: address.postal_code.clone(),
+                    country_code: address.country_code.clone(),
+                }),
+            latitude: coordinates.map(|coordinates| coordinates.latitude),
+            longitude: coordinates.map(|coordinates| coordinates.longitude),
+            phone_number: accommodation.phone_number.clone(),
+            email: accommodation.email.clone(),
+        }
+    }
+}
+
+impl DuffelStayServer {
+    async fn get_accommodation(&self, accommodation_id: &str) -> Result<StayDetails> {
+        let response = self
+            .duffel
+            .get(&format!("/stays/accommodation/{}", accommodation_id))
+            .await?;
+        let accommodation = Accommodation::deserialize(&response["data"])?;
+        Ok(StayDetails::from_duffel(accommodation, None))
+    }
+
+    /// Details of the hotel behind a search result, via its full rate listing.
+    async fn get_search_result_accommodation(&self, search_result_id: &str) -> Result<StayDetails> {
+        let path = format!("/stays/search_results/{}/actions/fetch_all_rates", search_result_id);
+        let response = self.duffel.post(&path, &json!({})).await?;
+        let result = StaysSearchResult::deserialize(&response["data"])?;
+        Ok(StayDetails::from_duffel(result.accommodation, Some(search_result_id.to_string())))
+    }
+
+    fn format_details(&self, details: &StayDetails) -> String {
+        let mut result = details.name.clone();
+        if let Some(rating) = details.rating {
+            result.push_str(&format!(" ({:.0}-star)", rating));
+        }
+        result.push('\n');
+        if let Some(score) = details.review_score {
+            result.push_str(&format!("   Guest rating: {:.1}/10\n", score));
+        }
+        if let Some(address) = &details.address {
+            let parts: Vec<&str> = [
+                &address.line_one,
+                &address.city_name,
+                &address.region,
+                &address.postal_code,
+                &address.country_code,
+            ]
+            .into_iter()
+            .filter_map(|part| part.as_deref())
+            .collect();
+            if !parts.is_empty() {
+                result.push_str(&format!("   Address: {}\n", parts.join(", ")));
+            }
+        }
+        if let (Some(latitude), Some(longitude)) = (details.latitude, details.longitude) {
+            result.push_str(&format!("   Coordinates: {:.5}, {:.5}\n", latitude, longitude));
+        }
+        match (&details.check_in_after, &details.check_out_before) {
+            (Some(check_in), Some(check_out)) => {
+                result.push_str(&format!("   Check-in from {}, check-out by {}\n", check_in, check_out))
+            }
+            (Some(check_in), None) => result.push_str(&format!("   Check-in from {}\n", check_in)),
+            (None, Some(check_out)) => result.push_str(&format!("   Check-out by {}\n", check_out)),
+            (None, None) => {}
+        }
+        if let Some(phone_number) = &details.phone_number {
+            result.push_str(&format!("   Phone: {}\n", phone_number));
+        }
+        if let Some(email) = &details.email {
+            result.push_str(&format!("   Email: {}\n", email));
+        }
+        if !details.amenities.is_empty() {
+            result.push_str(&format!("   Amenities: {}\n", details.amenities.join(", ")));
+        }
+        if let Some(description) = &details.description {
+            result.push_str(&format!("\n{}\n", description));
+        }
+        if !details.photos.is_empty() {
+            result.push_str(&format!("\nPhotos ({}):\n", details.photos.len()));
+            for url in details.photos.iter().take(PHOTOS_SHOWN) {
+                result.push_str(&format!("   {}\n", url));
+            }
+        }
+        if let Some(id) = &details.accommodation_id {
+            result.push_str(&format!("\nAccommodation ID: {}", id));
+        }
+        result
+    }
+}
+
+pub fn get_stay_details_tool() -> Tool {
+    Tool::new(
+        "get_stay_details",
+        "Get full details of a hotel from a stay search: description, photos, check-in and check-out times, every amenity, address, and coordinates",
+        json!({
+            "type": "object",
+            "properties": {
+                "id": {
+                    "type": "string",
+                    "description": "Search result ID from search_stays (starts with 'srr_') or accommodation ID (starts with 'acc_')"
+                }
+            },
+            "required": ["id"]
+        }),
+    )
+    .cacheable()
+    .with_output_schema(json!({
+        "type": "object",
+        "properties": {
+            "accommodation_id": { "type": ["string", "null"] },
+            "search_result_id": { "type": ["string", "null"] },
+            "name": { "type": "string" },
+            "description": { "type": ["string", "null"] },
+            "rating": { "type": ["number", "null"], "description": "Star rating, 1-5" },
+            "review_score": { "type": ["number", "null"], "description": "Guest review score out of 10" },
+            "photos": { "type": "array", "items": { "type": "string" }, "description": "Photo URLs" },
+            "check_in_after": { "type": ["string", "null"], "description": "Local time, e.g. 15:00" },
+            "check_in_before": { "type": ["string", "null"] },
+            "check_out_before": { "type": ["string", "null"] },
+            "amenities": { "type": "array", "items": { "type": "string" } },
+            "address": {
+                "type": ["object", "null"],
+                "properties": {
+                    "line_one": { "type": ["string", "null"] },
+                    "city_name": { "type": ["string", "null"] },
+                    "region": { "type": ["string", "null"] },
+                    "postal_code": { "type": ["string", "null"] },
+                    "country_code": { "type": ["string", "null"] }
+                }
+            },
+            "latitude": { "type": ["number", "null"] },
+            "longitude": { "type": ["number", "null"] },
+            "phone_number": { "type": ["string", "null"] },
+            "email": { "type": ["string", "null"] }
+        },
+        "required": ["name", "photos", "amenities"]
+    }))
+}
+
+pub async fn get_stay_details(stays: DuffelStayServer, arguments: Value) -> Result<Value, RpcError> {
+    let request: StayDetailsRequest = mcp::parse_arguments("get_stay_details", arguments)?;
+    let id = request.id.trim();
+    let valid = |prefix: &str| {
+        id.strip_prefix(prefix)
+            .is_some_and(|rest| !rest.is_empty() && rest.bytes().all(|b| b.is_ascii_alphanumeric()))
+    };
+
+    let details = if valid("srr_") {
+        stays.get_search_result_accommodation(id).await
+    } else if valid("acc_") {
+        stays.get_accommodation(id).await
+    } else {
+        return Err(RpcError::invalid_params(format!(
+            "Invalid parameters: id must be a search result ID starting with 'srr_' or an accommodation ID starting with 'acc_', got '{}'",
+            request.id
+        )));
+    }
+    .map_err(|e| {
+        error!("Stay details error: {}", e);
+        errors::rpc_error("Stay details request failed", &e)
+    })?;
+
+    mcp::structured_content(stays.format_details(&details), &details)
+}
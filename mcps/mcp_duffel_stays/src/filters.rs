@@ -0,0 +1,96 @@
+//! Post-fetch filtering of stay results.
+
+use bookedai_core::RpcError;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::StayOffer;
+
+/// Optional constraints applied to stay results before they are paged.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StayFilters {
+    pub min_rating: Option<f64>,
+    pub max_nightly_price: Option<f64>,
+    pub required_amenities: Option<Vec<String>>,
+    /// Hotel group or brand, matched against the chain or, failing that, the hotel name.
+    pub chain: Option<String>,
+}
+
+/// Number of nights between two `YYYY-MM-DD` dates, at least one.
+pub fn nights(check_in_date: &str, check_out_date: &str) -> i64 {
+    let check_in = NaiveDate::parse_from_str(check_in_date, "%Y-%m-%d");
+    let check_out = NaiveDate::parse_from_str(check_out_date, "%Y-%m-%d");
+    match (check_in, check_out) {
+        (Ok(check_in), Ok(check_out)) => (check_out - check_in).num_days().max(1),
+        _ => 1,
+    }
+}
+
+impl StayFilters {
+    /// Check parameters that serde cannot, before any Duffel call is made.
+    pub fn validate(&self) -> Result<(), RpcError> {
+        if let Some(min_rating) = self.min_rating {
+            if !(0.0..=5.0).contains(&min_rating) {
+                return Err(RpcError::invalid_params(
+                    "Invalid parameters: min_rating must be between 0 and 5",
+                ));
+            }
+        }
+        if let Some(max_nightly_price) = self.max_nightly_price {
+            if max_nightly_price <= 0.0 {
+                return Err(RpcError::invalid_params(
+                    "Invalid parameters: max_nightly_price must be positive",
+                ));
+            }
+        }
+        if self.chain.as_ref().is_some_and(|chain| chain.trim().is_empty()) {
+            return Err(RpcError::invalid_params("Invalid parameters: chain must not be empty"));
+        }
+        Ok(())
+    }
+
+    /// Whether `offer` satisfies every constraint.
+    pub fn matches(&self, offer: &StayOffer) -> bool {
+        if let Some(min_rating) = self.min_rating {
+            // Unrated properties cannot prove they meet the minimum
+            if offer.hotel_rating.is_none_or(|rating| rating < min_rating) {
+                return false;
+            }
+        }
+
+        if let Some(max_nightly_price) = self.max_nightly_price {
+            let total = offer
+                .converted_amount
+                .as_ref()
+                .map_or(&offer.total_amount, |converted| &converted.amount);
+            let total: f64 = total.parse().unwrap_or(f64::MAX);
+            let nightly = total / nights(&offer.check_in_date, &offer.check_out_date) as f64;
+            if nightly > max_nightly_price {
+                return false;
+            }
+        }
+
+        if let Some(required) = &self.required_amenities {
+            let has_all = required.iter().all(|wanted| {
+                let wanted = wanted.to_lowercase();
+                offer
+                    .amenities
+                    .iter()
+                    .any(|amenity| amenity.to_lowercase().contains(&wanted))
+            });
+            if !has_all {
+                return false;
+            }
+        }
+
+        if let Some(chain) = &self.chain {
+            let chain = chain.trim().to_lowercase();
+            let name = offer.chain.as_deref().unwrap_or(&offer.hotel_name).to_lowercase();
+            if !name.contains(&chain) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
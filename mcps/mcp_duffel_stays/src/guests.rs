@@ -0,0 +1,176 @@
+//! Guests and rooms for Duffel Stays searches.
+
+use bookedai_core::RpcError;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Most rooms Duffel Stays searches for at once.
+pub const MAX_ROOMS: u32 = 8;
+
+/// Most guests, children included, sharing one room.
+pub const MAX_GUESTS_PER_ROOM: u32 = 6;
+
+/// Oldest age booked as a child; older guests are adults.
+pub const MAX_CHILD_AGE: u8 = 17;
+
+/// The guests sharing one room.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomLayout {
+    pub adults: u32,
+    #[serde(default)]
+    pub children_ages: Vec<u8>,
+}
+
+/// Adults and children (by age), and the rooms they need.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GuestMix {
+    pub adults: Option<u32>,
+    /// Legacy child count; `children_ages` is needed whenever there are children.
+    pub children: Option<u32>,
+    pub children_ages: Option<Vec<u8>>,
+    pub rooms: Option<u32>,
+    /// Guests per room, in place of the totals above. Duffel only takes the party and
+    /// a room count, so this checks the split is possible; it does not choose it.
+    pub room_layouts: Option<Vec<RoomLayout>>,
+}
+
+/// Guests and rooms as Duffel Stays takes them.
+#[derive(Debug, Clone)]
+pub struct Occupancy {
+    pub guests: Vec<Value>,
+    pub rooms: u32,
+}
+
+fn invalid(message: String) -> RpcError {
+    RpcError::invalid_params(format!("Invalid parameters: {}", message))
+}
+
+fn check_ages(field: &str, ages: &[u8]) -> Result<(), RpcError> {
+    match ages.iter().find(|age| **age > MAX_CHILD_AGE) {
+        Some(age) => Err(invalid(format!(
+            "{} has age {}; children are 0-{}, older guests count as adults",
+            field, age, MAX_CHILD_AGE
+        ))),
+        None => Ok(()),
+    }
+}
+
+fn sorted(ages: &[u8]) -> Vec<u8> {
+    let mut ages = ages.to_vec();
+    ages.sort_unstable();
+    ages
+}
+
+impl GuestMix {
+    /// Children's ages, from `children_ages` or, with no children, an empty list.
+    fn children_ages(&self) -> Result<Vec<u8>, RpcError> {
+        match (&self.children_ages, self.children) {
+            (Some(ages), Some(children)) if ages.len() != children as usize => Err(invalid(format!(
+                "children ({}) and children_ages ({} ages) disagree; use children_ages only",
+                children,
+                ages.len()
+            ))),
+            (Some(ages), _) => Ok(ages.clone()),
+            (None, None | Some(0)) => Ok(Vec::new()),
+            (None, Some(children)) => Err(invalid(format!(
+                "children_ages is required for the {} children, one age (0-{}) each, as hotels price by age",
+                children, MAX_CHILD_AGE
+            ))),
+        }
+    }
+
+    /// The layouts asked for, or one built from the totals: adults spread across the
+    /// rooms and children placed with them in turn.
+    fn layouts(&self) -> Result<Vec<RoomLayout>, RpcError> {
+        let Some(layouts) = &self.room_layouts else {
+            let adults = self.adults.unwrap_or(1);
+            let rooms = self.rooms.unwrap_or(1);
+            let ages = self.children_ages()?;
+            check_ages("children_ages", &ages)?;
+            if rooms == 0 || rooms > MAX_ROOMS {
+                return Err(invalid(format!("rooms must be between 1 and {}", MAX_ROOMS)));
+            }
+            if adults < rooms {
+                return Err(invalid(format!(
+                    "each room needs an adult ({} rooms, {} adults)",
+                    rooms, adults
+                )));
+            }
+            let mut layouts: Vec<RoomLayout> = (0..rooms)
+                .map(|room| RoomLayout {
+                    adults: adults / rooms + u32::from(room < adults % rooms),
+                    children_ages: Vec::new(),
+                })
+                .collect();
+            for (index, age) in ages.into_iter().enumerate() {
+                layouts[index % rooms as usize].children_ages.push(age);
+            }
+            return Ok(layouts);
+        };
+
+        if layouts.is_empty() || layouts.len() > MAX_ROOMS as usize {
+            return Err(invalid(format!("room_layouts must list between 1 and {} rooms", MAX_ROOMS)));
+        }
+        for (index, layout) in layouts.iter().enumerate() {
+            if layout.adults == 0 {
+                return Err(invalid(format!("room_layouts[{}] needs at least one adult", index)));
+            }
+            check_ages(&format!("room_layouts[{}].children_ages", index), &layout.children_ages)?;
+        }
+
+        // Totals given alongside the layouts must describe the same party
+        let adults: u32 = layouts.iter().map(|layout| layout.adults).sum();
+        let ages: Vec<u8> = layouts.iter().flat_map(|layout| layout.children_ages.iter().copied()).collect();
+        if self.adults.is_some_and(|given| given != adults) {
+            return Err(invalid(format!(
+                "adults ({}) disagrees with the {} adults in room_layouts; give room_layouts alone",
+                self.adults.unwrap_or_default(),
+                adults
+            )));
+        }
+        if self.rooms.is_some_and(|given| given as usize != layouts.len()) {
+            return Err(invalid(format!(
+                "rooms ({}) disagrees with the {} room_layouts; give room_layouts alone",
+                self.rooms.unwrap_or_default(),
+                layouts.len()
+            )));
+        }
+        if (self.children_ages.is_some() || self.children.is_some()) && sorted(&self.children_ages()?) != sorted(&ages) {
+            return Err(invalid(
+                "children_ages disagrees with the children in room_layouts; give room_layouts alone".to_string(),
+            ));
+        }
+        Ok(layouts.clone())
+    }
+
+    /// Validate the guests and rooms and build Duffel's `guests` array and room count.
+    ///
+    /// Children are sent with their age, as Duffel requires. The layouts only decide
+    /// whether the party fits the rooms; Duffel assigns guests to rooms itself.
+    pub fn to_duffel(&self) -> Result<Occupancy, RpcError> {
+        let layouts = self.layouts()?;
+        for (index, layout) in layouts.iter().enumerate() {
+            let guests = layout.adults + layout.children_ages.len() as u32;
+            if guests > MAX_GUESTS_PER_ROOM {
+                return Err(invalid(format!(
+                    "at most {} guests per room, but room {} has {}",
+                    MAX_GUESTS_PER_ROOM,
+                    index + 1,
+                    guests
+                )));
+            }
+        }
+
+        let mut guests = Vec::new();
+        for layout in &layouts {
+            guests.extend((0..layout.adults).map(|_| json!({ "type": "adult" })));
+        }
+        for layout in &layouts {
+            guests.extend(layout.children_ages.iter().map(|age| json!({ "type": "child", "age": age })));
+        }
+        Ok(Occupancy {
+            guests,
+            rooms: layouts.len() as u32,
+        })
+    }
+}
@@ -1,20 +1,62 @@
-use std::env;
-use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Result;
+use bookedai_core::audit::{self, AuditLog};
+use bookedai_core::config::{self, Verbosity};
+use bookedai_core::duffel_models::StaysSearchResult;
+use bookedai_core::fx::{ConvertedAmount, CurrencyConverter, DisplayCurrency};
+use bookedai_core::http::HttpOptions;
+use bookedai_core::notifications::Notifier;
+use bookedai_core::readiness::Readiness;
+use bookedai_core::search_results::{NewOffer, SearchResults};
+use bookedai_core::store::Store;
+use bookedai_core::streaming::ListPage;
+use bookedai_core::{airports, errors, mcp, pagination, progress, scheduler, transport, validation};
+use bookedai_core::{DuffelClient, McpServer, ResponseCache, RpcError, Tool, Transport};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use tracing::{error, info};
-use warp::Filter;
+use tracing::{error, info, warn};
+
+mod accommodation;
+mod bookings;
+mod details;
+mod filters;
+mod guests;
+mod rates;
+mod results;
+mod reviews;
+
+use accommodation::HotelSelection;
+use filters::StayFilters;
+use guests::{GuestMix, Occupancy};
+use rates::StayRoom;
+use reviews::{ReviewProvider, ReviewSnippet};
 
 #[derive(Debug, Serialize, Deserialize)]
 struct StaySearchRequest {
+    /// City or area to search around; optional when `accommodation_name` is given.
+    #[serde(default)]
     location: String,
+    /// A specific hotel, e.g. `Hilton Paris Opera`, searched instead of the whole area.
+    accommodation_name: Option<String>,
     check_in_date: String,
     check_out_date: String,
-    adults: Option<i32>,
-    children: Option<i32>,
-    rooms: Option<i32>,
+    #[serde(flatten)]
+    guests: GuestMix,
+    radius_km: Option<f64>,
+    /// Fetch every room and rate for the returned hotels (default: true).
+    include_rates: Option<bool>,
+    /// Add guest ratings and review snippets from the review provider (default: false).
+    include_reviews: Option<bool>,
+    /// ISO 4217 code to convert prices into, alongside the original amounts.
+    display_currency: Option<String>,
+    limit: Option<usize>,
+    page_token: Option<String>,
+    /// `summary` leaves amenities, rooms and review snippets out of the results.
+    verbosity: Option<Verbosity>,
+    #[serde(flatten)]
+    filters: StayFilters,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -22,14 +64,31 @@ struct StayOffer {
     id: String,
     hotel_name: String,
     hotel_rating: Option<f64>,
+    /// Hotel group, e.g. `Hilton`.
+    #[serde(default)]
+    chain: Option<String>,
     location: String,
     total_amount: String,
     currency: String,
+    /// `total_amount` in the requested `display_currency`.
+    converted_amount: Option<ConvertedAmount>,
     check_in_date: String,
     check_out_date: String,
     room_type: Option<String>,
     amenities: Vec<String>,
     cancellation_policy: Option<String>,
+    /// Room types with their rates, cheapest rate first.
+    rooms: Vec<StayRoom>,
+    /// Average guest rating out of 5, when `include_reviews` found the hotel.
+    #[serde(default)]
+    review_score: Option<f64>,
+    #[serde(default)]
+    review_count: Option<u32>,
+    #[serde(default)]
+    review_snippets: Vec<ReviewSnippet>,
+    /// Where the reviews came from, e.g. `Google Places`.
+    #[serde(default)]
+    review_source: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -38,232 +97,356 @@ struct StaySearchResponse {
     total_results: i32,
     search_id: String,
     location_searched: String,
+    next_page_token: Option<String>,
+    /// Results dropped because Duffel returned them in an unexpected shape.
+    skipped_results: usize,
+    /// Parts of the search that failed; the hotels returned are unaffected.
+    #[serde(default)]
+    warnings: Vec<String>,
+}
+
+/// Position in the hotels of an earlier search, encoded as a page token.
+///
+/// Later pages search Duffel again but stay under the first page's search ID, so
+/// their option numbers carry on from it instead of starting again at 1.
+#[derive(Debug)]
+struct StaysCursor {
+    search_id: String,
+    offset: usize,
+}
+
+impl StaysCursor {
+    fn encode(&self) -> String {
+        format!("{}:{}", self.search_id, self.offset)
+    }
+
+    fn decode(token: &str) -> Result<Self, RpcError> {
+        token
+            .rsplit_once(':')
+            .filter(|(search_id, _)| !search_id.is_empty())
+            .and_then(|(search_id, offset)| {
+                Some(Self {
+                    search_id: search_id.to_string(),
+                    offset: offset.parse().ok()?,
+                })
+            })
+            .ok_or_else(|| RpcError::invalid_params("Invalid parameters: unrecognized page_token"))
+    }
+}
+
+/// Offer fields `structuredContent` keeps at `summary` verbosity.
+const SUMMARY_OFFER_FIELDS: &[&str] = &[
+    "id", "hotel_name", "hotel_rating", "location", "total_amount", "currency", "converted_amount", "review_score",
+];
+
+/// Search radius around the geocoded location when `radius_km` is not given.
+const DEFAULT_RADIUS_KM: f64 = 10.0;
+
+/// Largest search radius Duffel Stays accepts.
+const MAX_RADIUS_KM: f64 = 100.0;
+
+/// How often expired saved searches are deleted.
+const SEARCH_RESULTS_PURGE_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Centres of cities hotels are most often searched in, ahead of airport coordinates.
+fn city_centre(name: &str) -> Option<(f64, f64)> {
+    let centre = match name.to_lowercase().as_str() {
+        "new york" | "nyc" => (40.7128, -74.0060),
+        "london" => (51.5074, -0.1278),
+        "paris" => (48.8566, 2.3522),
+        "tokyo" => (35.6762, 139.6503),
+        "sydney" => (-33.8688, 151.2093),
+        "los angeles" | "la" => (34.0522, -118.2437),
+        "chicago" => (41.8781, -87.6298),
+        "melbourne" => (-37.8136, 144.9631),
+        "dubai" => (25.2048, 55.2708),
+        "singapore" => (1.3521, 103.8198),
+        "miami" => (25.7617, -80.1918),
+        "san francisco" => (37.7749, -122.4194),
+        "las vegas" => (36.1699, -115.1398),
+        "toronto" => (43.6532, -79.3832),
+        "berlin" => (52.5200, 13.4050),
+        "rome" => (41.9028, 12.4964),
+        "madrid" => (40.4168, -3.7038),
+        "amsterdam" => (52.3676, 4.9041),
+        "barcelona" => (41.3851, 2.1734),
+        _ => return None,
+    };
+    Some(centre)
 }
 
 #[derive(Debug, Clone)]
 struct DuffelStayServer {
-    api_token: String,
-    client: reqwest::Client,
+    duffel: DuffelClient,
+    fx: CurrencyConverter,
+    /// Where returned offers are saved for `select_offer`; `None` without a database.
+    results: Option<SearchResults>,
+    /// Source of `include_reviews`; `None` when no provider is configured.
+    reviews: Option<Arc<dyn ReviewProvider>>,
+    /// Email and webhook channels for booking confirmations.
+    notifier: Notifier,
 }
 
 impl DuffelStayServer {
-    fn new() -> Result<Self> {
-        let api_token = env::var("DUFFEL_API_TOKEN")
-            .map_err(|_| anyhow::anyhow!("DUFFEL_API_TOKEN environment variable must be set"))?;
-
-        let client = reqwest::Client::new();
-
-        Ok(Self { api_token, client })
+    fn new(results: Option<SearchResults>) -> Result<Self> {
+        Ok(Self {
+            duffel: DuffelClient::from_config()?,
+            fx: CurrencyConverter::from_config(),
+            results,
+            reviews: reviews::from_config()?,
+            notifier: Notifier::from_config()?,
+        })
     }
 
-    async fn search_stays(&self, request: StaySearchRequest) -> Result<StaySearchResponse> {
+    async fn search_stays(
+        &self,
+        request: StaySearchRequest,
+        hotels: Option<HotelSelection>,
+        occupancy: Occupancy,
+        limit: usize,
+        cursor: Option<StaysCursor>,
+        display: Option<DisplayCurrency>,
+    ) -> Result<StaySearchResponse> {
         info!("Searching stays for location: {}", request.location);
         
-        // First, get coordinates for the location using a simple geocoding approach
-        let coordinates = self.geocode_location(&request.location).await?;
-        
-        // Prepare guests array - Duffel expects guests as an array of objects
-        let mut guests = Vec::new();
-        let adults = request.adults.unwrap_or(1);
-        let children = request.children.unwrap_or(0);
-        
-        for _ in 0..adults {
-            guests.push(json!({"type": "adult"}));
-        }
-        for _ in 0..children {
-            guests.push(json!({"type": "child"}));
-        }
-
         // Prepare the request payload for Duffel Stays API
-        let payload = json!({
+        let mut payload = json!({
             "data": {
-                "location": {
-                    "radius": 10, // 10km radius
+                "check_in_date": request.check_in_date,
+                "check_out_date": request.check_out_date,
+                "guests": occupancy.guests,
+                "rooms": occupancy.rooms
+            }
+        });
+        let location_searched = match &hotels {
+            // Known hotels are searched by ID, so nothing else nearby is priced
+            Some(hotels) => {
+                payload["data"]["accommodation"] = json!({ "ids": hotels.ids() });
+                progress::report(format!("Searching rates at {}", hotels.describe(&request)));
+                hotels.describe(&request)
+            }
+            None => {
+                let coordinates = self.geocode_location(&request.location)?;
+                payload["data"]["location"] = json!({
+                    "radius": request.radius_km.unwrap_or(DEFAULT_RADIUS_KM),
                     "geographic_coordinates": {
                         "latitude": coordinates.0,
                         "longitude": coordinates.1
                     }
-                },
-                "check_in_date": request.check_in_date,
-                "check_out_date": request.check_out_date,
-                "guests": guests,
-                "rooms": request.rooms.unwrap_or(1)
+                });
+                progress::report(format!("Searching hotels within {} km of {}", request.radius_km.unwrap_or(DEFAULT_RADIUS_KM), request.location));
+                request.location.clone()
             }
-        });
+        };
 
         info!("Searching stays with payload: {}", serde_json::to_string_pretty(&payload)?);
 
-        // Use the actual Duffel Stays API endpoint
-        let response = self
-            .client
-            .post("https://api.duffel.com/stays/search")
-            .header("Authorization", format!("Bearer {}", self.api_token))
-            .header("Content-Type", "application/json")
-            .header("Accept", "application/json")
-            .header("Duffel-Version", "v2")
-            .json(&payload)
-            .send()
+        // Results are parsed one at a time as they are read, so a large city stays within bounds
+        let page = self
+            .duffel
+            .post_list::<StaysSearchResult>("/stays/search", &payload, &["data", "results"], "stay result")
             .await?;
+        info!("Duffel returned {} stay results", page.received);
 
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!("Duffel Stays API error: {}", error_text));
-        }
-
-        let response_data: Value = response.json().await?;
-        
-        // Debug: Log the actual response structure (first 1000 chars to avoid too much output)
-        let response_str = serde_json::to_string_pretty(&response_data)?;
-        let truncated = if response_str.len() > 1000 { &response_str[..1000] } else { &response_str };
-        info!("Raw Duffel response (truncated): {}", truncated);
-        
-        // Parse the actual Duffel response
-        self.parse_duffel_stays_response(response_data, &request).await
+        self.parse_duffel_stays_response(page, &request, location_searched, limit, cursor, display.as_ref())
+            .await
     }
 
-    async fn geocode_location(&self, location: &str) -> Result<(f64, f64)> {
-        // Simple geocoding for major cities - in production, use a proper geocoding service
-        let coordinates = match location.to_lowercase().as_str() {
-            "new york" | "nyc" => (40.7128, -74.0060),
-            "london" => (51.5074, -0.1278),
-            "paris" => (48.8566, 2.3522),
-            "tokyo" => (35.6762, 139.6503),
-            "sydney" => (-33.8688, 151.2093),
-            "los angeles" | "la" => (34.0522, -118.2437),
-            "chicago" => (41.8781, -87.6298),
-            "melbourne" => (-37.8136, 144.9631),
-            "dubai" => (25.2048, 55.2708),
-            "singapore" => (1.3521, 103.8198),
-            "miami" => (25.7617, -80.1918),
-            "san francisco" => (37.7749, -122.4194),
-            "las vegas" => (36.1699, -115.1398),
-            "toronto" => (43.6532, -79.3832),
-            "berlin" => (52.5200, 13.4050),
-            "rome" => (41.9028, 12.4964),
-            "madrid" => (40.4168, -3.7038),
-            "amsterdam" => (52.3676, 4.9041),
-            "barcelona" => (41.3851, 2.1734),
-            _ => {
-                // Default to London if location not found
-                info!("Location '{}' not found in geocoding, defaulting to London", location);
-                (51.5074, -0.1278)
-            }
+    /// Coordinates to search around: a city centre this server knows, else the
+    /// airport (or metropolitan area) for an IATA code or a city in the airport list.
+    fn geocode_location(&self, location: &str) -> Result<(f64, f64), RpcError> {
+        let name = location.trim();
+        if let Some(centre) = city_centre(name) {
+            return Ok(centre);
+        }
+        let code = if name.len() == 3 && name.bytes().all(|b| b.is_ascii_alphabetic()) {
+            Some(name.to_ascii_uppercase())
+        } else {
+            airports::city_code(name).map(str::to_string)
         };
-        Ok(coordinates)
+        let coordinates = code.and_then(|code| {
+            airports::city_name(&code)
+                .and_then(city_centre)
+                .or_else(|| airports::coordinates(&code))
+        });
+        coordinates.ok_or_else(|| {
+            RpcError::invalid_params(format!(
+                "Invalid parameters: location '{}' is not a place this server can find; use a city name (e.g. 'Lisbon') or an airport code (e.g. 'LIS')",
+                name
+            ))
+        })
     }
 
-    async fn parse_duffel_stays_response(&self, response_data: Value, request: &StaySearchRequest) -> Result<StaySearchResponse> {
-        // Debug: Log the response structure to understand the format
-        info!("Parsing response with top-level keys: {:?}", response_data.as_object().map(|o| o.keys().collect::<Vec<_>>()));
-        
-        if let Some(data) = response_data.get("data") {
-            info!("Data section keys: {:?}", data.as_object().map(|o| o.keys().collect::<Vec<_>>()));
-            info!("Data section is_array: {}, is_object: {}", data.is_array(), data.is_object());
-            
-            if data.is_array() {
-                info!("Data is an array with {} items", data.as_array().unwrap().len());
-            }
-            if data.is_object() {
-                info!("Data is an object");
-                if let Some(search_results) = data.get("search_results") {
-                    info!("Found search_results, type: {:?}", search_results);
-                    if search_results.is_array() {
-                        info!("search_results is array with {} items", search_results.as_array().unwrap().len());
-                    }
+    async fn parse_duffel_stays_response(
+        &self,
+        page: ListPage<StaysSearchResult>,
+        request: &StaySearchRequest,
+        location_searched: String,
+        limit: usize,
+        cursor: Option<StaysCursor>,
+        display: Option<&DisplayCurrency>,
+    ) -> Result<StaySearchResponse> {
+        let (results, mut warnings) = (page.items, page.skipped);
+        // Saved results are keyed by this ID, so a missing one must still be unique
+        let (search_id, offset) = match cursor {
+            Some(cursor) => (cursor.search_id, cursor.offset),
+            None => (
+                page.meta["request_id"]
+                    .as_str()
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("local_{}", uuid::Uuid::new_v4().simple())),
+                0,
+            ),
+        };
+        let skipped_results = warnings.len();
+        if page.truncated {
+            warnings.push(format!(
+                "Duffel's answer was larger than {} bytes, so only the first {} hotels were read; narrow the search radius to see the rest",
+                config::get().duffel.max_response_bytes,
+                page.received
+            ));
+        }
+
+        // Filter first: Duffel Stays returns every result at once, so pages are cut locally.
+        // Prices are converted beforehand so max_nightly_price applies to the display currency.
+        let matching: Vec<StayOffer> = results
+            .iter()
+            .map(|result| {
+                let mut offer = self.parse_stay_result(result, request);
+                if let Some(display) = display {
+                    offer.converted_amount = display.convert(&offer.total_amount, &offer.currency);
                 }
-            }
+                offer
+            })
+            .filter(|offer| request.filters.matches(offer))
+            .collect();
+        let total_results = matching.len();
+
+        let mut offers: Vec<StayOffer> = matching.into_iter().skip(offset).take(limit).collect();
+
+        // Search results only carry the cheapest rate, so rooms are fetched for this page alone
+        if request.include_rates.unwrap_or(true) {
+            warnings.extend(self.add_missing_rooms(&mut offers).await);
         }
-        
-        // Extract search results from the response
-        let search_results = response_data
-            .get("data")
-            .and_then(|data| data.get("results"))
-            .and_then(|results| results.as_array())
-            .ok_or_else(|| {
-                error!("Could not find results array in response");
-                anyhow::anyhow!("No search results found in API response")
-            })?;
-
-        let mut offers = Vec::new();
-        
-        for result in search_results.iter().take(10) { // Limit to 10 results
-            if let Some(stay_offer) = self.parse_stay_result(result, request) {
-                offers.push(stay_offer);
-            }
+        if request.include_reviews.unwrap_or(false) {
+            warnings.extend(self.add_reviews(&mut offers, &results).await);
         }
 
+        let next_offset = offset + limit;
+        let next_page_token = (next_offset < total_results).then(|| {
+            StaysCursor {
+                search_id: search_id.clone(),
+                offset: next_offset,
+            }
+            .encode()
+        });
+
         Ok(StaySearchResponse {
             offers,
-            total_results: search_results.len() as i32,
-            search_id: response_data["meta"]["request_id"]
-                .as_str()
-                .unwrap_or("unknown")
-                .to_string(),
-            location_searched: request.location.clone(),
+            total_results: total_results as i32,
+            search_id,
+            location_searched,
+            next_page_token,
+            skipped_results,
+            warnings,
         })
     }
 
-    fn parse_stay_result(&self, result: &Value, request: &StaySearchRequest) -> Option<StayOffer> {
-        let accommodation = &result["accommodation"];
-        
-        let id = result["id"].as_str()?.to_string();
-        let hotel_name = accommodation["name"].as_str()?.to_string();
-        let hotel_rating = accommodation["rating"].as_f64();
-        
+    fn parse_stay_result(&self, result: &StaysSearchResult, request: &StaySearchRequest) -> StayOffer {
+        let accommodation = &result.accommodation;
+
         // Get location info from accommodation.location.address.city_name
-        let location_name = accommodation["location"]["address"]["city_name"].as_str()
-            .unwrap_or(&request.location)
-            .to_string();
-        
-        // Get cheapest rate from root level fields
-        let total_amount = result["cheapest_rate_total_amount"].as_str()
-            .unwrap_or("0.00")
-            .to_string();
-        let currency = result["cheapest_rate_currency"].as_str()
-            .unwrap_or("USD")
-            .to_string();
-        
-        // Get amenities - they have description field instead of name
-        let amenities = accommodation["amenities"]
-            .as_array()
-            .map(|arr| {
-                arr.iter()
-                    .filter_map(|a| a["description"].as_str())
-                    .map(|s| s.to_string())
-                    .collect()
-            })
-            .unwrap_or_else(|| vec!["WiFi".to_string()]);
+        let location_name = accommodation
+            .location
+            .as_ref()
+            .and_then(|location| location.address.as_ref())
+            .and_then(|address| address.city_name.clone())
+            .unwrap_or_else(|| request.location.clone());
 
-        Some(StayOffer {
-            id,
-            hotel_name,
-            hotel_rating,
+        // Get amenities - they have description field instead of name
+        let amenities = accommodation
+            .amenities
+            .iter()
+            .filter_map(|amenity| amenity.description.clone())
+            .collect();
+
+        let mut offer = StayOffer {
+            id: result.id.clone(),
+            hotel_name: accommodation.name.clone(),
+            hotel_rating: accommodation.rating,
+            chain: accommodation.chain.as_ref().map(|chain| chain.name.clone()),
             location: location_name,
-            total_amount,
-            currency,
+            // Get cheapest rate from root level fields
+            total_amount: result
+                .cheapest_rate_total_amount
+                .clone()
+                .unwrap_or_else(|| "0.00".to_string()),
+            currency: result
+                .cheapest_rate_currency
+                .clone()
+                .unwrap_or_else(|| "USD".to_string()),
+            converted_amount: None,
             check_in_date: request.check_in_date.clone(),
             check_out_date: request.check_out_date.clone(),
-            room_type: None, // Room details not available in this response
+            room_type: None,
             amenities,
-            cancellation_policy: None, // Cancellation policy not available in this response
-        })
+            cancellation_policy: None,
+            rooms: Vec::new(),
+            review_score: None,
+            review_count: None,
+            review_snippets: Vec::new(),
+            review_source: None,
+        };
+        offer.set_rooms(rates::rooms_from_duffel(&accommodation.rooms));
+        offer
     }
 
-    fn format_stay_results(&self, response: &StaySearchResponse) -> String {
+    /// Save a page of offers under its search ID; returns the option number of the first,
+    /// which without a database follows from the page's `offset`.
+    async fn save_results(&self, response: &StaySearchResponse, offset: usize) -> usize {
+        let Some(results) = &self.results else {
+            return offset + 1;
+        };
+        let offers = response
+            .offers
+            .iter()
+            .map(|offer| NewOffer {
+                offer_id: offer.id.clone(),
+                expires_at: None,
+                offer: serde_json::to_value(offer).unwrap_or(Value::Null),
+            })
+            .collect();
+        results
+            .append(results::STAY_SEARCH, &response.search_id, offers)
+            .await
+            .unwrap_or_else(|e| {
+                warn!("Could not save results of search {}: {}", response.search_id, e);
+                offset + 1
+            })
+    }
+
+    /// Numbered hotel list; `first_option` is the number of the first offer on this page.
+    fn format_stay_results(&self, response: &StaySearchResponse, first_option: usize, verbosity: Verbosity) -> String {
         if response.offers.is_empty() {
-            return format!("No hotels found in {} for the specified dates.", response.location_searched);
+            let mut result = format!("No hotels found in {} for the specified dates.", response.location_searched);
+            errors::append_warnings(&mut result, &response.warnings);
+            return result;
         }
 
         let mut result = format!("Found {} hotel offers in {}:\n\n", response.total_results, response.location_searched);
         
         for (i, offer) in response.offers.iter().enumerate() {
             result.push_str(&format!(
-                "{}. {} - {} {}\n",
-                i + 1,
+                "{}. {} - {} {}",
+                first_option + i,
                 offer.hotel_name,
                 offer.total_amount,
                 offer.currency
             ));
+            if let Some(converted) = &offer.converted_amount {
+                result.push_str(&format!(" (~{} {})", converted.amount, converted.currency));
+            }
+            result.push('\n');
             
             if let Some(rating) = offer.hotel_rating {
                 result.push_str(&format!(
@@ -271,6 +454,15 @@ impl DuffelStayServer {
                     rating
                 ));
             }
+            if verbosity == Verbosity::Summary {
+                reviews::describe_score(&mut result, offer);
+                result.push_str(&format!("   Location: {}\n\n", offer.location));
+                continue;
+            }
+            if let Some(chain) = &offer.chain {
+                result.push_str(&format!("   Chain: {}\n", chain));
+            }
+            reviews::describe(&mut result, offer);
             
             result.push_str(&format!(
                 "   Location: {}\n",
@@ -303,236 +495,448 @@ impl DuffelStayServer {
                     policy
                 ));
             }
+
+            for room in &offer.rooms {
+                result.push_str(&format!("   {}:\n", room.name));
+                for rate in &room.rates {
+                    result.push_str(&format!(
+                        "      {} {} - {}, {}\n",
+                        rate.total_amount,
+                        rate.currency,
+                        rates::describe_board(rate.board_type.as_deref()),
+                        rate.cancellation_policy
+                    ));
+                }
+            }
             
-            result.push_str("\n");
+            result.push('\n');
         }
 
         result.push_str(&format!("Search ID: {}", response.search_id));
+        errors::append_warnings(&mut result, &response.warnings);
+        if let Some(token) = &response.next_page_token {
+            result.push_str(&format!("\nMore hotels available (page_token: {})", token));
+        }
+        if self.results.is_some() {
+            result.push_str("\nUse select_offer with this Search ID and an option number to pick a hotel");
+        }
         result
     }
 }
 
-async fn handle_mcp_request(
-    server: DuffelStayServer,
-    request: Value,
-) -> Result<impl warp::Reply, Infallible> {
-    let response = handle_request(&server, request).await;
-    Ok(warp::reply::json(&response))
+/// The tool result for a page of hotels, cut down to [`SUMMARY_OFFER_FIELDS`] at `summary` verbosity.
+fn search_results_content(
+    stays: &DuffelStayServer,
+    response: &StaySearchResponse,
+    first_option: usize,
+    verbosity: Verbosity,
+) -> Result<Value, RpcError> {
+    let text = stays.format_stay_results(response, first_option, verbosity);
+    if verbosity == Verbosity::Full {
+        return mcp::structured_content(text, response);
+    }
+    let mut summary = serde_json::to_value(response)
+        .map_err(|e| RpcError::server_error(format!("Failed to serialize tool result: {}", e)))?;
+    mcp::retain_fields(&mut summary["offers"], SUMMARY_OFFER_FIELDS);
+    mcp::structured_content(text, &summary)
+}
+
+fn verbosity_schema() -> Value {
+    json!({
+        "type": "string",
+        "enum": ["summary", "full"],
+        "description": "'summary' returns only price, hotel, star and guest rating per offer, to save context; 'full' adds amenities, rooms, rates and reviews (default: the server's RESPONSE_VERBOSITY, normally 'full')"
+    })
 }
 
-async fn handle_request(server: &DuffelStayServer, request: Value) -> Value {
-    let method = request["method"].as_str().unwrap_or("");
-    let id = request["id"].clone();
-
-    match method {
-        "initialize" => {
-            json!({
-                "jsonrpc": "2.0",
-                "result": {
-                    "protocolVersion": "2024-11-05",
-                    "capabilities": {
-                        "tools": {}
-                    },
-                    "serverInfo": {
-                        "name": "duffel-stays-mcp",
-                        "version": "0.1.0"
+fn search_stays_tool() -> Tool {
+    Tool::new(
+        "search_stays",
+        "Search for hotels and accommodations using the Duffel API",
+        json!({
+            "type": "object",
+            "properties": {
+                "location": {
+                    "type": "string",
+                    "description": "Location/city to search for hotels (e.g., 'New York', 'Paris', 'Tokyo'); optional with accommodation_name"
+                },
+                "accommodation_name": {
+                    "type": "string",
+                    "description": "A specific hotel to price, e.g. 'Hilton Paris Opera'; only its rooms and rates are returned"
+                },
+                "chain": {
+                    "type": "string",
+                    "description": "Only hotels of this chain or brand near the location, e.g. 'Hilton'"
+                },
+                "check_in_date": {
+                    "type": "string",
+                    "description": "Check-in date in YYYY-MM-DD format"
+                },
+                "check_out_date": {
+                    "type": "string",
+                    "description": "Check-out date in YYYY-MM-DD format"
+                },
+                "adults": {
+                    "type": "integer",
+                    "description": "Number of adult guests (default: 1)"
+                },
+                "children_ages": {
+                    "type": "array",
+                    "items": { "type": "integer", "minimum": 0, "maximum": guests::MAX_CHILD_AGE },
+                    "description": "Age of each child guest at check-in, e.g. [4, 9]; hotels price children by age"
+                },
+                "children": {
+                    "type": "integer",
+                    "description": "Number of child guests (default: 0); deprecated, children_ages is required when there are children"
+                },
+                "rooms": {
+                    "type": "integer",
+                    "description": format!("Number of rooms needed (default: 1, max: {}); each needs an adult", guests::MAX_ROOMS)
+                },
+                "room_layouts": {
+                    "type": "array",
+                    "description": format!(
+                        "Guests per room, in place of adults/children_ages/rooms (1-{} rooms, at most {} guests each); checks the party fits, but Duffel assigns guests to rooms itself",
+                        guests::MAX_ROOMS,
+                        guests::MAX_GUESTS_PER_ROOM
+                    ),
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "adults": { "type": "integer", "minimum": 1 },
+                            "children_ages": { "type": "array", "items": { "type": "integer", "minimum": 0, "maximum": guests::MAX_CHILD_AGE } }
+                        },
+                        "required": ["adults"]
                     }
                 },
-                "id": id
-            })
-        }
-        "tools/list" => {
-            json!({
-                "jsonrpc": "2.0",
-                "result": {
-                    "tools": [
-                        {
-                            "name": "search_stays",
-                            "description": "Search for hotels and accommodations using the Duffel API",
-                            "inputSchema": {
+                "radius_km": {
+                    "type": "number",
+                    "description": format!(
+                        "Search radius around the location in km (default: {}, max: {})",
+                        DEFAULT_RADIUS_KM, MAX_RADIUS_KM
+                    )
+                },
+                "include_rates": {
+                    "type": "boolean",
+                    "description": "List every room type and rate for the returned hotels (default: true); false is faster"
+                },
+                "include_reviews": {
+                    "type": "boolean",
+                    "description": "Add each hotel's guest rating, review count and top review snippets (default: false); needs a review provider to be configured"
+                },
+                "min_rating": {
+                    "type": "number",
+                    "description": "Minimum star rating, 0-5 (unrated hotels are excluded)"
+                },
+                "max_nightly_price": {
+                    "type": "number",
+                    "description": "Maximum price per night, in display_currency when set, otherwise the offer currency"
+                },
+                "display_currency": {
+                    "type": "string",
+                    "description": "ISO 4217 code (e.g. 'USD', 'GBP') to also show every price in, using ECB daily rates"
+                },
+                "required_amenities": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Amenities every hotel must have, e.g. ['pool', 'parking']"
+                },
+                "limit": {
+                    "type": "integer",
+                    "description": format!(
+                        "Maximum hotels to return (1-{}, default: {})",
+                        pagination::MAX_LIMIT,
+                        pagination::DEFAULT_LIMIT
+                    )
+                },
+                "page_token": {
+                    "type": "string",
+                    "description": "next_page_token from a previous search with the same parameters to fetch more hotels"
+                },
+                "verbosity": verbosity_schema()
+            },
+            "required": ["check_in_date", "check_out_date"]
+        }),
+    )
+    .with_output_schema(stay_search_output_schema())
+    .cacheable()
+}
+
+fn stay_search_output_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "offers": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "id": { "type": "string", "description": "Duffel search result ID" },
+                        "hotel_name": { "type": "string" },
+                        "hotel_rating": { "type": ["number", "null"], "description": "Star rating out of 5" },
+                        "chain": { "type": ["string", "null"], "description": "Hotel group the property belongs to" },
+                        "location": { "type": "string" },
+                        "total_amount": { "type": "string", "description": "Cheapest total price as a decimal string" },
+                        "currency": { "type": "string", "description": "ISO 4217 currency code" },
+                        "converted_amount": {
+                            "type": ["object", "null"],
+                            "description": "total_amount in display_currency; null when display_currency was not requested",
+                            "properties": {
+                                "amount": { "type": "string", "description": "Converted total, rounded to 2 decimals" },
+                                "currency": { "type": "string" },
+                                "exchange_rate": {
+                                    "type": "number",
+                                    "description": "Units of currency per unit of the original currency"
+                                },
+                                "rates_date": { "type": "string", "description": "Publication date of the ECB rate used" }
+                            },
+                            "required": ["amount", "currency", "exchange_rate", "rates_date"]
+                        },
+                        "check_in_date": { "type": "string" },
+                        "check_out_date": { "type": "string" },
+                        "room_type": { "type": ["string", "null"] },
+                        "amenities": { "type": "array", "items": { "type": "string" } },
+                        "cancellation_policy": { "type": ["string", "null"] },
+                        "rooms": {
+                            "type": "array",
+                            "items": {
                                 "type": "object",
                                 "properties": {
-                                    "location": {
-                                        "type": "string",
-                                        "description": "Location/city to search for hotels (e.g., 'New York', 'Paris', 'Tokyo')"
-                                    },
-                                    "check_in_date": {
-                                        "type": "string",
-                                        "description": "Check-in date in YYYY-MM-DD format"
-                                    },
-                                    "check_out_date": {
-                                        "type": "string",
-                                        "description": "Check-out date in YYYY-MM-DD format"
-                                    },
-                                    "adults": {
-                                        "type": "integer",
-                                        "description": "Number of adult guests (default: 1)"
-                                    },
-                                    "children": {
-                                        "type": "integer",
-                                        "description": "Number of child guests (default: 0)"
-                                    },
-                                    "rooms": {
-                                        "type": "integer",
-                                        "description": "Number of rooms needed (default: 1)"
+                                    "name": { "type": "string" },
+                                    "rates": {
+                                        "type": "array",
+                                        "items": {
+                                            "type": "object",
+                                            "properties": {
+                                                "rate_id": { "type": "string" },
+                                                "total_amount": { "type": "string" },
+                                                "currency": { "type": "string" },
+                                                "board_type": {
+                                                    "type": ["string", "null"],
+                                                    "description": "Meal plan, e.g. room_only, breakfast, half_board, all_inclusive"
+                                                },
+                                                "refundable": { "type": "boolean" },
+                                                "cancellation_policy": { "type": "string" }
+                                            },
+                                            "required": [
+                                                "rate_id", "total_amount", "currency",
+                                                "refundable", "cancellation_policy"
+                                            ]
+                                        }
                                     }
                                 },
-                                "required": ["location", "check_in_date", "check_out_date"]
-                            }
-                        }
-                    ]
-                },
-                "id": id
-            })
-        }
-        "tools/call" => {
-            let params = &request["params"];
-            let tool_name = params["name"].as_str().unwrap_or("");
-            let arguments = &params["arguments"];
-
-            match tool_name {
-                "search_stays" => {
-                    match serde_json::from_value::<StaySearchRequest>(arguments.clone()) {
-                        Ok(search_request) => {
-                            match server.search_stays(search_request).await {
-                                Ok(search_response) => {
-                                    let formatted_results = server.format_stay_results(&search_response);
-                                    json!({
-                                        "jsonrpc": "2.0",
-                                        "result": {
-                                            "content": [
-                                                {
-                                                    "type": "text",
-                                                    "text": formatted_results
-                                                }
-                                            ]
-                                        },
-                                        "id": id
-                                    })
-                                }
-                                Err(e) => {
-                                    error!("Stay search error: {}", e);
-                                    json!({
-                                        "jsonrpc": "2.0",
-                                        "error": {
-                                            "code": -32000,
-                                            "message": format!("Stay search failed: {}", e)
-                                        },
-                                        "id": id
-                                    })
-                                }
+                                "required": ["name", "rates"]
                             }
-                        }
-                        Err(e) => {
-                            error!("Invalid arguments for search_stays: {}", e);
-                            json!({
-                                "jsonrpc": "2.0",
-                                "error": {
-                                    "code": -32602,
-                                    "message": format!("Invalid parameters: {}", e)
-                                },
-                                "id": id
-                            })
-                        }
-                    }
-                }
-                _ => {
-                    json!({
-                        "jsonrpc": "2.0",
-                        "error": {
-                            "code": -32601,
-                            "message": "Method not found"
                         },
-                        "id": id
-                    })
+                        "review_score": {
+                            "type": ["number", "null"],
+                            "description": "Average guest rating out of 5; set when include_reviews found the hotel"
+                        },
+                        "review_count": { "type": ["integer", "null"], "description": "Guest reviews behind review_score" },
+                        "review_snippets": {
+                            "type": "array",
+                            "items": reviews::snippet_schema(),
+                            "description": "Up to 3 of the most relevant reviews, shortened"
+                        },
+                        "review_source": { "type": ["string", "null"], "description": "Where the reviews came from" }
+                    },
+                    // Summary results keep only these and the other SUMMARY_OFFER_FIELDS
+                    "required": ["id", "hotel_name", "location", "total_amount", "currency"]
                 }
+            },
+            "total_results": { "type": "integer", "description": "Results matching the filters across all pages" },
+            "search_id": { "type": "string" },
+            "location_searched": {
+                "type": "string",
+                "description": "The area searched, or the hotel or chain when accommodation_name or chain was given"
+            },
+            "next_page_token": {
+                "type": ["string", "null"],
+                "description": "Pass as page_token to fetch the next page; null when there are no more hotels"
+            },
+            "skipped_results": {
+                "type": "integer",
+                "description": "Hotels Duffel returned in an unexpected shape that were left out"
+            },
+            "warnings": {
+                "type": "array",
+                "items": { "type": "string" },
+                "description": "Parts of the search that failed, e.g. skipped hotels or rates that could not be fetched; the hotels returned are still valid"
             }
+        },
+        "required": ["offers", "total_results", "search_id", "location_searched", "skipped_results"]
+    })
+}
+
+async fn search_stays(stays: DuffelStayServer, arguments: Value) -> Result<Value, RpcError> {
+    let mut search_request: StaySearchRequest = mcp::parse_arguments("search_stays", arguments)?;
+    let limit = pagination::resolve_limit(search_request.limit)?;
+    let verbosity = Verbosity::resolve(search_request.verbosity);
+    if verbosity == Verbosity::Summary {
+        // Summaries do not list rooms, so fetching their rates would be wasted
+        search_request.include_rates.get_or_insert(false);
+    }
+    search_request.filters.validate()?;
+    let occupancy = search_request.guests.to_duffel()?;
+    if let Some(name) = &search_request.accommodation_name {
+        if name.trim().is_empty() {
+            return Err(RpcError::invalid_params("Invalid parameters: accommodation_name must not be empty"));
         }
-        _ => {
-            json!({
-                "jsonrpc": "2.0",
-                "error": {
-                    "code": -32601,
-                    "message": "Method not found"
-                },
-                "id": id
-            })
+    } else if search_request.location.trim().is_empty() {
+        return Err(RpcError::invalid_params(
+            "Invalid parameters: location is required unless accommodation_name is given",
+        ));
+    }
+    let check_in = validation::upcoming_date("check_in_date", &search_request.check_in_date)?;
+    let check_out = validation::date("check_out_date", &search_request.check_out_date)?;
+    validation::after("check_out_date", check_out, "check_in_date", check_in)?;
+    if let Some(radius_km) = search_request.radius_km {
+        if radius_km <= 0.0 || radius_km > MAX_RADIUS_KM {
+            return Err(RpcError::invalid_params(format!(
+                "Invalid parameters: radius_km must be greater than 0 and at most {}",
+                MAX_RADIUS_KM
+            )));
         }
     }
+    let cursor = search_request.page_token.as_deref().map(StaysCursor::decode).transpose()?;
+    let offset = cursor.as_ref().map_or(0, |cursor| cursor.offset);
+    let display = stays.fx.display_currency(search_request.display_currency.as_deref()).await?;
+    let hotels = accommodation::resolve(&stays, &search_request).await?;
+
+    let search_response = stays.search_stays(search_request, hotels, occupancy, limit, cursor, display).await.map_err(|e| {
+        error!("Stay search error: {}", e);
+        errors::rpc_error("Stay search failed", &e)
+    })?;
+
+    let first_option = stays.save_results(&search_response, offset).await;
+    search_results_content(&stays, &search_response, first_option, verbosity)
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize logging
-    tracing_subscriber::fmt::init();
-    info!("Starting Duffel Stays MCP HTTP Server");
+    let transport = Transport::from_args()?;
+    // config.toml plus environment overrides; `--print-config` prints it and exits
+    config::init()?;
+
+    // Initialize logging (stderr, so stdout stays free for the stdio transport)
+    bookedai_core::logging::init();
+    info!("Starting Duffel Stays MCP Server ({} transport)", transport);
 
     // Initialize the server
-    let server = DuffelStayServer::new()?;
-    info!("Duffel API token loaded successfully");
-
-    // Create CORS configuration
-    let cors = warp::cors()
-        .allow_any_origin()
-        .allow_headers(vec!["content-type"])
-        .allow_methods(vec!["GET", "POST", "OPTIONS"]);
-
-    // Health check endpoint
-    let health = warp::path("health")
-        .and(warp::get())
-        .map(|| {
-            warp::reply::json(&json!({
-                "status": "healthy",
-                "service": "duffel-stays-mcp",
-                "version": "0.1.0"
-            }))
+    let store = Store::from_config().map_err(|e| warn!("Database unavailable: {:#}", e)).ok();
+    let search_results = match &store {
+        Some(store) => SearchResults::new(store.clone())
+            .await
+            .map_err(|e| warn!("Saved search results disabled: {:#}", e))
+            .ok(),
+        None => None,
+    };
+    // On unless AUDIT_LOG_ENABLED=false
+    let audit_log = match &store {
+        Some(store) => AuditLog::from_config(store.clone())
+            .await
+            .map_err(|e| warn!("Audit log disabled: {:#}", e))
+            .ok()
+            .flatten(),
+        None => None,
+    };
+    let stays = DuffelStayServer::new(search_results.clone())?;
+    info!("Duffel mode: {}", config::get().duffel.mode);
+    let readiness = Readiness::new().duffel(stays.duffel.clone()).store(store);
+    if let Some(search_results) = search_results {
+        scheduler::spawn_periodic("Saved search cleanup", SEARCH_RESULTS_PURGE_INTERVAL, move || {
+            let search_results = search_results.clone();
+            async move {
+                let purged = search_results.purge_expired().await?;
+                if purged > 0 {
+                    info!("Deleted {} expired saved searches", purged);
+                }
+                Ok(())
+            }
         });
+    }
 
-    // MCP endpoint
-    let server_clone = server.clone();
-    let mcp = warp::path("mcp")
-        .and(warp::post())
-        .and(warp::body::json())
-        .and_then(move |request: Value| {
-            let server = server_clone.clone();
+    if let Some(audit_log) = audit_log.clone() {
+        scheduler::spawn_periodic("Audit log rotation", audit::PRUNE_INTERVAL, move || {
+            let audit_log = audit_log.clone();
             async move {
-                handle_mcp_request(server, request).await
+                let pruned = audit_log.prune_from_config().await?;
+                if pruned > 0 {
+                    info!("Deleted {} old audit log entries", pruned);
+                }
+                Ok(())
             }
         });
+    }
 
-    // Root endpoint with info
-    let root = warp::path::end()
-        .and(warp::get())
-        .map(|| {
-            warp::reply::json(&json!({
-                "service": "Duffel Stays MCP Server",
-                "version": "0.1.0",
-                "endpoints": {
-                    "health": "GET /health",
-                    "mcp": "POST /mcp"
-                },
-                "tools": ["search_stays"]
-            }))
+    let mut server = McpServer::builder("duffel-stays-mcp", env!("CARGO_PKG_VERSION"))
+        .title("Duffel Stays MCP Server")
+        .cache(ResponseCache::from_config())
+        .audit(audit_log.clone())
+        .tool(search_stays_tool(), {
+            let stays = stays.clone();
+            move |arguments| search_stays(stays.clone(), arguments)
+        })
+        .tool(bookings::get_booking_tool(), {
+            let stays = stays.clone();
+            move |arguments| bookings::get_booking(stays.clone(), arguments)
+        })
+        .tool(bookings::cancel_booking_tool(), {
+            let stays = stays.clone();
+            move |arguments| bookings::cancel_booking(stays.clone(), arguments)
+        })
+        .tool(bookings::send_confirmation_tool(), {
+            let stays = stays.clone();
+            move |arguments| bookings::send_confirmation(stays.clone(), arguments)
+        })
+        .tool(results::get_search_results_tool(), {
+            let stays = stays.clone();
+            move |arguments| results::get_search_results(stays.clone(), arguments)
+        })
+        .tool(results::select_offer_tool(), {
+            let stays = stays.clone();
+            move |arguments| results::select_offer(stays.clone(), arguments)
+        })
+        .tool(details::get_stay_details_tool(), {
+            let stays = stays.clone();
+            move |arguments| details::get_stay_details(stays.clone(), arguments)
+        })
+        .resources(
+            results::search_resource_template(),
+            {
+                let stays = stays.clone();
+                move || results::list_search_resources(stays.clone())
+            },
+            {
+                let stays = stays.clone();
+                move |search_id| results::read_search_resource(stays.clone(), search_id)
+            },
+        )
+        .resources(
+            bookings::booking_resource_template(),
+            {
+                let stays = stays.clone();
+                move || bookings::list_booking_resources(stays.clone())
+            },
+            move |booking_id| bookings::read_booking_resource(stays.clone(), booking_id),
+        );
+    // Admin only: every client could read every recorded call
+    if config::get().audit.query_tool {
+        server = server.tool(audit::query_audit_log_tool(), move |arguments| {
+            audit::query_audit_log(audit_log.clone(), arguments)
         });
-
-    let routes = health
-        .or(mcp)
-        .or(root)
-        .with(cors)
-        .with(warp::log("duffel_stays"));
-
-    let port = env::var("PORT")
-        .unwrap_or_else(|_| "3002".to_string())
-        .parse::<u16>()
-        .unwrap_or(3002);
-
-    info!("Server starting on http://localhost:{}", port);
-    info!("MCP endpoint: http://localhost:{}/mcp", port);
-    info!("Health check: http://localhost:{}/health", port);
-
-    warp::serve(routes)
-        .run(([127, 0, 0, 1], port))
-        .await;
-
-    Ok(())
-} 
\ No newline at end of file
+    }
+    let server = server.build();
+
+    transport::serve(
+        server,
+        transport,
+        HttpOptions {
+            default_port: 3002,
+            log_target: "duffel_stays",
+            webhooks: None,
+            readiness,
+        },
+    )
+    .await
+}
@@ -0,0 +1,151 @@
+//! Room types and their rates for a stay search result.
+
+use anyhow::Result;
+use bookedai_core::duffel_models::{self, StaysSearchResult};
+use bookedai_core::progress;
+use futures_util::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::warn;
+
+use crate::{DuffelStayServer, StayOffer};
+
+/// Rate fetches running at once for one results page.
+const RATE_FETCH_CONCURRENCY: usize = 4;
+
+/// One bookable price for a room.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StayRate {
+    pub rate_id: String,
+    pub total_amount: String,
+    pub currency: String,
+    /// Meal plan, e.g. `room_only`, `breakfast`, `half_board`.
+    pub board_type: Option<String>,
+    pub refundable: bool,
+    /// Human-readable cancellation terms.
+    pub cancellation_policy: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StayRoom {
+    pub name: String,
+    pub rates: Vec<StayRate>,
+}
+
+fn amount(value: &str) -> f64 {
+    value.parse().unwrap_or(f64::MAX)
+}
+
+fn cancellation_policy(rate: &duffel_models::Rate) -> String {
+    // The first window offers the largest refund; later ones refund less
+    let Some(first) = rate.cancellation_timeline.first() else {
+        return "Non-refundable".to_string();
+    };
+    if amount(&first.refund_amount) >= amount(&rate.total_amount) {
+        format!("Free cancellation before {}", first.before)
+    } else {
+        format!(
+            "Partial refund of {} {} before {}",
+            first.refund_amount, first.currency, first.before
+        )
+    }
+}
+
+/// Convert Duffel rooms, cheapest rate first within each room.
+pub fn rooms_from_duffel(rooms: &[duffel_models::Room]) -> Vec<StayRoom> {
+    rooms
+        .iter()
+        .filter(|room| !room.rates.is_empty())
+        .map(|room| {
+            let mut rates: Vec<StayRate> = room
+                .rates
+                .iter()
+                .map(|rate| StayRate {
+                    rate_id: rate.id.clone(),
+                    total_amount: rate.total_amount.clone(),
+                    currency: rate.total_currency.clone(),
+                    board_type: rate.board_type.clone(),
+                    refundable: !rate.cancellation_timeline.is_empty(),
+                    cancellation_policy: cancellation_policy(rate),
+                })
+                .collect();
+            rates.sort_by(|a, b| amount(&a.total_amount).total_cmp(&amount(&b.total_amount)));
+            StayRoom {
+                name: room.name.clone().unwrap_or_else(|| "Room".to_string()),
+                rates,
+            }
+        })
+        .collect()
+}
+
+/// `half_board` as `half board`.
+pub fn describe_board(board_type: Option<&str>) -> String {
+    board_type.unwrap_or("room_only").replace('_', " ")
+}
+
+impl StayOffer {
+    /// Fill in the summary fields from the cheapest rate across all rooms.
+    pub fn set_rooms(&mut self, rooms: Vec<StayRoom>) {
+        let cheapest = rooms
+            .iter()
+            .flat_map(|room| room.rates.iter().map(move |rate| (room, rate)))
+            .min_by(|(_, a), (_, b)| amount(&a.total_amount).total_cmp(&amount(&b.total_amount)));
+
+        if let Some((room, rate)) = cheapest {
+            self.room_type = Some(format!("{} ({})", room.name, describe_board(rate.board_type.as_deref())));
+            self.cancellation_policy = Some(rate.cancellation_policy.clone());
+        }
+        self.rooms = rooms;
+    }
+}
+
+impl DuffelStayServer {
+    /// Fetch every room and rate for one search result.
+    async fn fetch_rooms(&self, search_result_id: &str) -> Result<Vec<StayRoom>> {
+        let path = format!("/stays/search_results/{}/actions/fetch_all_rates", search_result_id);
+        let response = self.duffel.post(&path, &json!({})).await?;
+        let result = StaysSearchResult::deserialize(&response["data"])?;
+        Ok(rooms_from_duffel(&result.accommodation.rooms))
+    }
+
+    /// Fetch rooms for the offers on a page that did not come with any.
+    ///
+    /// A failed fetch leaves that offer with only its cheapest-rate summary; one
+    /// warning per failure is returned.
+    pub async fn add_missing_rooms(&self, offers: &mut [StayOffer]) -> Vec<String> {
+        let pending: Vec<(usize, String)> = offers
+            .iter()
+            .enumerate()
+            .filter(|(_, offer)| offer.rooms.is_empty())
+            .map(|(index, offer)| (index, offer.id.clone()))
+            .collect();
+
+        let total = pending.len();
+        let mut done = 0;
+        let fetched: Vec<(usize, Result<Vec<StayRoom>>)> = stream::iter(pending)
+            .map(|(index, id)| async move { (index, self.fetch_rooms(&id).await) })
+            .buffer_unordered(RATE_FETCH_CONCURRENCY)
+            .inspect(|_| {
+                done += 1;
+                progress::report(format!("Fetched rates for {} of {} hotels", done, total));
+            })
+            .collect()
+            .await;
+
+        let mut warnings = Vec::new();
+        for (index, rooms) in fetched {
+            match rooms {
+                Ok(rooms) => offers[index].set_rooms(rooms),
+                Err(e) => {
+                    let warning = format!(
+                        "Rates for {} could not be fetched; only its cheapest price is shown: {}",
+                        offers[index].hotel_name, e
+                    );
+                    warn!("{} ({})", warning, offers[index].id);
+                    warnings.push(warning);
+                }
+            }
+        }
+        warnings
+    }
+}
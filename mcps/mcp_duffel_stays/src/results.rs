@@ -0,0 +1,200 @@
+//! Saved search results: re-reading an earlier hotel search, picking "option N" from it,
+//! and browsing recent searches as `search://stays/{search_id}` resources.
+
+use bookedai_core::config::Verbosity;
+use bookedai_core::resources::{Resource, ResourceTemplate};
+use bookedai_core::search_results::SearchResults;
+use bookedai_core::{mcp, RpcError, Tool};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    search_results_content, stay_search_output_schema, verbosity_schema, DuffelStayServer, StayOffer,
+    StaySearchResponse,
+};
+
+/// `kind` of the searches this server saves in the shared store.
+pub const STAY_SEARCH: &str = "stay";
+
+/// Saved searches listed by `resources/list`.
+const RECENT_SEARCH_RESOURCES: usize = 20;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct GetSearchResultsRequest {
+    search_id: String,
+    verbosity: Option<Verbosity>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SelectOfferRequest {
+    search_id: String,
+    /// 1-based option number, as shown in the search results.
+    option: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SelectedOffer {
+    search_id: String,
+    option: usize,
+    offer_id: String,
+    expires_at: Option<String>,
+    offer: Value,
+}
+
+fn unavailable() -> RpcError {
+    RpcError::server_error("Saved search results are unavailable: the database at BOOKEDAI_DB_PATH could not be opened")
+}
+
+fn saved_results(stays: &DuffelStayServer) -> Result<&SearchResults, RpcError> {
+    stays.results.as_ref().ok_or_else(unavailable)
+}
+
+fn format_selected(selected: &SelectedOffer) -> String {
+    let mut result = format!("Option {} of search {}", selected.option, selected.search_id);
+    if let Ok(offer) = serde_json::from_value::<StayOffer>(selected.offer.clone()) {
+        result.push_str(&format!(": {} - {} {}", offer.hotel_name, offer.total_amount, offer.currency));
+    }
+    result.push_str(&format!("\nOffer ID: {}", selected.offer_id));
+    if let Some(expires_at) = &selected.expires_at {
+        result.push_str(&format!("\nBookable until {}", expires_at));
+    }
+    result
+}
+
+pub fn get_search_results_tool() -> Tool {
+    Tool::new(
+        "get_search_results",
+        "Re-read the offers an earlier hotel search returned, numbered as options",
+        json!({
+            "type": "object",
+            "properties": {
+                "search_id": {
+                    "type": "string",
+                    "description": "Search ID from search_stays"
+                },
+                "verbosity": verbosity_schema()
+            },
+            "required": ["search_id"]
+        }),
+    )
+    .with_output_schema(stay_search_output_schema())
+}
+
+pub fn select_offer_tool() -> Tool {
+    Tool::new(
+        "select_offer",
+        "Resolve an option number from an earlier hotel search (e.g. 'option 3') to its Duffel search result ID",
+        json!({
+            "type": "object",
+            "properties": {
+                "search_id": {
+                    "type": "string",
+                    "description": "Search ID from search_stays"
+                },
+                "option": {
+                    "type": "integer",
+                    "description": "Option number shown next to the offer in the search results, starting at 1"
+                }
+            },
+            "required": ["search_id", "option"]
+        }),
+    )
+    .with_output_schema(json!({
+        "type": "object",
+        "properties": {
+            "search_id": { "type": "string" },
+            "option": { "type": "integer" },
+            "offer_id": { "type": "string", "description": "Duffel search result ID" },
+            "expires_at": { "type": ["string", "null"], "description": "When the offer can no longer be booked" },
+            "offer": { "type": "object", "description": "The hotel offer as search_stays returned it" }
+        },
+        "required": ["search_id", "option", "offer_id", "offer"]
+    }))
+}
+
+pub fn search_resource_template() -> ResourceTemplate {
+    ResourceTemplate::new(
+        "search://stays/{search_id}",
+        "stay-search",
+        "Hotels a recent stay search returned, numbered as options",
+    )
+}
+
+/// Recent saved searches, as resources; none without a database.
+pub async fn list_search_resources(stays: DuffelStayServer) -> Result<Vec<Resource>, RpcError> {
+    let Some(results) = &stays.results else {
+        return Ok(Vec::new());
+    };
+    let template = search_resource_template();
+    let searches = results
+        .recent(STAY_SEARCH, RECENT_SEARCH_RESOURCES)
+        .await
+        .map_err(|e| RpcError::server_error(format!("Saved search lookup failed: {}", e)))?;
+
+    Ok(searches
+        .into_iter()
+        .map(|search| {
+            Resource::new(template.uri(&search.search_id), search.search_id.clone())
+                .with_title(format!("Hotel search {}", search.search_id))
+                .with_description(format!(
+                    "{} hotels, searched at {}, kept until {}",
+                    search.offer_count, search.created_at, search.expires_at
+                ))
+        })
+        .collect())
+}
+
+pub async fn read_search_resource(stays: DuffelStayServer, search_id: String) -> Result<Value, RpcError> {
+    let Some(results) = &stays.results else {
+        return Ok(Value::Null);
+    };
+    let search = results
+        .get(STAY_SEARCH, &search_id)
+        .await
+        .map_err(|e| RpcError::server_error(format!("Saved search lookup failed: {}", e)))?;
+    serde_json::to_value(search).map_err(|e| RpcError::server_error(format!("Failed to serialize search: {}", e)))
+}
+
+pub async fn get_search_results(stays: DuffelStayServer, arguments: Value) -> Result<Value, RpcError> {
+    let request: GetSearchResultsRequest = mcp::parse_arguments("get_search_results", arguments)?;
+    let search = saved_results(&stays)?
+        .search(STAY_SEARCH, &request.search_id)
+        .await?;
+
+    let offers: Vec<StayOffer> = search
+        .offers
+        .into_iter()
+        .filter_map(|saved| serde_json::from_value(saved.offer).ok())
+        .collect();
+    let location_searched = offers
+        .first()
+        .map(|offer| offer.location.clone())
+        .unwrap_or_default();
+    let response = StaySearchResponse {
+        total_results: offers.len() as i32,
+        offers,
+        search_id: search.search_id,
+        location_searched,
+        next_page_token: None,
+        skipped_results: 0,
+        warnings: Vec::new(),
+    };
+
+    search_results_content(&stays, &response, 1, Verbosity::resolve(request.verbosity))
+}
+
+pub async fn select_offer(stays: DuffelStayServer, arguments: Value) -> Result<Value, RpcError> {
+    let request: SelectOfferRequest = mcp::parse_arguments("select_offer", arguments)?;
+    let saved = saved_results(&stays)?
+        .option(STAY_SEARCH, &request.search_id, request.option)
+        .await?;
+
+    let selected = SelectedOffer {
+        search_id: request.search_id,
+        option: saved.option,
+        offer_id: saved.offer_id,
+        expires_at: saved.expires_at,
+        offer: saved.offer,
+    };
+    mcp::structured_content(format_selected(&selected), &selected)
+}
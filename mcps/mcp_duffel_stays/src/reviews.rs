@@ -0,0 +1,337 @@
+//! Guest reviews for hotel search results (`include_reviews`).
+//!
+//! Duffel's star rating says little about what guests thought, so search results
+//! can be enriched from a [`ReviewProvider`]. `REVIEWS_PROVIDER` selects one at
+//! startup; `google_places` (the default) needs `GOOGLE_PLACES_API_KEY`, and without
+//! it reviews are off.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use bookedai_core::duffel_models::StaysSearchResult;
+use bookedai_core::{config, progress};
+use futures_util::future::BoxFuture;
+use futures_util::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::{DuffelStayServer, StayOffer};
+
+const GOOGLE_PLACES_SEARCH_URL: &str = "https://places.googleapis.com/v1/places:searchText";
+
+/// Only what is shown is requested; Google bills Places calls by field.
+const GOOGLE_PLACES_FIELDS: &str = "places.displayName,places.rating,places.userRatingCount,places.reviews";
+
+/// How far from Duffel's coordinates a Places match may be.
+const LOCATION_BIAS_RADIUS_M: f64 = 500.0;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Review lookups in flight at once for one page of hotels.
+const REVIEW_FETCH_CONCURRENCY: usize = 4;
+
+/// Snippets kept per hotel.
+const MAX_SNIPPETS: usize = 3;
+
+/// Longest snippet, in characters; longer reviews are cut at a word boundary.
+const MAX_SNIPPET_CHARS: usize = 200;
+
+/// What a provider needs to find a hotel.
+#[derive(Debug, Clone)]
+pub struct ReviewQuery {
+    pub hotel_name: String,
+    /// Street address and city, as far as Duffel knows them.
+    pub address: Option<String>,
+    /// Latitude and longitude.
+    pub coordinates: Option<(f64, f64)>,
+}
+
+impl ReviewQuery {
+    fn from_duffel(result: &StaysSearchResult) -> Self {
+        let location = result.accommodation.location.as_ref();
+        let address = location.and_then(|location| location.address.as_ref()).map(|address| {
+            [&address.line_one, &address.city_name, &address.country_code]
+                .into_iter()
+                .flatten()
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(", ")
+        });
+        Self {
+            hotel_name: result.accommodation.name.clone(),
+            address: address.filter(|address| !address.is_empty()),
+            coordinates: location
+                .and_then(|location| location.geographic_coordinates.as_ref())
+                .map(|coordinates| (coordinates.latitude, coordinates.longitude)),
+        }
+    }
+}
+
+/// One guest review, shortened.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewSnippet {
+    pub text: String,
+    /// Out of 5.
+    pub rating: Option<f64>,
+    pub author: Option<String>,
+    /// When it was written, e.g. `2 months ago`.
+    pub published: Option<String>,
+}
+
+/// A hotel's guest rating and its most relevant reviews.
+#[derive(Debug, Clone)]
+pub struct HotelReviews {
+    /// Average guest rating out of 5.
+    pub score: Option<f64>,
+    pub count: Option<u32>,
+    pub snippets: Vec<ReviewSnippet>,
+}
+
+/// A source of hotel guest ratings and reviews.
+pub trait ReviewProvider: fmt::Debug + Send + Sync {
+    /// Name shown with the reviews, e.g. `Google Places`.
+    fn name(&self) -> &str;
+
+    /// Reviews for the hotel, or `None` when the provider does not know it.
+    fn reviews<'a>(&'a self, query: &'a ReviewQuery) -> BoxFuture<'a, Result<Option<HotelReviews>>>;
+}
+
+/// Build the provider named by `reviews.provider` (`REVIEWS_PROVIDER`, default:
+/// `google_places`); `None` when it has no API key.
+pub fn from_config() -> Result<Option<Arc<dyn ReviewProvider>>> {
+    let reviews = &config::get().reviews;
+    match reviews.provider.trim() {
+        "google_places" => Ok(reviews.google_places_api_key.as_ref().map(|api_key| {
+            Arc::new(GooglePlacesProvider::new(api_key.clone())) as Arc<dyn ReviewProvider>
+        })),
+        other => Err(anyhow::anyhow!("Unknown reviews.provider '{}', expected google_places", other)),
+    }
+}
+
+/// Ratings and reviews from the Google Places API (Text Search).
+#[derive(Clone)]
+pub struct GooglePlacesProvider {
+    client: reqwest::Client,
+    api_key: String,
+}
+
+impl fmt::Debug for GooglePlacesProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GooglePlacesProvider").finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PlacesResponse {
+    #[serde(default)]
+    places: Vec<Place>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Place {
+    rating: Option<f64>,
+    user_rating_count: Option<u32>,
+    #[serde(default)]
+    reviews: Vec<PlaceReview>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PlaceReview {
+    rating: Option<f64>,
+    text: Option<LocalizedText>,
+    author_attribution: Option<AuthorAttribution>,
+    relative_publish_time_description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LocalizedText {
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AuthorAttribution {
+    display_name: Option<String>,
+}
+
+impl GooglePlacesProvider {
+    pub fn new(api_key: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_key,
+        }
+    }
+
+    async fn search(&self, query: &ReviewQuery) -> Result<Option<HotelReviews>> {
+        let text_query = match &query.address {
+            Some(address) => format!("{}, {}", query.hotel_name, address),
+            None => query.hotel_name.clone(),
+        };
+        let mut payload = json!({
+            "textQuery": text_query,
+            "includedType": "lodging",
+            "pageSize": 1
+        });
+        if let Some((latitude, longitude)) = query.coordinates {
+            payload["locationBias"] = json!({
+                "circle": {
+                    "center": { "latitude": latitude, "longitude": longitude },
+                    "radius": LOCATION_BIAS_RADIUS_M
+                }
+            });
+        }
+
+        let response: PlacesResponse = self
+            .client
+            .post(GOOGLE_PLACES_SEARCH_URL)
+            .header("X-Goog-Api-Key", &self.api_key)
+            .header("X-Goog-FieldMask", GOOGLE_PLACES_FIELDS)
+            .json(&payload)
+            .timeout(FETCH_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(response.places.into_iter().next().map(|place| HotelReviews {
+            score: place.rating,
+            count: place.user_rating_count,
+            snippets: place
+                .reviews
+                .into_iter()
+                .filter_map(|review| {
+                    let text = review.text.map(|text| shorten(&text.text))?;
+                    Some(ReviewSnippet {
+                        text,
+                        rating: review.rating,
+                        author: review.author_attribution.and_then(|author| author.display_name),
+                        published: review.relative_publish_time_description,
+                    })
+                })
+                .filter(|snippet| !snippet.text.is_empty())
+                .take(MAX_SNIPPETS)
+                .collect(),
+        }))
+    }
+}
+
+impl ReviewProvider for GooglePlacesProvider {
+    fn name(&self) -> &str {
+        "Google Places"
+    }
+
+    fn reviews<'a>(&'a self, query: &'a ReviewQuery) -> BoxFuture<'a, Result<Option<HotelReviews>>> {
+        Box::pin(self.search(query))
+    }
+}
+
+/// A review on one line, at most [`MAX_SNIPPET_CHARS`] characters.
+fn shorten(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= MAX_SNIPPET_CHARS {
+        return text;
+    }
+    let cut: String = text.chars().take(MAX_SNIPPET_CHARS).collect();
+    let cut = cut.rsplit_once(' ').map_or(cut.as_str(), |(words, _)| words);
+    format!("{}...", cut.trim_end_matches(|c: char| c.is_ascii_punctuation()))
+}
+
+impl DuffelStayServer {
+    /// Add guest reviews to a page of offers, looked up by the Duffel results they came from.
+    ///
+    /// Returns warnings for reviews that could not be fetched, or one when reviews are off.
+    pub async fn add_reviews(&self, offers: &mut [StayOffer], results: &[StaysSearchResult]) -> Vec<String> {
+        let Some(provider) = &self.reviews else {
+            return vec!["Reviews are not configured; set GOOGLE_PLACES_API_KEY to include them".to_string()];
+        };
+
+        let mut queries: HashMap<&str, ReviewQuery> = results
+            .iter()
+            .map(|result| (result.id.as_str(), ReviewQuery::from_duffel(result)))
+            .collect();
+        let pending: Vec<(usize, ReviewQuery)> = offers
+            .iter()
+            .enumerate()
+            .filter_map(|(index, offer)| Some((index, queries.remove(offer.id.as_str())?)))
+            .collect();
+
+        let total = pending.len();
+        let mut done = 0;
+        let fetched: Vec<(usize, Result<Option<HotelReviews>>)> = stream::iter(pending)
+            .map(|(index, query)| async move { (index, provider.reviews(&query).await) })
+            .buffer_unordered(REVIEW_FETCH_CONCURRENCY)
+            .inspect(|_| {
+                done += 1;
+                progress::report(format!("Fetched reviews for {} of {} hotels", done, total));
+            })
+            .collect()
+            .await;
+
+        let mut warnings = Vec::new();
+        for (index, reviews) in fetched {
+            let offer = &mut offers[index];
+            match reviews {
+                Ok(Some(reviews)) => {
+                    offer.review_score = reviews.score;
+                    offer.review_count = reviews.count;
+                    offer.review_snippets = reviews.snippets;
+                    offer.review_source = Some(provider.name().to_string());
+                }
+                Ok(None) => info!("{} has no reviews for {}", provider.name(), offer.hotel_name),
+                Err(e) => {
+                    let warning = format!("Reviews for {} could not be fetched: {}", offer.hotel_name, e);
+                    warn!("{} ({})", warning, offer.id);
+                    warnings.push(warning);
+                }
+            }
+        }
+        warnings
+    }
+}
+
+/// Guest rating line and snippets for the text listing.
+pub fn describe(result: &mut String, offer: &StayOffer) {
+    describe_score(result, offer);
+    for snippet in &offer.review_snippets {
+        result.push_str(&format!("      \"{}\"", snippet.text));
+        if let Some(author) = &snippet.author {
+            result.push_str(&format!(" - {}", author));
+        }
+        result.push('\n');
+    }
+}
+
+/// Guest rating line alone, for summary listings.
+pub fn describe_score(result: &mut String, offer: &StayOffer) {
+    if let Some(score) = offer.review_score {
+        result.push_str(&format!("   Guest rating: {:.1}/5", score));
+        if let Some(count) = offer.review_count {
+            result.push_str(&format!(" from {} reviews", count));
+        }
+        if let Some(source) = &offer.review_source {
+            result.push_str(&format!(" ({})", source));
+        }
+        result.push('\n');
+    }
+}
+
+/// `review_snippets` items in the search output schema.
+pub fn snippet_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "text": { "type": "string" },
+            "rating": { "type": ["number", "null"], "description": "Out of 5" },
+            "author": { "type": ["string", "null"] },
+            "published": { "type": ["string", "null"], "description": "When it was written, e.g. '2 months ago'" }
+        },
+        "required": ["text"]
+    })
+}
@@ -0,0 +1,121 @@
+//! `/mcp` end to end: the stays server over HTTP against a fake Duffel.
+
+use bookedai_testing::{duffel_error, FakeDuffel, TestServer, API_TOKEN};
+use serde_json::{json, Value};
+
+const BINARY: &str = env!("CARGO_BIN_EXE_mcp_duffel_stays");
+
+fn search() -> Value {
+    json!({
+        "location": "London",
+        "check_in_date": "2030-06-01",
+        "check_out_date": "2030-06-03",
+        "adults": 2
+    })
+}
+
+async fn started(duffel: &FakeDuffel) -> TestServer {
+    let mut server = TestServer::start(BINARY, duffel, &[]).await;
+    let result = server.initialize().await;
+    assert!(result["serverInfo"]["name"].is_string(), "initialize result: {}", result);
+    server
+}
+
+#[tokio::test]
+async fn lists_tools_and_searches_stays() {
+    let duffel = FakeDuffel::start().await;
+    let mut server = started(&duffel).await;
+
+    let tools = server.tool_names().await;
+    assert!(tools.iter().any(|name| name == "search_stays"), "tools: {:?}", tools);
+
+    let response = server.call_tool("search_stays", search()).await;
+    let results = &response["result"]["structuredContent"];
+    let offers = results["offers"].as_array().unwrap_or_else(|| panic!("search response: {}", response));
+    assert!(!offers.is_empty());
+    assert!(results["search_id"].is_string());
+
+    let sent = duffel.requests_to("/stays/search").await;
+    assert_eq!(sent.len(), 1);
+    let headers = &sent[0].headers;
+    assert_eq!(headers.get("authorization").unwrap(), &format!("Bearer {}", API_TOKEN));
+    assert_eq!(headers.get("duffel-version").unwrap(), "v2");
+}
+
+async fn error_code(status: u16, error_type: &str, code: &str) -> Value {
+    let duffel = FakeDuffel::start().await;
+    duffel
+        .fail("POST", "/stays/search", status, duffel_error(error_type, code, "Rejected by the fake"))
+        .await;
+    let mut server = started(&duffel).await;
+    let response = server.call_tool("search_stays", search()).await;
+    assert!(response["result"].is_null(), "expected an error: {}", response);
+    response["error"]["code"].clone()
+}
+
+#[tokio::test]
+async fn maps_duffel_validation_errors() {
+    assert_eq!(error_code(422, "validation_error", "invalid_field").await, json!(-32010));
+}
+
+#[tokio::test]
+async fn maps_duffel_rate_limits() {
+    assert_eq!(error_code(429, "rate_limit_error", "rate_limit_exceeded").await, json!(-32012));
+}
+
+#[tokio::test]
+async fn maps_duffel_outages() {
+    assert_eq!(error_code(500, "api_error", "internal_server_error").await, json!(-32016));
+}
+
+#[tokio::test]
+async fn searches_around_airport_codes() {
+    let duffel = FakeDuffel::start().await;
+    let mut server = started(&duffel).await;
+    let mut arguments = search();
+    arguments["location"] = json!("LIS");
+    let response = server.call_tool("search_stays", arguments).await;
+    assert!(response["result"]["structuredContent"]["offers"].is_array(), "search response: {}", response);
+
+    let sent = duffel.requests_to("/stays/search").await;
+    let body: Value = serde_json::from_slice(&sent[0].body).unwrap();
+    let coordinates = &body["data"]["location"]["geographic_coordinates"];
+    assert!((coordinates["latitude"].as_f64().unwrap() - 38.77).abs() < 0.1, "{}", body);
+    assert!((coordinates["longitude"].as_f64().unwrap() + 9.13).abs() < 0.1, "{}", body);
+}
+
+#[tokio::test]
+async fn refuses_places_it_cannot_find() {
+    let duffel = FakeDuffel::start().await;
+    let mut server = started(&duffel).await;
+    let mut arguments = search();
+    arguments["location"] = json!("Nowhereville");
+    let response = server.call_tool("search_stays", arguments).await;
+    assert_eq!(response["error"]["code"], json!(-32602), "{}", response);
+    assert!(duffel.requests_to("/stays/search").await.is_empty());
+}
+
+#[tokio::test]
+async fn numbers_later_pages_on_from_the_first() {
+    let duffel = FakeDuffel::start().await;
+    let mut server = started(&duffel).await;
+
+    let mut arguments = search();
+    arguments["limit"] = json!(1);
+    let first = server.call_tool("search_stays", arguments.clone()).await;
+    let first = &first["result"]["structuredContent"];
+    let token = first["next_page_token"].as_str().unwrap_or_else(|| panic!("first page: {}", first));
+
+    // Duffel gives the repeated search an ID of its own
+    let mut again: Value =
+        serde_json::from_str(include_str!("../../bookedai-core/fixtures/duffel/stays_search.json")).unwrap();
+    again["meta"]["request_id"] = json!("second_request");
+    duffel.fail("POST", "/stays/search", 201, again).await;
+    arguments["page_token"] = json!(token);
+    let response = server.call_tool("search_stays", arguments).await;
+    let second = &response["result"]["structuredContent"];
+    assert_eq!(second["search_id"], first["search_id"], "{}", response);
+    assert_ne!(second["offers"][0]["id"], first["offers"][0]["id"]);
+    let text = response["result"]["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains("\n2. ") && !text.contains("\n1. "), "{}", text);
+}
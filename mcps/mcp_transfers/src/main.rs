@@ -0,0 +1,478 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use bookedai_core::http::HttpOptions;
+use bookedai_core::readiness::Readiness;
+use bookedai_core::{config, mcp, pagination, transport, validation};
+use bookedai_core::{McpServer, ResponseCache, RpcError, Tool, Transport};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{error, info};
+
+mod provider;
+
+use provider::{CarRentalQuery, CarRentalQuote, GroundProvider, TransferQuery, TransferQuote};
+
+/// Largest party a single transfer search covers.
+const MAX_PASSENGERS: u32 = 16;
+
+const MIN_DRIVER_AGE: u32 = 18;
+const DEFAULT_DRIVER_AGE: u32 = 30;
+
+const CAR_CLASSES: &[&str] = &["economy", "compact", "midsize", "suv", "premium", "van"];
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TransferSearchRequest {
+    pickup: String,
+    dropoff: String,
+    pickup_datetime: String,
+    passengers: Option<u32>,
+    luggage: Option<u32>,
+    limit: Option<usize>,
+}
+
+impl TransferSearchRequest {
+    fn to_query(&self) -> Result<TransferQuery, RpcError> {
+        let pickup = required("pickup", &self.pickup)?;
+        let dropoff = required("dropoff", &self.dropoff)?;
+        if pickup.eq_ignore_ascii_case(&dropoff) {
+            return Err(RpcError::invalid_params(
+                "Invalid parameters: pickup and dropoff must be different places",
+            ));
+        }
+        let passengers = self.passengers.unwrap_or(1);
+        if passengers == 0 || passengers > MAX_PASSENGERS {
+            return Err(RpcError::invalid_params(format!(
+                "Invalid parameters: passengers must be between 1 and {}",
+                MAX_PASSENGERS
+            )));
+        }
+
+        Ok(TransferQuery {
+            pickup,
+            dropoff,
+            pickup_time: validation::upcoming_date_time("pickup_datetime", &self.pickup_datetime)?,
+            passengers,
+            // One bag per passenger unless told otherwise
+            luggage: self.luggage.unwrap_or(passengers),
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CarRentalSearchRequest {
+    pickup_location: String,
+    dropoff_location: Option<String>,
+    pickup_datetime: String,
+    dropoff_datetime: String,
+    driver_age: Option<u32>,
+    car_class: Option<String>,
+    limit: Option<usize>,
+}
+
+impl CarRentalSearchRequest {
+    fn to_query(&self) -> Result<CarRentalQuery, RpcError> {
+        let pickup_location = required("pickup_location", &self.pickup_location)?;
+        let dropoff_location = match &self.dropoff_location {
+            Some(location) => required("dropoff_location", location)?,
+            None => pickup_location.clone(),
+        };
+
+        let pickup_time = validation::upcoming_date_time("pickup_datetime", &self.pickup_datetime)?;
+        let dropoff_time = validation::date_time("dropoff_datetime", &self.dropoff_datetime)?;
+        if dropoff_time <= pickup_time {
+            return Err(RpcError::invalid_params(format!(
+                "Invalid parameters: dropoff_datetime ({}) must be after pickup_datetime ({})",
+                self.dropoff_datetime, self.pickup_datetime
+            )));
+        }
+
+        let driver_age = self.driver_age.unwrap_or(DEFAULT_DRIVER_AGE);
+        if driver_age < MIN_DRIVER_AGE {
+            return Err(RpcError::invalid_params(format!(
+                "Invalid parameters: driver_age must be at least {}",
+                MIN_DRIVER_AGE
+            )));
+        }
+
+        let car_class = self.car_class.as_deref().map(|class| class.trim().to_lowercase());
+        if let Some(class) = &car_class {
+            if !CAR_CLASSES.contains(&class.as_str()) {
+                return Err(RpcError::invalid_params(format!(
+                    "Invalid parameters: car_class must be one of {}, got '{}'",
+                    CAR_CLASSES.join(", "),
+                    class
+                )));
+            }
+        }
+
+        Ok(CarRentalQuery {
+            pickup_location,
+            dropoff_location,
+            pickup_time,
+            dropoff_time,
+            driver_age,
+            car_class,
+        })
+    }
+}
+
+fn required(field: &str, value: &str) -> Result<String, RpcError> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(RpcError::invalid_params(format!("Invalid parameters: {} must not be empty", field)));
+    }
+    Ok(value.to_string())
+}
+
+fn price(amount: &str) -> f64 {
+    amount.parse().unwrap_or(f64::MAX)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TransferSearchResponse {
+    quotes: Vec<TransferQuote>,
+    total_results: usize,
+    provider: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CarRentalSearchResponse {
+    quotes: Vec<CarRentalQuote>,
+    total_results: usize,
+    provider: String,
+}
+
+#[derive(Debug, Clone)]
+struct TransfersServer {
+    provider: Arc<dyn GroundProvider>,
+}
+
+impl TransfersServer {
+    fn new() -> Result<Self> {
+        Ok(Self {
+            provider: provider::from_config()?,
+        })
+    }
+
+    async fn search_transfers(&self, query: &TransferQuery, limit: usize) -> Result<TransferSearchResponse> {
+        info!("Searching transfers from {} to {}", query.pickup, query.dropoff);
+        let mut quotes = self.provider.search_transfers(query).await?;
+        let total_results = quotes.len();
+        quotes.sort_by(|a, b| price(&a.price).total_cmp(&price(&b.price)));
+        quotes.truncate(limit);
+
+        Ok(TransferSearchResponse {
+            quotes,
+            total_results,
+            provider: self.provider.name().to_string(),
+        })
+    }
+
+    async fn search_car_rentals(&self, query: &CarRentalQuery, limit: usize) -> Result<CarRentalSearchResponse> {
+        info!("Searching car rentals at {}", query.pickup_location);
+        let mut quotes = self.provider.search_car_rentals(query).await?;
+        let total_results = quotes.len();
+        quotes.sort_by(|a, b| price(&a.price).total_cmp(&price(&b.price)));
+        quotes.truncate(limit);
+
+        Ok(CarRentalSearchResponse {
+            quotes,
+            total_results,
+            provider: self.provider.name().to_string(),
+        })
+    }
+
+    fn format_transfer_results(&self, response: &TransferSearchResponse) -> String {
+        let Some(first) = response.quotes.first() else {
+            return "No transfers found for the specified trip.".to_string();
+        };
+
+        let mut result = format!(
+            "Found {} transfer options from {} to {} ({}):\n\n",
+            response.total_results, first.pickup, first.dropoff, first.pickup_time
+        );
+        for (i, quote) in response.quotes.iter().enumerate() {
+            result.push_str(&format!(
+                "{}. {} - {} {}\n",
+                i + 1,
+                quote.vehicle_type,
+                quote.price,
+                quote.currency
+            ));
+            result.push_str(&format!(
+                "   Up to {} passengers, {} bags\n",
+                quote.max_passengers, quote.max_luggage
+            ));
+            if let Some(minutes) = quote.estimated_duration_minutes {
+                result.push_str(&format!("   Estimated journey: {} min\n", minutes));
+            }
+            result.push_str(&format!("   Cancellation: {}\n", quote.cancellation_policy));
+            result.push_str(&format!("   Quote ID: {}\n\n", quote.id));
+        }
+        result.push_str(&format!("Provider: {}", response.provider));
+        result
+    }
+
+    fn format_car_rental_results(&self, response: &CarRentalSearchResponse) -> String {
+        let Some(first) = response.quotes.first() else {
+            return "No rental cars found for the specified dates.".to_string();
+        };
+
+        let mut result = format!(
+            "Found {} rental cars at {} ({} to {}):\n\n",
+            response.total_results, first.pickup_location, first.pickup_time, first.dropoff_time
+        );
+        for (i, quote) in response.quotes.iter().enumerate() {
+            result.push_str(&format!(
+                "{}. {} ({}) - {} {} for {} days\n",
+                i + 1,
+                quote.example_model,
+                quote.car_class,
+                quote.price,
+                quote.currency,
+                quote.rental_days
+            ));
+            result.push_str(&format!(
+                "   {} seats, {}, {}\n",
+                quote.seats, quote.transmission, quote.mileage_policy
+            ));
+            if quote.dropoff_location != quote.pickup_location {
+                result.push_str(&format!("   Return to: {}\n", quote.dropoff_location));
+            }
+            result.push_str(&format!("   Supplier: {}\n", quote.supplier));
+            result.push_str(&format!("   Quote ID: {}\n\n", quote.id));
+        }
+        result.push_str(&format!("Provider: {}", response.provider));
+        result
+    }
+}
+
+fn search_transfers_tool() -> Tool {
+    Tool::new(
+        "search_transfers",
+        "Search private airport and city transfers (sedan, van, minibus) between two places",
+        json!({
+            "type": "object",
+            "properties": {
+                "pickup": {
+                    "type": "string",
+                    "description": "Pickup point: an airport code (e.g. 'LHR') or an address or hotel name"
+                },
+                "dropoff": {
+                    "type": "string",
+                    "description": "Drop-off point: an airport code or an address or hotel name"
+                },
+                "pickup_datetime": {
+                    "type": "string",
+                    "description": "Local pickup time in YYYY-MM-DDTHH:MM format"
+                },
+                "passengers": {
+                    "type": "integer",
+                    "description": format!("Number of passengers, 1-{} (default: 1)", MAX_PASSENGERS)
+                },
+                "luggage": {
+                    "type": "integer",
+                    "description": "Number of large bags (default: one per passenger)"
+                },
+                "limit": {
+                    "type": "integer",
+                    "description": format!(
+                        "Maximum quotes to return (1-{}, default: {})",
+                        pagination::MAX_LIMIT,
+                        pagination::DEFAULT_LIMIT
+                    )
+                }
+            },
+            "required": ["pickup", "dropoff", "pickup_datetime"]
+        }),
+    )
+    .with_output_schema(json!({
+        "type": "object",
+        "properties": {
+            "quotes": {
+                "type": "array",
+                "description": "Cheapest first",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "id": { "type": "string" },
+                        "provider": { "type": "string" },
+                        "vehicle_type": { "type": "string", "description": "sedan, van, or minibus" },
+                        "max_passengers": { "type": "integer" },
+                        "max_luggage": { "type": "integer" },
+                        "price": { "type": "string", "description": "Total price as a decimal string" },
+                        "currency": { "type": "string", "description": "ISO 4217 currency code" },
+                        "estimated_duration_minutes": { "type": ["integer", "null"] },
+                        "pickup": { "type": "string" },
+                        "dropoff": { "type": "string" },
+                        "pickup_time": { "type": "string" },
+                        "cancellation_policy": { "type": "string" }
+                    },
+                    "required": [
+                        "id", "provider", "vehicle_type", "max_passengers", "max_luggage",
+                        "price", "currency", "pickup", "dropoff", "pickup_time", "cancellation_policy"
+                    ]
+                }
+            },
+            "total_results": { "type": "integer", "description": "Quotes before the limit was applied" },
+            "provider": { "type": "string", "description": "Provider that priced the quotes" }
+        },
+        "required": ["quotes", "total_results", "provider"]
+    }))
+    .cacheable()
+}
+
+fn search_car_rentals_tool() -> Tool {
+    Tool::new(
+        "search_car_rentals",
+        "Search rental cars for a pickup location and rental period",
+        json!({
+            "type": "object",
+            "properties": {
+                "pickup_location": {
+                    "type": "string",
+                    "description": "Where to collect the car: an airport code (e.g. 'CDG') or a city"
+                },
+                "dropoff_location": {
+                    "type": "string",
+                    "description": "Where to return the car (default: the pickup location)"
+                },
+                "pickup_datetime": {
+                    "type": "string",
+                    "description": "Local pickup time in YYYY-MM-DDTHH:MM format"
+                },
+                "dropoff_datetime": {
+                    "type": "string",
+                    "description": "Local return time in YYYY-MM-DDTHH:MM format"
+                },
+                "driver_age": {
+                    "type": "integer",
+                    "description": format!(
+                        "Age of the main driver, at least {} (default: {}); drivers under 25 pay a surcharge",
+                        MIN_DRIVER_AGE, DEFAULT_DRIVER_AGE
+                    )
+                },
+                "car_class": {
+                    "type": "string",
+                    "enum": CAR_CLASSES,
+                    "description": "Only return cars of this class"
+                },
+                "limit": {
+                    "type": "integer",
+                    "description": format!(
+                        "Maximum quotes to return (1-{}, default: {})",
+                        pagination::MAX_LIMIT,
+                        pagination::DEFAULT_LIMIT
+                    )
+                }
+            },
+            "required": ["pickup_location", "pickup_datetime", "dropoff_datetime"]
+        }),
+    )
+    .with_output_schema(json!({
+        "type": "object",
+        "properties": {
+            "quotes": {
+                "type": "array",
+                "description": "Cheapest first",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "id": { "type": "string" },
+                        "provider": { "type": "string" },
+                        "supplier": { "type": "string", "description": "Rental company" },
+                        "car_class": { "type": "string" },
+                        "example_model": { "type": "string" },
+                        "seats": { "type": "integer" },
+                        "transmission": { "type": "string" },
+                        "price": { "type": "string", "description": "Total price for the rental as a decimal string" },
+                        "currency": { "type": "string", "description": "ISO 4217 currency code" },
+                        "rental_days": { "type": "integer" },
+                        "pickup_location": { "type": "string" },
+                        "dropoff_location": { "type": "string" },
+                        "pickup_time": { "type": "string" },
+                        "dropoff_time": { "type": "string" },
+                        "mileage_policy": { "type": "string" }
+                    },
+                    "required": [
+                        "id", "provider", "supplier", "car_class", "example_model", "seats", "transmission",
+                        "price", "currency", "rental_days", "pickup_location", "dropoff_location",
+                        "pickup_time", "dropoff_time", "mileage_policy"
+                    ]
+                }
+            },
+            "total_results": { "type": "integer", "description": "Quotes before the limit was applied" },
+            "provider": { "type": "string", "description": "Provider that priced the quotes" }
+        },
+        "required": ["quotes", "total_results", "provider"]
+    }))
+    .cacheable()
+}
+
+async fn search_transfers(transfers: TransfersServer, arguments: Value) -> Result<Value, RpcError> {
+    let search_request: TransferSearchRequest = mcp::parse_arguments("search_transfers", arguments)?;
+    let limit = pagination::resolve_limit(search_request.limit)?;
+    let query = search_request.to_query()?;
+
+    let search_response = transfers.search_transfers(&query, limit).await.map_err(|e| {
+        error!("Transfer search error: {}", e);
+        RpcError::server_error(format!("Transfer search failed: {}", e))
+    })?;
+
+    let formatted_results = transfers.format_transfer_results(&search_response);
+    mcp::structured_content(formatted_results, &search_response)
+}
+
+async fn search_car_rentals(transfers: TransfersServer, arguments: Value) -> Result<Value, RpcError> {
+    let search_request: CarRentalSearchRequest = mcp::parse_arguments("search_car_rentals", arguments)?;
+    let limit = pagination::resolve_limit(search_request.limit)?;
+    let query = search_request.to_query()?;
+
+    let search_response = transfers.search_car_rentals(&query, limit).await.map_err(|e| {
+        error!("Car rental search error: {}", e);
+        RpcError::server_error(format!("Car rental search failed: {}", e))
+    })?;
+
+    let formatted_results = transfers.format_car_rental_results(&search_response);
+    mcp::structured_content(formatted_results, &search_response)
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let transport = Transport::from_args()?;
+    // config.toml plus environment overrides; `--print-config` prints it and exits
+    config::init()?;
+
+    // Initialize logging (stderr, so stdout stays free for the stdio transport)
+    bookedai_core::logging::init();
+    info!("Starting Transfers MCP Server ({} transport)", transport);
+
+    // Initialize the server
+    let transfers = TransfersServer::new()?;
+    info!("Ground transport provider: {}", transfers.provider.name());
+
+    let server = McpServer::builder("transfers-mcp", env!("CARGO_PKG_VERSION"))
+        .title("Transfers & Car Rentals MCP Server")
+        .cache(ResponseCache::from_config())
+        .tool(search_transfers_tool(), {
+            let transfers = transfers.clone();
+            move |arguments| search_transfers(transfers.clone(), arguments)
+        })
+        .tool(search_car_rentals_tool(), move |arguments| {
+            search_car_rentals(transfers.clone(), arguments)
+        })
+        .build();
+
+    transport::serve(
+        server,
+        transport,
+        HttpOptions {
+            default_port: 3003,
+            log_target: "transfers",
+            webhooks: None,
+            readiness: Readiness::new(),
+        },
+    )
+    .await
+}
@@ -0,0 +1,227 @@
+//! Ground transport providers.
+//!
+//! Tools talk to a [`GroundProvider`] rather than a specific API, so a partner
+//! integration can be added without touching the tool layer. `TRANSFERS_PROVIDER`
+//! selects one at startup; `sample` (the default) is the only built-in provider.
+
+use std::fmt;
+use std::sync::Arc;
+
+use anyhow::Result;
+use bookedai_core::{airports, config};
+use chrono::NaiveDateTime;
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+
+/// A point-to-point transfer request, already validated.
+#[derive(Debug, Clone)]
+pub struct TransferQuery {
+    pub pickup: String,
+    pub dropoff: String,
+    pub pickup_time: NaiveDateTime,
+    pub passengers: u32,
+    pub luggage: u32,
+}
+
+/// A car rental request, already validated.
+#[derive(Debug, Clone)]
+pub struct CarRentalQuery {
+    pub pickup_location: String,
+    pub dropoff_location: String,
+    pub pickup_time: NaiveDateTime,
+    pub dropoff_time: NaiveDateTime,
+    pub driver_age: u32,
+    pub car_class: Option<String>,
+}
+
+/// A priced private transfer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferQuote {
+    pub id: String,
+    pub provider: String,
+    /// `sedan`, `van`, or `minibus`.
+    pub vehicle_type: String,
+    pub max_passengers: u32,
+    pub max_luggage: u32,
+    pub price: String,
+    pub currency: String,
+    pub estimated_duration_minutes: Option<i64>,
+    pub pickup: String,
+    pub dropoff: String,
+    pub pickup_time: String,
+    pub cancellation_policy: String,
+}
+
+/// A priced rental car.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CarRentalQuote {
+    pub id: String,
+    pub provider: String,
+    pub supplier: String,
+    /// `economy`, `compact`, `midsize`, `suv`, `premium`, or `van`.
+    pub car_class: String,
+    /// Representative model; the actual car may differ within the class.
+    pub example_model: String,
+    pub seats: u32,
+    pub transmission: String,
+    pub price: String,
+    pub currency: String,
+    pub rental_days: i64,
+    pub pickup_location: String,
+    pub dropoff_location: String,
+    pub pickup_time: String,
+    pub dropoff_time: String,
+    pub mileage_policy: String,
+}
+
+/// A source of transfer and car rental quotes.
+pub trait GroundProvider: fmt::Debug + Send + Sync {
+    /// Name reported in every quote, e.g. `sample`.
+    fn name(&self) -> &str;
+
+    fn search_transfers<'a>(&'a self, query: &'a TransferQuery) -> BoxFuture<'a, Result<Vec<TransferQuote>>>;
+
+    fn search_car_rentals<'a>(&'a self, query: &'a CarRentalQuery) -> BoxFuture<'a, Result<Vec<CarRentalQuote>>>;
+}
+
+/// Build the provider named by `transfers.provider` (`TRANSFERS_PROVIDER`, default: `sample`).
+pub fn from_config() -> Result<Arc<dyn GroundProvider>> {
+    match config::get().transfers.provider.trim() {
+        "sample" => Ok(Arc::new(SampleProvider)),
+        other => Err(anyhow::anyhow!("Unknown transfers.provider '{}', expected sample", other)),
+    }
+}
+
+/// Distance assumed when an endpoint is not an airport code we can locate.
+const UNKNOWN_DISTANCE_KM: f64 = 30.0;
+
+/// Average door-to-door speed used for duration estimates.
+const AVERAGE_SPEED_KMH: f64 = 45.0;
+
+/// (vehicle, seats, bags, base fare, per km) in EUR.
+const TRANSFER_VEHICLES: &[(&str, u32, u32, f64, f64)] = &[
+    ("sedan", 3, 3, 25.0, 1.6),
+    ("van", 7, 7, 40.0, 2.2),
+    ("minibus", 16, 16, 80.0, 3.5),
+];
+
+/// (class, example model, seats, transmission, EUR per day).
+const CAR_CLASSES: &[(&str, &str, u32, &str, f64)] = &[
+    ("economy", "Fiat 500 or similar", 4, "manual", 32.0),
+    ("compact", "Volkswagen Golf or similar", 5, "manual", 41.0),
+    ("midsize", "Toyota Corolla or similar", 5, "automatic", 52.0),
+    ("suv", "Nissan Qashqai or similar", 5, "automatic", 68.0),
+    ("premium", "BMW 5 Series or similar", 5, "automatic", 105.0),
+    ("van", "Ford Transit Custom or similar", 9, "manual", 95.0),
+];
+
+/// Drivers under this age pay a young-driver surcharge.
+const YOUNG_DRIVER_AGE: u32 = 25;
+
+/// Offline provider with deterministic, formula-based prices.
+///
+/// Useful for demos and for exercising clients before a partner API is wired up.
+/// Prices scale with the distance between airports in the embedded dataset; its
+/// quotes cannot be booked.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SampleProvider;
+
+/// Coordinates of an airport or metro-area code.
+fn coordinates(location: &str) -> Option<(f64, f64)> {
+    let code = location.trim().to_ascii_uppercase();
+    let code = airports::city_airports(&code)
+        .and_then(|codes| codes.first().copied())
+        .unwrap_or(&code);
+    airports::find(code).map(|airport| (airport.latitude, airport.longitude))
+}
+
+fn quote_id(kind: &str, parts: &[&str]) -> String {
+    let key: String = parts.join("|");
+    // FNV-1a keeps IDs stable across runs for the same request
+    let hash = key
+        .bytes()
+        .fold(0xcbf29ce484222325u64, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3));
+    format!("{}_{:016x}", kind, hash)
+}
+
+impl SampleProvider {
+    fn transfers(&self, query: &TransferQuery) -> Vec<TransferQuote> {
+        let distance_km = match (coordinates(&query.pickup), coordinates(&query.dropoff)) {
+            (Some(a), Some(b)) => airports::distance_km(a.0, a.1, b.0, b.1).max(5.0),
+            _ => UNKNOWN_DISTANCE_KM,
+        };
+        let pickup_time = query.pickup_time.format("%Y-%m-%dT%H:%M").to_string();
+
+        TRANSFER_VEHICLES
+            .iter()
+            .filter(|(_, seats, bags, _, _)| *seats >= query.passengers && *bags >= query.luggage)
+            .map(|(vehicle, seats, bags, base, per_km)| TransferQuote {
+                id: quote_id("trf", &[vehicle, &query.pickup, &query.dropoff, &pickup_time]),
+                provider: self.name().to_string(),
+                vehicle_type: vehicle.to_string(),
+                max_passengers: *seats,
+                max_luggage: *bags,
+                price: format!("{:.2}", base + per_km * distance_km),
+                currency: "EUR".to_string(),
+                estimated_duration_minutes: Some((distance_km / AVERAGE_SPEED_KMH * 60.0).round() as i64 + 10),
+                pickup: query.pickup.clone(),
+                dropoff: query.dropoff.clone(),
+                pickup_time: pickup_time.clone(),
+                cancellation_policy: "Free cancellation up to 24 hours before pickup".to_string(),
+            })
+            .collect()
+    }
+
+    fn car_rentals(&self, query: &CarRentalQuery) -> Vec<CarRentalQuote> {
+        // Rentals are charged per started 24 hours
+        let minutes = (query.dropoff_time - query.pickup_time).num_minutes();
+        let rental_days = ((minutes + 24 * 60 - 1) / (24 * 60)).max(1);
+        let one_way_fee = if query.pickup_location.eq_ignore_ascii_case(&query.dropoff_location) {
+            0.0
+        } else {
+            60.0
+        };
+        let young_driver_fee = if query.driver_age < YOUNG_DRIVER_AGE { 15.0 } else { 0.0 };
+        let pickup_time = query.pickup_time.format("%Y-%m-%dT%H:%M").to_string();
+        let dropoff_time = query.dropoff_time.format("%Y-%m-%dT%H:%M").to_string();
+
+        CAR_CLASSES
+            .iter()
+            .filter(|(class, ..)| query.car_class.as_deref().is_none_or(|wanted| wanted == *class))
+            .map(|(class, model, seats, transmission, per_day)| CarRentalQuote {
+                id: quote_id("car", &[class, &query.pickup_location, &pickup_time, &dropoff_time]),
+                provider: self.name().to_string(),
+                supplier: "Sample Rent-a-Car".to_string(),
+                car_class: class.to_string(),
+                example_model: model.to_string(),
+                seats: *seats,
+                transmission: transmission.to_string(),
+                price: format!(
+                    "{:.2}",
+                    (per_day + young_driver_fee) * rental_days as f64 + one_way_fee
+                ),
+                currency: "EUR".to_string(),
+                rental_days,
+                pickup_location: query.pickup_location.clone(),
+                dropoff_location: query.dropoff_location.clone(),
+                pickup_time: pickup_time.clone(),
+                dropoff_time: dropoff_time.clone(),
+                mileage_policy: "Unlimited mileage".to_string(),
+            })
+            .collect()
+    }
+}
+
+impl GroundProvider for SampleProvider {
+    fn name(&self) -> &str {
+        "sample"
+    }
+
+    fn search_transfers<'a>(&'a self, query: &'a TransferQuery) -> BoxFuture<'a, Result<Vec<TransferQuote>>> {
+        Box::pin(async move { Ok(self.transfers(query)) })
+    }
+
+    fn search_car_rentals<'a>(&'a self, query: &'a CarRentalQuery) -> BoxFuture<'a, Result<Vec<CarRentalQuote>>> {
+        Box::pin(async move { Ok(self.car_rentals(query)) })
+    }
+}
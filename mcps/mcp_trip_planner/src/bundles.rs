@@ -0,0 +1,225 @@
+//! Pairing flight and stay offers into priced trip bundles.
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// A price converted by the upstream server into the requested currency.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConvertedAmount {
+    pub amount: String,
+    pub currency: String,
+}
+
+/// One slice of a flight offer, as much of it as a bundle summary needs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlightSliceSummary {
+    pub origin: String,
+    pub destination: String,
+    pub departure_time: String,
+    pub arrival_time: String,
+    pub stops: usize,
+}
+
+/// A flight offer from the flights server's `search_flights` result.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlightOption {
+    pub id: String,
+    pub price: String,
+    pub currency: String,
+    pub airline: String,
+    pub converted_amount: Option<ConvertedAmount>,
+    pub slices: Vec<FlightSliceSummary>,
+}
+
+/// A hotel offer from the stays server's `search_stays` result.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StayOption {
+    pub id: String,
+    pub hotel_name: String,
+    pub hotel_rating: Option<f64>,
+    pub location: String,
+    pub total_amount: String,
+    pub currency: String,
+    pub converted_amount: Option<ConvertedAmount>,
+    pub room_type: Option<String>,
+    pub cancellation_policy: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FlightResults {
+    pub offers: Vec<FlightOption>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StayResults {
+    pub offers: Vec<StayOption>,
+}
+
+/// A flight and a stay that together fit the trip.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TripBundle {
+    pub rank: usize,
+    /// Flight plus stay, as a decimal string.
+    pub total_price: String,
+    pub currency: String,
+    pub remaining_budget: String,
+    pub flight: FlightOption,
+    pub stay: StayOption,
+}
+
+/// Price used for comparison: the converted amount when there is one.
+fn comparable(amount: &str, currency: &str, converted: Option<&ConvertedAmount>) -> Option<(f64, String)> {
+    let (amount, currency) = match converted {
+        Some(converted) => (converted.amount.as_str(), converted.currency.as_str()),
+        None => (amount, currency),
+    };
+    Some((amount.parse().ok()?, currency.to_string()))
+}
+
+impl FlightOption {
+    fn comparable_price(&self) -> Option<(f64, String)> {
+        comparable(&self.price, &self.currency, self.converted_amount.as_ref())
+    }
+}
+
+impl StayOption {
+    fn comparable_price(&self) -> Option<(f64, String)> {
+        comparable(&self.total_amount, &self.currency, self.converted_amount.as_ref())
+    }
+}
+
+/// The cheapest pairing found, whether or not it fits the budget.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheapestPairing {
+    pub total_price: String,
+    pub currency: String,
+}
+
+/// Every flight/stay pair priced in the same currency, cheapest first.
+///
+/// With `currency` set, only offers priced in it are paired, since `budget` is in
+/// that currency; offers whose conversion failed upstream are left out.
+/// Returns the pairs within `budget` (at most `limit`) and the cheapest pair overall.
+pub fn combine(
+    flights: &[FlightOption],
+    stays: &[StayOption],
+    currency: Option<&str>,
+    budget: f64,
+    limit: usize,
+) -> (Vec<TripBundle>, Option<CheapestPairing>) {
+    let in_currency = |price: &(f64, String)| currency.is_none_or(|currency| price.1 == currency);
+    let flight_prices: Vec<_> = flights
+        .iter()
+        .filter_map(|flight| Some((flight.comparable_price().filter(in_currency)?, flight)))
+        .collect();
+    let stay_prices: Vec<_> = stays
+        .iter()
+        .filter_map(|stay| Some((stay.comparable_price().filter(in_currency)?, stay)))
+        .collect();
+    if let Some(currency) = currency {
+        let dropped = (flights.len() - flight_prices.len(), stays.len() - stay_prices.len());
+        if dropped != (0, 0) {
+            warn!(
+                "Left out {} flight and {} stay offers not priced in {} (unconverted or unpriced)",
+                dropped.0, dropped.1, currency
+            );
+        }
+    }
+
+    let mut pairs: Vec<(f64, String, &FlightOption, &StayOption)> = Vec::new();
+    for ((flight_price, flight_currency), flight) in &flight_prices {
+        for ((stay_price, stay_currency), stay) in &stay_prices {
+            // Without a common display currency the totals cannot be compared
+            if stay_currency == flight_currency {
+                pairs.push((flight_price + stay_price, flight_currency.clone(), *flight, *stay));
+            }
+        }
+    }
+
+    // Cheapest first; among equal totals prefer the better-rated hotel
+    pairs.sort_by(|a, b| {
+        a.0.total_cmp(&b.0).then_with(|| {
+            let rating = |stay: &StayOption| stay.hotel_rating.unwrap_or(0.0);
+            rating(b.3).total_cmp(&rating(a.3))
+        })
+    });
+
+    let cheapest = pairs.first().map(|(total, currency, _, _)| CheapestPairing {
+        total_price: format!("{:.2}", total),
+        currency: currency.clone(),
+    });
+    let bundles = pairs
+        .into_iter()
+        .filter(|(total, _, _, _)| *total <= budget)
+        .take(limit)
+        .enumerate()
+        .map(|(i, (total, currency, flight, stay))| TripBundle {
+            rank: i + 1,
+            total_price: format!("{:.2}", total),
+            currency,
+            remaining_budget: format!("{:.2}", budget - total),
+            flight: flight.clone(),
+            stay: stay.clone(),
+        })
+        .collect();
+    (bundles, cheapest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flight(price: &str, currency: &str, converted: Option<(&str, &str)>) -> FlightOption {
+        FlightOption {
+            id: format!("off_{}{}", price, currency),
+            price: price.to_string(),
+            currency: currency.to_string(),
+            airline: "British Airways".to_string(),
+            converted_amount: converted.map(|(amount, currency)| ConvertedAmount {
+                amount: amount.to_string(),
+                currency: currency.to_string(),
+            }),
+            slices: Vec::new(),
+        }
+    }
+
+    fn stay(amount: &str, currency: &str, converted: Option<(&str, &str)>) -> StayOption {
+        StayOption {
+            id: format!("rat_{}{}", amount, currency),
+            hotel_name: "Hotel".to_string(),
+            hotel_rating: None,
+            location: "Lisbon".to_string(),
+            total_amount: amount.to_string(),
+            currency: currency.to_string(),
+            converted_amount: converted.map(|(amount, currency)| ConvertedAmount {
+                amount: amount.to_string(),
+                currency: currency.to_string(),
+            }),
+            room_type: None,
+            cancellation_policy: None,
+        }
+    }
+
+    #[test]
+    fn pairs_only_offers_in_the_requested_currency() {
+        let flights = [flight("300.00", "EUR", Some(("330.00", "USD"))), flight("100.00", "EUR", None)];
+        let stays = [stay("200.00", "EUR", Some(("220.00", "USD"))), stay("50.00", "EUR", None)];
+
+        let (bundles, cheapest) = combine(&flights, &stays, Some("USD"), 600.0, 10);
+        assert_eq!(bundles.len(), 1);
+        assert_eq!(bundles[0].total_price, "550.00");
+        assert_eq!(bundles[0].currency, "USD");
+        assert_eq!(bundles[0].remaining_budget, "50.00");
+        assert_eq!(cheapest.unwrap().total_price, "550.00");
+    }
+
+    #[test]
+    fn pairs_native_prices_without_a_currency() {
+        let flights = [flight("100.00", "EUR", None), flight("90.00", "GBP", None)];
+        let stays = [stay("50.00", "EUR", None)];
+
+        let (bundles, _) = combine(&flights, &stays, None, 1000.0, 10);
+        assert_eq!(bundles.len(), 1);
+        assert_eq!(bundles[0].total_price, "150.00");
+    }
+}
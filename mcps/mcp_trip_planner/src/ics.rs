@@ -0,0 +1,217 @@
+//! iCalendar (RFC 5545) output for exported itineraries.
+
+use chrono::{NaiveDate, NaiveDateTime, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+const PRODUCT_ID: &str = "-//BookedAI//Trip Planner//EN";
+
+/// Longest content line, in octets, before it is folded.
+const MAX_LINE_OCTETS: usize = 75;
+
+const LOCAL_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+const UTC_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// One calendar entry: a flight, or a hotel check-in or check-out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalendarEvent {
+    pub uid: String,
+    pub summary: String,
+    /// Local `YYYY-MM-DDTHH:MM:SS`, or `YYYY-MM-DD` for all-day events.
+    pub start: String,
+    pub end: String,
+    /// IANA time zones `start` and `end` are local to; `None`, or a zone chrono-tz does
+    /// not know, leaves them floating.
+    pub start_time_zone: Option<String>,
+    pub end_time_zone: Option<String>,
+    pub all_day: bool,
+    pub location: Option<String>,
+    pub description: String,
+    /// `false` for offers that have not been booked yet.
+    pub confirmed: bool,
+}
+
+impl CalendarEvent {
+    /// Start time for sorting; all-day events sort at the start of their day.
+    pub fn sort_key(&self) -> Option<NaiveDateTime> {
+        parse_local(&self.start).or_else(|| parse_date(&self.start)?.and_hms_opt(0, 0, 0))
+    }
+}
+
+fn parse_local(value: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value, LOCAL_TIME_FORMAT).ok()
+}
+
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    value.parse().ok()
+}
+
+/// Backslash-escape the characters RFC 5545 reserves in text values.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Append a content line, folded at [`MAX_LINE_OCTETS`] without splitting a character.
+fn push_line(ics: &mut String, line: &str) {
+    let mut octets = 0;
+    for c in line.chars() {
+        if octets + c.len_utf8() > MAX_LINE_OCTETS {
+            ics.push_str("\r\n ");
+            // The leading space counts towards the continuation line
+            octets = 1;
+        }
+        ics.push(c);
+        octets += c.len_utf8();
+    }
+    ics.push_str("\r\n");
+}
+
+/// `DTSTART`/`DTEND` property: a date, a UTC time, or a floating local time.
+///
+/// Zoned times are converted to UTC rather than written with `TZID`, which would
+/// need a `VTIMEZONE` definition for every zone used.
+fn time_property(name: &str, value: &str, time_zone: Option<&str>, all_day: bool) -> Option<String> {
+    if all_day {
+        let date = parse_date(value)?;
+        // All-day events end on the following day, exclusively
+        let date = if name == "DTEND" { date.succ_opt()? } else { date };
+        return Some(format!("{};VALUE=DATE:{}", name, date.format("%Y%m%d")));
+    }
+    let local = parse_local(value)?;
+    let utc = time_zone
+        .and_then(|time_zone| time_zone.parse::<Tz>().ok())
+        .and_then(|time_zone| local.and_local_timezone(time_zone).earliest())
+        .map(|time| time.with_timezone(&Utc));
+    Some(match utc {
+        Some(utc) => format!("{}:{}", name, utc.format(UTC_FORMAT)),
+        None => format!("{}:{}", name, local.format("%Y%m%dT%H%M%S")),
+    })
+}
+
+/// A `VCALENDAR` document with one `VEVENT` per event; events whose times cannot
+/// be read are left out.
+pub fn render(calendar_name: &str, events: &[CalendarEvent]) -> String {
+    let stamp = Utc::now().format(UTC_FORMAT).to_string();
+    let mut ics = String::new();
+    push_line(&mut ics, "BEGIN:VCALENDAR");
+    push_line(&mut ics, "VERSION:2.0");
+    push_line(&mut ics, &format!("PRODID:{}", PRODUCT_ID));
+    push_line(&mut ics, "CALSCALE:GREGORIAN");
+    push_line(&mut ics, "METHOD:PUBLISH");
+    push_line(&mut ics, &format!("X-WR-CALNAME:{}", escape(calendar_name)));
+
+    for event in events {
+        let start = time_property("DTSTART", &event.start, event.start_time_zone.as_deref(), event.all_day);
+        let end = time_property("DTEND", &event.end, event.end_time_zone.as_deref(), event.all_day);
+        let (Some(start), Some(end)) = (start, end) else {
+            continue;
+        };
+        push_line(&mut ics, "BEGIN:VEVENT");
+        push_line(&mut ics, &format!("UID:{}", event.uid));
+        push_line(&mut ics, &format!("DTSTAMP:{}", stamp));
+        push_line(&mut ics, &start);
+        push_line(&mut ics, &end);
+        push_line(&mut ics, &format!("SUMMARY:{}", escape(&event.summary)));
+        if let Some(location) = &event.location {
+            push_line(&mut ics, &format!("LOCATION:{}", escape(location)));
+        }
+        push_line(&mut ics, &format!("DESCRIPTION:{}", escape(&event.description)));
+        push_line(&mut ics, if event.confirmed { "STATUS:CONFIRMED" } else { "STATUS:TENTATIVE" });
+        if event.all_day {
+            // Check-in and check-out days should not block the calendar
+            push_line(&mut ics, "TRANSP:TRANSPARENT");
+        }
+        push_line(&mut ics, "END:VEVENT");
+    }
+    push_line(&mut ics, "END:VCALENDAR");
+    ics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(ics: &str) -> Vec<&str> {
+        ics.strip_suffix("\r\n").unwrap().split("\r\n").collect()
+    }
+
+    #[test]
+    fn escapes_reserved_characters() {
+        assert_eq!(escape("a\\b;c,d"), "a\\\\b\\;c\\,d");
+        assert_eq!(escape("one\r\ntwo\nthree"), "one\\ntwo\\nthree");
+        assert_eq!(escape("Lisboa: Hotel Avenida"), "Lisboa: Hotel Avenida");
+    }
+
+    #[test]
+    fn leaves_short_lines_unfolded() {
+        let mut ics = String::new();
+        push_line(&mut ics, &"a".repeat(MAX_LINE_OCTETS));
+        assert_eq!(lines(&ics), [&"a".repeat(MAX_LINE_OCTETS)]);
+    }
+
+    #[test]
+    fn folds_long_lines_at_75_octets() {
+        let mut ics = String::new();
+        push_line(&mut ics, &"a".repeat(200));
+        let lines = lines(&ics);
+        assert_eq!(lines.iter().map(|line| line.len()).collect::<Vec<_>>(), [75, 75, 52]);
+        assert!(lines[1..].iter().all(|line| line.starts_with(' ')));
+        let unfolded: String = lines.iter().map(|line| line.strip_prefix(' ').unwrap_or(line)).collect();
+        assert_eq!(unfolded, "a".repeat(200));
+    }
+
+    #[test]
+    fn folds_without_splitting_characters() {
+        let mut ics = String::new();
+        // 74 octets, then a 3-octet character that does not fit on the first line
+        let line = format!("{}€€", "a".repeat(74));
+        push_line(&mut ics, &line);
+        let lines = lines(&ics);
+        assert_eq!(lines, [&"a".repeat(74), " €€"]);
+        assert!(lines.iter().all(|line| line.len() <= MAX_LINE_OCTETS));
+    }
+
+    #[test]
+    fn writes_zoned_times_in_utc() {
+        let summer = time_property("DTSTART", "2027-07-01T09:30:00", Some("Europe/London"), false);
+        assert_eq!(summer.as_deref(), Some("DTSTART:20270701T083000Z"));
+        let winter = time_property("DTEND", "2027-01-15T18:00:00", Some("America/New_York"), false);
+        assert_eq!(winter.as_deref(), Some("DTEND:20270115T230000Z"));
+        let floating = time_property("DTSTART", "2027-07-01T09:30:00", Some("Not/AZone"), false);
+        assert_eq!(floating.as_deref(), Some("DTSTART:20270701T093000"));
+        let all_day = time_property("DTEND", "2027-07-03", None, true);
+        assert_eq!(all_day.as_deref(), Some("DTEND;VALUE=DATE:20270704"));
+    }
+
+    #[test]
+    fn never_writes_tzid() {
+        let event = CalendarEvent {
+            uid: "seg_1@bookedai".to_string(),
+            summary: "Flight BA117 LHR to JFK".to_string(),
+            start: "2027-07-01T09:30:00".to_string(),
+            end: "2027-07-01T12:30:00".to_string(),
+            start_time_zone: Some("Europe/London".to_string()),
+            end_time_zone: Some("America/New_York".to_string()),
+            all_day: false,
+            location: None,
+            description: String::new(),
+            confirmed: true,
+        };
+        let ics = render("Trip", &[event]);
+        assert!(!ics.contains("TZID"), "{}", ics);
+        assert!(ics.contains("DTEND:20270701T163000Z\r\n"), "{}", ics);
+    }
+}
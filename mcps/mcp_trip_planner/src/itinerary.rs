@@ -0,0 +1,480 @@
+//! `export_itinerary`: booked orders and selected offers as an iCalendar file.
+//!
+//! Flights come from the flights server (`get_order`, `select_offer`) and hotels
+//! from the stays server (`get_booking`, `select_offer`); every flight segment and
+//! every hotel check-in and check-out becomes an event. The `.ics` document is
+//! returned as an embedded resource so clients can save it or open it directly.
+
+use bookedai_core::{airports, errors, mcp, validation, RpcError, Tool};
+use futures_util::future;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{error, warn};
+
+use crate::ics::{self, CalendarEvent};
+use crate::{parse_results, TripPlannerServer};
+
+/// Most orders, bookings and offers one export may combine.
+const MAX_ITEMS: usize = 20;
+
+const DEFAULT_CALENDAR_NAME: &str = "Trip itinerary";
+
+const CALENDAR_MIME_TYPE: &str = "text/calendar";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ExportItineraryRequest {
+    /// Flight orders, `ord_...`.
+    #[serde(default)]
+    order_ids: Vec<String>,
+    /// Hotel bookings, `bok_...`.
+    #[serde(default)]
+    booking_ids: Vec<String>,
+    /// Offers picked from earlier searches but not booked yet.
+    #[serde(default)]
+    selected_offers: Vec<OfferSelection>,
+    calendar_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum OfferKind {
+    Flight,
+    Stay,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct OfferSelection {
+    kind: OfferKind,
+    search_id: String,
+    /// 1-based option number, as shown in the search results.
+    option: usize,
+}
+
+/// One thing to put in the calendar.
+#[derive(Debug, Clone)]
+enum ExportItem {
+    FlightOrder(String),
+    StayBooking(String),
+    Offer(OfferSelection),
+}
+
+impl ExportItem {
+    fn describe(&self) -> String {
+        match self {
+            ExportItem::FlightOrder(order_id) => format!("Order {}", order_id),
+            ExportItem::StayBooking(booking_id) => format!("Booking {}", booking_id),
+            ExportItem::Offer(selection) => format!("Option {} of search {}", selection.option, selection.search_id),
+        }
+    }
+}
+
+impl ExportItineraryRequest {
+    fn items(&self) -> Result<Vec<ExportItem>, RpcError> {
+        let mut items = Vec::new();
+        for order_id in &self.order_ids {
+            items.push(ExportItem::FlightOrder(validation::duffel_id("order_ids", order_id, "ord_")?));
+        }
+        for booking_id in &self.booking_ids {
+            items.push(ExportItem::StayBooking(validation::duffel_id("booking_ids", booking_id, "bok_")?));
+        }
+        for selection in &self.selected_offers {
+            if selection.option == 0 {
+                return Err(RpcError::invalid_params(
+                    "Invalid parameters: selected_offers option numbers start at 1",
+                ));
+            }
+            items.push(ExportItem::Offer(selection.clone()));
+        }
+
+        if items.is_empty() {
+            return Err(RpcError::invalid_params(
+                "Invalid parameters: give at least one of order_ids, booking_ids or selected_offers",
+            ));
+        }
+        if items.len() > MAX_ITEMS {
+            return Err(RpcError::invalid_params(format!(
+                "Invalid parameters: at most {} orders, bookings and offers can be exported at once",
+                MAX_ITEMS
+            )));
+        }
+        Ok(items)
+    }
+}
+
+/// A flight segment as the flights server reports it.
+#[derive(Debug, Deserialize)]
+struct Segment {
+    origin: String,
+    destination: String,
+    departure_time: String,
+    arrival_time: String,
+    #[serde(default)]
+    departure_time_zone: Option<String>,
+    #[serde(default)]
+    arrival_time_zone: Option<String>,
+    airline: String,
+    airline_code: Option<String>,
+    flight_number: String,
+    aircraft: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Slice {
+    segments: Vec<Segment>,
+}
+
+/// `get_order` result.
+#[derive(Debug, Deserialize)]
+struct FlightOrder {
+    id: String,
+    booking_reference: Option<String>,
+    status: String,
+    #[serde(default)]
+    slices: Vec<Slice>,
+}
+
+/// `offer` of a flights `select_offer` result.
+#[derive(Debug, Deserialize)]
+struct FlightOffer {
+    id: String,
+    slices: Vec<Slice>,
+}
+
+/// `get_booking` result.
+#[derive(Debug, Deserialize)]
+struct StayBooking {
+    id: String,
+    reference: Option<String>,
+    status: String,
+    hotel_name: String,
+    check_in_date: String,
+    check_out_date: String,
+}
+
+/// `offer` of a stays `select_offer` result.
+#[derive(Debug, Deserialize)]
+struct StayOffer {
+    id: String,
+    hotel_name: String,
+    location: String,
+    check_in_date: String,
+    check_out_date: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SelectedOffer<T> {
+    offer: T,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ItineraryExport {
+    calendar_name: String,
+    filename: String,
+    /// Every event in the calendar, earliest first.
+    events: Vec<CalendarEvent>,
+    /// The iCalendar document, also returned as an embedded `text/calendar` resource.
+    ics: String,
+    /// Orders, bookings or offers that were left out, and why.
+    #[serde(default)]
+    warnings: Vec<String>,
+}
+
+/// `2027-03-15T09:25:00` as `2027-03-15 09:25`.
+fn display_time(value: &str) -> String {
+    value.replacen('T', " ", 1).chars().take(16).collect()
+}
+
+fn airport(code: &str) -> String {
+    match airports::find(code) {
+        Some(airport) => format!("{} ({})", airport.name, code),
+        None => code.to_string(),
+    }
+}
+
+/// One event per segment; `booked` is the booking reference line, or the offer to book.
+fn flight_events(id: &str, slices: &[Slice], booked: &str, confirmed: bool) -> Vec<CalendarEvent> {
+    let mut events = Vec::new();
+    for (slice_index, slice) in slices.iter().enumerate() {
+        for (segment_index, segment) in slice.segments.iter().enumerate() {
+            let flight = format!(
+                "{}{}",
+                segment.airline_code.as_deref().unwrap_or_default(),
+                segment.flight_number
+            );
+            let mut description = format!("{} flight {}", segment.airline, flight);
+            if let Some(aircraft) = &segment.aircraft {
+                description.push_str(&format!(", {}", aircraft));
+            }
+            description.push_str(&format!(
+                "\nDeparts {} {} (local time)\nArrives {} {} (local time)\n{}",
+                segment.origin,
+                display_time(&segment.departure_time),
+                segment.destination,
+                display_time(&segment.arrival_time),
+                booked
+            ));
+            events.push(CalendarEvent {
+                uid: format!("{}-{}-{}@bookedai", id, slice_index + 1, segment_index + 1),
+                summary: format!("Flight {} {} to {}", flight, segment.origin, segment.destination),
+                start: segment.departure_time.clone(),
+                end: segment.arrival_time.clone(),
+                start_time_zone: segment.departure_time_zone.clone(),
+                end_time_zone: segment.arrival_time_zone.clone(),
+                all_day: false,
+                location: Some(airport(&segment.origin)),
+                description,
+                confirmed,
+            });
+        }
+    }
+    events
+}
+
+/// All-day check-in and check-out events for a hotel stay.
+fn stay_events(
+    id: &str,
+    hotel_name: &str,
+    location: Option<&str>,
+    (check_in_date, check_out_date): (&str, &str),
+    booked: &str,
+    confirmed: bool,
+) -> Vec<CalendarEvent> {
+    let description = format!("{}, {} to {}\n{}", hotel_name, check_in_date, check_out_date, booked);
+    [("check-in", "Check in", check_in_date), ("check-out", "Check out", check_out_date)]
+        .into_iter()
+        .map(|(uid, label, date)| CalendarEvent {
+            uid: format!("{}-{}@bookedai", id, uid),
+            summary: format!("{}: {}", label, hotel_name),
+            start: date.to_string(),
+            end: date.to_string(),
+            start_time_zone: None,
+            end_time_zone: None,
+            all_day: true,
+            location: location.map(str::to_string),
+            description: description.clone(),
+            confirmed,
+        })
+        .collect()
+}
+
+fn reference_line(reference: Option<&str>) -> String {
+    match reference {
+        Some(reference) => format!("Booking reference: {}", reference),
+        None => "Booked".to_string(),
+    }
+}
+
+impl TripPlannerServer {
+    /// The events for one item; an `Err` message names the item.
+    async fn itinerary_events(&self, item: &ExportItem) -> Result<Vec<CalendarEvent>, RpcError> {
+        match item {
+            ExportItem::FlightOrder(order_id) => {
+                let result = self
+                    .flights
+                    .call_tool("get_order", json!({ "order_id": order_id }))
+                    .await?;
+                let order: FlightOrder = parse_results(result, "get_order")?;
+                if order.status == "cancelled" {
+                    return Err(RpcError::invalid_params(format!("Order {} is cancelled", order.id)));
+                }
+                let booked = reference_line(order.booking_reference.as_deref());
+                Ok(flight_events(&order.id, &order.slices, &booked, true))
+            }
+            ExportItem::StayBooking(booking_id) => {
+                let result = self
+                    .stays
+                    .call_tool("get_booking", json!({ "booking_id": booking_id }))
+                    .await?;
+                let booking: StayBooking = parse_results(result, "get_booking")?;
+                if booking.status == "cancelled" {
+                    return Err(RpcError::invalid_params(format!("Booking {} is cancelled", booking.id)));
+                }
+                let booked = reference_line(booking.reference.as_deref());
+                let dates = (booking.check_in_date.as_str(), booking.check_out_date.as_str());
+                Ok(stay_events(&booking.id, &booking.hotel_name, None, dates, &booked, true))
+            }
+            ExportItem::Offer(selection) => {
+                let arguments = json!({ "search_id": selection.search_id, "option": selection.option });
+                match selection.kind {
+                    OfferKind::Flight => {
+                        let result = self
+                            .flights
+                            .call_tool("select_offer", arguments)
+                            .await?;
+                        let selected: SelectedOffer<FlightOffer> = parse_results(result, "select_offer")?;
+                        let offer = selected.offer;
+                        let booked = format!("Not booked yet: flight offer {}", offer.id);
+                        Ok(flight_events(&offer.id, &offer.slices, &booked, false))
+                    }
+                    OfferKind::Stay => {
+                        let result = self
+                            .stays
+                            .call_tool("select_offer", arguments)
+                            .await?;
+                        let selected: SelectedOffer<StayOffer> = parse_results(result, "select_offer")?;
+                        let offer = selected.offer;
+                        let booked = format!("Not booked yet: hotel offer {}", offer.id);
+                        let dates = (offer.check_in_date.as_str(), offer.check_out_date.as_str());
+                        Ok(stay_events(&offer.id, &offer.hotel_name, Some(&offer.location), dates, &booked, false))
+                    }
+                }
+            }
+        }
+    }
+
+    /// Fetch every item concurrently and build the calendar; items that fail become
+    /// warnings, and the export fails only when nothing could be added.
+    async fn export_itinerary(&self, request: &ExportItineraryRequest) -> Result<ItineraryExport, RpcError> {
+        let items = request.items()?;
+        let results = future::join_all(items.iter().map(|item| self.itinerary_events(item))).await;
+
+        let mut events = Vec::new();
+        let mut warnings = Vec::new();
+        let mut first_error = None;
+        for (item, result) in items.iter().zip(results) {
+            match result {
+                Ok(item_events) if item_events.is_empty() => {
+                    warnings.push(format!("{} has no flights or stay dates to add", item.describe()));
+                }
+                Ok(item_events) => events.extend(item_events),
+                Err(e) => {
+                    let warning = format!("{} was left out: {}", item.describe(), e.message);
+                    warn!("{}", warning);
+                    warnings.push(warning);
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        if events.is_empty() {
+            return Err(first_error.unwrap_or_else(|| {
+                RpcError::invalid_params("Invalid parameters: nothing to export; the orders and offers have no dates")
+            }));
+        }
+        events.sort_by_key(CalendarEvent::sort_key);
+
+        let calendar_name = request
+            .calendar_name
+            .clone()
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_CALENDAR_NAME.to_string());
+        let first_day: String = events[0].start.chars().take(10).collect();
+        Ok(ItineraryExport {
+            ics: ics::render(&calendar_name, &events),
+            filename: format!("itinerary-{}.ics", first_day),
+            calendar_name,
+            events,
+            warnings,
+        })
+    }
+}
+
+fn format_export(export: &ItineraryExport) -> String {
+    let mut result = format!(
+        "Exported {} events to {} ({}):\n\n",
+        export.events.len(),
+        export.filename,
+        export.calendar_name
+    );
+    for event in &export.events {
+        let when = if event.all_day { event.start.clone() } else { display_time(&event.start) };
+        result.push_str(&format!("- {} {}", when, event.summary));
+        if !event.confirmed {
+            result.push_str(" (not booked)");
+        }
+        result.push('\n');
+    }
+    result.push_str("\nThe calendar file is attached; import it into any calendar app.");
+    errors::append_warnings(&mut result, &export.warnings);
+    result
+}
+
+pub fn export_itinerary_tool() -> Tool {
+    Tool::new(
+        "export_itinerary",
+        "Export booked flights and hotels, or offers picked from searches, as an iCalendar (.ics) file \
+         with an event per flight and for each hotel check-in and check-out",
+        json!({
+            "type": "object",
+            "properties": {
+                "order_ids": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Flight order IDs (starting with 'ord_') from create_order or list_orders"
+                },
+                "booking_ids": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Hotel booking IDs (starting with 'bok_')"
+                },
+                "selected_offers": {
+                    "type": "array",
+                    "description": "Offers picked from an earlier search_flights or search_stays, added as tentative events",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "kind": { "type": "string", "enum": ["flight", "stay"] },
+                            "search_id": { "type": "string", "description": "Search ID from the search results" },
+                            "option": { "type": "integer", "description": "Option number shown in the search results" }
+                        },
+                        "required": ["kind", "search_id", "option"]
+                    }
+                },
+                "calendar_name": {
+                    "type": "string",
+                    "description": format!("Name calendar apps show for the file (default: '{}')", DEFAULT_CALENDAR_NAME)
+                }
+            }
+        }),
+    )
+    .with_output_schema(json!({
+        "type": "object",
+        "properties": {
+            "calendar_name": { "type": "string" },
+            "filename": { "type": "string", "description": "Suggested file name, e.g. itinerary-2025-07-14.ics" },
+            "events": {
+                "type": "array",
+                "description": "Calendar events, earliest first",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "uid": { "type": "string" },
+                        "summary": { "type": "string" },
+                        "start": {
+                            "type": "string",
+                            "description": "Local time (YYYY-MM-DDTHH:MM:SS), or a date for all-day events"
+                        },
+                        "end": { "type": "string" },
+                        "start_time_zone": { "type": ["string", "null"], "description": "IANA time zone of start" },
+                        "end_time_zone": { "type": ["string", "null"], "description": "IANA time zone of end" },
+                        "all_day": { "type": "boolean" },
+                        "location": { "type": ["string", "null"] },
+                        "description": { "type": "string" },
+                        "confirmed": { "type": "boolean", "description": "false for offers that are not booked yet" }
+                    },
+                    "required": ["uid", "summary", "start", "end", "all_day", "description", "confirmed"]
+                }
+            },
+            "ics": { "type": "string", "description": "The iCalendar document" },
+            "warnings": {
+                "type": "array",
+                "items": { "type": "string" },
+                "description": "Orders, bookings or offers that were left out, and why"
+            }
+        },
+        "required": ["calendar_name", "filename", "events", "ics"]
+    }))
+}
+
+pub async fn export_itinerary(planner: TripPlannerServer, arguments: Value) -> Result<Value, RpcError> {
+    let request: ExportItineraryRequest = mcp::parse_arguments("export_itinerary", arguments)?;
+    let export = planner.export_itinerary(&request).await.inspect_err(|e| {
+        error!("Itinerary export error: {}", e);
+    })?;
+
+    let mut result = mcp::structured_content(format_export(&export), &export)?;
+    if let Some(content) = result["content"].as_array_mut() {
+        let uri = format!("itinerary://{}", export.filename);
+        content.push(mcp::embedded_resource(&uri, CALENDAR_MIME_TYPE, export.ics.as_bytes()));
+    }
+    Ok(result)
+}
@@ -0,0 +1,462 @@
+use anyhow::Result;
+use bookedai_core::http::HttpOptions;
+use bookedai_core::mcp_client::McpClient;
+use bookedai_core::readiness::Readiness;
+use bookedai_core::{airports, config, mcp, transport, validation};
+use bookedai_core::{McpServer, ResponseCache, RpcError, Tool, Transport};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{error, info};
+
+mod bundles;
+mod ics;
+mod itinerary;
+
+use bundles::{CheapestPairing, FlightResults, StayResults, TripBundle};
+
+/// Offers fetched from each server before pairing.
+const CANDIDATES_PER_SEARCH: usize = 20;
+
+const DEFAULT_BUNDLES: usize = 5;
+const MAX_BUNDLES: usize = 20;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PlanTripRequest {
+    origin: String,
+    destination: String,
+    departure_date: String,
+    return_date: String,
+    adults: Option<u32>,
+    children_ages: Option<Vec<u32>>,
+    rooms: Option<u32>,
+    budget: f64,
+    /// Currency of `budget`; prices from both searches are converted into it.
+    currency: Option<String>,
+    /// Where to look for hotels; defaults to the destination's city.
+    location: Option<String>,
+    cabin_class: Option<String>,
+    max_stops: Option<u32>,
+    min_hotel_rating: Option<f64>,
+    limit: Option<usize>,
+}
+
+impl PlanTripRequest {
+    /// Check the trip shape; each server validates the rest of its own arguments.
+    fn validate(&mut self) -> Result<(), RpcError> {
+        self.origin = validation::iata_code("origin", &self.origin)?;
+        self.destination = validation::iata_code("destination", &self.destination)?;
+        let departure_date = validation::upcoming_date("departure_date", &self.departure_date)?;
+        let return_date = validation::date("return_date", &self.return_date)?;
+        // The hotel needs at least one night
+        validation::after("return_date", return_date, "departure_date", departure_date)?;
+        self.departure_date = departure_date.to_string();
+        self.return_date = return_date.to_string();
+        // Matched against the converted prices the servers return, which are upper case
+        if let Some(currency) = &mut self.currency {
+            *currency = currency.trim().to_ascii_uppercase();
+        }
+
+        if self.budget <= 0.0 {
+            return Err(RpcError::invalid_params("Invalid parameters: budget must be positive"));
+        }
+        if let Some(limit) = self.limit {
+            if limit == 0 || limit > MAX_BUNDLES {
+                return Err(RpcError::invalid_params(format!(
+                    "Invalid parameters: limit must be between 1 and {}",
+                    MAX_BUNDLES
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Where hotels are searched, as shown in the plan: `location`, else the destination's city.
+    fn hotel_location(&self) -> String {
+        match &self.location {
+            Some(location) => location.clone(),
+            None => airports::city_name(&self.destination).unwrap_or(&self.destination).to_string(),
+        }
+    }
+
+    /// `location` as `search_stays` takes it: the destination code itself unless one was
+    /// given, which the stays server places exactly, where a city name can be ambiguous.
+    fn stay_location(&self) -> &str {
+        self.location.as_deref().unwrap_or(&self.destination)
+    }
+
+    fn flight_arguments(&self) -> Value {
+        let mut arguments = json!({
+            "origin": self.origin,
+            "destination": self.destination,
+            "departure_date": self.departure_date,
+            "return_date": self.return_date,
+            "adults": self.adults.unwrap_or(1),
+            "sort_by": "price",
+            "limit": CANDIDATES_PER_SEARCH,
+            // Bundles show each slice's times and stops, not its segments
+            "verbosity": "summary"
+        });
+        if let Some(children_ages) = &self.children_ages {
+            arguments["children_ages"] = json!(children_ages);
+        }
+        if let Some(cabin_class) = &self.cabin_class {
+            arguments["cabin_class"] = json!(cabin_class);
+        }
+        if let Some(max_stops) = self.max_stops {
+            arguments["max_stops"] = json!(max_stops);
+        }
+        if let Some(currency) = &self.currency {
+            arguments["display_currency"] = json!(currency);
+        }
+        arguments
+    }
+
+    fn stay_arguments(&self, location: &str) -> Value {
+        let mut arguments = json!({
+            "location": location,
+            "check_in_date": self.departure_date,
+            "check_out_date": self.return_date,
+            "adults": self.adults.unwrap_or(1),
+            "children_ages": self.children_ages.clone().unwrap_or_default(),
+            "rooms": self.rooms.unwrap_or(1),
+            // Only the cheapest rate per hotel is needed to price a bundle
+            "include_rates": false,
+            "limit": CANDIDATES_PER_SEARCH,
+            // Bundles need the room type and cancellation policy, whatever the server default
+            "verbosity": "full"
+        });
+        if let Some(min_rating) = self.min_hotel_rating {
+            arguments["min_rating"] = json!(min_rating);
+        }
+        if let Some(currency) = &self.currency {
+            arguments["display_currency"] = json!(currency);
+        }
+        arguments
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TripPlanResponse {
+    bundles: Vec<TripBundle>,
+    budget: f64,
+    currency: Option<String>,
+    hotel_location: String,
+    flights_considered: usize,
+    stays_considered: usize,
+    /// Cheapest pairing found, even when it is over budget.
+    cheapest: Option<CheapestPairing>,
+}
+
+#[derive(Debug, Clone)]
+struct TripPlannerServer {
+    flights: McpClient,
+    stays: McpClient,
+}
+
+/// Name the upstream search in an error, keeping its code.
+fn upstream(search: &str, e: RpcError) -> RpcError {
+    RpcError {
+        message: format!("{} search: {}", search, e.message),
+        ..e
+    }
+}
+
+impl TripPlannerServer {
+    fn new() -> Self {
+        Self {
+            flights: McpClient::from_config(&config::get().upstream.flights_mcp_url),
+            stays: McpClient::from_config(&config::get().upstream.stays_mcp_url),
+        }
+    }
+
+    async fn plan_trip(&self, request: &PlanTripRequest) -> Result<TripPlanResponse, RpcError> {
+        let hotel_location = request.hotel_location();
+        info!(
+            "Planning trip {} -> {} ({} to {}), hotels in {}",
+            request.origin, request.destination, request.departure_date, request.return_date, hotel_location
+        );
+
+        // Both searches are independent, so run them side by side
+        let (flights, stays) = tokio::join!(
+            self.flights.call_tool("search_flights", request.flight_arguments()),
+            self.stays.call_tool("search_stays", request.stay_arguments(request.stay_location())),
+        );
+        let flights: FlightResults = parse_results(flights.map_err(|e| upstream("Flight", e))?, "search_flights")?;
+        let stays: StayResults = parse_results(stays.map_err(|e| upstream("Stay", e))?, "search_stays")?;
+
+        let limit = request.limit.unwrap_or(DEFAULT_BUNDLES);
+        let (bundles, cheapest) = bundles::combine(
+            &flights.offers,
+            &stays.offers,
+            request.currency.as_deref(),
+            request.budget,
+            limit,
+        );
+
+        Ok(TripPlanResponse {
+            bundles,
+            budget: request.budget,
+            currency: request.currency.clone(),
+            hotel_location,
+            flights_considered: flights.offers.len(),
+            stays_considered: stays.offers.len(),
+            cheapest,
+        })
+    }
+
+    fn format_trip_plan(&self, response: &TripPlanResponse) -> String {
+        let budget = match &response.currency {
+            Some(currency) => format!("{:.2} {}", response.budget, currency),
+            None => format!("{:.2}", response.budget),
+        };
+
+        if response.bundles.is_empty() {
+            let mut result = format!(
+                "No flight + hotel combination fits a budget of {} ({} flights and {} hotels in {} considered).",
+                budget, response.flights_considered, response.stays_considered, response.hotel_location
+            );
+            match &response.cheapest {
+                Some(cheapest) => result.push_str(&format!(
+                    "\nThe cheapest combination costs {} {}.",
+                    cheapest.total_price, cheapest.currency
+                )),
+                None if response.currency.is_none() => result.push_str(
+                    "\nFlights and hotels may be priced in different currencies; pass currency to compare them.",
+                ),
+                None => {}
+            }
+            return result;
+        }
+
+        let mut result = format!(
+            "Found {} trip bundles within {} (hotels in {}):\n\n",
+            response.bundles.len(),
+            budget,
+            response.hotel_location
+        );
+        for bundle in &response.bundles {
+            result.push_str(&format!(
+                "{}. {} {} total ({} {} left)\n",
+                bundle.rank, bundle.total_price, bundle.currency, bundle.remaining_budget, bundle.currency
+            ));
+
+            let flight = &bundle.flight;
+            result.push_str(&format!("   Flight: {} - {} {}\n", flight.airline, flight.price, flight.currency));
+            for slice in &flight.slices {
+                let stops = match slice.stops {
+                    0 => "direct".to_string(),
+                    1 => "1 stop".to_string(),
+                    n => format!("{} stops", n),
+                };
+                result.push_str(&format!(
+                    "      {} -> {}, {} -> {} ({})\n",
+                    slice.origin, slice.destination, slice.departure_time, slice.arrival_time, stops
+                ));
+            }
+
+            let stay = &bundle.stay;
+            result.push_str(&format!("   Hotel: {}", stay.hotel_name));
+            if let Some(rating) = stay.hotel_rating {
+                result.push_str(&format!(" ({:.1}/5)", rating));
+            }
+            result.push_str(&format!(" - {} {}, {}\n", stay.total_amount, stay.currency, stay.location));
+            if let Some(policy) = &stay.cancellation_policy {
+                result.push_str(&format!("      Cancellation: {}\n", policy));
+            }
+            result.push_str(&format!("   Flight offer ID: {} | Stay ID: {}\n\n", flight.id, stay.id));
+        }
+        result.trim_end().to_string()
+    }
+}
+
+fn parse_results<T: serde::de::DeserializeOwned>(structured: Value, tool: &str) -> Result<T, RpcError> {
+    serde_json::from_value(structured).map_err(|e| {
+        error!("Unexpected {} result: {}", tool, e);
+        RpcError::server_error(format!("Unexpected {} result: {}", tool, e))
+    })
+}
+
+fn plan_trip_tool() -> Tool {
+    Tool::new(
+        "plan_trip",
+        "Plan a round trip: search flights and hotels together and suggest the cheapest flight + hotel bundles within a budget",
+        json!({
+            "type": "object",
+            "properties": {
+                "origin": {
+                    "type": "string",
+                    "description": "Origin IATA airport or city code (e.g., 'JFK', 'NYC')"
+                },
+                "destination": {
+                    "type": "string",
+                    "description": "Destination IATA airport or city code (e.g., 'LHR', 'PAR')"
+                },
+                "departure_date": {
+                    "type": "string",
+                    "description": "Outbound flight date and hotel check-in, YYYY-MM-DD"
+                },
+                "return_date": {
+                    "type": "string",
+                    "description": "Return flight date and hotel check-out, YYYY-MM-DD"
+                },
+                "adults": {
+                    "type": "integer",
+                    "description": "Number of adult travelers (default: 1)"
+                },
+                "children_ages": {
+                    "type": "array",
+                    "items": { "type": "integer" },
+                    "description": "Age of each child traveler"
+                },
+                "rooms": {
+                    "type": "integer",
+                    "description": "Hotel rooms needed (default: 1)"
+                },
+                "budget": {
+                    "type": "number",
+                    "description": "Maximum total for flights plus hotel, for all travelers"
+                },
+                "currency": {
+                    "type": "string",
+                    "description": "ISO 4217 currency of the budget (e.g. 'USD'); all prices are converted into it"
+                },
+                "location": {
+                    "type": "string",
+                    "description": "Where to look for hotels (default: the destination's city)"
+                },
+                "cabin_class": {
+                    "type": "string",
+                    "description": "Cabin class: economy, premium_economy, business, first (default: economy)"
+                },
+                "max_stops": {
+                    "type": "integer",
+                    "description": "Maximum stops on each flight slice"
+                },
+                "min_hotel_rating": {
+                    "type": "number",
+                    "description": "Minimum hotel star rating, 0-5"
+                },
+                "limit": {
+                    "type": "integer",
+                    "description": format!("Maximum bundles to return (1-{}, default: {})", MAX_BUNDLES, DEFAULT_BUNDLES)
+                }
+            },
+            "required": ["origin", "destination", "departure_date", "return_date", "budget"]
+        }),
+    )
+    .with_output_schema(json!({
+        "type": "object",
+        "properties": {
+            "bundles": {
+                "type": "array",
+                "description": "Flight + hotel pairs within budget, cheapest first",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "rank": { "type": "integer" },
+                        "total_price": { "type": "string", "description": "Flight plus hotel, as a decimal string" },
+                        "currency": { "type": "string" },
+                        "remaining_budget": { "type": "string" },
+                        "flight": {
+                            "type": "object",
+                            "description": "Offer from search_flights; book with its id",
+                            "properties": {
+                                "id": { "type": "string" },
+                                "price": { "type": "string" },
+                                "currency": { "type": "string" },
+                                "airline": { "type": "string" },
+                                "slices": { "type": "array", "items": { "type": "object" } }
+                            },
+                            "required": ["id", "price", "currency", "airline", "slices"]
+                        },
+                        "stay": {
+                            "type": "object",
+                            "description": "Hotel from search_stays; book with its id",
+                            "properties": {
+                                "id": { "type": "string" },
+                                "hotel_name": { "type": "string" },
+                                "hotel_rating": { "type": ["number", "null"] },
+                                "location": { "type": "string" },
+                                "total_amount": { "type": "string" },
+                                "currency": { "type": "string" }
+                            },
+                            "required": ["id", "hotel_name", "location", "total_amount", "currency"]
+                        }
+                    },
+                    "required": ["rank", "total_price", "currency", "remaining_budget", "flight", "stay"]
+                }
+            },
+            "budget": { "type": "number" },
+            "currency": { "type": ["string", "null"] },
+            "hotel_location": { "type": "string" },
+            "flights_considered": { "type": "integer" },
+            "stays_considered": { "type": "integer" },
+            "cheapest": {
+                "type": ["object", "null"],
+                "description": "Cheapest pairing found, even when over budget",
+                "properties": {
+                    "total_price": { "type": "string" },
+                    "currency": { "type": "string" }
+                }
+            }
+        },
+        "required": ["bundles", "budget", "hotel_location", "flights_considered", "stays_considered"]
+    }))
+    .cacheable()
+}
+
+async fn plan_trip(planner: TripPlannerServer, arguments: Value) -> Result<Value, RpcError> {
+    let mut request: PlanTripRequest = mcp::parse_arguments("plan_trip", arguments)?;
+    request.validate()?;
+
+    let plan = planner.plan_trip(&request).await.inspect_err(|e| {
+        error!("Trip planning error: {}", e);
+    })?;
+
+    let formatted_results = planner.format_trip_plan(&plan);
+    mcp::structured_content(formatted_results, &plan)
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let transport = Transport::from_args()?;
+    // config.toml plus environment overrides; `--print-config` prints it and exits
+    config::init()?;
+
+    // Initialize logging (stderr, so stdout stays free for the stdio transport)
+    bookedai_core::logging::init();
+    info!("Starting Trip Planner MCP Server ({} transport)", transport);
+
+    // Initialize the server
+    let planner = TripPlannerServer::new();
+    info!("Flights server: {}", planner.flights.url());
+    info!("Stays server: {}", planner.stays.url());
+    // The planner can only answer while both servers it calls can
+    let readiness = Readiness::new()
+        .mcp_server("flights_server", planner.flights.clone())
+        .mcp_server("stays_server", planner.stays.clone());
+
+    let server = McpServer::builder("trip-planner-mcp", env!("CARGO_PKG_VERSION"))
+        .title("Trip Planner MCP Server")
+        .cache(ResponseCache::from_config())
+        .tool(plan_trip_tool(), {
+            let planner = planner.clone();
+            move |arguments| plan_trip(planner.clone(), arguments)
+        })
+        .tool(itinerary::export_itinerary_tool(), move |arguments| {
+            itinerary::export_itinerary(planner.clone(), arguments)
+        })
+        .build();
+
+    transport::serve(
+        server,
+        transport,
+        HttpOptions {
+            default_port: 3004,
+            log_target: "trip_planner",
+            webhooks: None,
+            readiness,
+        },
+    )
+    .await
+}